@@ -0,0 +1,76 @@
+//! Requests per second through epoll and io_uring, one keep-alive request
+//! in flight per connection, with the load generated on the same machine.
+//!
+//! cargo bench -p tachyon-core --features io-uring --bench backends
+
+use std::io::{Read, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use tachyon_core::config::ServerConfig;
+use tachyon_core::router::Router;
+use tachyon_core::server::{Server, sync_handler};
+use tachyon_http::methods::Method;
+
+const BODY: &str = "Hello, World!";
+const RUN: Duration = Duration::from_secs(5);
+
+fn main() {
+    for connections in [1, 16, 64] {
+        for io_uring in [false, true] {
+            let rate = requests_per_sec(io_uring, connections);
+            let backend = if io_uring { "io_uring" } else { "epoll" };
+            println!("{backend:>8} {connections:>3} connections: {rate:>8.0} req/s");
+        }
+    }
+}
+
+fn requests_per_sec(io_uring: bool, connections: usize) -> f64 {
+    let mut router = Router::new();
+    router.route(Method::Get, "/", sync_handler(|_| BODY));
+    let config = ServerConfig::new()
+        .bind("127.0.0.1:0")
+        .shutdown_signals(false)
+        .io_uring(io_uring);
+    let handle = Server::new(config).spawn(router).unwrap();
+    let addr = handle.local_addr();
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let served = Arc::new(AtomicU64::new(0));
+    let clients: Vec<_> = (0..connections)
+        .map(|_| {
+            let (stop, served) = (stop.clone(), served.clone());
+            std::thread::spawn(move || {
+                let mut stream = std::net::TcpStream::connect(addr).unwrap();
+                stream.set_nodelay(true).unwrap();
+                let mut buf = [0u8; 1024];
+                while !stop.load(Ordering::Relaxed) {
+                    stream
+                        .write_all(b"GET / HTTP/1.1\r\nHost: bench\r\n\r\n")
+                        .unwrap();
+                    let mut len = 0;
+                    while !buf[..len].ends_with(BODY.as_bytes()) {
+                        len += stream.read(&mut buf[len..]).unwrap();
+                    }
+                    served.fetch_add(1, Ordering::Relaxed);
+                }
+            })
+        })
+        .collect();
+    let start = Instant::now();
+    std::thread::sleep(RUN);
+    stop.store(true, Ordering::Relaxed);
+    let rate = served.load(Ordering::Relaxed) as f64 / start.elapsed().as_secs_f64();
+    for client in clients {
+        client.join().unwrap();
+    }
+    handle.shutdown();
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(handle.join())
+        .unwrap();
+    rate
+}
@@ -0,0 +1,88 @@
+//! Route cache lookups per second by shard count and capacity, from as
+//! many threads as there are CPUs and from 4x as many. Hits read keys that
+//! all fit; misses read keys never stored before, so every lookup also
+//! stores a response and, once full, evicts one.
+//!
+//! cargo bench -p tachyon-core --features test-util --bench cache
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use tachyon_core::cache::{Eviction, ResponseCache};
+use tachyon_core::testing::cache_lookup;
+
+const BODY: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Length: 13\r\n\r\nHello, World!";
+const RUN: Duration = Duration::from_secs(2);
+
+fn main() {
+    let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+    for eviction in [Eviction::Lru, Eviction::SegmentedLru, Eviction::TinyLfu] {
+        for entries in [1024, 16384] {
+            for shards in [1, 4, 16] {
+                for threads in [cpus, cpus * 4] {
+                    let hits = lookups_per_sec(eviction, entries, shards, threads, true);
+                    let misses = lookups_per_sec(eviction, entries, shards, threads, false);
+                    println!(
+                        "{:>12} {entries:>5} entries {shards:>2} shards {threads:>2} threads: \
+                         {hits:>10.0} hits/s {misses:>10.0} misses/s",
+                        format!("{eviction:?}"),
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn lookups_per_sec(
+    eviction: Eviction,
+    entries: usize,
+    shards: usize,
+    threads: usize,
+    hits: bool,
+) -> f64 {
+    let cache = ResponseCache::with_eviction(Duration::MAX, entries, shards, eviction);
+    // Half the capacity, so that uneven shards still hold every key.
+    let targets: Arc<Vec<Vec<u8>>> = Arc::new(
+        (0..entries / 2)
+            .map(|i| format!("/items/{i}").into_bytes())
+            .collect(),
+    );
+    if hits {
+        for target in targets.iter() {
+            cache_lookup(&cache, target, BODY);
+        }
+    }
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let done = Arc::new(AtomicU64::new(0));
+    let workers: Vec<_> = (0..threads)
+        .map(|thread| {
+            let (cache, targets) = (cache.clone(), targets.clone());
+            let (stop, done) = (stop.clone(), done.clone());
+            std::thread::spawn(move || {
+                let mut n = 0u64;
+                while !stop.load(Ordering::Relaxed) {
+                    let found = if hits {
+                        let target = &targets[(n as usize * 7919 + thread) % targets.len()];
+                        cache_lookup(&cache, target, BODY)
+                    } else {
+                        let target = format!("/new/{thread}/{n}");
+                        cache_lookup(&cache, target.as_bytes(), BODY)
+                    };
+                    assert_eq!(found, hits);
+                    n += 1;
+                }
+                done.fetch_add(n, Ordering::Relaxed);
+            })
+        })
+        .collect();
+    let start = Instant::now();
+    std::thread::sleep(RUN);
+    stop.store(true, Ordering::Relaxed);
+    let elapsed = start.elapsed().as_secs_f64();
+    for worker in workers {
+        worker.join().unwrap();
+    }
+    done.load(Ordering::Relaxed) as f64 / elapsed
+}
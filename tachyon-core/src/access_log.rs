@@ -0,0 +1,292 @@
+//! Access log file with size/time-based rotation.
+//!
+//! Connection tasks format one line per request and hand it to a dedicated
+//! writer thread over a bounded queue; the thread writes lines in batches
+//! with a single flush per batch. When the queue is full (the disk cannot
+//! keep up) new lines are dropped and counted instead of stalling the event
+//! loop — see [`AccessLog::dropped`].
+//!
+//! Line format (bytes = whole response, headers included):
+//!
+//! ```text
+//! 203.0.113.7 [Thu, 01 Jan 1970 00:00:00 GMT] "GET /users?page=2 HTTP/1.1" 200 512 83us
+//! ```
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{Receiver, SyncSender, TrySendError};
+use std::time::{Duration, Instant};
+
+use tachyon_http::http::Request;
+
+/// Lines written per flush, at most.
+const BATCH: usize = 256;
+
+/// Where and how to write the access log. Finish with [`open`](Self::open).
+#[derive(Debug, Clone)]
+pub struct AccessLogBuilder {
+    path: PathBuf,
+    max_size: Option<u64>,
+    interval: Option<Duration>,
+    keep: usize,
+    capacity: usize,
+}
+
+impl AccessLogBuilder {
+    /// Rotate once the file reaches `bytes`.
+    pub fn max_size(mut self, bytes: u64) -> Self {
+        self.max_size = Some(bytes);
+        self
+    }
+
+    /// Rotate every `interval` (e.g. daily), whatever the size.
+    pub fn rotate_every(mut self, interval: Duration) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+
+    /// Rotated files to keep: `access.log.1` (newest) … `access.log.N` (default: 5).
+    pub fn keep(mut self, files: usize) -> Self {
+        self.keep = files;
+        self
+    }
+
+    /// Lines that may queue up before new ones are dropped (default: 8192).
+    pub fn capacity(mut self, lines: usize) -> Self {
+        self.capacity = lines.max(1);
+        self
+    }
+
+    /// Open (append to) the file and start the writer thread.
+    pub fn open(self) -> io::Result<AccessLog> {
+        let (tx, rx) = std::sync::mpsc::sync_channel(self.capacity);
+        let writer = self.writer()?;
+        std::thread::Builder::new()
+            .name("tachyon-access-log".into())
+            .spawn(move || writer.run(rx))?;
+        Ok(AccessLog {
+            tx,
+            dropped: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    fn writer(self) -> io::Result<Writer> {
+        if let Some(dir) = self.path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let file = open_append(&self.path)?;
+        let size = file.metadata()?.len();
+        Ok(Writer {
+            out: BufWriter::new(file),
+            size,
+            opened: Instant::now(),
+            config: self,
+        })
+    }
+}
+
+/// Handle to a running access log. Cheap to clone; the writer thread
+/// flushes and exits once every handle is dropped.
+#[derive(Debug, Clone)]
+pub struct AccessLog {
+    tx: SyncSender<Vec<u8>>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl AccessLog {
+    /// Log to `path`. No rotation unless configured.
+    pub fn builder(path: impl AsRef<Path>) -> AccessLogBuilder {
+        AccessLogBuilder {
+            path: path.as_ref().to_path_buf(),
+            max_size: None,
+            interval: None,
+            keep: 5,
+            capacity: 8192,
+        }
+    }
+
+    /// Lines dropped because the writer fell behind.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Queue a line for `request`. Never blocks.
+    pub(crate) fn record(
+        &self,
+        peer: SocketAddr,
+        request: &Request<'_>,
+        status: u16,
+        bytes: usize,
+        latency: Duration,
+    ) {
+        let line = format_line(
+            peer,
+            crate::date::cached_date_header(),
+            request,
+            status,
+            bytes,
+            latency,
+        );
+        if let Err(TrySendError::Full(_)) = self.tx.try_send(line) {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+fn format_line(
+    peer: SocketAddr,
+    date_header: &[u8],
+    request: &Request<'_>,
+    status: u16,
+    bytes: usize,
+    latency: Duration,
+) -> Vec<u8> {
+    // "Date: <HTTP-date>\r\n" → "<HTTP-date>"
+    let date = date_header
+        .strip_prefix(b"Date: ")
+        .and_then(|d| d.strip_suffix(b"\r\n"))
+        .unwrap_or(date_header);
+    let mut line = Vec::with_capacity(96 + request.path.len());
+    let _ = write!(line, "{} [", peer.ip());
+    line.extend_from_slice(date);
+    let _ = write!(line, "] \"{} ", request.method.as_str());
+    // The target is client-controlled: keep the line one line.
+    line.extend(
+        request
+            .path
+            .iter()
+            .map(|&b| if b.is_ascii_graphic() { b } else { b'?' }),
+    );
+    let _ = writeln!(
+        line,
+        " HTTP/1.{}\" {} {} {}us",
+        request.version_minor,
+        status,
+        bytes,
+        latency.as_micros()
+    );
+    line
+}
+
+struct Writer {
+    out: BufWriter<File>,
+    size: u64,
+    opened: Instant,
+    config: AccessLogBuilder,
+}
+
+impl Writer {
+    fn run(mut self, rx: Receiver<Vec<u8>>) {
+        while let Ok(line) = rx.recv() {
+            self.write(&line);
+            for line in rx.try_iter().take(BATCH - 1) {
+                self.write(&line);
+            }
+            if let Err(e) = self.out.flush() {
+                eprintln!("[tachyon] Access log write failed: {}", e);
+            }
+        }
+        let _ = self.out.flush();
+    }
+
+    fn write(&mut self, line: &[u8]) {
+        if self.due()
+            && let Err(e) = self.rotate()
+        {
+            eprintln!("[tachyon] Access log rotation failed: {}", e);
+        }
+        if self.out.write_all(line).is_ok() {
+            self.size += line.len() as u64;
+        }
+    }
+
+    fn due(&self) -> bool {
+        self.size > 0
+            && (self.config.max_size.is_some_and(|max| self.size >= max)
+                || self
+                    .config
+                    .interval
+                    .is_some_and(|every| self.opened.elapsed() >= every))
+    }
+
+    /// `log.(N-1)` → `log.N`, …, `log` → `log.1`, then start a fresh `log`.
+    fn rotate(&mut self) -> io::Result<()> {
+        self.out.flush()?;
+        let path = &self.config.path;
+        let numbered = |n: usize| {
+            let mut name = path.clone().into_os_string();
+            name.push(format!(".{n}"));
+            PathBuf::from(name)
+        };
+        if self.config.keep == 0 {
+            fs::remove_file(path)?;
+        } else {
+            for n in (1..self.config.keep).rev() {
+                let from = numbered(n);
+                if from.exists() {
+                    fs::rename(&from, numbered(n + 1))?;
+                }
+            }
+            fs::rename(path, numbered(1))?;
+        }
+        self.out = BufWriter::new(open_append(path)?);
+        self.size = 0;
+        self.opened = Instant::now();
+        Ok(())
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tachyon_http::parser::{ParseResult, parse};
+
+    #[test]
+    fn formats_and_rotates_by_size() {
+        let ParseResult::Complete(req) = parse(b"GET /a\x01b HTTP/1.1\r\n\r\n") else {
+            panic!("bad request");
+        };
+        let line = format_line(
+            "203.0.113.7:5000".parse().unwrap(),
+            b"Date: Thu, 01 Jan 1970 00:00:00 GMT\r\n",
+            &req,
+            200,
+            512,
+            Duration::from_micros(83),
+        );
+        assert_eq!(
+            line,
+            b"203.0.113.7 [Thu, 01 Jan 1970 00:00:00 GMT] \"GET /a?b HTTP/1.1\" 200 512 83us\n"
+        );
+
+        let dir = std::env::temp_dir().join(format!("tachyon-log-{}", std::process::id()));
+        let path = dir.join("access.log");
+        let writer = AccessLog::builder(&path)
+            .max_size(150)
+            .keep(2)
+            .writer()
+            .unwrap();
+        let (tx, rx) = std::sync::mpsc::sync_channel(8);
+        for _ in 0..8 {
+            tx.send(line.clone()).unwrap();
+        }
+        drop(tx);
+        writer.run(rx);
+
+        let lines =
+            |p: PathBuf| fs::read(p).map_or(0, |d| d.iter().filter(|&&b| b == b'\n').count());
+        assert_eq!(lines(path.clone()), 2);
+        assert_eq!(lines(path.with_extension("log.1")), 2);
+        assert_eq!(lines(path.with_extension("log.2")), 2);
+        assert!(!path.with_extension("log.3").exists());
+        let _ = fs::remove_dir_all(dir);
+    }
+}
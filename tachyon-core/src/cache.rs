@@ -0,0 +1,876 @@
+//! Per-route response cache for idempotent (GET/HEAD) routes.
+//!
+//! Stores the wire response, less its `Date` header, keyed by method,
+//! negotiated encoding, path and query. A hit is a memcpy around the
+//! current date — the handler never runs.
+//! The cache is a cheap `Arc` handle: keep a clone to read stats or
+//! invalidate entries while the server runs.
+//!
+//! Entries are spread over shards, each behind its own lock: requests for
+//! keys in different shards never wait on each other. [`ResponseCache::new`]
+//! uses one shard; pick more with [`ResponseCache::with_shards`].
+//!
+//! A full shard drops one entry in O(1), picked by its [`Eviction`] policy.
+
+use std::collections::HashMap;
+use std::hash::{BuildHasher, RandomState};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use tachyon_http::encoding::Encoding;
+use tachyon_http::methods::Method;
+
+struct Entry {
+    data: Arc<[u8]>,
+    /// `None` = never expires.
+    expires: Option<Instant>,
+}
+
+impl Entry {
+    #[inline]
+    fn fresh(&self, now: Instant) -> bool {
+        self.expires.is_none_or(|at| at > now)
+    }
+}
+
+/// Which entry a full shard drops to make room.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Eviction {
+    /// The least recently used one.
+    #[default]
+    Lru,
+    /// Segmented LRU: entries read at least twice move to a protected
+    /// segment (4/5 of the capacity), so a burst of one-off requests only
+    /// evicts other one-off responses.
+    SegmentedLru,
+    /// W-TinyLFU: new entries wait in a small LRU window (1% of the
+    /// capacity); leaving it, one replaces the segmented LRU's victim only
+    /// if its key was asked for more often lately, going by a compact
+    /// frequency sketch. Misses count too, so a popular response that
+    /// was evicted wins its place back.
+    TinyLfu,
+}
+
+impl Eviction {
+    fn policy(self, capacity: usize) -> Box<dyn Policy> {
+        match self {
+            Self::Lru => Box::new(Lru),
+            Self::SegmentedLru => Box::new(SegmentedLru {
+                protected: capacity * 4 / 5,
+            }),
+            Self::TinyLfu => Box::new(TinyLfu::new(capacity)),
+        }
+    }
+}
+
+const NIL: usize = usize::MAX;
+
+/// Segments a slot can be linked into; which ones are used is up to the
+/// [`Policy`].
+const WINDOW: usize = 0;
+const PROBATION: usize = 1;
+const PROTECTED: usize = 2;
+
+struct Slot {
+    key: Arc<[u8]>,
+    /// Of `key`, from the cache's hasher.
+    hash: u64,
+    entry: Entry,
+    prev: usize,
+    next: usize,
+    segment: usize,
+}
+
+/// Doubly linked list threaded through `Slots`, most recent first.
+#[derive(Clone, Copy)]
+struct List {
+    head: usize,
+    tail: usize,
+    len: usize,
+}
+
+impl List {
+    const EMPTY: Self = Self {
+        head: NIL,
+        tail: NIL,
+        len: 0,
+    };
+}
+
+/// The entries of a shard and the recency lists linking them.
+struct Slots {
+    slots: Vec<Option<Slot>>,
+    free: Vec<usize>,
+    segments: [List; 3],
+}
+
+impl Default for Slots {
+    fn default() -> Self {
+        Self {
+            slots: Vec::new(),
+            free: Vec::new(),
+            segments: [List::EMPTY; 3],
+        }
+    }
+}
+
+impl Slots {
+    fn slot(&self, i: usize) -> &Slot {
+        self.slots[i].as_ref().expect("linked slot")
+    }
+
+    fn slot_mut(&mut self, i: usize) -> &mut Slot {
+        self.slots[i].as_mut().expect("linked slot")
+    }
+
+    fn len(&self, segment: usize) -> usize {
+        self.segments[segment].len
+    }
+
+    /// Least recent slot of `segment`, or [`NIL`].
+    fn tail(&self, segment: usize) -> usize {
+        self.segments[segment].tail
+    }
+
+    fn unlink(&mut self, i: usize) {
+        let (prev, next, segment) = {
+            let s = self.slot(i);
+            (s.prev, s.next, s.segment)
+        };
+        match prev {
+            NIL => self.segments[segment].head = next,
+            p => self.slot_mut(p).next = next,
+        }
+        match next {
+            NIL => self.segments[segment].tail = prev,
+            n => self.slot_mut(n).prev = prev,
+        }
+        self.segments[segment].len -= 1;
+    }
+
+    fn push_front(&mut self, i: usize, segment: usize) {
+        let head = self.segments[segment].head;
+        {
+            let s = self.slot_mut(i);
+            s.prev = NIL;
+            s.next = head;
+            s.segment = segment;
+        }
+        match head {
+            NIL => self.segments[segment].tail = i,
+            h => self.slot_mut(h).prev = i,
+        }
+        let list = &mut self.segments[segment];
+        list.head = i;
+        list.len += 1;
+    }
+
+    /// Relink slot `i` at the front of `segment`.
+    fn move_to(&mut self, i: usize, segment: usize) {
+        self.unlink(i);
+        self.push_front(i, segment);
+    }
+
+    /// Store an unlinked slot, returning its index.
+    fn add(&mut self, slot: Slot) -> usize {
+        match self.free.pop() {
+            Some(i) => {
+                self.slots[i] = Some(slot);
+                i
+            }
+            None => {
+                self.slots.push(Some(slot));
+                self.slots.len() - 1
+            }
+        }
+    }
+
+    fn remove(&mut self, i: usize) -> Slot {
+        self.unlink(i);
+        self.free.push(i);
+        self.slots[i].take().expect("linked slot")
+    }
+
+    /// Move a hit on probation slot `i` to the protected segment of at most
+    /// `cap` slots, demoting its least recent slot when that overflows.
+    fn promote(&mut self, i: usize, cap: usize) {
+        if cap == 0 {
+            return self.move_to(i, PROBATION);
+        }
+        self.move_to(i, PROTECTED);
+        if self.len(PROTECTED) > cap {
+            self.move_to(self.tail(PROTECTED), PROBATION);
+        }
+    }
+}
+
+/// How a shard orders its entries: where new ones go, what a hit does and
+/// which one a full shard drops. Each call is O(1).
+trait Policy: Send {
+    /// Link the new slot `i`.
+    fn insert(&mut self, slots: &mut Slots, i: usize);
+    /// Record a hit on slot `i`.
+    fn hit(&mut self, slots: &mut Slots, i: usize);
+    /// Record a lookup of a key with this hash that found nothing.
+    fn miss(&mut self, _hash: u64) {}
+    /// The slot a full shard drops; it may relink others on the way.
+    fn victim(&mut self, slots: &mut Slots) -> usize;
+}
+
+/// [`Eviction::Lru`]: a single list.
+struct Lru;
+
+impl Policy for Lru {
+    fn insert(&mut self, slots: &mut Slots, i: usize) {
+        slots.push_front(i, PROBATION);
+    }
+
+    fn hit(&mut self, slots: &mut Slots, i: usize) {
+        slots.move_to(i, PROBATION);
+    }
+
+    fn victim(&mut self, slots: &mut Slots) -> usize {
+        slots.tail(PROBATION)
+    }
+}
+
+/// Least recent probation slot, else least recent protected one.
+fn main_victim(slots: &Slots) -> usize {
+    match slots.tail(PROBATION) {
+        NIL => slots.tail(PROTECTED),
+        tail => tail,
+    }
+}
+
+/// [`Eviction::SegmentedLru`].
+struct SegmentedLru {
+    /// Capacity of the protected segment.
+    protected: usize,
+}
+
+impl Policy for SegmentedLru {
+    fn insert(&mut self, slots: &mut Slots, i: usize) {
+        slots.push_front(i, PROBATION);
+    }
+
+    fn hit(&mut self, slots: &mut Slots, i: usize) {
+        slots.promote(i, self.protected);
+    }
+
+    fn victim(&mut self, slots: &mut Slots) -> usize {
+        main_victim(slots)
+    }
+}
+
+/// [`Eviction::TinyLfu`]: a window LRU in front of a segmented LRU, with
+/// admission to the latter decided by [`Sketch`] frequencies.
+struct TinyLfu {
+    window: usize,
+    protected: usize,
+    sketch: Sketch,
+}
+
+impl TinyLfu {
+    fn new(capacity: usize) -> Self {
+        let window = (capacity / 100).max(1);
+        Self {
+            window,
+            protected: capacity.saturating_sub(window) * 4 / 5,
+            sketch: Sketch::new(capacity),
+        }
+    }
+}
+
+impl Policy for TinyLfu {
+    fn insert(&mut self, slots: &mut Slots, i: usize) {
+        slots.push_front(i, WINDOW);
+        // Room left in the main segments: the window's oldest moves on.
+        if slots.len(WINDOW) > self.window {
+            slots.move_to(slots.tail(WINDOW), PROBATION);
+        }
+    }
+
+    fn hit(&mut self, slots: &mut Slots, i: usize) {
+        self.sketch.record(slots.slot(i).hash);
+        match slots.slot(i).segment {
+            WINDOW => slots.move_to(i, WINDOW),
+            _ => slots.promote(i, self.protected),
+        }
+    }
+
+    fn miss(&mut self, hash: u64) {
+        self.sketch.record(hash);
+    }
+
+    fn victim(&mut self, slots: &mut Slots) -> usize {
+        let candidate = slots.tail(WINDOW);
+        let victim = main_victim(slots);
+        if candidate == NIL || slots.len(WINDOW) < self.window {
+            return victim;
+        }
+        if victim == NIL {
+            return candidate;
+        }
+        let frequency = |i| self.sketch.frequency(slots.slot(i).hash);
+        if frequency(candidate) > frequency(victim) {
+            slots.move_to(candidate, PROBATION);
+            victim
+        } else {
+            candidate
+        }
+    }
+}
+
+/// Count-min sketch of how often keys were looked up: 4 rows of counters
+/// saturating at 15, all halved every `10 * capacity` records so that
+/// past popularity fades.
+struct Sketch {
+    counters: Box<[u8]>,
+    mask: usize,
+    records: usize,
+    period: usize,
+}
+
+/// Odd multipliers deriving each row's index from the key hash.
+const SKETCH_SEEDS: [u64; 4] = [
+    0x9e37_79b9_7f4a_7c15,
+    0xc2b2_ae3d_27d4_eb4f,
+    0x1656_67b1_9e37_79f9,
+    0x85eb_ca77_c2b2_ae63,
+];
+
+impl Sketch {
+    fn new(capacity: usize) -> Self {
+        let width = capacity.clamp(256, 1 << 16).next_power_of_two();
+        Self {
+            counters: vec![0; width * SKETCH_SEEDS.len()].into(),
+            mask: width - 1,
+            records: 0,
+            period: capacity.saturating_mul(10).max(16),
+        }
+    }
+
+    fn cells(&self, hash: u64) -> [usize; 4] {
+        let width = self.mask + 1;
+        std::array::from_fn(|row| {
+            row * width + ((hash.wrapping_mul(SKETCH_SEEDS[row]) >> 32) as usize & self.mask)
+        })
+    }
+
+    fn record(&mut self, hash: u64) {
+        for cell in self.cells(hash) {
+            let counter = &mut self.counters[cell];
+            *counter = (*counter + 1).min(15);
+        }
+        self.records += 1;
+        if self.records >= self.period {
+            self.counters.iter_mut().for_each(|c| *c /= 2);
+            self.records /= 2;
+        }
+    }
+
+    fn frequency(&self, hash: u64) -> u8 {
+        self.cells(hash)
+            .into_iter()
+            .map(|cell| self.counters[cell])
+            .min()
+            .unwrap_or(0)
+    }
+}
+
+/// One lock's worth of entries: a map from key to slot plus the lists of
+/// its [`Policy`], so lookups, inserts and evictions are all O(1).
+struct Shard {
+    index: HashMap<Arc<[u8]>, usize>,
+    slots: Slots,
+    policy: Box<dyn Policy>,
+}
+
+impl Shard {
+    fn new(eviction: Eviction, capacity: usize) -> Self {
+        Self {
+            index: HashMap::new(),
+            slots: Slots::default(),
+            policy: eviction.policy(capacity),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    fn remove(&mut self, i: usize) -> Slot {
+        let slot = self.slots.remove(i);
+        self.index.remove(&slot.key);
+        slot
+    }
+
+    /// Fresh data for `key`; an expired entry is dropped.
+    fn get(&mut self, key: &[u8], hash: u64, now: Instant) -> Option<Arc<[u8]>> {
+        let Some(&i) = self.index.get(key) else {
+            self.policy.miss(hash);
+            return None;
+        };
+        if !self.slots.slot(i).entry.fresh(now) {
+            self.remove(i);
+            self.policy.miss(hash);
+            return None;
+        }
+        self.policy.hit(&mut self.slots, i);
+        Some(self.slots.slot(i).entry.data.clone())
+    }
+
+    /// Store `entry`, returning the evicted entry if the shard was full.
+    fn insert(
+        &mut self,
+        key: Box<[u8]>,
+        hash: u64,
+        entry: Entry,
+        capacity: usize,
+    ) -> Option<Entry> {
+        if let Some(&i) = self.index.get(&*key) {
+            self.slots.slot_mut(i).entry = entry;
+            return None;
+        }
+        let evicted = if self.len() >= capacity {
+            let victim = self.policy.victim(&mut self.slots);
+            Some(self.remove(victim).entry)
+        } else {
+            None
+        };
+        let key: Arc<[u8]> = key.into();
+        let i = self.slots.add(Slot {
+            key: key.clone(),
+            hash,
+            entry,
+            prev: NIL,
+            next: NIL,
+            segment: PROBATION,
+        });
+        self.index.insert(key, i);
+        self.policy.insert(&mut self.slots, i);
+        evicted
+    }
+
+    /// Keep only the entries `keep` returns true for, returning how many
+    /// were dropped.
+    fn retain(&mut self, mut keep: impl FnMut(&[u8], &Entry) -> bool) -> usize {
+        let mut dropped = 0;
+        for i in 0..self.slots.slots.len() {
+            if let Some(s) = &self.slots.slots[i]
+                && !keep(&s.key, &s.entry)
+            {
+                self.remove(i);
+                dropped += 1;
+            }
+        }
+        dropped
+    }
+
+    /// Drop all entries; the policy's own state (lookup frequencies) stays.
+    fn clear(&mut self) {
+        self.index.clear();
+        self.slots = Slots::default();
+    }
+}
+
+struct Inner {
+    ttl: Duration,
+    eviction: Eviction,
+    /// Capacity of each shard.
+    shard_entries: usize,
+    shards: Box<[Mutex<Shard>]>,
+    hasher: RandomState,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+/// Snapshot of cache counters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+    /// Fresh responses dropped to make room for new ones.
+    pub evictions: u64,
+}
+
+impl CacheStats {
+    /// Share of lookups served from the cache, 0.0 before the first one.
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
+/// The cache of one route, from [`Router::cache_stats`](crate::router::Router::cache_stats).
+#[derive(Debug, Clone)]
+pub struct RouteCacheStats {
+    pub method: Method,
+    pub path: String,
+    pub stats: CacheStats,
+    /// Entries held by each shard.
+    pub shard_entries: Vec<usize>,
+}
+
+/// TTL-bounded response cache. Clone to share between routes and the app.
+#[derive(Clone)]
+pub struct ResponseCache {
+    inner: Arc<Inner>,
+}
+
+impl ResponseCache {
+    /// Cache up to `max_entries` responses, each fresh for `ttl`.
+    /// `Duration::MAX` keeps them until evicted or invalidated.
+    pub fn new(ttl: Duration, max_entries: usize) -> Self {
+        Self::with_shards(ttl, max_entries, 1)
+    }
+
+    /// Like [`new`](Self::new), spreading entries over `shards` locks
+    /// (at least 1). Each shard holds `max_entries / shards` responses,
+    /// rounded up, and evicts on its own once full.
+    pub fn with_shards(ttl: Duration, max_entries: usize, shards: usize) -> Self {
+        Self::with_eviction(ttl, max_entries, shards, Eviction::default())
+    }
+
+    /// Like [`with_shards`](Self::with_shards), choosing which entry a full
+    /// shard drops (default: [`Eviction::Lru`]).
+    pub fn with_eviction(
+        ttl: Duration,
+        max_entries: usize,
+        shards: usize,
+        eviction: Eviction,
+    ) -> Self {
+        let shards = shards.max(1);
+        let shard_entries = max_entries.div_ceil(shards);
+        Self {
+            inner: Arc::new(Inner {
+                ttl,
+                eviction,
+                shard_entries,
+                shards: (0..shards)
+                    .map(|_| Mutex::new(Shard::new(eviction, shard_entries)))
+                    .collect(),
+                hasher: RandomState::new(),
+                hits: AtomicU64::new(0),
+                misses: AtomicU64::new(0),
+                evictions: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    pub fn eviction(&self) -> Eviction {
+        self.inner.eviction
+    }
+
+    /// The shard a key with this hash lives in.
+    fn shard(&self, hash: u64) -> MutexGuard<'_, Shard> {
+        let shards = &self.inner.shards;
+        let shard = &shards[hash as usize % shards.len()];
+        shard.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Every shard, locked one at a time.
+    fn each_shard(&self, mut f: impl FnMut(&mut Shard)) {
+        for shard in &self.inner.shards {
+            f(&mut shard.lock().unwrap_or_else(|e| e.into_inner()));
+        }
+    }
+
+    /// Build the lookup key: method, encoding, then the full request target.
+    pub(crate) fn key(method: Method, encoding: Encoding, target: &[u8]) -> Box<[u8]> {
+        let mut key = Vec::with_capacity(target.len() + 2);
+        key.push(method as u8);
+        key.push(encoding as u8);
+        key.extend_from_slice(target);
+        key.into_boxed_slice()
+    }
+
+    /// Fresh cached response for `key`, counting the hit or miss. An
+    /// expired entry found on the way is dropped.
+    pub(crate) fn get(&self, key: &[u8]) -> Option<Arc<[u8]>> {
+        let inner = &*self.inner;
+        let hash = inner.hasher.hash_one(key);
+        let found = self.shard(hash).get(key, hash, Instant::now());
+        let counter = if found.is_some() {
+            &inner.hits
+        } else {
+            &inner.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        found
+    }
+
+    /// Store a response. When its shard is full, the entry chosen by the
+    /// eviction policy makes room.
+    pub(crate) fn put(&self, key: Box<[u8]>, data: &[u8]) {
+        let capacity = self.inner.shard_entries;
+        if capacity == 0 {
+            return;
+        }
+        let now = Instant::now();
+        let entry = Entry {
+            data: data.into(),
+            expires: now.checked_add(self.inner.ttl),
+        };
+        let hash = self.inner.hasher.hash_one(&key);
+        let evicted = self.shard(hash).insert(key, hash, entry, capacity);
+        if evicted.is_some_and(|e| e.fresh(now)) {
+            self.inner.evictions.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Drop every cached variant of `path` (any method, encoding or query).
+    pub fn invalidate(&self, path: &str) {
+        let path = path.as_bytes();
+        self.each_shard(|entries| {
+            entries.retain(|key, _| {
+                let target = &key[2..];
+                let key_path = target
+                    .iter()
+                    .position(|&b| b == b'?')
+                    .map_or(target, |q| &target[..q]);
+                key_path != path
+            });
+        });
+    }
+
+    /// Drop every expired entry, returning how many. Expired entries are
+    /// never served and make room when the cache fills up; this frees
+    /// their memory earlier, e.g. from a periodic task.
+    pub fn purge_expired(&self) -> usize {
+        let now = Instant::now();
+        let mut purged = 0;
+        self.each_shard(|entries| purged += entries.retain(|_, e| e.fresh(now)));
+        purged
+    }
+
+    /// Drop all entries.
+    pub fn clear(&self) {
+        self.each_shard(Shard::clear);
+    }
+
+    /// Whether both handles point at the same cache.
+    pub(crate) fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let mut entries = 0;
+        self.each_shard(|shard| entries += shard.len());
+        CacheStats {
+            hits: self.inner.hits.load(Ordering::Relaxed),
+            misses: self.inner.misses.load(Ordering::Relaxed),
+            entries,
+            evictions: self.inner.evictions.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Zero the hit, miss and eviction counters; entries are kept.
+    pub fn reset_stats(&self) {
+        self.inner.hits.store(0, Ordering::Relaxed);
+        self.inner.misses.store(0, Ordering::Relaxed);
+        self.inner.evictions.store(0, Ordering::Relaxed);
+    }
+
+    /// Entries held by each shard, to check they are spread evenly.
+    pub fn shard_entries(&self) -> Vec<usize> {
+        let mut sizes = Vec::with_capacity(self.inner.shards.len());
+        self.each_shard(|shard| sizes.push(shard.len()));
+        sizes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hit_miss_and_invalidate() {
+        let cache = ResponseCache::new(Duration::from_secs(60), 2);
+        let a = ResponseCache::key(Method::Get, Encoding::Identity, b"/a?x=1");
+        let b = ResponseCache::key(Method::Get, Encoding::Gzip, b"/b");
+
+        assert!(cache.get(&a).is_none());
+        cache.put(a.clone(), b"resp-a");
+        cache.put(b.clone(), b"resp-b");
+        assert_eq!(&*cache.get(&a).unwrap(), b"resp-a");
+
+        cache.invalidate("/a");
+        assert!(cache.get(&a).is_none());
+        assert!(cache.get(&b).is_some());
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 2,
+                misses: 2,
+                entries: 1,
+                evictions: 0,
+            }
+        );
+        assert_eq!(cache.stats().hit_rate(), 0.5);
+
+        // Full: a new response evicts the fresh one.
+        cache.put(
+            ResponseCache::key(Method::Get, Encoding::Identity, b"/c"),
+            b"c",
+        );
+        cache.put(
+            ResponseCache::key(Method::Get, Encoding::Identity, b"/d"),
+            b"d",
+        );
+        assert_eq!(cache.stats().evictions, 1);
+
+        cache.reset_stats();
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.evictions), (0, 0, 0));
+        assert_eq!(stats.entries, 2);
+    }
+
+    #[test]
+    fn shards_split_capacity() {
+        let cache = ResponseCache::with_shards(Duration::from_secs(60), 64, 4);
+        let keys: Vec<_> = (0..256)
+            .map(|i| {
+                ResponseCache::key(Method::Get, Encoding::Identity, format!("/{i}").as_bytes())
+            })
+            .collect();
+        for key in &keys {
+            cache.put(key.clone(), b"x");
+        }
+        // Each shard stops at 16, so never more than 64 in total.
+        let held = cache.stats().entries;
+        assert!(held <= 64 && held > 16, "{held}");
+        let shards = cache.shard_entries();
+        assert_eq!(shards.len(), 4);
+        assert!(shards.iter().all(|&n| n <= 16));
+        assert_eq!(shards.iter().sum::<usize>(), held);
+        assert!(cache.get(keys.last().unwrap()).is_some());
+
+        cache.invalidate("/255");
+        assert!(cache.get(keys.last().unwrap()).is_none());
+        cache.clear();
+        assert_eq!(cache.stats().entries, 0);
+    }
+
+    fn keys(n: usize) -> Vec<Box<[u8]>> {
+        (0..n)
+            .map(|i| {
+                ResponseCache::key(Method::Get, Encoding::Identity, format!("/{i}").as_bytes())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn lru_keeps_recently_read() {
+        let cache = ResponseCache::new(Duration::from_secs(60), 3);
+        let k = keys(5);
+        for key in &k[..3] {
+            cache.put(key.clone(), b"x");
+        }
+        assert!(cache.get(&k[0]).is_some());
+        cache.put(k[3].clone(), b"x");
+        // 1 was the least recently used.
+        assert!(cache.get(&k[1]).is_none());
+        assert!(cache.get(&k[0]).is_some());
+        assert!(cache.get(&k[2]).is_some());
+        assert_eq!(cache.stats().evictions, 1);
+
+        // Slots freed by eviction and invalidation are reused.
+        cache.invalidate("/2");
+        cache.put(k[4].clone(), b"x");
+        assert_eq!(cache.stats().entries, 3);
+    }
+
+    #[test]
+    fn segmented_lru_and_tiny_lfu_survive_scans() {
+        let hot = ResponseCache::key(Method::Get, Encoding::Identity, b"/hot");
+        let scan = keys(20);
+        for (eviction, kept) in [
+            (Eviction::Lru, false),
+            (Eviction::SegmentedLru, true),
+            (Eviction::TinyLfu, true),
+        ] {
+            let cache = ResponseCache::with_eviction(Duration::from_secs(60), 5, 1, eviction);
+            assert_eq!(cache.eviction(), eviction);
+            cache.put(hot.clone(), b"hot");
+            for _ in 0..3 {
+                assert!(cache.get(&hot).is_some());
+            }
+            for key in &scan {
+                assert!(cache.get(key).is_none());
+                cache.put(key.clone(), b"x");
+            }
+            assert_eq!(cache.get(&hot).is_some(), kept, "{eviction:?}");
+            assert_eq!(cache.stats().entries, 5);
+        }
+    }
+
+    #[test]
+    fn tiny_lfu_admits_by_frequency() {
+        let popular = ResponseCache::key(Method::Get, Encoding::Identity, b"/popular");
+        let k = keys(6);
+        for (eviction, kept) in [(Eviction::Lru, false), (Eviction::TinyLfu, true)] {
+            let cache = ResponseCache::with_eviction(Duration::from_secs(60), 2, 1, eviction);
+            cache.put(k[0].clone(), b"x");
+            cache.put(k[1].clone(), b"x");
+            // Asked for often while it was not cached.
+            for _ in 0..3 {
+                assert!(cache.get(&popular).is_none());
+            }
+            cache.put(popular.clone(), b"popular");
+            for key in &k[2..] {
+                cache.put(key.clone(), b"x");
+            }
+            assert_eq!(cache.get(&popular).is_some(), kept, "{eviction:?}");
+            assert_eq!(cache.stats().entries, 2);
+        }
+    }
+
+    #[test]
+    fn sketch_counts_and_ages() {
+        let mut sketch = Sketch::new(16);
+        for _ in 0..20 {
+            sketch.record(7);
+        }
+        // Saturates at 15.
+        assert_eq!(sketch.frequency(7), 15);
+        assert_eq!(sketch.frequency(8), 0);
+        // Halved every 160 records.
+        for hash in 100..400 {
+            sketch.record(hash);
+        }
+        assert!(sketch.frequency(7) < 4, "{}", sketch.frequency(7));
+    }
+
+    #[test]
+    fn expired_entries_miss() {
+        let cache = ResponseCache::new(Duration::ZERO, 8);
+        let key = ResponseCache::key(Method::Get, Encoding::Identity, b"/");
+        cache.put(key.clone(), b"stale");
+        assert!(cache.get(&key).is_none());
+        assert_eq!(cache.stats().entries, 0);
+
+        cache.put(key.clone(), b"stale");
+        cache.put(
+            ResponseCache::key(Method::Head, Encoding::Identity, b"/"),
+            b"stale",
+        );
+        assert_eq!(cache.purge_expired(), 2);
+    }
+
+    #[test]
+    fn max_ttl_never_expires() {
+        let cache = ResponseCache::new(Duration::MAX, 8);
+        let key = ResponseCache::key(Method::Get, Encoding::Identity, b"/");
+        cache.put(key.clone(), b"kept");
+        assert_eq!(cache.purge_expired(), 0);
+        assert_eq!(&*cache.get(&key).unwrap(), b"kept");
+    }
+}
@@ -0,0 +1,129 @@
+//! Request cancellation.
+//!
+//! [`token`] returns a [`CancelToken`] for the request being handled. It
+//! fires when the client disconnects before the response is written, so a
+//! long-running handler can stop working for nobody:
+//!
+//! ```ignore
+//! router.get("/report", async_handler(|_| {
+//!     let cancel = tachyon_core::cancel::token();
+//!     async move {
+//!         tokio::select! {
+//!             report = build_report() => report,
+//!             () = cancel.cancelled() => String::new(), // never sent
+//!         }
+//!     }
+//! }));
+//! ```
+//!
+//! The token is also an extractor (`async fn h(cancel: CancelToken)`).
+//! Take it before spawning work or moving to `spawn_blocking`: it is
+//! looked up from the task serving the request.
+//!
+//! The connection is only watched once a handler has asked for the token,
+//! so handlers that never do pay nothing. While watching, bytes the client
+//! sends ahead (a pipelined request) are kept for after the response.
+//! A client that half-closes its side after sending the request counts as
+//! gone. Tokens never fire for `TachyonService` (the embedding server drops
+//! the handler instead) or on the io_uring backend.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tokio::sync::{Notify, watch};
+
+tokio::task_local! {
+    static CURRENT: CancelToken;
+}
+
+/// Fires when the client of a request goes away. Clones share the state.
+#[derive(Clone, Debug)]
+pub struct CancelToken(Arc<Inner>);
+
+#[derive(Debug)]
+struct Inner {
+    cancelled: watch::Sender<bool>,
+    /// Set once someone asked for the token; the server starts watching.
+    wanted: AtomicBool,
+    asked: Notify,
+}
+
+impl CancelToken {
+    pub(crate) fn new() -> Self {
+        Self(Arc::new(Inner {
+            cancelled: watch::Sender::new(false),
+            wanted: AtomicBool::new(false),
+            asked: Notify::new(),
+        }))
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        *self.0.cancelled.borrow()
+    }
+
+    /// Resolves once the client has gone away.
+    pub async fn cancelled(&self) {
+        let mut rx = self.0.cancelled.subscribe();
+        let _ = rx.wait_for(|&cancelled| cancelled).await;
+    }
+
+    pub(crate) fn cancel(&self) {
+        self.0.cancelled.send_replace(true);
+    }
+
+    fn want(&self) {
+        if !self.0.wanted.swap(true, Ordering::Relaxed) {
+            self.0.asked.notify_one();
+        }
+    }
+
+    /// Resolves once a handler has asked for the token.
+    pub(crate) async fn wanted(&self) {
+        if !self.0.wanted.load(Ordering::Relaxed) {
+            self.0.asked.notified().await;
+        }
+    }
+
+    /// Run `fut` with this token as the current one.
+    pub(crate) async fn scope<F: Future>(&self, fut: F) -> F::Output {
+        CURRENT.scope(self.clone(), fut).await
+    }
+}
+
+/// The token of the request being handled. Outside of a request (a handler
+/// called directly, e.g. in tests) it is a token that never fires.
+pub fn token() -> CancelToken {
+    CURRENT
+        .try_with(|token| {
+            token.want();
+            token.clone()
+        })
+        .unwrap_or_else(|_| CancelToken::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn fires_for_the_scoped_request_only() {
+        crate::testing::block_on(async {
+            assert!(!token().is_cancelled());
+
+            let conn = CancelToken::new();
+            let handler = conn.scope(async {
+                let cancel = token();
+                tokio::time::timeout(Duration::from_secs(5), cancel.cancelled())
+                    .await
+                    .is_ok()
+            });
+            let watcher = async {
+                conn.wanted().await;
+                conn.cancel();
+            };
+            let (fired, ()) = tokio::join!(handler, watcher);
+            assert!(fired && conn.is_cancelled());
+        });
+    }
+}
@@ -2,7 +2,7 @@
 //!
 //! Inspired by FaF's approach: few knobs, all performance-relevant.
 
-use std::time::Duration;
+use std::{collections::HashMap, fmt, sync::Arc, time::Duration};
 
 /// Socket-level tuning options.
 ///
@@ -34,6 +34,23 @@ pub struct SocketConfig {
 
     /// SO_SNDBUF override in bytes. 0 = OS default.
     pub send_buf_size: i32,
+
+    /// Listen backlog: connections the kernel queues before they are
+    /// accepted (default: 1024). The kernel caps it at `somaxconn`.
+    pub backlog: u32,
+
+    /// TCP keepalive: idle time before the first probe and the interval
+    /// between probes. Accepted sockets inherit it from the listener, so
+    /// dead peers are detected even while a request is in flight.
+    /// `None` (default) = OS default (usually off).
+    pub keepalive: Option<(Duration, Duration)>,
+
+    /// IPV6_V6ONLY for IPv6 bind addresses. `false` makes `[::]:port`
+    /// dual-stack: it also accepts IPv4 clients, whose addresses are then
+    /// reported as plain IPv4. `true` serves IPv6 only, so `0.0.0.0:port`
+    /// can be bound next to it. `None` (default) = OS default (dual-stack
+    /// on Linux and macOS, IPv6-only on Windows and OpenBSD).
+    pub ipv6_only: Option<bool>,
 }
 
 impl Default for SocketConfig {
@@ -45,6 +62,9 @@ impl Default for SocketConfig {
             busy_poll_us: 0,
             recv_buf_size: 0,
             send_buf_size: 0,
+            backlog: 1024,
+            keepalive: None,
+            ipv6_only: None,
         }
     }
 }
@@ -59,6 +79,154 @@ impl SocketConfig {
             busy_poll_us: 0,
             recv_buf_size: 0,
             send_buf_size: 0,
+            backlog: 1024,
+            keepalive: None,
+            ipv6_only: None,
+        }
+    }
+}
+
+/// A replacement body for a server-generated error response.
+#[derive(Debug, Clone)]
+pub struct ErrorPage {
+    pub content_type: String,
+    pub body: Arc<[u8]>,
+}
+
+/// Custom bodies for the errors the server answers on its own
+/// (400, 404, 405, 413, 415, 431, 500, 504, …). Statuses without an entry use the
+/// built-in JSON bodies.
+#[derive(Debug, Clone, Default)]
+pub struct ErrorPages {
+    pages: HashMap<u16, ErrorPage>,
+}
+
+impl ErrorPages {
+    pub fn set(&mut self, status: u16, content_type: &str, body: impl Into<Arc<[u8]>>) {
+        self.pages.insert(
+            status,
+            ErrorPage {
+                content_type: content_type.to_string(),
+                body: body.into(),
+            },
+        );
+    }
+
+    #[inline]
+    pub fn get(&self, status: u16) -> Option<&ErrorPage> {
+        self.pages.get(&status)
+    }
+
+    /// Built-in body for a server-generated error.
+    pub fn default_body(status: u16) -> &'static [u8] {
+        match status {
+            400 => b"{\"error\":\"bad request\"}",
+            403 => b"{\"error\":\"forbidden\"}",
+            404 => b"{\"error\":\"not found\"}",
+            405 => b"{\"error\":\"method not allowed\"}",
+            408 => b"{\"error\":\"request timeout\"}",
+            413 => b"{\"error\":\"payload too large\"}",
+            415 => b"{\"error\":\"unsupported media type\"}",
+            431 => b"{\"error\":\"request header fields too large\"}",
+            503 => b"{\"error\":\"service unavailable\"}",
+            504 => b"{\"error\":\"timeout\"}",
+            _ => b"{\"error\":\"internal\"}",
+        }
+    }
+}
+
+/// The Tokio runtime the server builds for itself.
+#[derive(Debug, Clone)]
+pub struct RuntimeConfig {
+    /// 1 (default) = single-threaded runtime on the calling thread, the
+    /// fastest setup for short handlers. More spreads connections over a
+    /// work-stealing pool; 0 = one worker per core.
+    pub worker_threads: usize,
+    /// Upper bound on threads for `spawn_blocking` work (default: 512).
+    pub max_blocking_threads: usize,
+    /// Name given to runtime threads (default: "tachyon-worker").
+    pub thread_name: String,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            worker_threads: 1,
+            max_blocking_threads: 512,
+            thread_name: "tachyon-worker".to_string(),
+        }
+    }
+}
+
+/// Per-connection I/O timeouts. `Duration::MAX` disables one.
+///
+/// When a read timeout fires mid-request the client gets a 408 and the
+/// connection is closed; an idle keep-alive connection, or one that never
+/// sent a byte, is closed silently.
+///
+/// `header_read` plus the minimum transfer rate stop slowloris clients that
+/// trickle a request a few bytes at a time to hold the connection open.
+#[derive(Debug, Clone)]
+pub struct TimeoutConfig {
+    /// From the start of a request (or accept, for the first one) until its
+    /// headers are complete (default: 10s). Bounds slow-header clients.
+    pub header_read: Duration,
+    /// From the end of the headers until the body is complete (default: 30s).
+    pub body_read: Duration,
+    /// Writing one response (default: 30s). Bounds clients that stop reading.
+    pub write: Duration,
+    /// Keep-alive: how long to wait for the next request (default: 60s).
+    pub idle: Duration,
+    /// Minimum average receive rate for a request, in bytes per second,
+    /// measured from its start (default: 240). 0 disables the check.
+    pub min_rate: u32,
+    /// Time before `min_rate` is enforced (default: 5s).
+    pub min_rate_grace: Duration,
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        Self {
+            header_read: Duration::from_secs(10),
+            body_read: Duration::from_secs(30),
+            write: Duration::from_secs(30),
+            idle: Duration::from_secs(60),
+            min_rate: 240,
+            min_rate_grace: Duration::from_secs(5),
+        }
+    }
+}
+
+/// HTTP/1 connection behaviour. The request size limit is
+/// [`ServerConfig::buffer_size`].
+///
+/// There is no header casing option: the server writes its own headers in
+/// Title-Case and those set by handlers exactly as given, which is what
+/// hyper's `title_case_headers` plus `preserve_header_case` would produce.
+#[derive(Debug, Clone)]
+pub struct Http1Config {
+    /// Keep connections open between requests (default: true). When off,
+    /// every response carries `Connection: close`.
+    pub keep_alive: bool,
+    /// Close a connection after this many requests, with `Connection: close`
+    /// on the last response. 0 (default) = unlimited.
+    pub max_requests: usize,
+    /// Gather the responses to pipelined requests that arrived together and
+    /// send them in one write (default: false). Fewer syscalls for
+    /// pipelining clients; no effect on others.
+    pub pipeline_flush: bool,
+    /// Header count, header line and request target limits. Requests over
+    /// them get 431 or 414. The head must also fit in `buffer_size`.
+    pub limits: tachyon_http::parser::Limits,
+}
+
+impl Default for Http1Config {
+    fn default() -> Self {
+        Self {
+            keep_alive: true,
+            max_requests: 0,
+            pipeline_flush: false,
+            limits: tachyon_http::parser::Limits::default(),
         }
     }
 }
@@ -69,6 +237,30 @@ pub struct ServerConfig {
     /// Address to bind (default: "0.0.0.0:3000")
     pub bind_addr: String,
 
+    /// More addresses served by the same server, e.g. `"[::1]:3000"` or a
+    /// second port. Each gets its own listener and accept loop.
+    pub extra_binds: Vec<String>,
+
+    /// Acceptor threads (default: 1). Each runs its own single-threaded
+    /// runtime with its own SO_REUSEPORT listener per address, so the kernel
+    /// balances connections across cores. Unix only; elsewhere one is used.
+    pub acceptors: usize,
+
+    /// Runtime built by `Server::serve` (and by each extra acceptor).
+    pub runtime: RuntimeConfig,
+
+    /// Serve through io_uring instead of epoll (default: false). Needs Linux
+    /// and the `io-uring` feature; otherwise the epoll path is used. Each
+    /// acceptor is then one single-threaded io_uring runtime, so scale with
+    /// `acceptors` rather than `worker_threads`. Only `Server::serve`
+    /// honours it.
+    ///
+    /// It is not faster out of the box. With `benches/backends.rs` on a
+    /// one-vCPU VM, client on the same core, it was 15–30% slower than
+    /// epoll with one connection and within 10% either way with 16 or 64.
+    /// Measure on the target hardware before turning it on.
+    pub io_uring: bool,
+
     /// Buffer pool: number of pre-allocated buffers.
     /// Higher = more memory upfront, fewer allocation misses under load.
     pub pool_buffers: usize,
@@ -79,9 +271,17 @@ pub struct ServerConfig {
 
     /// Maximum time a handler can run before being considered stuck.
     /// The safety layer uses this to prevent hung coroutines from
-    /// blocking the worker thread forever.
+    /// blocking the worker thread forever: past it the handler is dropped
+    /// and the client gets a 504. Routes can override it with
+    /// `Router::deadline`; `Duration::MAX` disables it.
     pub handler_timeout: Duration,
 
+    /// Read, write and keep-alive timeouts for each connection.
+    pub timeouts: TimeoutConfig,
+
+    /// Keep-alive, per-connection request cap and pipelining.
+    pub http1: Http1Config,
+
     /// Whether to catch panics in handlers (recommended for library use).
     /// FaF doesn't need this (standalone server), but we do.
     pub catch_panics: bool,
@@ -94,23 +294,81 @@ pub struct ServerConfig {
     /// Default: Basic (essential headers with minimal overhead).
     pub security: tachyon_http::response::SecurityPreset,
 
-    /// Minimum body size in bytes to trigger gzip compression.
-    /// Only compresses when the client sends Accept-Encoding: gzip.
+    /// Minimum body size in bytes to trigger compression.
+    /// The encoding (zstd > br > gzip, as compiled in) is negotiated from
+    /// Accept-Encoding; already-compressed content types are never recompressed.
     /// 0 = compress all responses, usize::MAX = disabled. Default: 1024 (1KB).
     pub compression_threshold: usize,
+
+    /// ETag generation for buffered 2xx responses to GET/HEAD.
+    /// When enabled, a matching `If-None-Match` short-circuits to 304 with no body.
+    /// Default: Off (hashing the body costs a pass over every response).
+    pub etag: tachyon_http::etag::EtagMode,
+
+    /// Custom bodies for server-generated errors (404, 405, 413, 500, 504, …).
+    pub error_pages: ErrorPages,
+
+    /// CORS policy. `None` (default) sends no CORS headers and leaves
+    /// OPTIONS requests to the router.
+    pub cors: Option<crate::cors::Cors>,
+
+    /// Pretty-print JSON bodies, including error payloads (default: false).
+    /// Meant for development — keep it off in production.
+    pub pretty_json: bool,
+
+    /// Prometheus metrics and their scrape endpoint. `None` (default) records nothing.
+    pub metrics: Option<crate::metrics::Metrics>,
+
+    /// Access log writer. `None` (default) logs nothing.
+    pub access_log: Option<crate::access_log::AccessLog>,
+
+    /// CIDR allow/deny lists checked on accept. `None` (default) accepts everyone.
+    pub ip_filter: Option<crate::ip_filter::IpFilter>,
+
+    /// Overload protection: fast 503s for handler routes past its limits.
+    /// `None` (default) never sheds.
+    pub load_shed: Option<crate::shed::LoadShed>,
+
+    /// Cap on open connections. `None` (default) accepts without limit.
+    pub connection_limit: Option<crate::limit::ConnectionLimit>,
+
+    /// Start a graceful shutdown on SIGTERM / SIGINT (Ctrl-C on Windows).
+    /// Default: true. Turn off when the host process handles signals itself.
+    pub shutdown_signals: bool,
+
+    /// How long a graceful shutdown waits for open connections to finish
+    /// before closing them (default: 30s).
+    pub drain_timeout: Duration,
 }
 
 impl Default for ServerConfig {
     fn default() -> Self {
         Self {
             bind_addr: "0.0.0.0:3000".to_string(),
+            extra_binds: Vec::new(),
+            acceptors: 1,
+            runtime: RuntimeConfig::default(),
+            io_uring: false,
             pool_buffers: 32,
             buffer_size: 8 * 1024,
             handler_timeout: Duration::from_secs(30),
+            timeouts: TimeoutConfig::default(),
+            http1: Http1Config::default(),
             catch_panics: true,
             socket: SocketConfig::default(),
             security: tachyon_http::response::SecurityPreset::default(),
             compression_threshold: 1024,
+            etag: tachyon_http::etag::EtagMode::Off,
+            error_pages: ErrorPages::default(),
+            cors: None,
+            pretty_json: false,
+            metrics: None,
+            access_log: None,
+            ip_filter: None,
+            load_shed: None,
+            connection_limit: None,
+            shutdown_signals: true,
+            drain_timeout: Duration::from_secs(30),
         }
     }
 }
@@ -125,6 +383,40 @@ impl ServerConfig {
         self
     }
 
+    /// Also listen on `addr` (IPv4, `[IPv6]:port`, or `host:port`).
+    pub fn bind_also(mut self, addr: &str) -> Self {
+        self.extra_binds.push(addr.to_string());
+        self
+    }
+
+    /// Run `count` acceptors (typically one per core). See [`ServerConfig::acceptors`].
+    pub fn acceptors(mut self, count: usize) -> Self {
+        self.acceptors = count.max(1);
+        self
+    }
+
+    /// Worker threads for the server's runtime. See [`RuntimeConfig::worker_threads`].
+    pub fn worker_threads(mut self, count: usize) -> Self {
+        self.runtime.worker_threads = count;
+        self
+    }
+
+    /// Use the io_uring backend. See [`ServerConfig::io_uring`].
+    pub fn io_uring(mut self, enabled: bool) -> Self {
+        self.io_uring = enabled;
+        self
+    }
+
+    pub fn max_blocking_threads(mut self, count: usize) -> Self {
+        self.runtime.max_blocking_threads = count.max(1);
+        self
+    }
+
+    pub fn thread_name(mut self, name: &str) -> Self {
+        self.runtime.thread_name = name.to_string();
+        self
+    }
+
     pub fn buffer_pool(mut self, count: usize, size: usize) -> Self {
         self.pool_buffers = count;
         self.buffer_size = size;
@@ -136,6 +428,77 @@ impl ServerConfig {
         self
     }
 
+    pub fn timeouts(mut self, timeouts: TimeoutConfig) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    pub fn header_read_timeout(mut self, duration: Duration) -> Self {
+        self.timeouts.header_read = duration;
+        self
+    }
+
+    pub fn body_read_timeout(mut self, duration: Duration) -> Self {
+        self.timeouts.body_read = duration;
+        self
+    }
+
+    pub fn write_timeout(mut self, duration: Duration) -> Self {
+        self.timeouts.write = duration;
+        self
+    }
+
+    pub fn idle_timeout(mut self, duration: Duration) -> Self {
+        self.timeouts.idle = duration;
+        self
+    }
+
+    pub fn http1(mut self, http1: Http1Config) -> Self {
+        self.http1 = http1;
+        self
+    }
+
+    pub fn keep_alive(mut self, enabled: bool) -> Self {
+        self.http1.keep_alive = enabled;
+        self
+    }
+
+    pub fn max_requests_per_connection(mut self, requests: usize) -> Self {
+        self.http1.max_requests = requests;
+        self
+    }
+
+    pub fn pipeline_flush(mut self, enabled: bool) -> Self {
+        self.http1.pipeline_flush = enabled;
+        self
+    }
+
+    /// Most header fields per request (at most `MAX_HEADERS`, the default).
+    pub fn max_headers(mut self, count: usize) -> Self {
+        self.http1.limits.max_headers = count;
+        self
+    }
+
+    /// Longest header line, in bytes (default: 8 KiB).
+    pub fn max_header_size(mut self, bytes: usize) -> Self {
+        self.http1.limits.max_header_size = bytes;
+        self
+    }
+
+    /// Longest request target (path plus query), in bytes (default: 8 KiB).
+    pub fn max_uri_length(mut self, bytes: usize) -> Self {
+        self.http1.limits.max_uri = bytes;
+        self
+    }
+
+    /// Close requests arriving slower than `bytes_per_sec` once `grace` has
+    /// passed (see [`TimeoutConfig::min_rate`]).
+    pub fn min_receive_rate(mut self, bytes_per_sec: u32, grace: Duration) -> Self {
+        self.timeouts.min_rate = bytes_per_sec;
+        self.timeouts.min_rate_grace = grace;
+        self
+    }
+
     pub fn catch_panics(mut self, enabled: bool) -> Self {
         self.catch_panics = enabled;
         self
@@ -176,6 +539,23 @@ impl ServerConfig {
         self
     }
 
+    pub fn backlog(mut self, connections: u32) -> Self {
+        self.socket.backlog = connections;
+        self
+    }
+
+    /// Set IPV6_V6ONLY on IPv6 listeners. See [`SocketConfig::ipv6_only`].
+    pub fn ipv6_only(mut self, enabled: bool) -> Self {
+        self.socket.ipv6_only = Some(enabled);
+        self
+    }
+
+    /// Enable TCP keepalive probes: the first after `idle`, then every `interval`.
+    pub fn tcp_keepalive(mut self, idle: Duration, interval: Duration) -> Self {
+        self.socket.keepalive = Some((idle, interval));
+        self
+    }
+
     pub fn security(mut self, preset: tachyon_http::response::SecurityPreset) -> Self {
         self.security = preset;
         self
@@ -185,4 +565,179 @@ impl ServerConfig {
         self.compression_threshold = threshold_bytes;
         self
     }
+
+    pub fn etag(mut self, mode: tachyon_http::etag::EtagMode) -> Self {
+        self.etag = mode;
+        self
+    }
+
+    /// Enable CORS with `policy`; preflights are answered automatically.
+    pub fn cors(mut self, policy: crate::cors::Cors) -> Self {
+        self.cors = Some(policy);
+        self
+    }
+
+    /// Record metrics into `metrics` and serve them at its path, unless a
+    /// route is registered there.
+    pub fn metrics(mut self, metrics: crate::metrics::Metrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Log every request to `log` (see [`AccessLog::builder`](crate::access_log::AccessLog::builder)).
+    pub fn access_log(mut self, log: crate::access_log::AccessLog) -> Self {
+        self.access_log = Some(log);
+        self
+    }
+
+    pub fn ip_filter(mut self, filter: crate::ip_filter::IpFilter) -> Self {
+        self.ip_filter = Some(filter);
+        self
+    }
+
+    pub fn load_shed(mut self, policy: crate::shed::LoadShed) -> Self {
+        self.load_shed = Some(policy);
+        self
+    }
+
+    /// Limit open connections (see [`ConnectionLimit`](crate::limit::ConnectionLimit)).
+    pub fn connection_limit(mut self, limit: crate::limit::ConnectionLimit) -> Self {
+        self.connection_limit = Some(limit);
+        self
+    }
+
+    pub fn shutdown_signals(mut self, enabled: bool) -> Self {
+        self.shutdown_signals = enabled;
+        self
+    }
+
+    pub fn drain_timeout(mut self, timeout: Duration) -> Self {
+        self.drain_timeout = timeout;
+        self
+    }
+
+    pub fn pretty_json(mut self, enabled: bool) -> Self {
+        self.pretty_json = enabled;
+        self
+    }
+
+    /// Replace the body the server sends for `status` when it generates
+    /// that error itself (unmatched route, panic, oversized request, …).
+    pub fn error_page(
+        mut self,
+        status: u16,
+        content_type: &str,
+        body: impl Into<Arc<[u8]>>,
+    ) -> Self {
+        self.error_pages.set(status, content_type, body);
+        self
+    }
+}
+
+/// A [`ServerConfig`] value outside its valid range; see
+/// [`ServerConfig::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidConfig(pub String);
+
+impl fmt::Display for InvalidConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid server config: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidConfig {}
+
+impl ServerConfig {
+    /// Smallest and largest accepted `buffer_size`.
+    pub const BUFFER_SIZE_RANGE: std::ops::RangeInclusive<usize> = 1024..=64 * 1024 * 1024;
+
+    /// Check that every setting is in range. `Server::serve` calls this and
+    /// refuses to start with [`TachyonError::Config`](crate::error::TachyonError::Config)
+    /// otherwise.
+    pub fn validate(&self) -> Result<(), InvalidConfig> {
+        let fail = |msg: String| Err(InvalidConfig(msg));
+        if self.bind_addr.is_empty() {
+            return fail("bind_addr is empty".into());
+        }
+        if self.acceptors == 0 {
+            return fail("acceptors must be at least 1".into());
+        }
+        if !Self::BUFFER_SIZE_RANGE.contains(&self.buffer_size) {
+            return fail(format!(
+                "buffer_size {} is outside {}..={} bytes",
+                self.buffer_size,
+                Self::BUFFER_SIZE_RANGE.start(),
+                Self::BUFFER_SIZE_RANGE.end()
+            ));
+        }
+        if !(1..=65535).contains(&self.socket.backlog) {
+            return fail(format!(
+                "backlog {} is outside 1..=65535",
+                self.socket.backlog
+            ));
+        }
+        let limits = &self.http1.limits;
+        let max_headers = tachyon_http::http::MAX_HEADERS;
+        if !(1..=max_headers).contains(&limits.max_headers) {
+            return fail(format!(
+                "max_headers {} is outside 1..={max_headers}",
+                limits.max_headers
+            ));
+        }
+        if limits.max_header_size == 0 || limits.max_uri == 0 {
+            return fail("header size and URI limits must be non-zero".into());
+        }
+        if self.socket.recv_buf_size < 0 || self.socket.send_buf_size < 0 {
+            return fail("socket buffer sizes cannot be negative".into());
+        }
+        if let Some((idle, interval)) = self.socket.keepalive
+            && (idle.is_zero() || interval.is_zero())
+        {
+            return fail("TCP keepalive idle and interval must be non-zero".into());
+        }
+        let timeouts = [
+            ("handler_timeout", self.handler_timeout),
+            ("header_read timeout", self.timeouts.header_read),
+            ("body_read timeout", self.timeouts.body_read),
+            ("write timeout", self.timeouts.write),
+            ("idle timeout", self.timeouts.idle),
+        ];
+        if let Some((name, _)) = timeouts.iter().find(|(_, t)| t.is_zero()) {
+            return fail(format!("{name} is zero (use Duration::MAX to disable it)"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_ranges() {
+        let config = ServerConfig::new();
+        assert_eq!(config.validate(), Ok(()));
+        assert_eq!(config.socket.backlog, 1024);
+        assert!(ServerConfig::new().buffer_pool(32, 512).validate().is_err());
+        assert!(ServerConfig::new().backlog(0).validate().is_err());
+        assert!(ServerConfig::new().backlog(70_000).validate().is_err());
+        assert!(ServerConfig::new().max_headers(100).validate().is_err());
+        assert!(ServerConfig::new().max_uri_length(0).validate().is_err());
+        let mut config = ServerConfig::new();
+        config.acceptors = 0;
+        assert!(config.validate().is_err());
+        assert!(
+            ServerConfig::new()
+                .idle_timeout(Duration::ZERO)
+                .validate()
+                .is_err()
+        );
+        assert!(
+            ServerConfig::new()
+                .tcp_keepalive(Duration::from_secs(60), Duration::from_secs(10))
+                .idle_timeout(Duration::MAX)
+                .validate()
+                .is_ok()
+        );
+    }
 }
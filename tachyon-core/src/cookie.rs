@@ -0,0 +1,171 @@
+//! `Set-Cookie` headers (RFC 6265).
+//!
+//! ```ignore
+//! let session = Cookie::new("session", token)
+//!     .path("/")
+//!     .max_age(Duration::from_secs(3600))
+//!     .http_only(true)
+//!     .secure(true)
+//!     .same_site(SameSite::Lax);
+//! res.set_cookie(&session);
+//! res.json(200, b"{}")
+//! ```
+
+use std::time::Duration;
+
+/// The `SameSite` attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    /// Also sets `Secure`, which browsers require for it.
+    None,
+}
+
+/// A cookie to set on the client, written by
+/// [`Response::set_cookie`](crate::response::Response::set_cookie).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cookie {
+    name: String,
+    value: String,
+    path: Option<String>,
+    domain: Option<String>,
+    max_age: Option<Duration>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<SameSite>,
+}
+
+impl Cookie {
+    /// A session cookie: no attributes until set.
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+            path: None,
+            domain: None,
+            max_age: None,
+            secure: false,
+            http_only: false,
+            same_site: None,
+        }
+    }
+
+    /// A cookie that deletes `name` on the client. Give it the `path` and
+    /// `domain` the cookie was set with.
+    pub fn removal(name: impl Into<String>) -> Self {
+        Self::new(name, "").max_age(Duration::ZERO)
+    }
+
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    pub fn domain(mut self, domain: impl Into<String>) -> Self {
+        self.domain = Some(domain.into());
+        self
+    }
+
+    /// Keep the cookie this long (whole seconds); zero deletes it.
+    pub fn max_age(mut self, age: Duration) -> Self {
+        self.max_age = Some(age);
+        self
+    }
+
+    /// Only send the cookie over HTTPS.
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    /// Hide the cookie from scripts.
+    pub fn http_only(mut self, http_only: bool) -> Self {
+        self.http_only = http_only;
+        self
+    }
+
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = Some(same_site);
+        self
+    }
+
+    /// The `Set-Cookie` value, or `None` if the name, value, path or
+    /// domain holds characters a cookie can't carry.
+    pub fn to_header(&self) -> Option<Vec<u8>> {
+        let token = |s: &str| {
+            !s.is_empty()
+                && s.bytes()
+                    .all(|b| b.is_ascii_graphic() && !b"()<>@,;:\\\"/[]?={}".contains(&b))
+        };
+        let octets = |s: &str| {
+            s.bytes()
+                .all(|b| b.is_ascii_graphic() && !b"\",;\\".contains(&b))
+        };
+        let attribute = |s: &str| s.bytes().all(|b| (b' '..=b'~').contains(&b) && b != b';');
+        if !token(&self.name)
+            || !octets(&self.value)
+            || !self.path.as_deref().is_none_or(attribute)
+            || !self.domain.as_deref().is_none_or(attribute)
+        {
+            return None;
+        }
+
+        let mut out = format!("{}={}", self.name, self.value);
+        if let Some(path) = &self.path {
+            out.push_str("; Path=");
+            out.push_str(path);
+        }
+        if let Some(domain) = &self.domain {
+            out.push_str("; Domain=");
+            out.push_str(domain);
+        }
+        if let Some(age) = self.max_age {
+            out.push_str(&format!("; Max-Age={}", age.as_secs()));
+        }
+        if self.secure || self.same_site == Some(SameSite::None) {
+            out.push_str("; Secure");
+        }
+        if self.http_only {
+            out.push_str("; HttpOnly");
+        }
+        match self.same_site {
+            Some(SameSite::Strict) => out.push_str("; SameSite=Strict"),
+            Some(SameSite::Lax) => out.push_str("; SameSite=Lax"),
+            Some(SameSite::None) => out.push_str("; SameSite=None"),
+            None => {}
+        }
+        Some(out.into_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_attributes_and_rejects_injection() {
+        let cookie = Cookie::new("sid", "abc123")
+            .path("/")
+            .max_age(Duration::from_secs(60))
+            .http_only(true)
+            .same_site(SameSite::None);
+        assert_eq!(
+            cookie.to_header().unwrap(),
+            b"sid=abc123; Path=/; Max-Age=60; Secure; HttpOnly; SameSite=None"
+        );
+        assert_eq!(
+            Cookie::removal("sid").to_header().unwrap(),
+            b"sid=; Max-Age=0"
+        );
+
+        assert!(Cookie::new("sid", "a;b").to_header().is_none());
+        assert!(Cookie::new("s id", "x").to_header().is_none());
+        assert!(
+            Cookie::new("sid", "x")
+                .path("/\r\nX: y")
+                .to_header()
+                .is_none()
+        );
+    }
+}
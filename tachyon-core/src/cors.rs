@@ -0,0 +1,213 @@
+//! Cross-Origin Resource Sharing.
+//!
+//! With a [`Cors`] policy in the server config, preflight requests
+//! (`OPTIONS` + `Access-Control-Request-Method`) are answered automatically
+//! with a 204 and never reach a handler, and every response to an allowed
+//! `Origin` — handler, static, cached or error — gets the CORS headers.
+//! Requests from other origins get no CORS headers, so the browser blocks them.
+
+use std::time::Duration;
+
+use tachyon_http::methods::Method;
+
+use crate::response::Response;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Origins {
+    Any,
+    List(Vec<String>),
+}
+
+/// CORS policy. Defaults: any origin, the common methods, request headers
+/// mirrored back, no credentials, no `Max-Age`.
+#[derive(Debug, Clone)]
+pub struct Cors {
+    origins: Origins,
+    methods: String,
+    /// `None` mirrors the preflight's `Access-Control-Request-Headers`.
+    headers: Option<String>,
+    expose_headers: Option<String>,
+    credentials: bool,
+    max_age: Option<Duration>,
+}
+
+impl Default for Cors {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Cors {
+    pub fn new() -> Self {
+        Self {
+            origins: Origins::Any,
+            methods: "GET, HEAD, POST, PUT, PATCH, DELETE".to_string(),
+            headers: None,
+            expose_headers: None,
+            credentials: false,
+            max_age: None,
+        }
+    }
+
+    /// Allow `origin` (e.g. `"https://app.example.com"`). The first call
+    /// switches from "any origin" to an explicit list.
+    pub fn allow_origin(mut self, origin: &str) -> Self {
+        let origin = origin.trim_end_matches('/').to_string();
+        match &mut self.origins {
+            Origins::List(list) => list.push(origin),
+            Origins::Any => self.origins = Origins::List(vec![origin]),
+        }
+        self
+    }
+
+    pub fn allow_any_origin(mut self) -> Self {
+        self.origins = Origins::Any;
+        self
+    }
+
+    pub fn allow_methods(mut self, methods: &[Method]) -> Self {
+        self.methods = methods
+            .iter()
+            .map(|m| m.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        self
+    }
+
+    /// Request headers allowed in preflights (default: whatever the browser asks for).
+    pub fn allow_headers(mut self, headers: &[&str]) -> Self {
+        self.headers = Some(headers.join(", "));
+        self
+    }
+
+    /// Response headers scripts may read beyond the CORS-safelisted ones.
+    pub fn expose_headers(mut self, headers: &[&str]) -> Self {
+        self.expose_headers = Some(headers.join(", "));
+        self
+    }
+
+    /// Allow cookies / `Authorization`. The origin is then always echoed
+    /// back instead of `*`, as browsers require.
+    pub fn allow_credentials(mut self, enabled: bool) -> Self {
+        self.credentials = enabled;
+        self
+    }
+
+    /// How long browsers may cache a preflight result.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    fn allows(&self, origin: &[u8]) -> bool {
+        match &self.origins {
+            Origins::Any => true,
+            Origins::List(list) => list.iter().any(|o| o.as_bytes() == origin),
+        }
+    }
+
+    /// `Access-Control-Allow-Origin` (+ `Vary`, credentials) lines for `origin`.
+    fn origin_headers(&self, origin: &[u8], out: &mut Vec<u8>) {
+        out.extend_from_slice(b"Access-Control-Allow-Origin: ");
+        if self.origins == Origins::Any && !self.credentials {
+            out.extend_from_slice(b"*\r\n");
+        } else {
+            out.extend_from_slice(origin);
+            out.extend_from_slice(b"\r\nVary: Origin\r\n");
+        }
+        if self.credentials {
+            out.extend_from_slice(b"Access-Control-Allow-Credentials: true\r\n");
+        }
+    }
+
+    /// Answer a preflight with a 204. Disallowed origins get a bare 204.
+    pub(crate) fn preflight(
+        &self,
+        res: &mut Response<'_>,
+        origin: &[u8],
+        request_headers: Option<&[u8]>,
+    ) -> usize {
+        if self.allows(origin) && is_header_safe(origin) {
+            let mut lines = Vec::with_capacity(256);
+            self.origin_headers(origin, &mut lines);
+            lines.extend_from_slice(b"Access-Control-Allow-Methods: ");
+            lines.extend_from_slice(self.methods.as_bytes());
+            lines.extend_from_slice(b"\r\n");
+            let allow_headers = match &self.headers {
+                Some(h) => Some(h.as_bytes()),
+                None => request_headers.filter(|h| is_header_safe(h)),
+            };
+            if let Some(h) = allow_headers {
+                lines.extend_from_slice(b"Access-Control-Allow-Headers: ");
+                lines.extend_from_slice(h);
+                lines.extend_from_slice(b"\r\n");
+            }
+            if let Some(max_age) = self.max_age {
+                lines.extend_from_slice(b"Access-Control-Max-Age: ");
+                lines.extend_from_slice(max_age.as_secs().to_string().as_bytes());
+                lines.extend_from_slice(b"\r\n");
+            }
+            res.raw_headers(&lines);
+        }
+        res.text(204, b"")
+    }
+
+    /// Add CORS headers to an already-written response for `origin`.
+    pub(crate) fn apply(&self, res: &mut Response<'_>, origin: &[u8]) {
+        if !self.allows(origin) || !is_header_safe(origin) {
+            return;
+        }
+        let mut lines = Vec::with_capacity(128);
+        self.origin_headers(origin, &mut lines);
+        if let Some(expose) = &self.expose_headers {
+            lines.extend_from_slice(b"Access-Control-Expose-Headers: ");
+            lines.extend_from_slice(expose.as_bytes());
+            lines.extend_from_slice(b"\r\n");
+        }
+        res.insert_headers(&lines);
+    }
+}
+
+/// Echoed request values must not be able to inject header lines.
+fn is_header_safe(value: &[u8]) -> bool {
+    !value.iter().any(|&b| b == b'\r' || b == b'\n')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tachyon_http::encoding::Encoding;
+
+    #[test]
+    fn preflight_and_simple_requests() {
+        let cors = Cors::new()
+            .allow_origin("https://app.example.com/")
+            .allow_methods(&[Method::Get, Method::Post])
+            .max_age(Duration::from_secs(600));
+
+        let mut buf = [0u8; 512];
+        let mut res = Response::new(&mut buf, b"", Encoding::Identity, usize::MAX);
+        cors.preflight(&mut res, b"https://app.example.com", Some(b"content-type"));
+        let out = std::str::from_utf8(res.data()).unwrap();
+        assert!(out.starts_with("HTTP/1.1 204 No Content\r\n"));
+        assert!(out.contains("Access-Control-Allow-Origin: https://app.example.com\r\n"));
+        assert!(out.contains("Access-Control-Allow-Methods: GET, POST\r\n"));
+        assert!(out.contains("Access-Control-Allow-Headers: content-type\r\n"));
+        assert!(out.contains("Access-Control-Max-Age: 600\r\n"));
+
+        let mut buf = [0u8; 512];
+        let mut res = Response::new(&mut buf, b"", Encoding::Identity, usize::MAX);
+        res.text(200, b"hi");
+        cors.apply(&mut res, b"https://evil.example");
+        assert!(
+            !std::str::from_utf8(res.data())
+                .unwrap()
+                .contains("Access-Control")
+        );
+        cors.apply(&mut res, b"https://app.example.com");
+        let out = std::str::from_utf8(res.data()).unwrap();
+        assert!(out.contains(
+            "Access-Control-Allow-Origin: https://app.example.com\r\nVary: Origin\r\n\r\nhi"
+        ));
+    }
+}
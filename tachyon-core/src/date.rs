@@ -14,6 +14,16 @@ fn format_date_header() -> Vec<u8> {
         .unwrap_or_default()
         .as_secs();
 
+    // "Date: Thu, 01 Jan 1970 00:00:00 GMT\r\n" = 37 bytes
+    let mut buf = Vec::with_capacity(40);
+    buf.extend_from_slice(b"Date: ");
+    buf.extend_from_slice(&http_date(now));
+    buf.extend_from_slice(b"\r\n");
+    buf
+}
+
+/// `now` (seconds since the epoch) as an HTTP-date, e.g. `Thu, 01 Jan 1970 00:00:00 GMT`.
+pub(crate) fn http_date(now: u64) -> Vec<u8> {
     const DAYS: [&[u8]; 7] = [b"Sun", b"Mon", b"Tue", b"Wed", b"Thu", b"Fri", b"Sat"];
     const MONTHS: [&[u8]; 12] = [
         b"Jan", b"Feb", b"Mar", b"Apr", b"May", b"Jun", b"Jul", b"Aug", b"Sep", b"Oct", b"Nov",
@@ -29,9 +39,7 @@ fn format_date_header() -> Vec<u8> {
     let wday = ((total_days % 7 + 4) % 7) as usize;
     let (year, month, day) = civil_from_days(total_days);
 
-    // "Date: Thu, 01 Jan 1970 00:00:00 GMT\r\n" = 37 bytes
-    let mut buf = Vec::with_capacity(40);
-    buf.extend_from_slice(b"Date: ");
+    let mut buf = Vec::with_capacity(29);
     buf.extend_from_slice(DAYS[wday]);
     buf.extend_from_slice(b", ");
     buf.push(b'0' + (day / 10) as u8);
@@ -54,7 +62,7 @@ fn format_date_header() -> Vec<u8> {
     buf.push(b':');
     buf.push(b'0' + (sec / 10) as u8);
     buf.push(b'0' + (sec % 10) as u8);
-    buf.extend_from_slice(b" GMT\r\n");
+    buf.extend_from_slice(b" GMT");
     buf
 }
 
@@ -76,6 +84,11 @@ fn civil_from_days(days: i64) -> (i64, u32, u32) {
 /// Start the async task that updates the cached Date header every second.
 /// Must be called once inside the Tokio runtime before accepting connections.
 pub fn start_date_cache() {
+    spawn_ticker();
+}
+
+/// [`start_date_cache`], returning the task: it stops with its runtime.
+pub(crate) fn spawn_ticker() -> tokio::task::JoinHandle<()> {
     // Initial value
     let initial = Box::into_raw(Box::new(format_date_header()));
     CACHED_DATE.store(initial, Ordering::Release);
@@ -96,7 +109,7 @@ pub fn start_date_cache() {
                 prev = Some(unsafe { Box::from_raw(old) });
             }
         }
-    });
+    })
 }
 
 /// Get the current cached Date header bytes. Zero-cost per request (atomic load + pointer deref).
@@ -0,0 +1,157 @@
+//! Response envelopes, applied per path scope.
+//!
+//! An [`Envelope`] rewrites the JSON bodies written inside a scope
+//! (`Router::envelope("/api", ...)`) so handlers return plain payloads and
+//! the wire format is decided in one place: successes wrapped as
+//! `{"data":…,"meta":…,"request_id":…}`, errors converted to RFC 7807
+//! `application/problem+json`, or any custom transform.
+
+use std::sync::Arc;
+
+use tachyon_http::response as http;
+
+/// What a transform knows about the response it rewrites.
+#[derive(Debug, Clone, Copy)]
+pub struct EnvelopeContext<'a> {
+    pub status: u16,
+    /// The request's `X-Request-Id`, if it sent one.
+    pub request_id: Option<&'a [u8]>,
+}
+
+/// Turns a JSON body into the enveloped JSON body.
+pub type Transform = Arc<dyn Fn(&EnvelopeContext<'_>, &[u8]) -> Vec<u8> + Send + Sync>;
+
+/// Body rewrites for one scope: one transform for 2xx/3xx, one for 4xx/5xx.
+/// A side without a transform is sent unchanged.
+#[derive(Clone, Default)]
+pub struct Envelope {
+    success: Option<(Transform, &'static [u8])>,
+    error: Option<(Transform, &'static [u8])>,
+}
+
+impl Envelope {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wrap successful bodies: `{"data":<body>,"meta":{"status":200},"request_id":"…"|null}`.
+    pub fn data(mut self) -> Self {
+        self.success = Some((Arc::new(wrap_data), http::CONTENT_JSON));
+        self
+    }
+
+    /// Convert error bodies to RFC 7807 problem details. The built-in
+    /// `{"error":"msg"}` becomes the `detail`; any other body is kept under
+    /// an `errors` extension member.
+    pub fn problem_details(mut self) -> Self {
+        self.error = Some((Arc::new(problem), http::CONTENT_PROBLEM_JSON));
+        self
+    }
+
+    /// Custom transform for successful (< 400) JSON bodies.
+    pub fn on_success(mut self, transform: Transform) -> Self {
+        self.success = Some((transform, http::CONTENT_JSON));
+        self
+    }
+
+    /// Custom transform for error (>= 400) JSON bodies.
+    pub fn on_error(mut self, transform: Transform) -> Self {
+        self.error = Some((transform, http::CONTENT_JSON));
+        self
+    }
+
+    /// Rewrite `body`, returning the new Content-Type header and body.
+    pub(crate) fn apply(
+        &self,
+        ctx: &EnvelopeContext<'_>,
+        body: &[u8],
+    ) -> Option<(&'static [u8], Vec<u8>)> {
+        let (transform, content_type) = if ctx.status >= 400 {
+            self.error.as_ref()?
+        } else {
+            self.success.as_ref()?
+        };
+        Some((content_type, transform(ctx, body)))
+    }
+}
+
+fn wrap_data(ctx: &EnvelopeContext<'_>, body: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(body.len() + 64);
+    out.extend_from_slice(b"{\"data\":");
+    out.extend_from_slice(if body.is_empty() { b"null" } else { body });
+    out.extend_from_slice(b",\"meta\":{\"status\":");
+    out.extend_from_slice(ctx.status.to_string().as_bytes());
+    out.extend_from_slice(b"},\"request_id\":");
+    push_request_id(&mut out, ctx.request_id);
+    out.push(b'}');
+    out
+}
+
+fn problem(ctx: &EnvelopeContext<'_>, body: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(body.len() + 96);
+    out.extend_from_slice(b"{\"type\":\"about:blank\",\"title\":\"");
+    out.extend_from_slice(reason_phrase(ctx.status));
+    out.extend_from_slice(b"\",\"status\":");
+    out.extend_from_slice(ctx.status.to_string().as_bytes());
+    match error_message(body) {
+        Some(detail) => {
+            out.extend_from_slice(b",\"detail\":\"");
+            out.extend_from_slice(detail);
+            out.push(b'"');
+        }
+        None if !body.is_empty() => {
+            out.extend_from_slice(b",\"errors\":");
+            out.extend_from_slice(body);
+        }
+        None => {}
+    }
+    if ctx.request_id.is_some() {
+        out.extend_from_slice(b",\"request_id\":");
+        push_request_id(&mut out, ctx.request_id);
+    }
+    out.push(b'}');
+    out
+}
+
+/// The escaped message of a `{"error":"msg"}` body.
+fn error_message(body: &[u8]) -> Option<&[u8]> {
+    let msg = body.strip_prefix(b"{\"error\":\"")?.strip_suffix(b"\"}")?;
+    // Any unescaped quote means the object has more members.
+    let mut escaped = false;
+    for &b in msg {
+        match b {
+            _ if escaped => escaped = false,
+            b'\\' => escaped = true,
+            b'"' => return None,
+            _ => {}
+        }
+    }
+    (!escaped).then_some(msg)
+}
+
+/// "Not Found" from "HTTP/1.1 404 Not Found\r\n".
+fn reason_phrase(status: u16) -> &'static [u8] {
+    let line = http::status_line(status);
+    let code = status.to_string();
+    match line.get(9..12) {
+        Some(c) if c == code.as_bytes() => &line[13..line.len() - 2],
+        _ => b"Error",
+    }
+}
+
+/// Request IDs come from a header: keep them to printable ASCII minus
+/// quote and backslash, so they can be written as a JSON string verbatim.
+fn push_request_id(out: &mut Vec<u8>, id: Option<&[u8]>) {
+    match id {
+        Some(id) => {
+            out.push(b'"');
+            out.extend(
+                id.iter()
+                    .copied()
+                    .filter(|&b| (0x20..0x7f).contains(&b) && b != b'"' && b != b'\\'),
+            );
+            out.push(b'"');
+        }
+        None => out.extend_from_slice(b"null"),
+    }
+}
@@ -0,0 +1,117 @@
+//! Errors from starting and running a server.
+
+use std::fmt;
+use std::io;
+
+use crate::config::InvalidConfig;
+
+/// Why `Server::serve` (or `spawn`, `into_service`, `ServerHandle::join`)
+/// failed. Converts into `io::Error`, so `?` still works in functions
+/// returning `io::Result`.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum TachyonError {
+    /// A [`ServerConfig`](crate::config::ServerConfig) value is out of
+    /// range; nothing was bound.
+    Config(InvalidConfig),
+    /// Binding or configuring the listening socket for `addr` failed
+    /// (address in use, permission denied, unresolvable host, …).
+    Bind { addr: String, source: io::Error },
+    /// The Tokio or io_uring runtime could not be built.
+    Runtime(io::Error),
+    /// Any other I/O failure: spawning threads, inherited sockets, ….
+    Io(io::Error),
+    /// The thread serving a `ServerHandle` panicked.
+    Panicked,
+}
+
+impl TachyonError {
+    pub(crate) fn bind(addr: &str, source: io::Error) -> Self {
+        Self::Bind {
+            addr: addr.to_string(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for TachyonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Config(e) => e.fmt(f),
+            Self::Bind { addr, source } => write!(f, "failed to bind {}: {}", addr, source),
+            Self::Runtime(e) => write!(f, "failed to start the runtime: {}", e),
+            Self::Io(e) => e.fmt(f),
+            Self::Panicked => f.write_str("server thread panicked"),
+        }
+    }
+}
+
+impl std::error::Error for TachyonError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Config(e) => Some(e),
+            Self::Bind { source, .. } => Some(source),
+            Self::Runtime(e) | Self::Io(e) => Some(e),
+            Self::Panicked => None,
+        }
+    }
+}
+
+impl From<InvalidConfig> for TachyonError {
+    fn from(e: InvalidConfig) -> Self {
+        Self::Config(e)
+    }
+}
+
+impl From<io::Error> for TachyonError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<TachyonError> for io::Error {
+    fn from(e: TachyonError) -> Self {
+        let kind = match &e {
+            TachyonError::Config(_) => io::ErrorKind::InvalidInput,
+            TachyonError::Bind { source, .. } => source.kind(),
+            TachyonError::Runtime(source) | TachyonError::Io(source) => source.kind(),
+            TachyonError::Panicked => io::ErrorKind::Other,
+        };
+        match e {
+            TachyonError::Io(source) => source,
+            e => io::Error::new(kind, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ServerConfig;
+    use crate::router::Router;
+    use crate::server::Server;
+
+    #[test]
+    fn start_failures_are_matchable() {
+        let config = ServerConfig::new().bind("127.0.0.1:0").buffer_pool(4, 1);
+        let Err(err) = Server::new(config).spawn(Router::new()) else {
+            panic!("expected a config error");
+        };
+        assert!(matches!(err, TachyonError::Config(_)));
+        assert_eq!(io::Error::from(err).kind(), io::ErrorKind::InvalidInput);
+
+        let taken = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = taken.local_addr().unwrap().to_string();
+        let config = ServerConfig::new().bind(&addr).shutdown_signals(false);
+        match Server::new(config).spawn(Router::new()) {
+            Err(TachyonError::Bind {
+                addr: failed,
+                source,
+            }) => {
+                assert_eq!(failed, addr);
+                assert_eq!(source.kind(), io::ErrorKind::AddrInUse);
+            }
+            other => panic!("expected a bind error, got {:?}", other.err()),
+        }
+    }
+}
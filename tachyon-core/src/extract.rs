@@ -0,0 +1,352 @@
+//! Extractor-style handlers.
+//!
+//! [`handler`] turns an async function whose arguments implement
+//! [`FromRequest`] into a [`Handler`]. Each argument is pulled out of the
+//! request before the function runs, so the future owns everything it
+//! needs; when one can't be (bad JSON, say), its [`Rejection`] is sent and
+//! the function is never called.
+//!
+//! ```ignore
+//! async fn create(State(db): State<Db>, Json(user): Json<NewUser>) -> (u16, String) {
+//!     let id = db.insert(user).await;
+//!     (201, id.to_string())
+//! }
+//! router.route(Method::Post, "/users", extract::with_state(db, create));
+//! ```
+//!
+//! Routes match exact paths, so there are no path parameters to extract;
+//! [`Uri`] gives the raw target for handlers that parse it themselves.
+
+use std::borrow::Cow;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use tachyon_http::http::Request;
+use tachyon_http::methods::Method;
+
+use crate::cancel::CancelToken;
+use crate::response::{IntoResponse, Response};
+use crate::server::{Handler, HandlerFuture};
+
+/// A value built from the request (and the handler's state) before the
+/// handler runs.
+pub trait FromRequest<S>: Sized + Send + 'static {
+    fn from_request(req: &Request<'_>, state: &S) -> Result<Self, Rejection>;
+}
+
+/// Why an extractor refused a request: sent as `{"error": message}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rejection {
+    status: u16,
+    message: Cow<'static, str>,
+}
+
+impl Rejection {
+    pub fn new(status: u16, message: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl IntoResponse for Rejection {
+    fn write(self, _status: u16, res: &mut Response<'_>) -> usize {
+        let mut body = vec![0u8; self.message.len() * 6 + 16];
+        let mut w = tachyon_http::json::JsonWriter::new(&mut body);
+        w.object(|w| {
+            w.key("error").string(&self.message);
+        });
+        let len = w.finish();
+        res.json(self.status, &body[..len])
+    }
+}
+
+/// The state passed to [`with_state`], cloned for each request.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct State<S>(pub S);
+
+impl<S: Clone + Send + 'static> FromRequest<S> for State<S> {
+    fn from_request(_req: &Request<'_>, state: &S) -> Result<Self, Rejection> {
+        Ok(State(state.clone()))
+    }
+}
+
+/// The request body, with chunked framing removed.
+#[derive(Debug, Clone, Default)]
+pub struct Body(pub Vec<u8>);
+
+impl<S> FromRequest<S> for Body {
+    fn from_request(req: &Request<'_>, _state: &S) -> Result<Self, Rejection> {
+        Ok(Body(req.decoded_body().into_owned()))
+    }
+}
+
+/// The request target as sent: path plus query string.
+#[derive(Debug, Clone, Default)]
+pub struct Uri(pub String);
+
+impl<S> FromRequest<S> for Uri {
+    fn from_request(req: &Request<'_>, _state: &S) -> Result<Self, Rejection> {
+        Ok(Uri(req.path_str().to_string()))
+    }
+}
+
+/// The query string without the `?`, if there is one.
+#[derive(Debug, Clone, Default)]
+pub struct RawQuery(pub Option<String>);
+
+impl<S> FromRequest<S> for RawQuery {
+    fn from_request(req: &Request<'_>, _state: &S) -> Result<Self, Rejection> {
+        let query = req
+            .path_str()
+            .split_once('?')
+            .map(|(_, query)| query.to_string());
+        Ok(RawQuery(query))
+    }
+}
+
+/// Every request header, in order.
+#[derive(Debug, Clone, Default)]
+pub struct Headers(pub Vec<(Vec<u8>, Vec<u8>)>);
+
+impl Headers {
+    /// First value of `name` (case-insensitive).
+    pub fn get(&self, name: &[u8]) -> Option<&[u8]> {
+        self.0
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_slice())
+    }
+}
+
+impl<S> FromRequest<S> for Headers {
+    fn from_request(req: &Request<'_>, _state: &S) -> Result<Self, Rejection> {
+        let headers = req.headers[..req.header_count]
+            .iter()
+            .flatten()
+            .map(|h| (h.name.to_vec(), h.value.to_vec()))
+            .collect();
+        Ok(Headers(headers))
+    }
+}
+
+impl<S> FromRequest<S> for Method {
+    fn from_request(req: &Request<'_>, _state: &S) -> Result<Self, Rejection> {
+        Ok(req.method)
+    }
+}
+
+tokio::task_local! {
+    static PEER: SocketAddr;
+}
+
+/// Address of the client that sent the request. Behind a
+/// [`TachyonService`](crate::tower::TachyonService) it is the one the
+/// service was given, else `0.0.0.0:0`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientAddr(pub SocketAddr);
+
+impl<S> FromRequest<S> for ClientAddr {
+    fn from_request(_req: &Request<'_>, _state: &S) -> Result<Self, Rejection> {
+        client_addr()
+            .map(ClientAddr)
+            .ok_or_else(|| Rejection::new(500, "no client address"))
+    }
+}
+
+/// The client address of the request being handled, for handlers that
+/// don't use extractors. `None` outside of a request (a handler called
+/// directly, e.g. in tests); like [`crate::cancel::token`], call it before
+/// spawning work.
+pub fn client_addr() -> Option<SocketAddr> {
+    PEER.try_with(|peer| *peer).ok()
+}
+
+/// Run `fut` with `peer` as the current client address.
+pub(crate) async fn with_client_addr<F: Future>(peer: SocketAddr, fut: F) -> F::Output {
+    PEER.scope(peer, fut).await
+}
+
+/// Fires when the client disconnects; see [`crate::cancel`].
+impl<S> FromRequest<S> for CancelToken {
+    fn from_request(_req: &Request<'_>, _state: &S) -> Result<Self, Rejection> {
+        Ok(crate::cancel::token())
+    }
+}
+
+/// `None` instead of a rejection when `T` can't be extracted.
+impl<S, T: FromRequest<S>> FromRequest<S> for Option<T> {
+    fn from_request(req: &Request<'_>, state: &S) -> Result<Self, Rejection> {
+        Ok(T::from_request(req, state).ok())
+    }
+}
+
+/// A JSON request body, deserialized with serde. Rejects with 415 unless
+/// the `Content-Type` is JSON, and with 400 if the body doesn't parse.
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json<T>(pub T);
+
+#[cfg(feature = "serde")]
+impl<S, T> FromRequest<S> for Json<T>
+where
+    T: serde::de::DeserializeOwned + Send + 'static,
+{
+    fn from_request(req: &Request<'_>, _state: &S) -> Result<Self, Rejection> {
+        let is_json = req.header(b"content-type").is_some_and(|ct| {
+            let mime = ct.split(|&b| b == b';').next().unwrap_or_default();
+            let mime = mime.trim_ascii();
+            mime.eq_ignore_ascii_case(b"application/json")
+                || (mime.len() > 5 && mime[mime.len() - 5..].eq_ignore_ascii_case(b"+json"))
+        });
+        if !is_json {
+            return Err(Rejection::new(415, "expected a JSON body"));
+        }
+        serde_json::from_slice(&req.decoded_body())
+            .map(Json)
+            .map_err(|e| Rejection::new(400, format!("invalid JSON: {e}")))
+    }
+}
+
+mod sealed {
+    pub trait Sealed<S, Args> {}
+}
+
+/// Async functions of up to six [`FromRequest`] arguments that resolve to
+/// an [`IntoResponse`]. Implemented for every such function; not implementable
+/// outside this crate.
+pub trait ExtractHandler<S, Args>: sealed::Sealed<S, Args> + Send + Sync + 'static {
+    #[doc(hidden)]
+    fn call(&self, req: &Request<'_>, state: &S) -> HandlerFuture;
+}
+
+macro_rules! impl_extract_handler {
+    ($($arg:ident),*) => {
+        impl<S, F, Fut, $($arg,)*> sealed::Sealed<S, ($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> Fut,
+        {
+        }
+
+        impl<S, F, Fut, $($arg,)*> ExtractHandler<S, ($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> Fut + Send + Sync + 'static,
+            Fut: Future + Send + 'static,
+            Fut::Output: IntoResponse,
+            $($arg: FromRequest<S>,)*
+        {
+            #[allow(non_snake_case, unused_variables)]
+            fn call(&self, req: &Request<'_>, state: &S) -> HandlerFuture {
+                $(
+                    let $arg = match $arg::from_request(req, state) {
+                        Ok(value) => value,
+                        Err(rejection) => {
+                            return Box::pin(std::future::ready(rejection.into_write_fn()));
+                        }
+                    };
+                )*
+                let fut = self($($arg),*);
+                Box::pin(async move { fut.await.into_write_fn() })
+            }
+        }
+    };
+}
+
+impl_extract_handler!();
+impl_extract_handler!(A1);
+impl_extract_handler!(A1, A2);
+impl_extract_handler!(A1, A2, A3);
+impl_extract_handler!(A1, A2, A3, A4);
+impl_extract_handler!(A1, A2, A3, A4, A5);
+impl_extract_handler!(A1, A2, A3, A4, A5, A6);
+
+/// A [`Handler`] that extracts `f`'s arguments from each request.
+pub fn handler<Args, H: ExtractHandler<(), Args>>(f: H) -> Handler {
+    with_state((), f)
+}
+
+/// Like [`handler`], with `state` available through the [`State`] extractor.
+pub fn with_state<S, Args, H>(state: S, f: H) -> Handler
+where
+    S: Send + Sync + 'static,
+    H: ExtractHandler<S, Args>,
+{
+    Arc::new(move |req| f.call(req, &state))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::WriteFn;
+    use crate::testing::{block_on, render, request, respond as run};
+
+    #[test]
+    fn extracts_arguments_and_state() {
+        async fn echo(
+            State(prefix): State<&'static str>,
+            method: Method,
+            RawQuery(query): RawQuery,
+            headers: Headers,
+            Body(body): Body,
+        ) -> WriteFn {
+            let out = format!(
+                "{prefix} {} {} {} {}",
+                method.as_str(),
+                query.unwrap_or_default(),
+                String::from_utf8_lossy(headers.get(b"x-tag").unwrap_or_default()),
+                String::from_utf8_lossy(&body),
+            );
+            Box::new(move |res: &mut Response| res.text(200, out.as_bytes()))
+        }
+        let h = with_state("hi", echo);
+        let out = run(
+            &h,
+            b"POST /e?a=1 HTTP/1.1\r\nX-Tag: t\r\nContent-Length: 4\r\n\r\nbody",
+        );
+        assert!(out.ends_with("\r\n\r\nhi POST a=1 t body"), "{out}");
+
+        let bare = handler(|| async { (204, "") });
+        assert!(run(&bare, b"GET / HTTP/1.1\r\n\r\n").starts_with("HTTP/1.1 204"));
+    }
+
+    #[test]
+    fn client_addr_is_scoped_to_the_request() {
+        let h = handler(|ClientAddr(peer): ClientAddr| async move { peer.to_string() });
+        let req = request(b"GET / HTTP/1.1\r\n\r\n");
+        let peer: SocketAddr = "10.0.0.7:4000".parse().unwrap();
+        let write = block_on(with_client_addr(peer, async { h(&req).await }));
+        assert!(render(write).ends_with("\r\n\r\n10.0.0.7:4000"));
+
+        assert!(client_addr().is_none());
+        assert!(run(&h, b"GET / HTTP/1.1\r\n\r\n").starts_with("HTTP/1.1 500"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_rejections() {
+        let h = handler(|Json(v): Json<serde_json::Value>| async move {
+            Box::new(move |res: &mut Response| res.text(200, v["x"].to_string().as_bytes()))
+                as WriteFn
+        });
+        let ok = b"POST / HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: 7\r\n\r\n{\"x\":7}";
+        assert!(run(&h, ok).ends_with("\r\n\r\n7"));
+        let bad =
+            b"POST / HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: 3\r\n\r\n{x}";
+        let out = run(&h, bad);
+        assert!(out.starts_with("HTTP/1.1 400"));
+        assert!(out.contains("{\"error\":\"invalid JSON: "));
+        let text =
+            b"POST / HTTP/1.1\r\nContent-Type: text/plain\r\nContent-Length: 7\r\n\r\n{\"x\":7}";
+        assert!(run(&h, text).starts_with("HTTP/1.1 415"));
+    }
+}
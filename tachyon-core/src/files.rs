@@ -0,0 +1,373 @@
+//! Static files served from a directory, mounted with
+//! [`Router::files`](crate::router::Router::files).
+//!
+//! ```ignore
+//! router.files("/assets", ServeDir::new("./public").max_age(Duration::from_secs(3600)));
+//! ```
+//!
+//! `GET /assets/css/app.css` reads `./public/css/app.css` on the blocking
+//! pool. A directory is answered with its `index.html`. Paths that would
+//! leave the directory, hidden files (any segment starting with `.`) and
+//! anything missing get 404.
+//!
+//! Every file carries an `ETag` (size and modification time),
+//! `Last-Modified` and `Accept-Ranges`, plus `Cache-Control` when
+//! `max_age` is set. Matching `If-None-Match` / `If-Modified-Since`
+//! answer 304; a single `Range` answers 206, or 416 past the end (several
+//! ranges get the whole file). Files are sent as stored, without
+//! compression; bodies over 256 KiB are streamed.
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+
+use tachyon_http::etag::{Etag, EtagMode};
+use tachyon_http::methods::Method;
+
+use crate::config::ErrorPages;
+use crate::response::Response;
+use crate::server::{Handler, WriteFn};
+
+/// Bodies larger than this are streamed instead of read into memory.
+const STREAM_THRESHOLD: u64 = 256 * 1024;
+/// Read size of a streamed body.
+const CHUNK: usize = 64 * 1024;
+
+/// A directory of files to serve.
+#[derive(Debug, Clone)]
+pub struct ServeDir {
+    root: PathBuf,
+    index: Option<String>,
+    max_age: Option<Duration>,
+}
+
+impl ServeDir {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            index: Some("index.html".to_string()),
+            max_age: None,
+        }
+    }
+
+    /// File answering for a directory (default: `index.html`); `None`
+    /// answers directories 404.
+    pub fn index(mut self, name: Option<&str>) -> Self {
+        self.index = name.map(str::to_string);
+        self
+    }
+
+    /// Let clients and proxies cache files this long
+    /// (`Cache-Control: public, max-age=<secs>`).
+    pub fn max_age(mut self, age: Duration) -> Self {
+        self.max_age = Some(age);
+        self
+    }
+
+    /// A handler serving the request path below `prefix` (no trailing `/`),
+    /// answering 404 and 405 with `pages`.
+    pub(crate) fn handler(self, prefix: &[u8], pages: &ErrorPages) -> Handler {
+        let dir = Arc::new(self);
+        let prefix: Box<[u8]> = prefix.into();
+        let pages = Arc::new(pages.clone());
+        crate::server::blocking_handler(move |req| {
+            let (dir, pages) = (dir.clone(), pages.clone());
+            let copy = |name: &[u8]| req.header(name).map(<[u8]>::to_vec);
+            let path = strip_query(req.path)
+                .strip_prefix(&*prefix)
+                .map(<[u8]>::to_vec);
+            let method = req.method;
+            let conditions = Conditions {
+                if_none_match: copy(b"if-none-match"),
+                if_modified_since: copy(b"if-modified-since"),
+                range: copy(b"range"),
+                if_range: copy(b"if-range"),
+            };
+            move || {
+                if !matches!(method, Method::Get | Method::Head) {
+                    return Box::new(move |res: &mut Response| {
+                        res.header(b"Allow", b"GET, HEAD");
+                        res.error(405, &pages)
+                    }) as WriteFn;
+                }
+                match path.and_then(|path| dir.serve(&path, conditions)) {
+                    Some(write) => write,
+                    None => Box::new(move |res: &mut Response| res.error(404, &pages)),
+                }
+            }
+        })
+    }
+
+    /// The response for the file at `path` (relative to the mount), or
+    /// `None` for 404.
+    fn serve(&self, path: &[u8], conditions: Conditions) -> Option<WriteFn> {
+        let mut path = self.resolve(path)?;
+        let mut meta = std::fs::metadata(&path).ok()?;
+        if meta.is_dir() {
+            path.push(self.index.as_deref()?);
+            meta = std::fs::metadata(&path).ok()?;
+        }
+        if !meta.is_file() {
+            return None;
+        }
+        let mut file = File::open(&path).ok()?;
+
+        let len = meta.len();
+        let modified = meta
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_secs());
+        let etag = Etag::from_version(len, modified, false);
+        let last_modified = crate::date::http_date(modified);
+        let mut headers: Vec<(&'static [u8], Vec<u8>)> = vec![
+            (b"ETag", etag.as_bytes().to_vec()),
+            (b"Last-Modified", last_modified.clone()),
+            (b"Accept-Ranges", b"bytes".to_vec()),
+        ];
+        if let Some(age) = self.max_age {
+            let value = format!("public, max-age={}", age.as_secs());
+            headers.push((b"Cache-Control", value.into_bytes()));
+        }
+
+        let fresh = match &conditions.if_none_match {
+            Some(inm) => etag.matches_if_none_match(inm),
+            None => conditions.if_modified_since.as_deref() == Some(&last_modified[..]),
+        };
+        if fresh {
+            return Some(respond(304, "", headers, Body::Bytes(Vec::new())));
+        }
+
+        // A Range whose If-Range names another version gets the whole file.
+        let range = conditions
+            .range
+            .filter(|_| {
+                conditions
+                    .if_range
+                    .as_deref()
+                    .is_none_or(|v| v == etag.as_bytes() || v == &last_modified[..])
+            })
+            .and_then(|range| parse_range(&range, len));
+        let (status, start, count) = match range {
+            Some(Ok((start, end))) => {
+                let value = format!("bytes {start}-{end}/{len}");
+                headers.push((b"Content-Range", value.into_bytes()));
+                (206, start, end - start + 1)
+            }
+            Some(Err(())) => {
+                headers.push((b"Content-Range", format!("bytes */{len}").into_bytes()));
+                return Some(Box::new(move |res: &mut Response| {
+                    for (name, value) in &headers {
+                        res.header(name, value);
+                    }
+                    res.json(416, b"{\"error\":\"range not satisfiable\"}")
+                }));
+            }
+            None => (200, 0, len),
+        };
+
+        file.seek(SeekFrom::Start(start)).ok()?;
+        let body = if count <= STREAM_THRESHOLD {
+            let mut data = Vec::with_capacity(count as usize);
+            file.take(count).read_to_end(&mut data).ok()?;
+            Body::Bytes(data)
+        } else {
+            let (tx, body) = crate::stream::channel(4);
+            tokio::task::spawn_blocking(move || {
+                let mut file = file.take(count);
+                let mut buf = vec![0u8; CHUNK];
+                loop {
+                    match file.read(&mut buf) {
+                        Ok(0) => break,
+                        Ok(n) if tx.blocking_send(&buf[..n]) => {}
+                        Ok(_) => break,
+                        Err(_) => {
+                            tx.blocking_abort();
+                            break;
+                        }
+                    }
+                }
+            });
+            Body::Stream(body)
+        };
+        Some(respond(status, content_type(&path), headers, body))
+    }
+
+    /// `path` (percent-encoded, relative to the mount) inside the root, or
+    /// `None` if it would escape it or names a hidden file.
+    fn resolve(&self, path: &[u8]) -> Option<PathBuf> {
+        let decoded = percent_decode(path)?;
+        let mut out = self.root.clone();
+        for segment in decoded.split(|&b| b == b'/') {
+            if segment.is_empty() {
+                continue;
+            }
+            if segment[0] == b'.' || segment.iter().any(|b| matches!(b, b'\\' | b':' | 0)) {
+                return None;
+            }
+            out.push(std::str::from_utf8(segment).ok()?);
+        }
+        Some(out)
+    }
+}
+
+/// The request headers a file response depends on.
+struct Conditions {
+    if_none_match: Option<Vec<u8>>,
+    if_modified_since: Option<Vec<u8>>,
+    range: Option<Vec<u8>>,
+    if_range: Option<Vec<u8>>,
+}
+
+enum Body {
+    Bytes(Vec<u8>),
+    Stream(crate::stream::BodyStream),
+}
+
+fn respond(
+    status: u16,
+    content_type: &'static str,
+    headers: Vec<(&'static [u8], Vec<u8>)>,
+    body: Body,
+) -> WriteFn {
+    Box::new(move |res: &mut Response| {
+        // The file's own validator replaces the server-wide one.
+        res.enable_etag(EtagMode::Off, None);
+        res.skip_compression();
+        for (name, value) in &headers {
+            res.header(name, value);
+        }
+        match body {
+            Body::Bytes(data) => res.send(status, content_type, &data),
+            Body::Stream(stream) => res.stream(status, content_type, stream),
+        }
+    })
+}
+
+/// The byte range (inclusive) of a `Range` header for a body of `len`
+/// bytes: `None` to ignore it and send everything, `Err` if it starts past
+/// the end.
+fn parse_range(header: &[u8], len: u64) -> Option<Result<(u64, u64), ()>> {
+    let spec = std::str::from_utf8(header.strip_prefix(b"bytes=")?).ok()?;
+    if spec.contains(',') {
+        return None;
+    }
+    let (first, last) = spec.trim().split_once('-')?;
+    if first.is_empty() {
+        let suffix: u64 = last.parse().ok()?;
+        if suffix == 0 || len == 0 {
+            return Some(Err(()));
+        }
+        return Some(Ok((len.saturating_sub(suffix), len - 1)));
+    }
+    let start: u64 = first.parse().ok()?;
+    let end = match last {
+        "" => u64::MAX,
+        last => last.parse().ok()?,
+    };
+    if end < start {
+        return None;
+    }
+    if start >= len {
+        return Some(Err(()));
+    }
+    Some(Ok((start, end.min(len - 1))))
+}
+
+fn percent_decode(input: &[u8]) -> Option<Vec<u8>> {
+    let hex = |b: u8| (b as char).to_digit(16).map(|d| d as u8);
+    let mut out = Vec::with_capacity(input.len());
+    let mut i = 0;
+    while i < input.len() {
+        if input[i] == b'%' {
+            let hi = hex(*input.get(i + 1)?)?;
+            let lo = hex(*input.get(i + 2)?)?;
+            out.push(hi << 4 | lo);
+            i += 3;
+        } else {
+            out.push(input[i]);
+            i += 1;
+        }
+    }
+    Some(out)
+}
+
+fn strip_query(path: &[u8]) -> &[u8] {
+    path.iter()
+        .position(|&b| b == b'?')
+        .map_or(path, |q| &path[..q])
+}
+
+/// Content type by file extension.
+fn content_type(path: &std::path::Path) -> &'static str {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    match ext.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" | "map" => "application/json",
+        "txt" => "text/plain; charset=utf-8",
+        "csv" => "text/csv; charset=utf-8",
+        "md" => "text/markdown; charset=utf-8",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "wasm" => "application/wasm",
+        "pdf" => "application/pdf",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "ogg" => "audio/ogg",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_single_ranges() {
+        assert_eq!(parse_range(b"bytes=0-4", 10), Some(Ok((0, 4))));
+        assert_eq!(parse_range(b"bytes=5-", 10), Some(Ok((5, 9))));
+        assert_eq!(parse_range(b"bytes=-3", 10), Some(Ok((7, 9))));
+        assert_eq!(parse_range(b"bytes=8-100", 10), Some(Ok((8, 9))));
+        assert_eq!(parse_range(b"bytes=10-", 10), Some(Err(())));
+        assert_eq!(parse_range(b"bytes=0-1,4-5", 10), None);
+        assert_eq!(parse_range(b"bytes=4-1", 10), None);
+        assert_eq!(parse_range(b"items=0-1", 10), None);
+    }
+
+    #[test]
+    fn keeps_paths_inside_the_root() {
+        let dir = ServeDir::new("/srv/www");
+        assert_eq!(
+            dir.resolve(b"/css/app%20v2.css"),
+            Some(PathBuf::from("/srv/www/css/app v2.css"))
+        );
+        assert_eq!(dir.resolve(b"/"), Some(PathBuf::from("/srv/www")));
+        assert_eq!(dir.resolve(b"/../etc/passwd"), None);
+        assert_eq!(dir.resolve(b"/%2e%2e/etc/passwd"), None);
+        assert_eq!(dir.resolve(b"/a/.env"), None);
+        assert_eq!(dir.resolve(b"/a%5c..%5cb"), None);
+        assert_eq!(dir.resolve(b"/bad%zz"), None);
+    }
+}
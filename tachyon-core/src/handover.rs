@@ -0,0 +1,92 @@
+//! Zero-downtime restarts by passing the listening sockets to a new process.
+//!
+//! [`HandoverHandle::handover`] starts a command with the server's
+//! listening sockets as fds 3, 4, … and `LISTEN_FDS` set (the systemd
+//! socket activation layout), then drains the old server. The new process
+//! picks them up with `Server::listen_fds`. The sockets are never closed,
+//! so clients connecting in between wait in the listen backlog until the
+//! new process accepts them, and requests on the old process's
+//! connections finish under the usual drain (see `ServerConfig::drain_timeout`).
+//!
+//! Only the primary acceptor's sockets are passed. With several acceptors
+//! on SO_REUSEPORT, clients queued on the other acceptors' sockets are
+//! reset when the old process exits; serve the new process from the
+//! inherited sockets (as `listen_fds` does) to avoid that next time.
+
+use std::io;
+use std::os::fd::{AsRawFd, RawFd};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command};
+use std::sync::Arc;
+
+use crate::server::Listening;
+use crate::shutdown::Shutdown;
+
+/// Hands a running server's sockets to a new process. See
+/// [`Server::handover_handle`](crate::server::Server::handover_handle).
+#[derive(Clone)]
+pub struct HandoverHandle {
+    listening: Listening,
+    shutdown: Arc<Shutdown>,
+}
+
+impl HandoverHandle {
+    pub(crate) fn new(listening: Listening, shutdown: Arc<Shutdown>) -> Self {
+        Self {
+            listening,
+            shutdown,
+        }
+    }
+
+    /// Spawn `command` with the listening sockets, then start draining this
+    /// server. Fails (without draining) if the server is not listening yet
+    /// or the command cannot be started.
+    pub fn handover(&self, command: &mut Command) -> io::Result<Child> {
+        let listeners = self.listening.get().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotConnected, "server is not listening yet")
+        })?;
+        let fds: Vec<RawFd> = listeners.iter().map(AsRawFd::as_raw_fd).collect();
+        let first = 3;
+        let above = first + fds.len() as RawFd;
+        let mut moved = Vec::with_capacity(fds.len());
+        command
+            .env("LISTEN_FDS", fds.len().to_string())
+            .env_remove("LISTEN_PID")
+            .env_remove("LISTEN_FDNAMES");
+        // SAFETY: the closure runs between fork and exec and only calls
+        // async-signal-safe functions; `moved` never grows past the
+        // capacity reserved above, so it does not allocate.
+        unsafe {
+            command.pre_exec(move || {
+                // Copy every socket above the target range first, so placing
+                // one cannot overwrite another that is still to be moved.
+                moved.clear();
+                for &fd in &fds {
+                    let high = libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, above);
+                    if high < 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+                    moved.push(high);
+                }
+                // dup2 clears close-on-exec on the copy it makes.
+                for (i, &fd) in moved.iter().enumerate() {
+                    if libc::dup2(fd, first + i as RawFd) < 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+                }
+                Ok(())
+            });
+        }
+        let child = command.spawn()?;
+        self.shutdown.trigger();
+        Ok(child)
+    }
+
+    /// [`handover`](Self::handover) to a fresh copy of the running binary,
+    /// with the same arguments — e.g. after replacing it on disk.
+    pub fn restart(&self) -> io::Result<Child> {
+        let mut command = Command::new(std::env::current_exe()?);
+        command.args(std::env::args_os().skip(1));
+        self.handover(&mut command)
+    }
+}
@@ -0,0 +1,336 @@
+//! Post-processing hooks.
+//!
+//! An `after_response` hook runs once the response has been written and
+//! before it is sent. It sees what was produced (status, headers, body
+//! length, matched route, latency) and may edit the headers — the place for
+//! site-wide cache policy, extra security headers or timing headers without
+//! touching each handler. The head is only re-rendered when a hook changed it.
+//!
+//! `on_request` and `on_response` hooks are the read-only, cheaper
+//! counterparts for logging and metrics: they see the method, path, peer,
+//! status and latency but never the headers, so nothing is parsed or
+//! copied for them.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tachyon_http::methods::Method;
+use tachyon_http::response::CRLF;
+
+use crate::response::Response;
+
+/// Hook called after every response is written, before it hits the socket.
+pub type AfterResponse = Arc<dyn Fn(&ResponseInfo<'_>, &mut ResponseHeaders) + Send + Sync>;
+
+/// Hook called for every parsed request, before routing.
+pub type OnRequest = Arc<dyn Fn(&RequestInfo<'_>) + Send + Sync>;
+
+/// Hook called after every response is written (and after the
+/// `after_response` hooks), before it is sent.
+pub type OnResponse = Arc<dyn Fn(&ResponseInfo<'_>) + Send + Sync>;
+
+/// Hook called when a request is rejected for exceeding a size limit,
+/// before the error response is sent and the connection closed.
+pub type LimitHook = Arc<dyn Fn(&LimitExceeded) + Send + Sync>;
+
+/// Which limit a request ran into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    /// The request head did not fit in the read buffer (431).
+    Headers,
+    /// Head plus body did not fit in the read buffer (413).
+    Body,
+    /// The request target exceeded `Limits::max_uri`, or the request line
+    /// did not fit in the read buffer (414).
+    Uri,
+    /// A header line exceeded `Limits::max_header_size` (431).
+    HeaderSize,
+    /// More header fields than `Limits::max_headers` (431).
+    HeaderCount,
+}
+
+/// A request rejected for its size.
+#[derive(Debug, Clone)]
+pub struct LimitExceeded {
+    pub peer: SocketAddr,
+    pub limit: Limit,
+    /// Status sent to the client (413, 414 or 431).
+    pub status: u16,
+    /// Bytes of the request received when it was rejected.
+    pub received: usize,
+    /// The limit that was hit: bytes (`ServerConfig::buffer_size` or the
+    /// `Limits` field), or header fields for [`Limit::HeaderCount`].
+    pub max: usize,
+}
+
+/// What an `on_request` hook gets to know about the request.
+#[derive(Debug)]
+pub struct RequestInfo<'a> {
+    pub method: Method,
+    /// Request path without the query string.
+    pub path: &'a [u8],
+    pub peer: SocketAddr,
+}
+
+/// What a hook gets to know about the request/response pair.
+#[derive(Debug)]
+pub struct ResponseInfo<'a> {
+    pub method: Method,
+    /// Request path without the query string.
+    pub path: &'a [u8],
+    /// Route that handled the request; `None` for the fallback and 404/405.
+    pub route: Option<&'a [u8]>,
+    pub status: u16,
+    /// Bytes of body actually sent (0 for HEAD and bodiless statuses).
+    pub body_len: usize,
+    /// Time from parsed request to written response.
+    pub latency: Duration,
+}
+
+/// Editable view of the response headers (everything between the status
+/// line and the blank line).
+#[derive(Debug, Default)]
+pub struct ResponseHeaders {
+    fields: Vec<(Vec<u8>, Vec<u8>)>,
+    changed: bool,
+}
+
+impl ResponseHeaders {
+    fn parse(block: &[u8]) -> Self {
+        let fields = block
+            .split(|&b| b == b'\n')
+            .filter_map(|line| {
+                let line = line.strip_suffix(b"\r").unwrap_or(line);
+                let colon = line.iter().position(|&b| b == b':')?;
+                let value = line[colon + 1..].trim_ascii_start();
+                Some((line[..colon].to_vec(), value.to_vec()))
+            })
+            .collect();
+        Self {
+            fields,
+            changed: false,
+        }
+    }
+
+    /// First value of `name` (case-insensitive).
+    pub fn get(&self, name: &[u8]) -> Option<&[u8]> {
+        self.iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v)
+    }
+
+    pub fn contains(&self, name: &[u8]) -> bool {
+        self.get(name).is_some()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&[u8], &[u8])> {
+        self.fields
+            .iter()
+            .map(|(n, v)| (n.as_slice(), v.as_slice()))
+    }
+
+    /// Set `name`, replacing every existing value.
+    pub fn insert(&mut self, name: &[u8], value: &[u8]) {
+        self.remove(name);
+        self.append(name, value);
+    }
+
+    /// Add `name` without touching existing values.
+    pub fn append(&mut self, name: &[u8], value: &[u8]) {
+        let value: Vec<u8> = value
+            .iter()
+            .copied()
+            .filter(|&b| b != b'\r' && b != b'\n')
+            .collect();
+        self.fields.push((name.to_vec(), value));
+        self.changed = true;
+    }
+
+    /// Remove every value of `name`. Returns whether anything was removed.
+    pub fn remove(&mut self, name: &[u8]) -> bool {
+        let before = self.fields.len();
+        self.fields.retain(|(n, _)| !n.eq_ignore_ascii_case(name));
+        let removed = self.fields.len() != before;
+        self.changed |= removed;
+        removed
+    }
+
+    fn render(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.fields.len() * 32);
+        for (name, value) in &self.fields {
+            out.extend_from_slice(name);
+            out.extend_from_slice(b": ");
+            out.extend_from_slice(value);
+            out.extend_from_slice(CRLF);
+        }
+        out
+    }
+}
+
+/// Run `hooks` against a written response, re-rendering its head if any
+/// hook edited the headers.
+pub(crate) fn run(
+    hooks: &[AfterResponse],
+    res: &mut Response<'_>,
+    method: Method,
+    path: &[u8],
+    route: Option<&[u8]>,
+    started: Instant,
+) {
+    let data = res.data();
+    let Some(status_end) = data.windows(2).position(|w| w == CRLF) else {
+        return;
+    };
+    let Some(head_end) = data.windows(4).position(|w| w == b"\r\n\r\n") else {
+        return;
+    };
+    let mut headers = ResponseHeaders::parse(&data[status_end + 2..head_end + 2]);
+    let info = ResponseInfo {
+        method,
+        path: strip_query(path),
+        route,
+        status: res.status().unwrap_or(0),
+        body_len: data.len() - (head_end + 4),
+        latency: started.elapsed(),
+    };
+    for hook in hooks {
+        hook(&info, &mut headers);
+    }
+    if headers.changed {
+        let rebuilt = [
+            &data[..status_end + 2],
+            &headers.render(),
+            CRLF,
+            &data[head_end + 4..],
+        ]
+        .concat();
+        res.replace(&rebuilt);
+    }
+}
+
+/// Run the `on_request` hooks.
+#[inline]
+pub(crate) fn request_started(hooks: &[OnRequest], method: Method, path: &[u8], peer: SocketAddr) {
+    if hooks.is_empty() {
+        return;
+    }
+    let info = RequestInfo {
+        method,
+        path: strip_query(path),
+        peer,
+    };
+    for hook in hooks {
+        hook(&info);
+    }
+}
+
+/// Run the `on_response` hooks against a written response.
+pub(crate) fn response_written(
+    hooks: &[OnResponse],
+    res: &Response<'_>,
+    method: Method,
+    path: &[u8],
+    route: Option<&[u8]>,
+    started: Instant,
+) {
+    let info = ResponseInfo {
+        method,
+        path: strip_query(path),
+        route,
+        status: res.status().unwrap_or(0),
+        body_len: res.body_len(),
+        latency: started.elapsed(),
+    };
+    for hook in hooks {
+        hook(&info);
+    }
+}
+
+fn strip_query(path: &[u8]) -> &[u8] {
+    path.iter()
+        .position(|&b| b == b'?')
+        .map_or(path, |q| &path[..q])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tachyon_http::encoding::Encoding;
+
+    #[test]
+    fn hook_sees_response_and_edits_headers() {
+        let mut buf = [0u8; 512];
+        let mut res = Response::new(&mut buf, b"", Encoding::Identity, usize::MAX);
+        res.header(b"X-Drop", b"1");
+        res.text(200, b"hello");
+
+        let hook: AfterResponse = Arc::new(|info, headers| {
+            assert_eq!(info.status, 200);
+            assert_eq!(info.body_len, 5);
+            assert_eq!(info.path, b"/greet");
+            assert_eq!(info.route, Some(&b"/greet"[..]));
+            assert_eq!(headers.get(b"content-length"), Some(&b"5"[..]));
+            headers.remove(b"x-drop");
+            headers.insert(b"Cache-Control", b"no-store");
+        });
+        run(
+            &[hook],
+            &mut res,
+            Method::Get,
+            b"/greet?name=x",
+            Some(b"/greet"),
+            Instant::now(),
+        );
+
+        let out = std::str::from_utf8(res.data()).unwrap();
+        assert!(out.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(out.contains("Cache-Control: no-store\r\n"));
+        assert!(!out.contains("X-Drop"));
+        assert!(out.ends_with("\r\n\r\nhello"));
+    }
+
+    #[test]
+    fn observers_see_request_and_response() {
+        use std::sync::Mutex;
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let log = seen.clone();
+        let on_request: OnRequest = Arc::new(move |info| {
+            log.lock().unwrap().push(format!(
+                "{} {} {}",
+                info.method.as_str(),
+                String::from_utf8_lossy(info.path),
+                info.peer
+            ));
+        });
+        let log = seen.clone();
+        let on_response: OnResponse = Arc::new(move |info| {
+            log.lock().unwrap().push(format!(
+                "{} {} {:?}",
+                info.status,
+                info.body_len,
+                info.route.map(String::from_utf8_lossy)
+            ));
+        });
+
+        let peer = "127.0.0.1:5000".parse().unwrap();
+        request_started(&[on_request], Method::Post, b"/items?page=2", peer);
+        let mut buf = [0u8; 512];
+        let mut res = Response::new(&mut buf, b"", Encoding::Identity, usize::MAX);
+        res.text(201, b"made");
+        response_written(
+            &[on_response],
+            &res,
+            Method::Post,
+            b"/items?page=2",
+            Some(b"/items"),
+            Instant::now(),
+        );
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            ["POST /items 127.0.0.1:5000", "201 4 Some(\"/items\")"]
+        );
+    }
+}
@@ -0,0 +1,233 @@
+//! CIDR allow/deny lists, checked when a connection is accepted.
+//!
+//! A peer is rejected if it matches a deny entry, or if an allow list is set
+//! and it matches none of its entries. Lookups walk a binary prefix trie
+//! (one per address family), so cost depends on address width, not on the
+//! number of entries. IPv4-mapped IPv6 peers are checked as IPv4.
+//!
+//! Rejected peers either have their connection closed before any request is
+//! read ([`Reject::Close`], the default) or get a 403 error page
+//! ([`Reject::Forbidden`]) — both before routing.
+
+use std::fmt;
+use std::net::IpAddr;
+
+/// What happens to a rejected peer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Reject {
+    /// Close the connection without reading from it.
+    #[default]
+    Close,
+    /// Answer the first request with 403, then close.
+    Forbidden,
+}
+
+/// A CIDR string that could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidCidr(pub String);
+
+impl fmt::Display for InvalidCidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid CIDR: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidCidr {}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Node {
+    /// Child index per bit; 0 = none (the root is never a child).
+    child: [u32; 2],
+    /// A prefix ends here.
+    terminal: bool,
+}
+
+/// Binary trie over address bits, most significant first.
+#[derive(Debug, Clone)]
+struct Trie {
+    nodes: Vec<Node>,
+}
+
+impl Trie {
+    fn new() -> Self {
+        Self {
+            nodes: vec![Node::default()],
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.nodes.len() == 1 && !self.nodes[0].terminal
+    }
+
+    /// Insert the first `len` bits of `bits` (left-aligned in a `width`-bit word).
+    fn insert(&mut self, bits: u128, len: u8, width: u8) {
+        let mut node = 0;
+        for i in 0..len {
+            let bit = ((bits >> (width - 1 - i)) & 1) as usize;
+            if self.nodes[node].child[bit] == 0 {
+                self.nodes.push(Node::default());
+                self.nodes[node].child[bit] = (self.nodes.len() - 1) as u32;
+            }
+            node = self.nodes[node].child[bit] as usize;
+        }
+        self.nodes[node].terminal = true;
+    }
+
+    /// Whether any inserted prefix covers `bits`.
+    fn matches(&self, bits: u128, width: u8) -> bool {
+        let mut node = 0;
+        for i in 0..width {
+            if self.nodes[node].terminal {
+                return true;
+            }
+            let bit = ((bits >> (width - 1 - i)) & 1) as usize;
+            match self.nodes[node].child[bit] {
+                0 => return false,
+                next => node = next as usize,
+            }
+        }
+        self.nodes[node].terminal
+    }
+}
+
+#[derive(Debug, Clone)]
+struct List {
+    v4: Trie,
+    v6: Trie,
+}
+
+impl List {
+    fn new() -> Self {
+        Self {
+            v4: Trie::new(),
+            v6: Trie::new(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.v4.is_empty() && self.v6.is_empty()
+    }
+
+    fn insert(&mut self, cidr: &str) -> Result<(), InvalidCidr> {
+        let invalid = || InvalidCidr(cidr.to_string());
+        let (addr, len) = match cidr.split_once('/') {
+            Some((addr, len)) => (addr, Some(len.parse::<u8>().map_err(|_| invalid())?)),
+            None => (cidr, None),
+        };
+        match addr.trim().parse::<IpAddr>().map_err(|_| invalid())? {
+            IpAddr::V4(ip) => {
+                let len = len.unwrap_or(32);
+                if len > 32 {
+                    return Err(invalid());
+                }
+                self.v4.insert(u32::from(ip) as u128, len, 32);
+            }
+            IpAddr::V6(ip) => {
+                let len = len.unwrap_or(128);
+                if len > 128 {
+                    return Err(invalid());
+                }
+                match ip.to_ipv4_mapped() {
+                    Some(v4) if len >= 96 => self.v4.insert(u32::from(v4) as u128, len - 96, 32),
+                    _ => self.v6.insert(u128::from(ip), len, 128),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn matches(&self, ip: IpAddr) -> bool {
+        match ip {
+            IpAddr::V4(ip) => self.v4.matches(u32::from(ip) as u128, 32),
+            IpAddr::V6(ip) => self.v6.matches(u128::from(ip), 128),
+        }
+    }
+}
+
+/// Allow/deny lists. Deny wins over allow; an empty allow list allows all.
+#[derive(Debug, Clone)]
+pub struct IpFilter {
+    allow: List,
+    deny: List,
+    reject: Reject,
+}
+
+impl Default for IpFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IpFilter {
+    pub fn new() -> Self {
+        Self {
+            allow: List::new(),
+            deny: List::new(),
+            reject: Reject::default(),
+        }
+    }
+
+    /// Only accept peers in `cidr` (e.g. `"10.0.0.0/8"`, `"::1"`) or other
+    /// allowed ranges.
+    pub fn allow(mut self, cidr: &str) -> Result<Self, InvalidCidr> {
+        self.allow.insert(cidr)?;
+        Ok(self)
+    }
+
+    /// Reject peers in `cidr`, even if they are also allowed.
+    pub fn deny(mut self, cidr: &str) -> Result<Self, InvalidCidr> {
+        self.deny.insert(cidr)?;
+        Ok(self)
+    }
+
+    /// How to reject (default: close the connection).
+    pub fn reject(mut self, reject: Reject) -> Self {
+        self.reject = reject;
+        self
+    }
+
+    /// Whether `ip` may connect.
+    pub fn allows(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        !self.deny.matches(ip) && (self.allow.is_empty() || self.allow.matches(ip))
+    }
+
+    pub(crate) fn rejection(&self) -> Reject {
+        self.reject
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deny_wins_and_allow_restricts() {
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+        let filter = IpFilter::new()
+            .allow("10.0.0.0/8")
+            .unwrap()
+            .allow("2001:db8::/32")
+            .unwrap()
+            .deny("10.1.0.0/16")
+            .unwrap()
+            .deny("10.2.3.4")
+            .unwrap();
+
+        assert!(filter.allows(ip("10.9.9.9")));
+        assert!(filter.allows(ip("::ffff:10.9.9.9")));
+        assert!(filter.allows(ip("2001:db8:1::1")));
+        assert!(!filter.allows(ip("10.1.2.3")));
+        assert!(!filter.allows(ip("10.2.3.4")));
+        assert!(filter.allows(ip("10.2.3.5")));
+        assert!(!filter.allows(ip("192.168.0.1")));
+        assert!(!filter.allows(ip("2001:db9::1")));
+
+        let open = IpFilter::new().deny("0.0.0.0/0").unwrap();
+        assert!(!open.allows(ip("8.8.8.8")));
+        assert!(open.allows(ip("::1")));
+
+        assert!(IpFilter::new().allow("10.0.0.0/33").is_err());
+        assert!(IpFilter::new().allow("not-an-ip").is_err());
+    }
+}
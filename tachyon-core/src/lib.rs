@@ -1,6 +1,47 @@
+pub mod access_log;
+pub mod cache;
+pub mod cancel;
+pub mod compress;
 pub mod config;
+pub mod cookie;
+pub mod cors;
 pub mod date;
+pub mod envelope;
+pub mod error;
+pub mod extract;
+pub mod files;
+#[cfg(unix)]
+pub mod handover;
+pub mod hooks;
+pub mod ip_filter;
+pub mod limit;
+pub mod metrics;
+pub mod middleware;
+pub mod multipart;
+#[cfg(feature = "otel")]
+pub mod otel;
 pub mod response;
 pub mod rio;
+pub mod router;
 pub mod server;
+pub mod shed;
+mod shutdown;
+pub mod sse;
+pub mod stats;
+pub mod stream;
+#[cfg(feature = "templates")]
+pub mod template;
+#[cfg(any(test, feature = "test-util"))]
+#[doc(hidden)]
+pub mod testing;
+#[cfg(feature = "tower")]
+pub mod tower;
+mod trace;
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+mod uring;
 mod utils;
+pub mod ws;
+
+/// The HTTP types the API is built on (`Request`, `Method`, ...), for
+/// crates that only depend on this one.
+pub use tachyon_http;
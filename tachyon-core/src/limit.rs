@@ -0,0 +1,121 @@
+//! Cap on concurrent connections.
+//!
+//! Each open connection holds a slot until it closes. At the cap the server
+//! either stops accepting ([`OverLimit::Wait`]: new clients queue in the
+//! kernel's listen backlog until a slot frees up) or accepts and
+//! immediately answers `503` + `Connection: close` ([`OverLimit::Reject`]),
+//! so a flood cannot exhaust file descriptors or buffer memory. Slots are
+//! shared by every acceptor and bind address.
+//!
+//! There is no separate per-connection request cap: requests on one
+//! connection (including pipelined ones) are always handled one at a time.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// What to do with connections beyond the cap.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverLimit {
+    /// Stop accepting until a connection closes.
+    #[default]
+    Wait,
+    /// Accept, send 503 and close.
+    Reject,
+}
+
+/// Shared connection budget. Clones share the same slots.
+#[derive(Debug, Clone)]
+pub struct ConnectionLimit {
+    max: usize,
+    over: OverLimit,
+    slots: Arc<Semaphore>,
+    rejected: Arc<AtomicU64>,
+}
+
+/// Sent as-is when rejecting: no buffers, no task.
+pub(crate) const REJECT_RESPONSE: &[u8] =
+    b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+
+impl ConnectionLimit {
+    /// At most `max` open connections; beyond that, wait.
+    pub fn new(max: usize) -> Self {
+        let max = max.clamp(1, Semaphore::MAX_PERMITS);
+        Self {
+            max,
+            over: OverLimit::default(),
+            slots: Arc::new(Semaphore::new(max)),
+            rejected: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// What to do at the cap (default: wait).
+    pub fn on_limit(mut self, over: OverLimit) -> Self {
+        self.over = over;
+        self
+    }
+
+    /// Connections currently open.
+    pub fn active(&self) -> usize {
+        self.max - self.slots.available_permits()
+    }
+
+    /// Connections answered 503 so far ([`OverLimit::Reject`] only).
+    pub fn rejected(&self) -> u64 {
+        self.rejected.load(Ordering::Relaxed)
+    }
+
+    /// With [`OverLimit::Wait`], wait for a free slot before accepting.
+    pub(crate) async fn reserve(&self) -> Option<OwnedSemaphorePermit> {
+        match self.over {
+            OverLimit::Wait => self.slots.clone().acquire_owned().await.ok(),
+            OverLimit::Reject => None,
+        }
+    }
+
+    /// Slot for an accepted connection, or `None` if it must be rejected
+    /// (see [`reject`]).
+    pub(crate) fn admit(
+        &self,
+        reserved: Option<OwnedSemaphorePermit>,
+    ) -> Option<OwnedSemaphorePermit> {
+        let slot = reserved.or_else(|| self.slots.clone().try_acquire_owned().ok());
+        if slot.is_none() {
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+        }
+        slot
+    }
+}
+
+/// Answer 503 and close. Best effort: a fresh socket's send buffer takes
+/// the whole response at once, so this never waits.
+pub(crate) fn reject(stream: tokio::net::TcpStream) {
+    use std::io::Write;
+
+    // tokio's `try_write` reports WouldBlock until the first readiness event;
+    // the std socket (still non-blocking) writes straight away.
+    if let Ok(mut stream) = stream.into_std() {
+        let _ = stream.write_all(REJECT_RESPONSE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_active_connections() {
+        let limit = ConnectionLimit::new(2).on_limit(OverLimit::Reject);
+        let a = limit.admit(None);
+        let b = limit.admit(None);
+        assert!(a.is_some() && b.is_some());
+        assert_eq!(limit.active(), 2);
+        assert!(limit.admit(None).is_none());
+        assert_eq!(limit.rejected(), 1);
+        drop(a);
+        // Clones share the slots.
+        assert_eq!(limit.clone().active(), 1);
+        assert!(limit.clone().admit(None).is_some());
+    }
+}
@@ -0,0 +1,401 @@
+//! Prometheus metrics.
+//!
+//! With `ServerConfig::metrics`, the server counts requests and latency by
+//! method, matched route and status, tracks in-flight requests and open
+//! connections, counts connections refused at accept and failed accepts,
+//! reads the stats of every cached route's [`ResponseCache`],
+//! and answers `GET <path>` (default `/metrics`) with the Prometheus text
+//! exposition format. Unmatched requests are labelled `route="unmatched"`
+//! so arbitrary paths cannot blow up the series count.
+//!
+//! [`Metrics`] is a cheap `Arc` handle: keep a clone to render the same
+//! output elsewhere.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tachyon_http::methods::Method;
+
+use crate::cache::{CacheStats, ResponseCache};
+
+/// Histogram bucket upper bounds, in seconds.
+const BUCKETS: [f64; 14] = [
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+#[derive(Default)]
+struct Series {
+    count: u64,
+    sum_us: u64,
+    /// Non-cumulative counts per bucket; the last slot is `+Inf`.
+    buckets: [u64; BUCKETS.len() + 1],
+}
+
+/// (method, status) → series, for one route.
+type RouteSeries = HashMap<(Method, u16), Series>;
+
+/// One cache metric family: name, help, type, value.
+type CacheFamily = (
+    &'static str,
+    &'static str,
+    &'static str,
+    fn(&CacheStats) -> u64,
+);
+
+struct Inner {
+    series: Mutex<HashMap<Box<[u8]>, RouteSeries>>,
+    in_flight: AtomicI64,
+    connections: AtomicI64,
+    connections_total: AtomicU64,
+    rejected_limit: AtomicU64,
+    rejected_ip_filter: AtomicU64,
+    accept_errors: AtomicU64,
+    caches: Mutex<Vec<(String, ResponseCache)>>,
+}
+
+/// Server metrics registry and `/metrics` endpoint.
+#[derive(Clone)]
+pub struct Metrics {
+    path: Arc<[u8]>,
+    inner: Arc<Inner>,
+}
+
+impl std::fmt::Debug for Metrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Metrics")
+            .field("path", &String::from_utf8_lossy(&self.path))
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    /// Metrics served at `/metrics`.
+    pub fn new() -> Self {
+        Self {
+            path: b"/metrics"[..].into(),
+            inner: Arc::new(Inner {
+                series: Mutex::new(HashMap::new()),
+                in_flight: AtomicI64::new(0),
+                connections: AtomicI64::new(0),
+                connections_total: AtomicU64::new(0),
+                rejected_limit: AtomicU64::new(0),
+                rejected_ip_filter: AtomicU64::new(0),
+                accept_errors: AtomicU64::new(0),
+                caches: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Serve the exposition at `path` instead of `/metrics`.
+    pub fn path(mut self, path: &str) -> Self {
+        self.path = path.as_bytes().into();
+        self
+    }
+
+    /// Report `cache` under `cache="<name>"`. Cached routes are added
+    /// automatically (named after their path); a cache shared by several
+    /// routes is reported once.
+    pub fn watch_cache(&self, name: &str, cache: &ResponseCache) {
+        let mut caches = self.inner.caches.lock().unwrap_or_else(|e| e.into_inner());
+        if !caches.iter().any(|(_, c)| c.ptr_eq(cache)) {
+            caches.push((name.to_string(), cache.clone()));
+        }
+    }
+
+    /// Stop reporting `cache` (its routes were reloaded away).
+    pub(crate) fn unwatch_cache(&self, cache: &ResponseCache) {
+        let mut caches = self.inner.caches.lock().unwrap_or_else(|e| e.into_inner());
+        caches.retain(|(_, c)| !c.ptr_eq(cache));
+    }
+
+    /// Whether `method` + `target` is a scrape of the metrics endpoint.
+    #[inline]
+    pub(crate) fn is_scrape(&self, method: Method, target: &[u8]) -> bool {
+        matches!(method, Method::Get | Method::Head)
+            && target
+                .split(|&b| b == b'?')
+                .next()
+                .is_some_and(|p| p == &*self.path)
+    }
+
+    pub(crate) fn connection_opened(&self) -> ConnectionGuard {
+        self.inner.connections.fetch_add(1, Ordering::Relaxed);
+        self.inner.connections_total.fetch_add(1, Ordering::Relaxed);
+        ConnectionGuard(self.clone())
+    }
+
+    pub(crate) fn connection_rejected(&self, reason: Rejected) {
+        let counter = match reason {
+            Rejected::Limit => &self.inner.rejected_limit,
+            Rejected::IpFilter => &self.inner.rejected_ip_filter,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn accept_failed(&self) {
+        self.inner.accept_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn request_started(&self) {
+        self.inner.in_flight.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a finished request. `route` is the matched route, if any.
+    pub(crate) fn request_finished(
+        &self,
+        method: Method,
+        route: Option<&[u8]>,
+        status: u16,
+        latency: Duration,
+    ) {
+        self.inner.in_flight.fetch_sub(1, Ordering::Relaxed);
+        let route = route.unwrap_or(b"unmatched");
+        let secs = latency.as_secs_f64();
+        let bucket = BUCKETS
+            .iter()
+            .position(|&le| secs <= le)
+            .unwrap_or(BUCKETS.len());
+        let mut series = self.inner.series.lock().unwrap_or_else(|e| e.into_inner());
+        // Look up before inserting so the common case doesn't allocate a key.
+        let by_route = match series.get_mut(route) {
+            Some(by_route) => by_route,
+            None => series.entry(route.into()).or_default(),
+        };
+        let entry = by_route.entry((method, status)).or_default();
+        entry.count += 1;
+        entry.sum_us += latency.as_micros() as u64;
+        entry.buckets[bucket] += 1;
+    }
+
+    /// Prometheus text exposition (format 0.0.4).
+    pub fn render(&self) -> String {
+        let mut out = String::with_capacity(4096);
+        let series = self.inner.series.lock().unwrap_or_else(|e| e.into_inner());
+        let mut rows: Vec<_> = series
+            .iter()
+            .flat_map(|(route, by)| by.iter().map(move |(&(m, st), s)| ((m, &**route, st), s)))
+            .collect();
+        rows.sort_by_key(|&((m, route, st), _)| (route, m as u8, st));
+
+        out.push_str("# HELP tachyon_requests_total Requests handled.\n");
+        out.push_str("# TYPE tachyon_requests_total counter\n");
+        for ((method, route, status), s) in &rows {
+            let labels = labels(*method, route, *status);
+            let _ = writeln!(out, "tachyon_requests_total{{{labels}}} {}", s.count);
+        }
+
+        out.push_str(
+            "# HELP tachyon_request_duration_seconds Request latency, parse to response.\n",
+        );
+        out.push_str("# TYPE tachyon_request_duration_seconds histogram\n");
+        for ((method, route, status), s) in &rows {
+            let labels = labels(*method, route, *status);
+            let mut cumulative = 0;
+            for (i, le) in BUCKETS.iter().enumerate() {
+                cumulative += s.buckets[i];
+                let _ = writeln!(
+                    out,
+                    "tachyon_request_duration_seconds_bucket{{{labels},le=\"{le}\"}} {cumulative}"
+                );
+            }
+            let _ = writeln!(
+                out,
+                "tachyon_request_duration_seconds_bucket{{{labels},le=\"+Inf\"}} {}",
+                s.count
+            );
+            let _ = writeln!(
+                out,
+                "tachyon_request_duration_seconds_sum{{{labels}}} {}",
+                s.sum_us as f64 / 1e6
+            );
+            let _ = writeln!(
+                out,
+                "tachyon_request_duration_seconds_count{{{labels}}} {}",
+                s.count
+            );
+        }
+        drop(series);
+
+        let gauge = |out: &mut String, name: &str, help: &str, kind: &str, value: i64| {
+            let _ = writeln!(
+                out,
+                "# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}"
+            );
+        };
+        gauge(
+            &mut out,
+            "tachyon_requests_in_flight",
+            "Requests being handled.",
+            "gauge",
+            self.inner.in_flight.load(Ordering::Relaxed),
+        );
+        gauge(
+            &mut out,
+            "tachyon_connections_open",
+            "Open client connections.",
+            "gauge",
+            self.inner.connections.load(Ordering::Relaxed),
+        );
+        gauge(
+            &mut out,
+            "tachyon_connections_total",
+            "Accepted client connections.",
+            "counter",
+            self.inner.connections_total.load(Ordering::Relaxed) as i64,
+        );
+        out.push_str(
+            "# HELP tachyon_connections_rejected_total Connections refused at accept.\n\
+             # TYPE tachyon_connections_rejected_total counter\n",
+        );
+        for (reason, counter) in [
+            ("limit", &self.inner.rejected_limit),
+            ("ip_filter", &self.inner.rejected_ip_filter),
+        ] {
+            let _ = writeln!(
+                out,
+                "tachyon_connections_rejected_total{{reason=\"{reason}\"}} {}",
+                counter.load(Ordering::Relaxed)
+            );
+        }
+        gauge(
+            &mut out,
+            "tachyon_accept_errors_total",
+            "Failed accept calls.",
+            "counter",
+            self.inner.accept_errors.load(Ordering::Relaxed) as i64,
+        );
+
+        let caches = self.inner.caches.lock().unwrap_or_else(|e| e.into_inner());
+        if !caches.is_empty() {
+            let stats: Vec<_> = caches
+                .iter()
+                .map(|(name, c)| (escape(name.as_bytes()), c.stats()))
+                .collect();
+            let families: [CacheFamily; 4] = [
+                (
+                    "tachyon_cache_hits_total",
+                    "Response cache hits.",
+                    "counter",
+                    |s| s.hits,
+                ),
+                (
+                    "tachyon_cache_misses_total",
+                    "Response cache misses.",
+                    "counter",
+                    |s| s.misses,
+                ),
+                (
+                    "tachyon_cache_evictions_total",
+                    "Responses evicted to make room.",
+                    "counter",
+                    |s| s.evictions,
+                ),
+                ("tachyon_cache_entries", "Cached responses.", "gauge", |s| {
+                    s.entries as u64
+                }),
+            ];
+            for (metric, help, kind, value) in families {
+                let _ = writeln!(out, "# HELP {metric} {help}\n# TYPE {metric} {kind}");
+                for (name, s) in &stats {
+                    let _ = writeln!(out, "{metric}{{cache=\"{name}\"}} {}", value(s));
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Why a connection was refused at accept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Rejected {
+    /// Over `ServerConfig::connection_limit` (answered 503).
+    Limit,
+    /// Denied by `ServerConfig::ip_filter` (closed or answered 403).
+    IpFilter,
+}
+
+/// Decrements the open-connection gauge when the connection task ends.
+pub(crate) struct ConnectionGuard(Metrics);
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.0.inner.connections.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+fn labels(method: Method, route: &[u8], status: u16) -> String {
+    format!(
+        "method=\"{}\",route=\"{}\",status=\"{status}\"",
+        method.as_str(),
+        escape(route)
+    )
+}
+
+/// Escape a label value (`\`, `"`, newline).
+fn escape(value: &[u8]) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in String::from_utf8_lossy(value).chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_prometheus_exposition() {
+        let metrics = Metrics::new();
+        let cache = ResponseCache::new(Duration::from_secs(1), 8);
+        metrics.watch_cache("/feed", &cache);
+        metrics.watch_cache("/feed-alias", &cache);
+        assert!(metrics.is_scrape(Method::Get, b"/metrics?x=1"));
+        assert!(!metrics.is_scrape(Method::Post, b"/metrics"));
+
+        let _conn = metrics.connection_opened();
+        metrics.request_started();
+        metrics.request_finished(Method::Get, Some(b"/users"), 200, Duration::from_millis(3));
+        metrics.request_started();
+        metrics.request_finished(Method::Get, None, 404, Duration::from_micros(200));
+        metrics.request_started();
+
+        let out = metrics.render();
+        assert!(out.contains(
+            "tachyon_requests_total{method=\"GET\",route=\"/users\",status=\"200\"} 1\n"
+        ));
+        assert!(out.contains("route=\"unmatched\",status=\"404\"} 1\n"));
+        assert!(out.contains(
+            "tachyon_request_duration_seconds_bucket{method=\"GET\",route=\"/users\",status=\"200\",le=\"0.0025\"} 0\n"
+        ));
+        assert!(out.contains(
+            "tachyon_request_duration_seconds_bucket{method=\"GET\",route=\"/users\",status=\"200\",le=\"0.005\"} 1\n"
+        ));
+        assert!(out.contains("tachyon_requests_in_flight 1\n"));
+        assert!(out.contains("tachyon_connections_open 1\n"));
+        metrics.connection_rejected(Rejected::Limit);
+        metrics.connection_rejected(Rejected::Limit);
+        metrics.accept_failed();
+        let out = metrics.render();
+        assert!(out.contains("tachyon_connections_rejected_total{reason=\"limit\"} 2\n"));
+        assert!(out.contains("tachyon_connections_rejected_total{reason=\"ip_filter\"} 0\n"));
+        assert!(out.contains("tachyon_accept_errors_total 1\n"));
+        assert_eq!(out.matches("tachyon_cache_hits_total{").count(), 1);
+        assert!(out.contains("tachyon_cache_entries{cache=\"/feed\"} 0\n"));
+    }
+}
@@ -0,0 +1,122 @@
+//! Middleware chain.
+//!
+//! A middleware receives the request and a [`Next`] for the rest of the
+//! chain. It can run code before the handler, short-circuit by returning its
+//! own response without calling `next`, or wrap the handler's `WriteFn` to
+//! act after it (add headers, time it, …).
+//!
+//! ```ignore
+//! router.middleware(Arc::new(|req, next| {
+//!     if req.header(b"authorization").is_none() {
+//!         return Box::pin(async {
+//!             Box::new(|res: &mut Response| res.json(401, b"{\"error\":\"unauthorized\"}")) as WriteFn
+//!         });
+//!     }
+//!     let downstream = next.run(req);
+//!     Box::pin(async move {
+//!         let write = downstream.await;
+//!         Box::new(move |res: &mut Response| {
+//!             res.header(b"X-Authenticated", b"1");
+//!             write(res)
+//!         }) as WriteFn
+//!     })
+//! }));
+//! ```
+//!
+//! The chain is frozen with the router when the server starts, so running it
+//! takes no locks. Middleware wraps handler routes and the fallback;
+//! static routes and cache hits are answered before it runs.
+
+use std::sync::Arc;
+
+use tachyon_http::http::Request;
+
+use crate::server::{Handler, HandlerFuture};
+
+/// A middleware: request + rest of the chain → response future.
+pub type Middleware = Arc<dyn for<'r> Fn(&'r Request<'r>, Next) -> HandlerFuture + Send + Sync>;
+
+/// The remainder of the chain, ending in the matched handler.
+pub struct Next {
+    chain: Arc<[Middleware]>,
+    index: usize,
+    handler: Handler,
+}
+
+impl Next {
+    pub(crate) fn new(chain: Arc<[Middleware]>, handler: Handler) -> Self {
+        Self {
+            chain,
+            index: 0,
+            handler,
+        }
+    }
+
+    /// Run the next middleware, or the handler once the chain is exhausted.
+    pub fn run(self, req: &Request<'_>) -> HandlerFuture {
+        let Some(middleware) = self.chain.get(self.index).cloned() else {
+            return (self.handler)(req);
+        };
+        let next = Self {
+            chain: self.chain,
+            index: self.index + 1,
+            handler: self.handler,
+        };
+        middleware(req, next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::response::Response;
+    use crate::server::WriteFn;
+    use crate::testing::{block_on, render, request};
+
+    fn run(chain: Vec<Middleware>, raw: &[u8]) -> String {
+        let handler: Handler = Arc::new(|_req| {
+            Box::pin(async { Box::new(|res: &mut Response| res.text(200, b"handler")) as WriteFn })
+        });
+        let req = request(raw);
+        render(block_on(Next::new(chain.into(), handler).run(&req)))
+    }
+
+    #[test]
+    fn runs_in_order_and_short_circuits() {
+        let tag = |name: &'static [u8]| -> Middleware {
+            Arc::new(move |req, next| {
+                let downstream = next.run(req);
+                Box::pin(async move {
+                    let write = downstream.await;
+                    Box::new(move |res: &mut Response| {
+                        res.header(b"X-Order", name);
+                        write(res)
+                    }) as WriteFn
+                })
+            })
+        };
+        let guard: Middleware = Arc::new(|req, next| {
+            if req.header(b"x-block").is_some() {
+                return Box::pin(async {
+                    Box::new(|res: &mut Response| res.text(403, b"blocked")) as WriteFn
+                });
+            }
+            next.run(req)
+        });
+
+        let out = run(
+            vec![tag(b"outer"), guard.clone(), tag(b"inner")],
+            b"GET / HTTP/1.1\r\n\r\n",
+        );
+        assert!(out.contains("X-Order: outer\r\nX-Order: inner\r\n"));
+        assert!(out.ends_with("handler"));
+
+        let out = run(
+            vec![tag(b"outer"), guard, tag(b"inner")],
+            b"GET / HTTP/1.1\r\nX-Block: 1\r\n\r\n",
+        );
+        assert!(out.starts_with("HTTP/1.1 403 Forbidden"));
+        assert!(out.contains("X-Order: outer\r\n"));
+        assert!(!out.contains("inner"));
+    }
+}
@@ -0,0 +1,229 @@
+//! `multipart/form-data` request bodies (RFC 7578): form fields and file
+//! uploads.
+//!
+//! ```ignore
+//! async fn upload(form: Multipart) -> (u16, String) {
+//!     let title = form.text("title").unwrap_or("untitled");
+//!     let saved = form.files().map(|f| save(f.filename.as_deref(), &f.data)).count();
+//!     (201, format!("{saved} files for {title}"))
+//! }
+//! router.route(Method::Post, "/upload", extract::handler(upload));
+//! ```
+//!
+//! Uploads are not streamed: the server reads a request body in full
+//! before any handler runs, so the whole form is in memory when it is
+//! parsed, and its size is bound by `ServerConfig::buffer_size` (or a
+//! route's `body_limit`). Larger uploads need a proxy in front that spools
+//! them to disk.
+
+use memchr::memmem;
+use tachyon_http::http::Request;
+
+use crate::extract::{FromRequest, Rejection};
+
+/// One part of a form: a field, or a file when it has a `filename`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Part {
+    /// The form field name.
+    pub name: String,
+    /// Set for file uploads, as sent by the client: don't use it as a path.
+    pub filename: Option<String>,
+    /// The part's `Content-Type`, if it sent one.
+    pub content_type: Option<String>,
+    pub data: Vec<u8>,
+}
+
+impl Part {
+    pub fn is_file(&self) -> bool {
+        self.filename.is_some()
+    }
+}
+
+/// A parsed `multipart/form-data` body. Rejects with 415 unless the
+/// request is one, and with 400 if the body is malformed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Multipart(pub Vec<Part>);
+
+impl Multipart {
+    /// Parse `body` sent with `content_type`.
+    pub fn parse(content_type: &[u8], body: &[u8]) -> Result<Self, Rejection> {
+        let boundary = boundary(content_type)
+            .ok_or_else(|| Rejection::new(415, "expected a multipart/form-data body"))?;
+        parse(body, boundary)
+            .map(Multipart)
+            .ok_or_else(|| Rejection::new(400, "malformed multipart body"))
+    }
+
+    /// The first part named `name`.
+    pub fn get(&self, name: &str) -> Option<&Part> {
+        self.0.iter().find(|p| p.name == name)
+    }
+
+    /// The value of text field `name`, if it is valid UTF-8.
+    pub fn text(&self, name: &str) -> Option<&str> {
+        self.get(name)
+            .filter(|p| !p.is_file())
+            .and_then(|p| std::str::from_utf8(&p.data).ok())
+    }
+
+    /// Every uploaded file, in order.
+    pub fn files(&self) -> impl Iterator<Item = &Part> {
+        self.0.iter().filter(|p| p.is_file())
+    }
+}
+
+impl<S> FromRequest<S> for Multipart {
+    fn from_request(req: &Request<'_>, _state: &S) -> Result<Self, Rejection> {
+        let content_type = req.header(b"content-type").unwrap_or_default();
+        Self::parse(content_type, &req.decoded_body())
+    }
+}
+
+/// The `boundary` parameter of a `multipart/form-data` content type.
+fn boundary(content_type: &[u8]) -> Option<&[u8]> {
+    let mut params = params(content_type);
+    let mime = params.next()?.trim_ascii();
+    if !mime.eq_ignore_ascii_case(b"multipart/form-data") {
+        return None;
+    }
+    params.find_map(|param| {
+        let (name, value) = split_once(param.trim_ascii(), b'=')?;
+        if !name.trim_ascii().eq_ignore_ascii_case(b"boundary") {
+            return None;
+        }
+        let value = unquote(value.trim_ascii());
+        (!value.is_empty() && value.len() <= 70).then_some(value)
+    })
+}
+
+fn parse(body: &[u8], boundary: &[u8]) -> Option<Vec<Part>> {
+    // The first delimiter may be preceded by a preamble; later ones start
+    // on a new line.
+    let next = [b"\r\n--", boundary].concat();
+    let delimiter = &next[2..];
+    let mut rest = &body[memmem::find(body, delimiter)? + delimiter.len()..];
+    let finder = memmem::Finder::new(&next);
+    let mut parts = Vec::new();
+    loop {
+        if rest.starts_with(b"--") {
+            return Some(parts);
+        }
+        rest = rest.strip_prefix(b"\r\n")?;
+        let end = finder.find(rest)?;
+        parts.push(part(&rest[..end])?);
+        rest = &rest[end + next.len()..];
+    }
+}
+
+fn part(raw: &[u8]) -> Option<Part> {
+    let split = memmem::find(raw, b"\r\n\r\n");
+    let (head, data) = match split {
+        Some(i) => (&raw[..i], &raw[i + 4..]),
+        // No headers at all: only the blank line.
+        None => (&b""[..], raw.strip_prefix(b"\r\n")?),
+    };
+    let mut name = None;
+    let mut filename = None;
+    let mut content_type = None;
+    for line in head.split(|&b| b == b'\n') {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let Some((header, value)) = split_once(line, b':') else {
+            continue;
+        };
+        let value = value.trim_ascii();
+        if header.eq_ignore_ascii_case(b"content-type") {
+            content_type = Some(String::from_utf8_lossy(value).into_owned());
+        } else if header.eq_ignore_ascii_case(b"content-disposition") {
+            for param in params(value).skip(1) {
+                let Some((key, value)) = split_once(param.trim_ascii(), b'=') else {
+                    continue;
+                };
+                let value = String::from_utf8_lossy(unquote(value.trim_ascii())).into_owned();
+                match key.trim_ascii() {
+                    k if k.eq_ignore_ascii_case(b"name") => name = Some(value),
+                    k if k.eq_ignore_ascii_case(b"filename") => filename = Some(value),
+                    _ => {}
+                }
+            }
+        }
+    }
+    Some(Part {
+        name: name?,
+        filename,
+        content_type,
+        data: data.to_vec(),
+    })
+}
+
+/// `value` split on each `;` outside a quoted string, so that
+/// `filename="a;b.png"` stays whole.
+fn params(value: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let mut quoted = false;
+    let mut escaped = false;
+    value.split(move |&b| {
+        match b {
+            _ if escaped => escaped = false,
+            b'\\' if quoted => escaped = true,
+            b'"' => quoted = !quoted,
+            b';' => return !quoted,
+            _ => {}
+        }
+        false
+    })
+}
+
+fn unquote(value: &[u8]) -> &[u8] {
+    value
+        .strip_prefix(b"\"")
+        .and_then(|v| v.strip_suffix(b"\""))
+        .unwrap_or(value)
+}
+
+fn split_once(s: &[u8], sep: u8) -> Option<(&[u8], &[u8])> {
+    let i = s.iter().position(|&b| b == sep)?;
+    Some((&s[..i], &s[i + 1..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_fields_and_files() {
+        let body = b"preamble\r\n--XyZ\r\n\
+            Content-Disposition: form-data; name=\"title\"\r\n\r\n\
+            Holiday\r\n--XyZ\r\n\
+            Content-Disposition: form-data; name=\"photo\"; filename=\"a b.png\"\r\n\
+            Content-Type: image/png\r\n\r\n\
+            \x89PNG\r\n--X\r\n--XyZ--\r\n";
+        let form = Multipart::parse(b"multipart/form-data; boundary=\"XyZ\"", body).unwrap();
+        assert_eq!(form.text("title"), Some("Holiday"));
+        let files: Vec<_> = form.files().collect();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].name, "photo");
+        assert_eq!(files[0].filename.as_deref(), Some("a b.png"));
+        assert_eq!(files[0].content_type.as_deref(), Some("image/png"));
+        assert_eq!(files[0].data, b"\x89PNG\r\n--X");
+
+        let quoted = b"--XyZ\r\n\
+            Content-Disposition: form-data; filename=\"a;b.png\"; name=\"f\"\r\n\r\n\
+            x\r\n--XyZ--";
+        let form = Multipart::parse(b"multipart/form-data; boundary=XyZ", quoted).unwrap();
+        let file = form.get("f").unwrap();
+        assert_eq!(file.filename.as_deref(), Some("a;b.png"));
+
+        assert_eq!(
+            Multipart::parse(b"application/json", b"{}")
+                .unwrap_err()
+                .status(),
+            415
+        );
+        let truncated = b"--XyZ\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\nno end";
+        assert_eq!(
+            Multipart::parse(b"multipart/form-data; boundary=XyZ", truncated)
+                .unwrap_err()
+                .status(),
+            400
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! OpenTelemetry trace export and W3C context propagation (`otel` feature).
+//!
+//! Incoming `traceparent`/`tracestate` headers become the remote parent of
+//! each request span (see the `tracing` feature), so the server's spans join
+//! the caller's distributed trace. Add [`Otel::layer`] to your subscriber to
+//! export them over OTLP:
+//!
+//! ```ignore
+//! let otel = Otel::otlp("checkout", "http://localhost:4318/v1/traces")?;
+//! tracing_subscriber::registry()
+//!     .with(tracing_subscriber::fmt::layer())
+//!     .with(otel.layer())
+//!     .init();
+//! ```
+//!
+//! Keep the [`Otel`] alive for the life of the server; dropping it flushes
+//! pending spans.
+
+use std::collections::HashMap;
+
+use opentelemetry::propagation::Extractor;
+use opentelemetry::trace::TracerProvider;
+use opentelemetry::{Context, global};
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{SdkTracer, SdkTracerProvider};
+use tachyon_http::http::Request;
+use tracing::Subscriber;
+use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+use tracing_subscriber::registry::LookupSpan;
+
+pub use opentelemetry_otlp::ExporterBuildError;
+
+/// An OTLP trace pipeline. Spans are batched and exported on a background thread.
+pub struct Otel {
+    provider: SdkTracerProvider,
+    tracer: SdkTracer,
+}
+
+impl Otel {
+    /// Export spans over OTLP/HTTP (protobuf) to `endpoint`, tagged with
+    /// `service.name`. Also installs the W3C trace-context propagator globally.
+    pub fn otlp(service_name: &str, endpoint: &str) -> Result<Self, ExporterBuildError> {
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(endpoint)
+            .build()?;
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(
+                Resource::builder_empty()
+                    .with_service_name(service_name.to_string())
+                    .build(),
+            )
+            .build();
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        global::set_tracer_provider(provider.clone());
+        let tracer = provider.tracer("tachyon");
+        Ok(Self { provider, tracer })
+    }
+
+    /// `tracing` layer that turns spans into OpenTelemetry spans.
+    pub fn layer<S>(&self) -> OpenTelemetryLayer<S, SdkTracer>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        tracing_opentelemetry::layer().with_tracer(self.tracer.clone())
+    }
+}
+
+impl Drop for Otel {
+    fn drop(&mut self) {
+        let _ = self.provider.shutdown();
+    }
+}
+
+/// Headers (`traceparent`, `tracestate`) that carry the current span's
+/// context, for calls a handler makes to other services.
+pub fn outgoing_headers() -> Vec<(String, String)> {
+    let cx = tracing::Span::current().context();
+    let mut headers = HashMap::new();
+    global::get_text_map_propagator(|p| p.inject_context(&cx, &mut headers));
+    headers.into_iter().collect()
+}
+
+/// Make the request's remote trace context the parent of `span`.
+pub(crate) fn set_remote_parent(span: &tracing::Span, request: &Request<'_>) {
+    if request.header(b"traceparent").is_none() {
+        return;
+    }
+    let cx: Context = global::get_text_map_propagator(|p| p.extract(&RequestHeaders(request)));
+    let _ = span.set_parent(cx);
+}
+
+struct RequestHeaders<'a, 'r>(&'a Request<'r>);
+
+impl Extractor for RequestHeaders<'_, '_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .header(key.as_bytes())
+            .and_then(|v| std::str::from_utf8(v).ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        vec!["traceparent", "tracestate"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::propagation::TextMapPropagator;
+    use opentelemetry::trace::TraceContextExt;
+    use tachyon_http::parser::{ParseResult, parse};
+
+    #[test]
+    fn extracts_w3c_trace_context() {
+        let raw = b"GET / HTTP/1.1\r\ntraceparent: 00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01\r\ntracestate: vendor=abc\r\n\r\n";
+        let ParseResult::Complete(req) = parse(raw) else {
+            panic!("bad request");
+        };
+        let cx = TraceContextPropagator::new().extract(&RequestHeaders(&req));
+        let span = cx.span();
+        let sc = span.span_context();
+        assert!(sc.is_remote() && sc.is_sampled());
+        assert_eq!(
+            sc.trace_id().to_string(),
+            "4bf92f3577b34da6a3ce929d0e0e4736"
+        );
+        assert_eq!(sc.span_id().to_string(), "00f067aa0ba902b7");
+        assert_eq!(sc.trace_state().get("vendor"), Some("abc"));
+    }
+}
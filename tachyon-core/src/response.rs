@@ -1,6 +1,8 @@
-use flate2::Compression;
-use flate2::write::GzEncoder;
-use std::io::Write;
+use tachyon_http::encoding::Encoding;
+use tachyon_http::etag::{Etag, EtagMode};
+use tachyon_http::vary::Vary;
+
+use crate::server::WriteFn;
 
 /// Response builder passed to the user's handler callback.
 /// Wraps a buffer from the pool — FaF-style: you write into a pre-allocated
@@ -15,15 +17,24 @@ pub struct Response<'a> {
     overflow: Option<Vec<u8>>,
     security_headers: &'a [u8],
     custom_headers: Vec<u8>,
-    accepts_gzip: bool,
+    encoding: Encoding,
     compression_threshold: usize,
+    etag_mode: EtagMode,
+    if_none_match: Option<&'a [u8]>,
+    head_request: bool,
+    vary: Vary,
+    pretty_json: bool,
+    envelope: Option<(&'a crate::envelope::Envelope, Option<&'a [u8]>)>,
+    stream: Option<crate::stream::BodyStream>,
+    upgrade: Option<crate::ws::Session>,
+    upgradable: bool,
 }
 
 impl<'a> Response<'a> {
     pub fn new(
         buf: &'a mut [u8],
         security_headers: &'a [u8],
-        accepts_gzip: bool,
+        encoding: Encoding,
         compression_threshold: usize,
     ) -> Self {
         Self {
@@ -32,95 +43,405 @@ impl<'a> Response<'a> {
             overflow: None,
             security_headers,
             custom_headers: Vec::new(),
-            accepts_gzip,
+            encoding,
             compression_threshold,
+            etag_mode: EtagMode::Off,
+            if_none_match: None,
+            head_request: false,
+            vary: Vary::new(),
+            pretty_json: false,
+            envelope: None,
+            stream: None,
+            upgrade: None,
+            upgradable: false,
         }
     }
 
-    /// Add a custom header to the response.
+    /// Mark this as the response to a HEAD request: headers (including the
+    /// Content-Length of the would-be body) are written, the body is not.
+    pub fn set_head_request(&mut self, head: bool) {
+        self.head_request = head;
+    }
+
+    /// Pretty-print JSON bodies written by `json`, `json_writer` and
+    /// `json_value` (debugging aid — costs a re-indent pass per response).
+    pub fn set_pretty_json(&mut self, pretty: bool) {
+        self.pretty_json = pretty;
+    }
+
+    /// Rewrite JSON bodies through `envelope`. `request_id` is the
+    /// request's `X-Request-Id`, echoed into the envelope.
+    pub fn set_envelope(
+        &mut self,
+        envelope: &'a crate::envelope::Envelope,
+        request_id: Option<&'a [u8]>,
+    ) {
+        self.envelope = Some((envelope, request_id));
+    }
+
+    /// Enable ETag generation for this response. `if_none_match` is the raw
+    /// request header value; when it matches, a 2xx response becomes a 304.
+    pub fn enable_etag(&mut self, mode: EtagMode, if_none_match: Option<&'a [u8]>) {
+        self.etag_mode = mode;
+        self.if_none_match = if_none_match;
+    }
+
+    /// Send the body as written, whatever the client accepts.
+    pub(crate) fn skip_compression(&mut self) {
+        self.compression_threshold = usize::MAX;
+    }
+
+    /// Add a custom header to the response. `Vary` values are merged into
+    /// the response's single `Vary` header instead of repeated.
     pub fn header(&mut self, name: &[u8], value: &[u8]) {
+        if name.eq_ignore_ascii_case(b"vary") {
+            self.vary.add(value);
+            return;
+        }
         self.custom_headers.extend_from_slice(name);
         self.custom_headers.extend_from_slice(b": ");
         self.custom_headers.extend_from_slice(value);
         self.custom_headers.extend_from_slice(b"\r\n");
     }
 
-    /// Should we compress this body?
-    /// threshold == usize::MAX means compression is disabled.
-    /// threshold == 0 means compress everything (no minimum size).
-    fn should_compress(&self, body: &[u8]) -> bool {
-        self.accepts_gzip
-            && self.compression_threshold < usize::MAX
-            && body.len() >= self.compression_threshold
+    /// Add a `Set-Cookie` header for `cookie`. `false` (and no header) if
+    /// the cookie holds characters it can't carry; see
+    /// [`Cookie::to_header`](crate::cookie::Cookie::to_header).
+    pub fn set_cookie(&mut self, cookie: &crate::cookie::Cookie) -> bool {
+        match cookie.to_header() {
+            Some(value) => {
+                self.header(b"Set-Cookie", &value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Append pre-formatted `Name: value\r\n` lines to the custom headers.
+    pub(crate) fn raw_headers(&mut self, lines: &[u8]) {
+        self.custom_headers.extend_from_slice(lines);
     }
 
-    /// Compress body with gzip. Returns None if compression doesn't shrink it.
-    fn compress_gzip(body: &[u8]) -> Option<Vec<u8>> {
-        // Compressed output is typically 30-60% of input. Start at half to reduce
-        // over-allocation while avoiding realloc in most cases.
-        let mut encoder = GzEncoder::new(Vec::with_capacity(body.len() / 2), Compression::fast());
-        if encoder.write_all(body).is_err() {
-            return None;
+    /// Turn an already-written response into the last one on its
+    /// connection: `Connection: keep-alive` becomes `Connection: close`.
+    pub(crate) fn close_connection(&mut self) {
+        let data = self.data();
+        let head = data
+            .windows(4)
+            .position(|w| w == b"\r\n\r\n")
+            .unwrap_or(data.len());
+        let keep = tachyon_http::response::CONNECTION_KEEP;
+        if let Some(at) = data[..head].windows(keep.len()).position(|w| w == keep) {
+            match self.overflow {
+                Some(ref mut vec) => {
+                    vec.drain(at..at + keep.len());
+                }
+                None => {
+                    self.buf.copy_within(at + keep.len()..self.pos, at);
+                    self.pos -= keep.len();
+                }
+            }
         }
-        let compressed = encoder.finish().ok()?;
-        // Only use compressed version if it's actually smaller
-        if compressed.len() < body.len() {
-            Some(compressed)
-        } else {
-            None
+        self.insert_headers(tachyon_http::response::CONNECTION_CLOSE);
+    }
+
+    /// Insert pre-formatted header lines into an already-written response,
+    /// just before the blank line. Shifts the body in place when it fits.
+    pub(crate) fn insert_headers(&mut self, lines: &[u8]) {
+        let Some(at) = self
+            .data()
+            .windows(4)
+            .position(|w| w == b"\r\n\r\n")
+            .map(|i| i + 2)
+        else {
+            return;
+        };
+        match self.overflow {
+            Some(ref mut vec) => {
+                vec.splice(at..at, lines.iter().copied());
+            }
+            None if self.pos + lines.len() <= self.buf.len() => {
+                self.buf.copy_within(at..self.pos, at + lines.len());
+                self.buf[at..at + lines.len()].copy_from_slice(lines);
+                self.pos += lines.len();
+            }
+            None => {
+                let data = &self.buf[..self.pos];
+                self.overflow = Some([&data[..at], lines, &data[at..]].concat());
+            }
         }
     }
 
+    /// Record that the response depends on request header `name`
+    /// (e.g. `Accept-Language`). Members accumulate into one `Vary` header.
+    /// `Accept-Encoding` is added automatically for compressible bodies.
+    pub fn vary(&mut self, name: &[u8]) {
+        self.vary.add(name);
+    }
+
+    /// Could this body be compressed, i.e. does `Accept-Encoding` decide
+    /// its representation?
+    /// threshold == usize::MAX means compression is disabled.
+    /// threshold == 0 means compress everything (no minimum size).
+    fn is_negotiable(&self, content_type: &[u8], body: &[u8]) -> bool {
+        self.compression_threshold < usize::MAX
+            && body.len() >= self.compression_threshold
+            && crate::compress::is_compressible(content_type)
+    }
+
     /// Write a complete HTTP response with JSON body.
     pub fn json(&mut self, status: u16, body: &[u8]) -> usize {
-        let status_line = match status {
-            200 => tachyon_http::response::STATUS_200,
-            201 => tachyon_http::response::STATUS_201,
-            204 => tachyon_http::response::STATUS_204,
-            400 => tachyon_http::response::STATUS_400,
-            404 => tachyon_http::response::STATUS_404,
-            _ => tachyon_http::response::STATUS_500,
-        };
-        self.write_with_optional_compression(
-            status_line,
-            tachyon_http::response::CONTENT_JSON,
-            body,
-        )
+        let mut content_type = tachyon_http::response::CONTENT_JSON;
+        let mut rewritten = None;
+        if let Some((envelope, request_id)) = self.envelope {
+            let ctx = crate::envelope::EnvelopeContext { status, request_id };
+            if let Some((ct, body)) = envelope.apply(&ctx, body) {
+                content_type = ct;
+                rewritten = Some(body);
+            }
+        }
+        if self.pretty_json {
+            rewritten = Some(tachyon_http::json::pretty(
+                rewritten.as_deref().unwrap_or(body),
+            ));
+        }
+        let body = rewritten.as_deref().unwrap_or(body);
+        self.write_with_optional_compression(status, content_type, body)
     }
 
     /// Write a complete HTTP response with plain text body.
     pub fn text(&mut self, status: u16, body: &[u8]) -> usize {
-        let status_line = match status {
-            200 => tachyon_http::response::STATUS_200,
-            404 => tachyon_http::response::STATUS_404,
-            _ => tachyon_http::response::STATUS_500,
-        };
-        self.write_with_optional_compression(
-            status_line,
-            tachyon_http::response::CONTENT_TEXT,
-            body,
-        )
+        self.write_with_optional_compression(status, tachyon_http::response::CONTENT_TEXT, body)
+    }
+
+    /// Write a complete HTTP response with HTML body.
+    pub fn html(&mut self, status: u16, body: &[u8]) -> usize {
+        self.write_with_optional_compression(status, tachyon_http::response::CONTENT_HTML, body)
+    }
+
+    /// Write a complete HTTP response with an arbitrary content type
+    /// (e.g. `"image/png"`, `"application/x-protobuf"`).
+    ///
+    /// Prefer `json()`/`text()`/`html()` for those types — they skip
+    /// formatting the Content-Type header entirely.
+    pub fn send(&mut self, status: u16, content_type: &str, body: &[u8]) -> usize {
+        let content_type = tachyon_http::response::content_type_header(content_type);
+        self.write_with_optional_compression(status, &content_type, body)
+    }
+
+    /// Write a chunked response followed by trailer fields, e.g. a checksum
+    /// or a gRPC-style status computed after the body.
+    ///
+    /// Announces the trailer names in a `Trailer` header. Compression and
+    /// ETags are not applied on this path. A HEAD response stops after the
+    /// head, without the chunks or the trailer block.
+    pub fn with_trailers(
+        &mut self,
+        status: u16,
+        content_type: &str,
+        body: &[u8],
+        trailers: &[(&[u8], &[u8])],
+    ) -> usize {
+        use tachyon_http::response as http;
+
+        let mut head = Vec::with_capacity(128);
+        head.extend_from_slice(http::status_line(status));
+        head.extend_from_slice(&http::content_type_header(content_type));
+        head.extend_from_slice(http::TRANSFER_CHUNKED);
+        head.extend_from_slice(http::CONNECTION_KEEP);
+
+        let mut tail = Vec::with_capacity(body.len() + 128);
+        tail.extend_from_slice(self.security_headers);
+        tail.extend_from_slice(&self.custom_headers);
+        if !trailers.is_empty() {
+            tail.extend_from_slice(b"Trailer: ");
+            for (i, (name, _)) in trailers.iter().enumerate() {
+                if i > 0 {
+                    tail.extend_from_slice(b", ");
+                }
+                tail.extend_from_slice(name);
+            }
+            tail.extend_from_slice(http::CRLF);
+        }
+        tail.extend_from_slice(http::CRLF);
+        let head_len = tail.len();
+        if !body.is_empty() {
+            tachyon_http::chunked::write_chunk_size(&mut tail, body.len());
+            tail.extend_from_slice(body);
+            tail.extend_from_slice(http::CRLF);
+        }
+        tail.extend_from_slice(b"0\r\n");
+        for (name, value) in trailers {
+            tail.extend_from_slice(name);
+            tail.extend_from_slice(b": ");
+            tail.extend_from_slice(value);
+            tail.extend_from_slice(http::CRLF);
+        }
+        tail.extend_from_slice(http::CRLF);
+
+        self.write_prerendered(&head, &tail);
+        self.strip_body(tail.len() - head_len)
+    }
+
+    /// Write the head of a chunked response whose body comes from `body`;
+    /// see [`crate::stream`]. Compression and ETags are not applied on
+    /// this path. Returns the length of the head.
+    pub fn stream(
+        &mut self,
+        status: u16,
+        content_type: &str,
+        body: crate::stream::BodyStream,
+    ) -> usize {
+        use tachyon_http::response as http;
+
+        let mut head = Vec::with_capacity(128);
+        head.extend_from_slice(http::status_line(status));
+        head.extend_from_slice(&http::content_type_header(content_type));
+        head.extend_from_slice(http::TRANSFER_CHUNKED);
+        head.extend_from_slice(http::CONNECTION_KEEP);
+
+        let mut tail = Vec::with_capacity(self.custom_headers.len() + 64);
+        tail.extend_from_slice(self.security_headers);
+        tail.extend_from_slice(&self.custom_headers);
+        tail.extend_from_slice(http::CRLF);
+
+        self.stream = Some(body);
+        self.write_prerendered(&head, &tail)
+    }
+
+    /// The body of a [`stream`](Self::stream)ed response, for the server
+    /// to send after the head.
+    pub(crate) fn take_stream(&mut self) -> Option<crate::stream::BodyStream> {
+        self.stream.take()
+    }
+
+    /// Whether the body comes from a stream rather than the buffer.
+    pub(crate) fn is_streamed(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    /// Write the 101 that switches to the WebSocket protocol; the server
+    /// runs `session` on the connection once it is sent.
+    pub(crate) fn upgrade(&mut self, accept: &str, session: crate::ws::Session) -> usize {
+        use tachyon_http::response as http;
+
+        if !self.upgradable {
+            return self.json(
+                501,
+                b"{\"error\":\"websocket upgrades are not supported here\"}",
+            );
+        }
+
+        let mut head = Vec::with_capacity(128);
+        head.extend_from_slice(http::status_line(101));
+        head.extend_from_slice(b"Upgrade: websocket\r\nConnection: Upgrade\r\n");
+        head.extend_from_slice(b"Sec-WebSocket-Accept: ");
+        head.extend_from_slice(accept.as_bytes());
+        head.extend_from_slice(http::CRLF);
+
+        let mut tail = Vec::with_capacity(self.custom_headers.len() + 2);
+        tail.extend_from_slice(&self.custom_headers);
+        tail.extend_from_slice(http::CRLF);
+
+        self.upgrade = Some(session);
+        self.write_prerendered(&head, &tail)
+    }
+
+    /// Let [`upgrade`](Self::upgrade) switch protocols; set by the server
+    /// on transports that can carry a WebSocket.
+    pub(crate) fn allow_upgrade(&mut self) {
+        self.upgradable = true;
     }
 
-    fn write_with_optional_compression(
+    /// The session of an [`upgrade`](Self::upgrade)d response.
+    pub(crate) fn take_upgrade(&mut self) -> Option<crate::ws::Session> {
+        self.upgrade.take()
+    }
+
+    /// Write a file download: `Content-Disposition: attachment` with the
+    /// filename encoded per RFC 6266/5987, so browsers save instead of render.
+    ///
+    /// ```ignore
+    /// res.attachment(&csv_bytes, "relatório.csv", "text/csv");
+    /// ```
+    pub fn attachment(&mut self, body: &[u8], filename: &str, content_type: &str) -> usize {
+        let disposition = tachyon_http::response::content_disposition(filename);
+        self.header(b"Content-Disposition", &disposition);
+        self.send(200, content_type, body)
+    }
+
+    /// Write a server-generated error, using the configured error page for
+    /// `status` if there is one, the built-in JSON body otherwise.
+    pub fn error(&mut self, status: u16, pages: &crate::config::ErrorPages) -> usize {
+        match pages.get(status) {
+            Some(page) => {
+                let body = page.body.clone();
+                self.send(status, &page.content_type, &body)
+            }
+            None => self.json(status, crate::config::ErrorPages::default_body(status)),
+        }
+    }
+
+    /// Attach an ETag header if enabled. Returns true if the request's
+    /// `If-None-Match` matched and the response should be a bodiless 304.
+    fn apply_etag(&mut self, status: u16, body: &[u8], encoding: Encoding) -> bool {
+        if self.etag_mode == EtagMode::Off || !(200..300).contains(&status) {
+            return false;
+        }
+        let etag = Etag::compute(body, self.etag_mode == EtagMode::Weak).for_encoding(encoding);
+        self.custom_headers
+            .extend_from_slice(tachyon_http::response::ETAG_PREFIX);
+        self.custom_headers.extend_from_slice(etag.as_bytes());
+        self.custom_headers
+            .extend_from_slice(tachyon_http::response::CRLF);
+        self.if_none_match
+            .is_some_and(|inm| etag.matches_if_none_match(inm))
+    }
+
+    pub(crate) fn write_with_optional_compression(
         &mut self,
-        status_line: &[u8],
+        status: u16,
         content_type: &[u8],
         body: &[u8],
     ) -> usize {
-        if self.should_compress(body)
-            && let Some(compressed) = Self::compress_gzip(body)
+        // 1xx/204/304 never carry a body, Content-Type or Content-Length.
+        if tachyon_http::response::is_bodiless_status(status) {
+            return self.write_empty(tachyon_http::response::status_line(status));
+        }
+        let negotiable = self.is_negotiable(content_type, body);
+        if negotiable {
+            // Caches must key on Accept-Encoding even when we send identity.
+            self.vary.add(b"Accept-Encoding");
+        }
+        let encoding = if negotiable {
+            self.encoding
+        } else {
+            Encoding::Identity
+        };
+        if self.apply_etag(status, body, encoding) {
+            return self.write_empty(tachyon_http::response::STATUS_304);
+        }
+        let status_line = tachyon_http::response::status_line(status);
+        if negotiable
+            && self.encoding != Encoding::Identity
+            && let Some(compressed) = crate::compress::compress(self.encoding, body)
         {
-            // Single memcpy for both gzip headers
             self.custom_headers
-                .extend_from_slice(tachyon_http::response::GZIP_HEADERS);
+                .extend_from_slice(self.encoding.content_encoding());
             return self.write_final(status_line, content_type, &compressed);
         }
         self.write_final(status_line, content_type, body)
     }
 
+    /// Move the accumulated `Vary` members into the custom headers.
+    fn flush_vary(&mut self) {
+        let vary = std::mem::take(&mut self.vary);
+        vary.write_header(&mut self.custom_headers);
+    }
+
     /// Write the final response, using the pool buffer if it fits, or heap-allocating otherwise.
     fn write_final(&mut self, status_line: &[u8], content_type: &[u8], body: &[u8]) -> usize {
+        self.flush_vary();
         let date_header = crate::date::cached_date_header();
         let total = tachyon_http::response::response_size(
             status_line,
@@ -142,7 +463,6 @@ impl<'a> Response<'a> {
                 &self.custom_headers,
                 date_header,
             );
-            self.pos
         } else {
             // Overflow path: heap-allocate for large responses
             let vec = tachyon_http::response::write_response_vec(
@@ -153,9 +473,159 @@ impl<'a> Response<'a> {
                 &self.custom_headers,
                 date_header,
             );
-            let len = vec.len();
             self.overflow = Some(vec);
-            len
+        }
+        self.strip_body(body.len())
+    }
+
+    /// For HEAD requests, drop the trailing `body_len` bytes just written.
+    /// Returns the final response length.
+    pub(crate) fn strip_body(&mut self, body_len: usize) -> usize {
+        if self.head_request {
+            match self.overflow {
+                Some(ref mut vec) => vec.truncate(vec.len() - body_len),
+                None => self.pos -= body_len,
+            }
+        }
+        self.len()
+    }
+
+    /// Write a complete pre-built response (e.g. from the response cache) verbatim.
+    pub(crate) fn write_cached(&mut self, data: &[u8]) -> usize {
+        if data.len() <= self.buf.len() {
+            self.buf[..data.len()].copy_from_slice(data);
+            self.pos = data.len();
+        } else {
+            self.overflow = Some(data.to_vec());
+        }
+        data.len()
+    }
+
+    /// The written response as a route cache stores it: without its `Date`
+    /// header, which [`write_hit`](Self::write_hit) writes afresh. `None`
+    /// when it must not be shared with other clients: it sets a cookie, or
+    /// varies on a request header other than `Accept-Encoding`.
+    pub(crate) fn cache_entry(&self) -> Option<Vec<u8>> {
+        let data = self.data();
+        let head_end = data.windows(4).position(|w| w == b"\r\n\r\n")? + 2;
+        let mut entry = Vec::with_capacity(data.len());
+        for line in data[..head_end].split_inclusive(|&b| b == b'\n') {
+            let (name, value) = line
+                .iter()
+                .position(|&b| b == b':')
+                .map_or((line, &[][..]), |colon| {
+                    (&line[..colon], &line[colon + 1..])
+                });
+            if name.eq_ignore_ascii_case(b"set-cookie") {
+                return None;
+            }
+            if name.eq_ignore_ascii_case(b"vary")
+                && value
+                    .split(|&b| b == b',')
+                    .any(|member| !member.trim_ascii().eq_ignore_ascii_case(b"accept-encoding"))
+            {
+                return None;
+            }
+            if !name.eq_ignore_ascii_case(b"date") {
+                entry.extend_from_slice(line);
+            }
+        }
+        entry.extend_from_slice(&data[head_end..]);
+        Some(entry)
+    }
+
+    /// Write a cache hit with the current `Date` after its status line, or
+    /// a bodiless 304 when the ETag stored with it matches the request's
+    /// `If-None-Match`.
+    pub(crate) fn write_hit(&mut self, data: &[u8]) -> usize {
+        if let Some(inm) = self.if_none_match
+            && let Some(etag) = stored_etag(data)
+            && tachyon_http::etag::if_none_match(etag, inm)
+        {
+            self.custom_headers
+                .extend_from_slice(tachyon_http::response::ETAG_PREFIX);
+            self.custom_headers.extend_from_slice(etag);
+            self.custom_headers
+                .extend_from_slice(tachyon_http::response::CRLF);
+            return self.write_empty(tachyon_http::response::STATUS_304);
+        }
+        let status_end = data
+            .windows(2)
+            .position(|w| w == b"\r\n")
+            .map_or(0, |i| i + 2);
+        self.write_prerendered(&data[..status_end], &data[status_end..])
+    }
+
+    /// Discard what was written and write `data` verbatim instead.
+    pub(crate) fn replace(&mut self, data: &[u8]) -> usize {
+        self.overflow = None;
+        self.pos = 0;
+        self.write_cached(data)
+    }
+
+    /// Status code of the written response, parsed back from the status line.
+    pub fn status(&self) -> Option<u16> {
+        let data = self.data();
+        let code = data.get(9..12)?;
+        std::str::from_utf8(code).ok()?.parse().ok()
+    }
+
+    /// Length of the written body (everything after the blank line).
+    pub(crate) fn body_len(&self) -> usize {
+        let data = self.data();
+        data.windows(4)
+            .position(|w| w == b"\r\n\r\n")
+            .map_or(0, |head_end| data.len() - (head_end + 4))
+    }
+
+    /// Write a response pre-rendered around the Date header: `head`, Date, `tail`.
+    pub(crate) fn write_prerendered(&mut self, head: &[u8], tail: &[u8]) -> usize {
+        let date_header = crate::date::cached_date_header();
+        let total = head.len() + date_header.len() + tail.len();
+        if total <= self.buf.len() {
+            let mut pos = 0;
+            for src in [head, date_header, tail] {
+                self.buf[pos..pos + src.len()].copy_from_slice(src);
+                pos += src.len();
+            }
+            self.pos = pos;
+        } else {
+            self.overflow = Some([head, date_header, tail].concat());
+        }
+        total
+    }
+
+    /// Write a response with no body, no Content-Type and no Content-Length.
+    fn write_empty(&mut self, status_line: &[u8]) -> usize {
+        self.flush_vary();
+        let date_header = crate::date::cached_date_header();
+        let total = tachyon_http::response::empty_response_size(
+            status_line,
+            self.security_headers,
+            &self.custom_headers,
+            date_header,
+        );
+
+        if total <= self.buf.len() {
+            self.pos = tachyon_http::response::write_empty_response(
+                self.buf,
+                status_line,
+                self.security_headers,
+                &self.custom_headers,
+                date_header,
+            );
+            self.pos
+        } else {
+            let mut vec = vec![0u8; total];
+            tachyon_http::response::write_empty_response(
+                &mut vec,
+                status_line,
+                self.security_headers,
+                &self.custom_headers,
+                date_header,
+            );
+            self.overflow = Some(vec);
+            total
         }
     }
 
@@ -224,4 +694,328 @@ impl<'a> Response<'a> {
         let json_len = writer.finish();
         self.json(status, &json_buf[..json_len])
     }
+
+    /// Serialize `value` with serde and write it as a JSON response.
+    ///
+    /// The body is serialized into a buffer from the thread-local pool, so the
+    /// common case allocates nothing; bodies larger than a pool buffer spill to
+    /// a Vec. A serialization failure becomes a 500.
+    ///
+    /// ```ignore
+    /// #[derive(Serialize)]
+    /// struct User { id: u64, name: String }
+    /// res.json_value(200, &User { id: 1, name: "Ada".into() });
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn json_value<T: serde::Serialize + ?Sized>(&mut self, status: u16, value: &T) -> usize {
+        let mut pooled = tachyon_pool::pool::acquire();
+        let buf = pooled.as_write_buf();
+        let mut cursor = std::io::Cursor::new(&mut buf[..]);
+        match serde_json::to_writer(&mut cursor, value) {
+            Ok(()) => {
+                let len = cursor.position() as usize;
+                self.json(status, &buf[..len])
+            }
+            // Most likely the pool buffer was too small — retry on the heap.
+            Err(_) => match serde_json::to_vec(value) {
+                Ok(body) => self.json(status, &body),
+                Err(_) => self.json(500, b"{\"error\":\"serialization failed\"}"),
+            },
+        }
+    }
+
+    /// Render an HTML template with `context` and send it as a 200.
+    /// A render error (unknown template, strict-mode miss, …) is logged and
+    /// answered with a 500 — the error text never reaches the client.
+    ///
+    /// ```ignore
+    /// res.render(&templates, "user", &serde_json::json!({ "name": name }))
+    /// ```
+    #[cfg(feature = "templates")]
+    pub fn render<T: serde::Serialize>(
+        &mut self,
+        templates: &crate::template::Templates,
+        template: &str,
+        context: &T,
+    ) -> usize {
+        match templates.render(template, context) {
+            Ok(html) => self.html(200, html.as_bytes()),
+            Err(e) => {
+                eprintln!("[tachyon] Template '{}' failed: {}", template, e);
+                self.html(500, b"<h1>500 Internal Server Error</h1>")
+            }
+        }
+    }
+}
+
+/// A handler result that knows how to write itself, so handlers built with
+/// `async_handler`, `sync_handler`, `blocking_handler` or the extractors can
+/// return a `String` or `(404, "no such user")` instead of a [`WriteFn`].
+///
+/// | type                               | response                         |
+/// |------------------------------------|----------------------------------|
+/// | `String`, `&'static str`           | `text/plain`                     |
+/// | `Vec<u8>`, `&'static [u8]`         | `application/octet-stream`       |
+/// | `serde_json::Value` (`serde`)      | JSON                             |
+/// | `(u16, T)`                         | `T` with that status             |
+/// | `Result<T, E>`                     | `T`, or `E` with a default 500   |
+/// | [`WriteFn`]                        | whatever it writes               |
+pub trait IntoResponse: Send + 'static {
+    /// Write `self` with `status`, unless the value carries its own status.
+    fn write(self, status: u16, res: &mut Response<'_>) -> usize;
+
+    /// `self` as a [`WriteFn`] with status 200 by default.
+    fn into_write_fn(self) -> WriteFn
+    where
+        Self: Sized,
+    {
+        Box::new(move |res: &mut Response| self.write(200, res))
+    }
+}
+
+impl IntoResponse for WriteFn {
+    fn write(self, _status: u16, res: &mut Response<'_>) -> usize {
+        self(res)
+    }
+
+    fn into_write_fn(self) -> WriteFn {
+        self
+    }
+}
+
+impl IntoResponse for String {
+    fn write(self, status: u16, res: &mut Response<'_>) -> usize {
+        res.text(status, self.as_bytes())
+    }
+}
+
+impl IntoResponse for &'static str {
+    fn write(self, status: u16, res: &mut Response<'_>) -> usize {
+        res.text(status, self.as_bytes())
+    }
+}
+
+impl IntoResponse for Vec<u8> {
+    fn write(self, status: u16, res: &mut Response<'_>) -> usize {
+        res.send(status, "application/octet-stream", &self)
+    }
+}
+
+impl IntoResponse for &'static [u8] {
+    fn write(self, status: u16, res: &mut Response<'_>) -> usize {
+        res.send(status, "application/octet-stream", self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl IntoResponse for serde_json::Value {
+    fn write(self, status: u16, res: &mut Response<'_>) -> usize {
+        res.json_value(status, &self)
+    }
+}
+
+impl<T: IntoResponse> IntoResponse for (u16, T) {
+    fn write(self, _status: u16, res: &mut Response<'_>) -> usize {
+        self.1.write(self.0, res)
+    }
+}
+
+impl<T: IntoResponse, E: IntoResponse> IntoResponse for Result<T, E> {
+    fn write(self, status: u16, res: &mut Response<'_>) -> usize {
+        match self {
+            Ok(value) => value.write(status, res),
+            Err(e) => e.write(500, res),
+        }
+    }
+}
+
+/// The `ETag` value in the head of a stored response.
+fn stored_etag(data: &[u8]) -> Option<&[u8]> {
+    let head_end = data.windows(4).position(|w| w == b"\r\n\r\n")?;
+    data[..head_end].split(|&b| b == b'\n').find_map(|line| {
+        let (name, value) = line.split_at_checked(4)?;
+        let value = value.strip_prefix(b":")?;
+        name.eq_ignore_ascii_case(b"etag")
+            .then(|| value.trim_ascii())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_hits_carry_the_current_date() {
+        let mut buf = [0u8; 512];
+        let mut res = Response::new(&mut buf, b"", Encoding::Identity, usize::MAX);
+        res.text(200, b"hello");
+        let entry = res.cache_entry().unwrap();
+        assert!(!entry.windows(6).any(|w| w == b"Date: "));
+
+        let mut buf = [0u8; 512];
+        let mut res = Response::new(&mut buf, b"", Encoding::Identity, usize::MAX);
+        res.write_hit(&entry);
+        let out = res.data();
+        let date = crate::date::cached_date_header();
+        assert!(out.starts_with(&[b"HTTP/1.1 200 OK\r\n", date].concat()));
+        assert!(out.ends_with(b"\r\n\r\nhello"));
+    }
+
+    #[test]
+    fn trailers_follow_the_last_chunk() {
+        let trailers: &[(&[u8], &[u8])] = &[(b"Checksum", b"abc"), (b"Grpc-Status", b"0")];
+        let mut buf = [0u8; 512];
+        let mut res = Response::new(&mut buf, b"", Encoding::Identity, usize::MAX);
+        res.with_trailers(200, "text/plain", b"hello", trailers);
+        let out = std::str::from_utf8(res.data()).unwrap();
+        assert!(out.contains("Transfer-Encoding: chunked\r\n"));
+        assert!(out.contains("Trailer: Checksum, Grpc-Status\r\n"));
+        assert!(
+            out.ends_with("\r\n\r\n5\r\nhello\r\n0\r\nChecksum: abc\r\nGrpc-Status: 0\r\n\r\n")
+        );
+
+        let mut buf = [0u8; 512];
+        let mut res = Response::new(&mut buf, b"", Encoding::Identity, usize::MAX);
+        res.set_head_request(true);
+        res.with_trailers(200, "text/plain", b"hello", trailers);
+        let out = std::str::from_utf8(res.data()).unwrap();
+        assert!(out.contains("Trailer: Checksum, Grpc-Status\r\n"));
+        assert!(out.ends_with("Grpc-Status\r\n\r\n"), "{out}");
+    }
+
+    #[test]
+    fn no_content_has_no_body_headers() {
+        let mut buf = [0u8; 512];
+        let mut res = Response::new(&mut buf, b"", Encoding::Identity, usize::MAX);
+        res.json(204, b"{\"ignored\":true}");
+        let out = std::str::from_utf8(res.data()).unwrap();
+        assert!(out.starts_with("HTTP/1.1 204 No Content\r\n"));
+        assert!(!out.contains("Content-Length"));
+        assert!(!out.contains("Content-Type"));
+        assert!(out.ends_with("\r\n\r\n"));
+    }
+
+    #[test]
+    fn into_response_types() {
+        fn render(value: impl IntoResponse) -> String {
+            let mut buf = [0u8; 512];
+            let mut res = Response::new(&mut buf, b"", Encoding::Identity, usize::MAX);
+            value.into_write_fn()(&mut res);
+            String::from_utf8(res.data().to_vec()).unwrap()
+        }
+        let out = render("hi");
+        assert!(out.starts_with("HTTP/1.1 200") && out.ends_with("\r\n\r\nhi"));
+        assert!(render((404, String::from("gone"))).starts_with("HTTP/1.1 404"));
+        assert!(render(b"\x01".to_vec()).contains("application/octet-stream"));
+        assert!(render(Ok::<_, &str>((201, "made"))).starts_with("HTTP/1.1 201"));
+        assert!(render(Err::<&str, _>("boom")).starts_with("HTTP/1.1 500"));
+        assert!(render(Err::<&str, _>((400, "bad"))).starts_with("HTTP/1.1 400"));
+        let write: WriteFn = Box::new(|res: &mut Response| res.text(202, b""));
+        assert!(render(write).starts_with("HTTP/1.1 202"));
+    }
+
+    #[test]
+    fn error_uses_registered_page() {
+        let mut pages = crate::config::ErrorPages::default();
+        pages.set(404, "text/html; charset=utf-8", &b"<h1>gone</h1>"[..]);
+
+        let mut buf = [0u8; 512];
+        let mut res = Response::new(&mut buf, b"", Encoding::Identity, usize::MAX);
+        res.error(404, &pages);
+        let out = std::str::from_utf8(res.data()).unwrap();
+        assert!(out.contains("Content-Type: text/html"));
+        assert!(out.ends_with("<h1>gone</h1>"));
+
+        let mut buf = [0u8; 512];
+        let mut res = Response::new(&mut buf, b"", Encoding::Identity, usize::MAX);
+        res.error(405, &pages);
+        let out = std::str::from_utf8(res.data()).unwrap();
+        assert!(out.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+        assert!(out.ends_with("{\"error\":\"method not allowed\"}"));
+    }
+
+    #[test]
+    fn close_connection_replaces_keep_alive() {
+        let mut buf = [0u8; 512];
+        let mut res = Response::new(&mut buf, b"", Encoding::Identity, usize::MAX);
+        res.text(200, b"bye");
+        res.close_connection();
+        let out = std::str::from_utf8(res.data()).unwrap();
+        assert!(out.contains("Connection: close\r\n"));
+        assert!(!out.contains("keep-alive"));
+        assert!(out.ends_with("\r\n\r\nbye"));
+    }
+
+    #[test]
+    fn vary_merges_negotiated_and_custom_members() {
+        let mut buf = [0u8; 512];
+        let mut res = Response::new(&mut buf, b"", Encoding::Identity, 0);
+        res.header(b"Vary", b"Accept-Language");
+        res.vary(b"accept-language");
+        res.text(200, b"hello hello hello");
+        let out = std::str::from_utf8(res.data()).unwrap();
+        assert!(out.contains("Vary: Accept-Encoding, Accept-Language\r\n"));
+        assert_eq!(out.matches("Vary").count(), 1);
+        assert!(!out.contains("Content-Encoding"));
+    }
+
+    #[cfg(feature = "templates")]
+    #[test]
+    fn render_template_escapes_html() {
+        let mut templates = crate::template::Templates::new();
+        templates.register("hello", "<p>Hi {{name}}</p>").unwrap();
+
+        let mut buf = [0u8; 512];
+        let mut res = Response::new(&mut buf, b"", Encoding::Identity, usize::MAX);
+        res.render(
+            &templates,
+            "hello",
+            &[("name", "<b>Ada</b>")]
+                .into_iter()
+                .collect::<std::collections::HashMap<_, _>>(),
+        );
+        let out = std::str::from_utf8(res.data()).unwrap();
+        assert!(out.contains("Content-Type: text/html"));
+        assert!(out.ends_with("<p>Hi &lt;b&gt;Ada&lt;/b&gt;</p>"));
+
+        let mut buf = [0u8; 512];
+        let mut res = Response::new(&mut buf, b"", Encoding::Identity, usize::MAX);
+        res.render(&templates, "missing", &());
+        assert_eq!(res.status(), Some(500));
+    }
+
+    #[test]
+    fn pretty_json_reindents_body() {
+        let mut buf = [0u8; 512];
+        let mut res = Response::new(&mut buf, b"", Encoding::Identity, usize::MAX);
+        res.set_pretty_json(true);
+        res.json(404, b"{\"error\":\"not found\"}");
+        let out = std::str::from_utf8(res.data()).unwrap();
+        assert!(out.contains("Content-Length: 26\r\n"));
+        assert!(out.ends_with("{\n  \"error\": \"not found\"\n}"));
+    }
+
+    #[test]
+    fn envelope_wraps_data_and_problems() {
+        let envelope = crate::envelope::Envelope::new().data().problem_details();
+
+        let mut buf = [0u8; 512];
+        let mut res = Response::new(&mut buf, b"", Encoding::Identity, usize::MAX);
+        res.set_envelope(&envelope, Some(b"req-1"));
+        res.json(200, b"[1,2]");
+        let out = std::str::from_utf8(res.data()).unwrap();
+        assert!(
+            out.ends_with("{\"data\":[1,2],\"meta\":{\"status\":200},\"request_id\":\"req-1\"}")
+        );
+
+        let mut buf = [0u8; 512];
+        let mut res = Response::new(&mut buf, b"", Encoding::Identity, usize::MAX);
+        res.set_envelope(&envelope, None);
+        res.json(404, b"{\"error\":\"not found\"}");
+        let out = std::str::from_utf8(res.data()).unwrap();
+        assert!(out.contains("Content-Type: application/problem+json\r\n"));
+        assert!(out.ends_with(
+            "{\"type\":\"about:blank\",\"title\":\"Not Found\",\"status\":404,\"detail\":\"not found\"}"
+        ));
+    }
 }
@@ -0,0 +1,1069 @@
+//! Exact-match route table with O(1) lookup.
+//!
+//! Routes are registered up front, then the router is handed to the server
+//! and shared read-only across connections — no locks on the hot path.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use arc_swap::ArcSwap;
+
+use tachyon_http::encoding::Encoding;
+use tachyon_http::etag::{Etag, EtagMode};
+use tachyon_http::methods::Method;
+use tachyon_http::response as http;
+
+use crate::{
+    cache::{ResponseCache, RouteCacheStats},
+    config::ServerConfig,
+    envelope::Envelope,
+    files::ServeDir,
+    hooks::{AfterResponse, LimitHook, OnRequest, OnResponse},
+    middleware::{Middleware, Next},
+    response::Response,
+    server::{Handler, HandlerFuture, PanicHook, TimeoutHook},
+    stats::Stats,
+};
+
+/// A registered route target.
+pub enum Route {
+    /// Dynamic handler, called per request.
+    Handler(Handler),
+    /// Pre-rendered response: no handler, no future, no WriteFn — a memcpy.
+    Static(StaticResponse),
+    /// Handler whose 200 responses to GET/HEAD are cached for the cache's TTL.
+    Cached(Handler, ResponseCache),
+    /// Handler that can be replaced while the server runs.
+    Swappable(HandlerSlot),
+}
+
+/// The replaceable handler of a [`Router::swappable_route`].
+#[derive(Clone)]
+pub struct HandlerSlot(Arc<ArcSwap<Handler>>);
+
+impl HandlerSlot {
+    fn new(handler: Handler) -> Self {
+        Self(Arc::new(ArcSwap::from_pointee(handler)))
+    }
+
+    /// The handler new requests go to.
+    #[inline]
+    pub fn current(&self) -> Arc<Handler> {
+        self.0.load_full()
+    }
+
+    /// Send new requests to `handler`; returns the one it replaces.
+    pub fn swap(&self, handler: Handler) -> Handler {
+        Arc::unwrap_or_clone(self.0.swap(Arc::new(handler)))
+    }
+}
+
+/// A route declared with the `tachyon-macros` attributes (`#[get("/")]`
+/// and friends), collected with `routes![...]` and added with
+/// [`Router::mount`]. `S` is the state its handler extracts with `State`.
+pub struct Endpoint<S = ()> {
+    method: Method,
+    path: &'static str,
+    make: fn(S) -> Handler,
+}
+
+impl<S> Endpoint<S> {
+    pub fn new(method: Method, path: &'static str, make: fn(S) -> Handler) -> Self {
+        Self { method, path, make }
+    }
+
+    pub fn method(&self) -> Method {
+        self.method
+    }
+
+    pub fn path(&self) -> &'static str {
+        self.path
+    }
+}
+
+/// Settings for one route, for [`Router::route_with`]. Anything left unset
+/// follows the router and server-wide configuration.
+///
+/// ```ignore
+/// router.route_with(Method::Post, "/upload", upload, RouteConfig::new()
+///     .body_limit(1 << 20)
+///     .timeout(Duration::from_secs(30))
+///     .accept("application/octet-stream")
+///     .middleware(auth));
+/// ```
+#[derive(Clone, Default)]
+pub struct RouteConfig {
+    body_limit: Option<usize>,
+    timeout: Option<Duration>,
+    cache: Option<ResponseCache>,
+    middleware: Vec<Middleware>,
+    content_types: Vec<Box<[u8]>>,
+    swappable: bool,
+}
+
+impl RouteConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reject request bodies over `bytes` with 413. Bodies can never exceed
+    /// `ServerConfig::buffer_size`, so this only tightens it.
+    pub fn body_limit(mut self, bytes: usize) -> Self {
+        self.body_limit = Some(bytes);
+        self
+    }
+
+    /// Response deadline, as [`Router::deadline`].
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Serve successful GET/HEAD responses from `cache`, as
+    /// [`Router::cached_route`].
+    pub fn cache(mut self, cache: ResponseCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Add a middleware that runs for this route only, inside the
+    /// router-wide chain, in registration order.
+    pub fn middleware(mut self, middleware: Middleware) -> Self {
+        self.middleware.push(middleware);
+        self
+    }
+
+    /// Accept request bodies of media type `content_type` (parameters such
+    /// as `charset` ignored). Once any is set, requests with a body of
+    /// another type, or without a `Content-Type`, get a 415.
+    pub fn accept(mut self, content_type: &str) -> Self {
+        let mime = content_type.split(';').next().unwrap_or_default().trim();
+        self.content_types
+            .push(mime.to_ascii_lowercase().into_bytes().into());
+        self
+    }
+
+    /// Register the handler as [`Router::swappable_route`] does, so
+    /// [`Router::swap_handler`] can replace it, this route's middleware
+    /// included. No effect with a [`cache`](Self::cache): cached responses
+    /// would outlive the handler that made them.
+    pub fn swappable(mut self) -> Self {
+        self.swappable = true;
+        self
+    }
+}
+
+/// The request checks of a [`RouteConfig`], run before its handler.
+pub(crate) struct RequestPolicy {
+    body_limit: Option<usize>,
+    content_types: Vec<Box<[u8]>>,
+}
+
+impl RequestPolicy {
+    /// Status to reject `req` with, if it breaks the policy. Limits apply
+    /// to the body without chunk framing.
+    pub(crate) fn check(&self, req: &tachyon_http::http::Request<'_>) -> Option<u16> {
+        if self.body_limit.is_none() && self.content_types.is_empty() {
+            return None;
+        }
+        let len = req.decoded_len();
+        if self.body_limit.is_some_and(|limit| len > limit) {
+            return Some(413);
+        }
+        if !self.content_types.is_empty() && len > 0 {
+            let accepted = req.header(b"content-type").is_some_and(|ct| {
+                let mime = ct.split(|&b| b == b';').next().unwrap_or_default();
+                let mime = mime.trim_ascii();
+                self.content_types
+                    .iter()
+                    .any(|accepted| mime.eq_ignore_ascii_case(accepted))
+            });
+            if !accepted {
+                return Some(415);
+            }
+        }
+        None
+    }
+}
+
+/// A served directory and its handler, rebuilt by `prepare` with the
+/// server's error pages.
+type Mount = (ServeDir, Handler);
+
+/// Method → path → route. The fallback runs for unmatched requests;
+/// without one, the server answers 404 itself.
+#[derive(Default)]
+pub struct Router {
+    routes: HashMap<Method, HashMap<Box<[u8]>, Route>>,
+    fallback: Option<Handler>,
+    after_response: Vec<AfterResponse>,
+    on_request: Vec<OnRequest>,
+    on_response: Vec<OnResponse>,
+    deadlines: HashMap<Method, HashMap<Box<[u8]>, Duration>>,
+    policies: HashMap<Method, HashMap<Box<[u8]>, RequestPolicy>>,
+    on_timeout: Option<TimeoutHook>,
+    on_panic: Option<PanicHook>,
+    on_limit: Option<LimitHook>,
+    /// (path prefix, envelope), longest prefix first.
+    envelopes: Vec<(Box<[u8]>, Envelope)>,
+    /// (path prefix, mount), longest prefix first.
+    files: Vec<(Box<[u8]>, Mount)>,
+    middleware: Vec<Middleware>,
+    /// `middleware`, frozen by `prepare` for lock-free sharing.
+    chain: Arc<[Middleware]>,
+    stats: Option<Stats>,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A router with no routes that sends every request to `handler`.
+    pub fn with_fallback(handler: Handler) -> Self {
+        Self {
+            fallback: Some(handler),
+            ..Self::default()
+        }
+    }
+
+    /// Register a handler for `method` + exact `path`. Replaces any existing route.
+    pub fn route(&mut self, method: Method, path: &str, handler: Handler) -> &mut Self {
+        self.insert(method, path, Route::Handler(handler))
+    }
+
+    /// Register a handler for `method` + exact `path` with its own
+    /// [`RouteConfig`]. Replaces any existing route and its settings.
+    pub fn route_with(
+        &mut self,
+        method: Method,
+        path: &str,
+        handler: Handler,
+        config: RouteConfig,
+    ) -> &mut Self {
+        let handler = if config.middleware.is_empty() {
+            handler
+        } else {
+            let chain: Arc<[Middleware]> = config.middleware.into();
+            Arc::new(move |req: &tachyon_http::http::Request<'_>| {
+                Next::new(chain.clone(), handler.clone()).run(req)
+            })
+        };
+        self.clear_overrides(method, path);
+        if let Some(timeout) = config.timeout {
+            self.deadline(method, path, timeout);
+        }
+        if config.body_limit.is_some() || !config.content_types.is_empty() {
+            self.policies.entry(method).or_default().insert(
+                path.as_bytes().into(),
+                RequestPolicy {
+                    body_limit: config.body_limit,
+                    content_types: config.content_types,
+                },
+            );
+        }
+        match config.cache {
+            Some(cache) => self.cached_route(method, path, handler, cache),
+            None if config.swappable => self.swappable_route(method, path, handler),
+            None => self.route(method, path, handler),
+        }
+    }
+
+    /// Drop the per-route deadline and request policy of `method` + `path`.
+    fn clear_overrides(&mut self, method: Method, path: &str) {
+        if let Some(deadlines) = self.deadlines.get_mut(&method) {
+            deadlines.remove(path.as_bytes());
+        }
+        if let Some(policies) = self.policies.get_mut(&method) {
+            policies.remove(path.as_bytes());
+        }
+    }
+
+    /// Register a handler that can be replaced at runtime, by `method` +
+    /// `path`, with [`swap_handler`](Self::swap_handler) — e.g. for feature
+    /// flags or plugin reloads — without rebuilding the route table. Costs
+    /// one atomic load per request over [`route`](Self::route).
+    pub fn swappable_route(&mut self, method: Method, path: &str, handler: Handler) -> &mut Self {
+        self.insert(method, path, Route::Swappable(HandlerSlot::new(handler)))
+    }
+
+    /// Replace the handler of the swappable route at `method` + exact
+    /// `path`. Requests already running finish on the old handler. Returns
+    /// the old handler, or `None` (changing nothing) when there is no
+    /// swappable route there.
+    pub fn swap_handler(&self, method: Method, path: &str, handler: Handler) -> Option<Handler> {
+        match self.routes.get(&method)?.get(path.as_bytes())? {
+            Route::Swappable(slot) => Some(slot.swap(handler)),
+            _ => None,
+        }
+    }
+
+    /// Register endpoints declared with the route attributes.
+    pub fn mount(&mut self, endpoints: impl IntoIterator<Item = Endpoint>) -> &mut Self {
+        self.mount_with_state((), endpoints)
+    }
+
+    /// Like [`mount`](Self::mount), for endpoints that extract `State<S>`;
+    /// each gets its own clone of `state`.
+    pub fn mount_with_state<S: Clone>(
+        &mut self,
+        state: S,
+        endpoints: impl IntoIterator<Item = Endpoint<S>>,
+    ) -> &mut Self {
+        for endpoint in endpoints {
+            let handler = (endpoint.make)(state.clone());
+            self.route(endpoint.method, endpoint.path, handler);
+        }
+        self
+    }
+
+    /// Register a handler whose successful GET/HEAD responses are served from
+    /// `cache` until they expire. Responses that set a cookie or vary on a
+    /// request header other than `Accept-Encoding` belong to one client and
+    /// are not cached. Keep a clone of `cache` to read its stats or
+    /// invalidate entries at runtime.
+    pub fn cached_route(
+        &mut self,
+        method: Method,
+        path: &str,
+        handler: Handler,
+        cache: ResponseCache,
+    ) -> &mut Self {
+        self.insert(method, path, Route::Cached(handler, cache))
+    }
+
+    /// Register a constant response for `method` + exact `path`.
+    ///
+    /// Headers, Content-Length and (when the body is large enough) compressed
+    /// variants are rendered once at server start, so serving it costs a
+    /// couple of memcpys. Ideal for health checks and constant endpoints.
+    pub fn static_response(
+        &mut self,
+        method: Method,
+        path: &str,
+        body: impl Into<Vec<u8>>,
+        content_type: &str,
+    ) -> &mut Self {
+        let res = StaticResponse::new(200, body.into(), content_type);
+        self.insert(method, path, Route::Static(res))
+    }
+
+    /// Handler for requests that match no route.
+    pub fn fallback(&mut self, handler: Handler) -> &mut Self {
+        self.fallback = Some(handler);
+        self
+    }
+
+    /// Add a middleware. Middleware runs in registration order around every
+    /// handler route and the fallback, and may short-circuit the request.
+    pub fn middleware(&mut self, middleware: Middleware) -> &mut Self {
+        self.middleware.push(middleware);
+        self
+    }
+
+    /// Collect per-route request counts and latency, readable through the
+    /// returned handle while the server runs. Nothing is recorded unless
+    /// this is called.
+    pub fn stats(&mut self) -> Stats {
+        self.stats.get_or_insert_with(Stats::new).clone()
+    }
+
+    /// Run `hook` after every response is written, before it is sent.
+    /// Hooks run in registration order and may edit the response headers.
+    pub fn after_response(&mut self, hook: AfterResponse) -> &mut Self {
+        self.after_response.push(hook);
+        self
+    }
+
+    /// Run `hook` for every request once it is parsed, before routing —
+    /// including requests that end in a 404.
+    pub fn on_request(&mut self, hook: OnRequest) -> &mut Self {
+        self.on_request.push(hook);
+        self
+    }
+
+    /// Run `hook` for every response once written, with its status and
+    /// latency. Lighter than [`after_response`](Self::after_response): it
+    /// cannot edit the response, so the headers are never parsed.
+    pub fn on_response(&mut self, hook: OnResponse) -> &mut Self {
+        self.on_response.push(hook);
+        self
+    }
+
+    /// Give `method` + `path` its own response deadline, overriding the
+    /// server-wide `handler_timeout`. A handler still running at the
+    /// deadline is dropped and the client gets a 504.
+    pub fn deadline(&mut self, method: Method, path: &str, deadline: Duration) -> &mut Self {
+        self.deadlines
+            .entry(method)
+            .or_default()
+            .insert(path.as_bytes().into(), deadline);
+        self
+    }
+
+    /// Write the 504 response for handlers that miss their deadline.
+    /// Without a hook, the server sends the configured 504 error page.
+    pub fn on_timeout(&mut self, hook: TimeoutHook) -> &mut Self {
+        self.on_timeout = Some(hook);
+        self
+    }
+
+    /// Observe handler panics (requires `catch_panics`, the default). The
+    /// client still gets the 500 error page and the connection stays open.
+    pub fn on_panic(&mut self, hook: PanicHook) -> &mut Self {
+        self.on_panic = Some(hook);
+        self
+    }
+
+    /// Observe requests rejected for size (413/431), e.g. to spot abusive
+    /// clients or tune `buffer_size` from real traffic.
+    pub fn on_limit(&mut self, hook: LimitHook) -> &mut Self {
+        self.on_limit = Some(hook);
+        self
+    }
+
+    /// Rewrite the JSON bodies of every response under `prefix` (routes,
+    /// fallback and server errors alike) through `envelope`. The longest
+    /// matching prefix wins; `"/"` covers everything.
+    pub fn envelope(&mut self, prefix: &str, envelope: Envelope) -> &mut Self {
+        let prefix = prefix.trim_end_matches('/').as_bytes();
+        self.envelopes.retain(|(p, _)| &**p != prefix);
+        self.envelopes.push((prefix.into(), envelope));
+        self.envelopes
+            .sort_by_key(|(p, _)| std::cmp::Reverse(p.len()));
+        self
+    }
+
+    /// Serve the files of `dir` under `prefix`: `GET <prefix>/a/b.css`
+    /// reads `a/b.css` inside it; see [`crate::files`]. Routes take
+    /// precedence, and the longest matching prefix wins.
+    pub fn files(&mut self, prefix: &str, dir: ServeDir) -> &mut Self {
+        let prefix = prefix.trim_end_matches('/').as_bytes();
+        self.files.retain(|(p, _)| &**p != prefix);
+        let handler = dir.clone().handler(prefix, &Default::default());
+        self.files.push((prefix.into(), (dir, handler)));
+        self.files.sort_by_key(|(p, _)| std::cmp::Reverse(p.len()));
+        self
+    }
+
+    /// Drop every cached response of `path` (any method, encoding or
+    /// query) from the caches of this router's routes.
+    pub fn invalidate_cache(&self, path: &str) {
+        for cache in self.caches() {
+            cache.invalidate(path);
+        }
+    }
+
+    /// Empty the caches of every route.
+    pub fn invalidate_all(&self) {
+        for cache in self.caches() {
+            cache.clear();
+        }
+    }
+
+    /// Zero the counters of every route cache.
+    pub fn reset_cache_stats(&self) {
+        for cache in self.caches() {
+            cache.reset_stats();
+        }
+    }
+
+    /// Counters of every cached route, sorted by path then method.
+    pub fn cache_stats(&self) -> Vec<RouteCacheStats> {
+        let mut stats: Vec<_> = self
+            .routes
+            .iter()
+            .flat_map(|(&method, paths)| {
+                paths.iter().map(move |(path, route)| (method, path, route))
+            })
+            .filter_map(|(method, path, route)| match route {
+                Route::Cached(_, cache) => Some(RouteCacheStats {
+                    method,
+                    path: String::from_utf8_lossy(path).into_owned(),
+                    stats: cache.stats(),
+                    shard_entries: cache.shard_entries(),
+                }),
+                _ => None,
+            })
+            .collect();
+        stats.sort_by(|a, b| (&a.path, a.method as u8).cmp(&(&b.path, b.method as u8)));
+        stats
+    }
+
+    fn insert(&mut self, method: Method, path: &str, route: Route) -> &mut Self {
+        self.routes
+            .entry(method)
+            .or_default()
+            .insert(path.as_bytes().into(), route);
+        self
+    }
+
+    /// Look up a route. The query string is ignored. HEAD falls back to
+    /// the GET route (the server strips the body).
+    #[inline]
+    pub fn find(&self, method: Method, path: &[u8]) -> Option<&Route> {
+        let path = strip_query(path);
+        let found = self.routes.get(&method).and_then(|m| m.get(path));
+        if found.is_none() && method == Method::Head {
+            return self.routes.get(&Method::Get).and_then(|m| m.get(path));
+        }
+        found
+    }
+
+    /// Methods registered for `path` (query ignored), for 405 + `Allow`.
+    pub fn allowed_methods(&self, path: &[u8]) -> Vec<Method> {
+        let path = strip_query(path);
+        let mut methods: Vec<Method> = self
+            .routes
+            .iter()
+            .filter(|(_, paths)| paths.contains_key(path))
+            .map(|(m, _)| *m)
+            .collect();
+        methods.sort_by_key(|&m| m as u8);
+        methods
+    }
+
+    #[inline]
+    pub fn fallback_handler(&self) -> Option<&Handler> {
+        self.fallback.as_ref()
+    }
+
+    /// Start `handler` for `req`, through the middleware chain if any.
+    #[inline]
+    pub(crate) fn dispatch(
+        &self,
+        handler: &Handler,
+        req: &tachyon_http::http::Request<'_>,
+    ) -> HandlerFuture {
+        if self.chain.is_empty() {
+            handler(req)
+        } else {
+            Next::new(self.chain.clone(), handler.clone()).run(req)
+        }
+    }
+
+    /// Per-route deadline for `method` + `path`, if one was set.
+    #[inline]
+    pub(crate) fn deadline_for(&self, method: Method, path: &[u8]) -> Option<Duration> {
+        per_route(&self.deadlines, method, path).copied()
+    }
+
+    /// Request checks of the route at `method` + `path`, if it has any.
+    #[inline]
+    pub(crate) fn policy_for(&self, method: Method, path: &[u8]) -> Option<&RequestPolicy> {
+        per_route(&self.policies, method, path)
+    }
+
+    /// File handler of the innermost [`files`](Self::files) mount
+    /// containing `path`.
+    #[inline]
+    pub(crate) fn files_for(&self, path: &[u8]) -> Option<&Handler> {
+        within(&self.files, path).map(|(_, handler)| handler)
+    }
+
+    /// Envelope of the innermost scope containing `path`.
+    #[inline]
+    pub(crate) fn envelope_for(&self, path: &[u8]) -> Option<&Envelope> {
+        within(&self.envelopes, path)
+    }
+
+    #[inline]
+    pub(crate) fn panic_hook(&self) -> Option<&PanicHook> {
+        self.on_panic.as_ref()
+    }
+
+    #[inline]
+    pub(crate) fn limit_hook(&self) -> Option<&LimitHook> {
+        self.on_limit.as_ref()
+    }
+
+    #[inline]
+    pub(crate) fn timeout_hook(&self) -> Option<&TimeoutHook> {
+        self.on_timeout.as_ref()
+    }
+
+    #[inline]
+    pub(crate) fn route_stats(&self) -> Option<&Stats> {
+        self.stats.as_ref()
+    }
+
+    #[inline]
+    pub(crate) fn after_response_hooks(&self) -> &[AfterResponse] {
+        &self.after_response
+    }
+
+    #[inline]
+    pub(crate) fn on_request_hooks(&self) -> &[OnRequest] {
+        &self.on_request
+    }
+
+    #[inline]
+    pub(crate) fn on_response_hooks(&self) -> &[OnResponse] {
+        &self.on_response
+    }
+
+    /// Number of registered routes.
+    pub fn len(&self) -> usize {
+        self.routes.values().map(HashMap::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Response caches used by cached routes.
+    pub(crate) fn caches(&self) -> impl Iterator<Item = &ResponseCache> {
+        self.routes
+            .values()
+            .flat_map(HashMap::values)
+            .filter_map(|route| match route {
+                Route::Cached(_, cache) => Some(cache),
+                _ => None,
+            })
+    }
+
+    /// Path, handler and cache of every cached GET route.
+    pub(crate) fn cached_gets(&self) -> impl Iterator<Item = (&[u8], &Handler, &ResponseCache)> {
+        self.routes
+            .get(&Method::Get)
+            .into_iter()
+            .flatten()
+            .filter_map(|(path, route)| match route {
+                Route::Cached(handler, cache) => Some((&**path, handler, cache)),
+                _ => None,
+            })
+    }
+
+    /// Render all static responses against the final server config.
+    /// Called once by the server before accepting connections.
+    pub(crate) fn prepare(&mut self, config: &ServerConfig) {
+        self.chain = self.middleware.iter().cloned().collect();
+        for (prefix, (dir, handler)) in &mut self.files {
+            *handler = dir.clone().handler(prefix, &config.error_pages);
+        }
+        if let Some(stats) = &self.stats {
+            stats.freeze(
+                self.routes
+                    .iter()
+                    .flat_map(|(&m, paths)| paths.keys().map(move |p| (m, &**p))),
+            );
+        }
+        for (path, route) in self.routes.values_mut().flat_map(HashMap::iter_mut) {
+            match route {
+                Route::Static(res) => res.render(config),
+                Route::Cached(_, cache) => {
+                    if let Some(metrics) = &config.metrics {
+                        metrics.watch_cache(&String::from_utf8_lossy(path), cache);
+                    }
+                }
+                Route::Handler(_) | Route::Swappable(_) => {}
+            }
+        }
+    }
+}
+
+/// The entry for `method` + `path` in a per-route setting map, with HEAD
+/// falling back to GET like [`Router::find`].
+#[inline]
+fn per_route<'a, T>(
+    map: &'a HashMap<Method, HashMap<Box<[u8]>, T>>,
+    method: Method,
+    path: &[u8],
+) -> Option<&'a T> {
+    if map.is_empty() {
+        return None;
+    }
+    let path = strip_query(path);
+    let found = map.get(&method).and_then(|m| m.get(path));
+    if found.is_none() && method == Method::Head {
+        return map.get(&Method::Get)?.get(path);
+    }
+    found
+}
+
+/// The entry of the longest prefix (listed longest first) containing `path`.
+#[inline]
+fn within<'a, T>(scopes: &'a [(Box<[u8]>, T)], path: &[u8]) -> Option<&'a T> {
+    if scopes.is_empty() {
+        return None;
+    }
+    let path = strip_query(path);
+    scopes
+        .iter()
+        .find(|(prefix, _)| {
+            path.strip_prefix(&**prefix)
+                .is_some_and(|rest| rest.is_empty() || rest[0] == b'/')
+        })
+        .map(|(_, t)| t)
+}
+
+fn strip_query(path: &[u8]) -> &[u8] {
+    path.iter()
+        .position(|&b| b == b'?')
+        .map_or(path, |q| &path[..q])
+}
+
+/// One pre-rendered wire variant of a static response.
+/// The Date header is the only dynamic part, so it goes between `head` and `tail`.
+struct Prerendered {
+    encoding: Encoding,
+    /// Status line, Content-Type, Content-Length, Connection.
+    head: Vec<u8>,
+    /// Security headers, custom headers, blank line, body.
+    tail: Vec<u8>,
+    body_len: usize,
+    etag: Option<Etag>,
+    /// Pre-rendered 304 (head, tail) when ETags are enabled.
+    not_modified: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+/// A constant response served without invoking any handler.
+pub struct StaticResponse {
+    status: u16,
+    content_type: Vec<u8>,
+    body: Vec<u8>,
+    /// Identity first, then one entry per supported encoding that shrinks the body.
+    variants: Vec<Prerendered>,
+}
+
+impl StaticResponse {
+    pub fn new(status: u16, body: Vec<u8>, content_type: &str) -> Self {
+        Self {
+            status,
+            content_type: http::content_type_header(content_type).into_owned(),
+            body,
+            variants: Vec::new(),
+        }
+    }
+
+    fn render(&mut self, config: &ServerConfig) {
+        let security = config.security.as_bytes();
+        if config.pretty_json
+            && self
+                .content_type
+                .starts_with(b"Content-Type: application/json")
+        {
+            self.body = tachyon_http::json::pretty(&self.body);
+        }
+        let mut extra = Vec::new();
+        let etag = match config.etag {
+            EtagMode::Off => None,
+            mode => Some(Etag::compute(&self.body, mode == EtagMode::Weak)),
+        };
+        let negotiable = self.body.len() >= config.compression_threshold
+            && config.compression_threshold < usize::MAX
+            && crate::compress::is_compressible(&self.content_type);
+        if negotiable {
+            // Every variant (identity and 304 included) varies on Accept-Encoding.
+            extra.extend_from_slice(http::VARY_ACCEPT_ENCODING);
+        }
+
+        self.variants.clear();
+        self.variants
+            .push(self.prerender(Encoding::Identity, &self.body, security, &extra, etag));
+
+        if negotiable {
+            for &enc in crate::compress::SUPPORTED {
+                if let Some(compressed) = crate::compress::compress(enc, &self.body) {
+                    let etag = etag.map(|etag| etag.for_encoding(enc));
+                    let variant = self.prerender(enc, &compressed, security, &extra, etag);
+                    self.variants.push(variant);
+                }
+            }
+        }
+    }
+
+    fn prerender(
+        &self,
+        encoding: Encoding,
+        body: &[u8],
+        security: &[u8],
+        vary: &[u8],
+        etag: Option<Etag>,
+    ) -> Prerendered {
+        let mut headers = vary.to_vec();
+        let not_modified = etag.map(|etag| {
+            headers.extend_from_slice(http::ETAG_PREFIX);
+            headers.extend_from_slice(etag.as_bytes());
+            headers.extend_from_slice(http::CRLF);
+            let head = [http::STATUS_304, http::CONNECTION_KEEP].concat();
+            (head, [security, &headers, http::CRLF].concat())
+        });
+        headers.extend_from_slice(encoding.content_encoding());
+
+        let mut head = Vec::new();
+        head.extend_from_slice(http::status_line(self.status));
+        head.extend_from_slice(&self.content_type);
+        let mut cl_buf = [0u8; 40];
+        let cl_len = http::write_content_length(&mut cl_buf, body.len());
+        head.extend_from_slice(&cl_buf[..cl_len]);
+        head.extend_from_slice(http::CONNECTION_KEEP);
+
+        let tail = [security, &headers, http::CRLF, body].concat();
+        Prerendered {
+            encoding,
+            head,
+            tail,
+            body_len: body.len(),
+            etag,
+            not_modified,
+        }
+    }
+
+    /// Write the pre-rendered variant matching the negotiated `encoding`.
+    pub(crate) fn write(
+        &self,
+        res: &mut Response<'_>,
+        encoding: Encoding,
+        if_none_match: Option<&[u8]>,
+    ) -> usize {
+        let variant = self
+            .variants
+            .iter()
+            .find(|v| v.encoding == encoding)
+            .or_else(|| self.variants.first());
+        match variant {
+            Some(v) => {
+                if let (Some(etag), Some((head, tail)), Some(inm)) =
+                    (&v.etag, &v.not_modified, if_none_match)
+                    && etag.matches_if_none_match(inm)
+                {
+                    return res.write_prerendered(head, tail);
+                }
+                res.write_prerendered(&v.head, &v.tail);
+                res.strip_body(v.body_len)
+            }
+            // Not rendered (router used without a server): fall back to the slow path.
+            None => {
+                res.write_with_optional_compression(self.status, &self.content_type, &self.body)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swaps_handler_in_place() {
+        let text = |body: &'static str| crate::server::sync_handler(move |_| body);
+        let mut router = Router::new();
+        router.swappable_route(Method::Get, "/flag", text("old"));
+        router.route(Method::Get, "/fixed", text("fixed"));
+
+        let call = |router: &Router| {
+            let raw = b"GET /flag?x=1 HTTP/1.1\r\n\r\n";
+            let req = crate::testing::request(raw);
+            let Some(Route::Swappable(slot)) = router.find(req.method, req.path) else {
+                panic!("expected a swappable route");
+            };
+            crate::testing::respond(&slot.current(), raw)
+        };
+
+        assert!(call(&router).ends_with("old"));
+        assert!(
+            router
+                .swap_handler(Method::Get, "/flag", text("new"))
+                .is_some()
+        );
+        assert!(call(&router).ends_with("new"));
+        assert!(
+            router
+                .swap_handler(Method::Get, "/fixed", text("x"))
+                .is_none()
+        );
+        assert!(
+            router
+                .swap_handler(Method::Post, "/flag", text("x"))
+                .is_none()
+        );
+
+        let config = RouteConfig::new().body_limit(4).swappable();
+        router.route_with(Method::Post, "/flag", text("post"), config);
+        assert!(
+            router
+                .swap_handler(Method::Post, "/flag", text("x"))
+                .is_some()
+        );
+        let cached = RouteConfig::new()
+            .cache(ResponseCache::new(Duration::from_secs(1), 8))
+            .swappable();
+        router.route_with(Method::Get, "/cached", text("cached"), cached);
+        assert!(
+            router
+                .swap_handler(Method::Get, "/cached", text("x"))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn static_response_prerendered() {
+        let mut router = Router::new();
+        router.static_response(Method::Get, "/health", "ok", "text/plain; charset=utf-8");
+        router.prepare(&ServerConfig::new().compression(usize::MAX));
+
+        let Some(Route::Static(s)) = router.find(Method::Get, b"/health?probe=1") else {
+            panic!("expected static route");
+        };
+        let mut buf = [0u8; 512];
+        let mut res = Response::new(&mut buf, b"", Encoding::Identity, usize::MAX);
+        s.write(&mut res, Encoding::Gzip, None);
+        let out = std::str::from_utf8(res.data()).unwrap();
+        assert!(out.starts_with("HTTP/1.1 200 OK\r\nContent-Type: text/plain"));
+        assert!(out.contains("Content-Length: 2\r\n"));
+        assert!(out.contains("Date: "));
+        assert!(out.ends_with("\r\n\r\nok"));
+        assert!(router.find(Method::Post, b"/health").is_none());
+    }
+
+    #[test]
+    fn head_falls_back_to_get_without_body() {
+        let mut router = Router::new();
+        router.static_response(Method::Get, "/", "hello", "text/plain; charset=utf-8");
+        router.prepare(&ServerConfig::new());
+
+        let Some(Route::Static(s)) = router.find(Method::Head, b"/") else {
+            panic!("expected GET route for HEAD");
+        };
+        let mut buf = [0u8; 512];
+        let mut res = Response::new(&mut buf, b"", Encoding::Identity, usize::MAX);
+        res.set_head_request(true);
+        s.write(&mut res, Encoding::Identity, None);
+        let out = std::str::from_utf8(res.data()).unwrap();
+        assert!(out.contains("Content-Length: 5\r\n"));
+        assert!(out.ends_with("\r\n\r\n"));
+    }
+
+    #[test]
+    fn strong_etags_vary_by_encoding() {
+        let mut router = Router::new();
+        let body = "compressible ".repeat(64);
+        router.static_response(Method::Get, "/", body, "text/plain");
+        let config = ServerConfig::new().compression(16).etag(EtagMode::Strong);
+        router.prepare(&config);
+        let Some(Route::Static(s)) = router.find(Method::Get, b"/") else {
+            panic!("expected a static route");
+        };
+        let get = |encoding, inm| {
+            let mut buf = [0u8; 2048];
+            let mut res = Response::new(&mut buf, b"", Encoding::Identity, usize::MAX);
+            s.write(&mut res, encoding, inm);
+            String::from_utf8_lossy(res.data()).into_owned()
+        };
+        let tag = |out: &str| {
+            out.lines()
+                .find_map(|l| l.strip_prefix("ETag: "))
+                .map(str::to_owned)
+        };
+        let identity = tag(&get(Encoding::Identity, None)).unwrap();
+        let gzip = tag(&get(Encoding::Gzip, None)).unwrap();
+        assert_eq!(gzip, format!("{}-gzip\"", identity.trim_end_matches('"')));
+
+        let out = get(Encoding::Gzip, Some(gzip.as_bytes()));
+        assert!(
+            out.starts_with("HTTP/1.1 304") && out.contains(&gzip),
+            "{out}"
+        );
+        assert!(get(Encoding::Gzip, Some(identity.as_bytes())).starts_with("HTTP/1.1 200"));
+    }
+
+    #[test]
+    fn allowed_methods_for_path() {
+        let mut router = Router::new();
+        router.static_response(Method::Get, "/items", "[]", "application/json");
+        router.static_response(Method::Post, "/items", "{}", "application/json");
+        let allowed = router.allowed_methods(b"/items?page=2");
+        assert_eq!(allowed, [Method::Get, Method::Post]);
+        assert!(router.allowed_methods(b"/missing").is_empty());
+    }
+
+    #[test]
+    fn deadline_lookup() {
+        let mut router = Router::new();
+        router.deadline(Method::Get, "/slow", Duration::from_millis(50));
+        assert_eq!(
+            router.deadline_for(Method::Head, b"/slow?x=1"),
+            Some(Duration::from_millis(50))
+        );
+        assert_eq!(router.deadline_for(Method::Post, b"/slow"), None);
+    }
+
+    #[test]
+    fn envelope_scopes_by_prefix() {
+        let mut router = Router::new();
+        router.envelope("/api", Envelope::new().data());
+        router.envelope("/api/v2/", Envelope::new());
+        assert!(router.envelope_for(b"/apix").is_none());
+        let api = router.envelope_for(b"/api/users?x=1").unwrap();
+        let v2 = router.envelope_for(b"/api/v2/users").unwrap();
+        assert!(!std::ptr::eq(api, v2));
+        assert!(std::ptr::eq(api, router.envelope_for(b"/api").unwrap()));
+    }
+
+    #[test]
+    fn reload_swaps_table_and_clears_old_caches() {
+        let server = crate::server::Server::new(ServerConfig::new());
+        let reload = server.reload_handle();
+
+        let cache = ResponseCache::new(Duration::from_secs(60), 16);
+        let handler: Handler = Arc::new(|_| {
+            Box::pin(async { Box::new(|res: &mut Response| res.text(200, b"v1")) as _ })
+        });
+        let mut v1 = Router::new();
+        v1.cached_route(Method::Get, "/page", handler, cache.clone());
+        reload.reload(v1);
+        cache.put(
+            ResponseCache::key(Method::Get, Encoding::Identity, b"/page"),
+            b"v1",
+        );
+        assert_eq!(cache.stats().entries, 1);
+
+        let mut v2 = Router::new();
+        v2.static_response(Method::Get, "/page", "v2", "text/plain");
+        reload.reload(v2);
+        assert_eq!(cache.stats().entries, 0);
+        assert!(matches!(
+            reload.current().find(Method::Get, b"/page"),
+            Some(Route::Static(_))
+        ));
+    }
+
+    #[test]
+    fn invalidates_route_caches() {
+        let server = crate::server::Server::new(ServerConfig::new());
+        let reload = server.reload_handle();
+        let (users, posts) = (
+            ResponseCache::new(Duration::from_secs(60), 16),
+            ResponseCache::new(Duration::from_secs(60), 16),
+        );
+        let text = |body: &'static str| crate::server::sync_handler(move |_| body);
+        let mut router = Router::new();
+        router.cached_route(Method::Get, "/users", text("users"), users.clone());
+        router.cached_route(Method::Get, "/posts", text("posts"), posts.clone());
+        reload.reload(router);
+        let fill = |cache: &ResponseCache, target: &[u8]| {
+            cache.put(
+                ResponseCache::key(Method::Get, Encoding::Identity, target),
+                b"x",
+            );
+        };
+        fill(&users, b"/users");
+        fill(&users, b"/users?page=2");
+        fill(&posts, b"/posts");
+
+        reload.invalidate_cache("/users");
+        assert_eq!(users.stats().entries, 0);
+        assert_eq!(posts.stats().entries, 1);
+        let stats = reload.cache_stats();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(
+            (stats[0].path.as_str(), stats[0].stats.entries),
+            ("/posts", 1)
+        );
+        assert_eq!(stats[1].shard_entries, [0]);
+        reload.invalidate_all();
+        assert_eq!(posts.stats().entries, 0);
+    }
+}
@@ -1,17 +1,33 @@
 use std::{
     future::Future,
+    net::SocketAddr,
     pin::Pin,
     sync::{
-        Arc,
-        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, OnceLock,
+        atomic::{AtomicUsize, Ordering},
     },
-    time::Instant,
+    time::{Duration, Instant},
 };
 
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::TcpListener;
+use arc_swap::ArcSwap;
+use tachyon_http::encoding::Encoding;
+use tachyon_http::parser::ParseError;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::OwnedSemaphorePermit;
 
-use crate::{config::ServerConfig, response::Response, utils::apply_socket_config};
+use crate::{
+    cache::ResponseCache,
+    cancel::CancelToken,
+    config::{RuntimeConfig, ServerConfig},
+    error::TachyonError,
+    hooks::{Limit, LimitExceeded},
+    ip_filter::Reject,
+    response::{IntoResponse, Response},
+    router::{Route, Router},
+    shutdown::Shutdown,
+    trace::RequestSpan,
+    utils::{CatchUnwind, apply_socket_config, bind_listener, panic_message},
+};
 
 /// Write function returned by an async handler. Called synchronously after the future resolves.
 pub type WriteFn = Box<dyn FnOnce(&mut Response) -> usize + Send>;
@@ -19,32 +35,616 @@ pub type WriteFn = Box<dyn FnOnce(&mut Response) -> usize + Send>;
 /// The handler function type. Takes a borrowed request, returns a future.
 /// The future resolves to a WriteFn that writes the HTTP response.
 /// This design avoids block_in_place — the async bridge uses rx.await instead.
-pub type Handler = Arc<
-    dyn for<'r> Fn(
-            &'r tachyon_http::http::Request<'r>,
-        ) -> Pin<Box<dyn Future<Output = WriteFn> + Send>>
-        + Send
-        + Sync,
+pub type Handler =
+    Arc<dyn for<'r> Fn(&'r tachyon_http::http::Request<'r>) -> HandlerFuture + Send + Sync>;
+
+/// Future returned by handlers and middleware.
+pub type HandlerFuture = Pin<Box<dyn Future<Output = WriteFn> + Send>>;
+
+/// Writes the response for a handler that missed its deadline.
+pub type TimeoutHook = Arc<
+    dyn for<'r> Fn(&'r tachyon_http::http::Request<'r>, &mut Response<'_>) -> usize + Send + Sync,
 >;
 
+/// Observes a handler panic (logging, error reporting). The client still
+/// gets the 500 error page.
+pub type PanicHook = Arc<dyn for<'r> Fn(&'r tachyon_http::http::Request<'r>, &str) + Send + Sync>;
+
+/// Wrap an async function as a [`Handler`]. The future cannot borrow the
+/// request, so copy out what it needs before the first `.await`:
+///
+/// ```ignore
+/// router.route(Method::Get, "/me", async_handler(|req| {
+///     let token = req.header(b"authorization").map(<[u8]>::to_vec);
+///     async move {
+///         let user = db.user_for(token).await;
+///         Box::new(move |res: &mut Response| res.json_value(200, &user)) as WriteFn
+///     }
+/// }));
+/// ```
+pub fn async_handler<F, Fut>(f: F) -> Handler
+where
+    F: for<'r> Fn(&'r tachyon_http::http::Request<'r>) -> Fut + Send + Sync + 'static,
+    Fut: Future + Send + 'static,
+    Fut::Output: IntoResponse,
+{
+    Arc::new(move |req| {
+        let fut = f(req);
+        Box::pin(async move { fut.await.into_write_fn() })
+    })
+}
+
+/// Wrap a synchronous function as a [`Handler`]. It runs inline on the
+/// connection task with no future to drive, so keep it short and free of
+/// blocking IO — use [`async_handler`] for anything that waits.
+pub fn sync_handler<F, R>(f: F) -> Handler
+where
+    F: for<'r> Fn(&'r tachyon_http::http::Request<'r>) -> R + Send + Sync + 'static,
+    R: IntoResponse,
+{
+    Arc::new(move |req| Box::pin(std::future::ready(f(req).into_write_fn())))
+}
+
+/// Wrap a CPU-heavy or blocking function as a [`Handler`] that runs on
+/// tokio's blocking pool instead of the acceptor's thread. `f` runs inline
+/// and copies what the work needs out of the request; the closure it returns
+/// runs on the pool:
+///
+/// ```ignore
+/// router.route(Method::Post, "/thumb", blocking_handler(|req| {
+///     let image = req.decoded_body().into_owned();
+///     move || {
+///         let thumb = resize(&image);
+///         Box::new(move |res: &mut Response| res.send(200, "image/png", &thumb)) as WriteFn
+///     }
+/// }));
+/// ```
+///
+/// A deadline that runs out answers the client but cannot stop the work
+/// already started. Panics reach `on_panic` as for any handler.
+pub fn blocking_handler<F, W, R>(f: F) -> Handler
+where
+    F: for<'r> Fn(&'r tachyon_http::http::Request<'r>) -> W + Send + Sync + 'static,
+    W: FnOnce() -> R + Send + 'static,
+    R: IntoResponse,
+{
+    Arc::new(move |req| {
+        let work = f(req);
+        Box::pin(async move {
+            match tokio::task::spawn_blocking(work).await {
+                Ok(out) => out.into_write_fn(),
+                Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+                // The runtime is shutting down.
+                Err(_) => Box::new(|res: &mut Response| res.text(503, b"Service Unavailable")),
+            }
+        })
+    })
+}
+
+/// Give `handler` at most `timeout` to produce its response; after that it
+/// is dropped and the client gets a 504 with the built-in JSON body. Use it
+/// around calls to slow downstreams, so even handlers mounted without a
+/// deadline cannot hold a connection indefinitely. `Router::deadline` and
+/// `ServerConfig::handler_timeout` still apply on top, and are the ones
+/// that honour `on_timeout` and custom error pages.
+pub fn with_timeout(handler: Handler, timeout: Duration) -> Handler {
+    Arc::new(move |req| {
+        let response = handler(req);
+        Box::pin(async move {
+            match tokio::time::timeout(timeout, response).await {
+                Ok(write) => write,
+                Err(_) => Box::new(|res: &mut Response| {
+                    res.json(504, crate::config::ErrorPages::default_body(504))
+                }),
+            }
+        })
+    })
+}
+
+/// How a handler future ended.
+enum HandlerOutcome {
+    Done(WriteFn),
+    TimedOut,
+    Panicked(Box<dyn std::any::Any + Send>),
+}
+
+/// Run `handler` (through the middleware chain) under its deadline,
+/// isolating panics from the connection task when `catch_panics` is set.
+async fn run_handler(
+    router: &Router,
+    handler: &Handler,
+    request: &tachyon_http::http::Request<'_>,
+    deadline: Duration,
+    catch_panics: bool,
+    cancel: &CancelToken,
+    peer: SocketAddr,
+) -> HandlerOutcome {
+    use std::panic::{AssertUnwindSafe, catch_unwind};
+
+    let guarded = cancel.scope(crate::extract::with_client_addr(peer, async {
+        if !catch_panics {
+            return Ok(router.dispatch(handler, request).await);
+        }
+        match catch_unwind(AssertUnwindSafe(|| router.dispatch(handler, request))) {
+            Ok(future) => CatchUnwind(future).await,
+            Err(payload) => Err(payload),
+        }
+    }));
+    let result = if deadline == Duration::MAX {
+        Some(guarded.await)
+    } else {
+        tokio::time::timeout(deadline, guarded).await.ok()
+    };
+    match result {
+        Some(Ok(write)) => HandlerOutcome::Done(write),
+        Some(Err(payload)) => HandlerOutcome::Panicked(payload),
+        None => HandlerOutcome::TimedOut,
+    }
+}
+
+/// Run `fut` while watching `stream` for the client going away, once a
+/// handler has asked for `cancel`. Up to `room` bytes the client sends
+/// meanwhile end up in `early`; watching goes on until they fill it.
+async fn watching<S: Transport, F: Future>(
+    stream: &mut S,
+    cancel: &CancelToken,
+    room: usize,
+    early: &mut Vec<u8>,
+    fut: F,
+) -> F::Output {
+    if room == 0 || !stream.reports_disconnect() {
+        return fut.await;
+    }
+    let watch = async {
+        cancel.wanted().await;
+        let mut buf = vec![0; room];
+        while early.len() < room {
+            match stream.read(&mut buf[..room - early.len()]).await {
+                Ok(0) | Err(_) => {
+                    cancel.cancel();
+                    break;
+                }
+                Ok(n) => early.extend_from_slice(&buf[..n]),
+            }
+        }
+        std::future::pending::<()>().await;
+    };
+    tokio::select! {
+        biased;
+        out = fut => out,
+        () = watch => unreachable!(),
+    }
+}
+
+/// `start + limit`, or `None` when `limit` is `Duration::MAX` (no timeout).
+fn deadline(start: Instant, limit: Duration) -> Option<Instant> {
+    if limit == Duration::MAX {
+        None
+    } else {
+        start.checked_add(limit)
+    }
+}
+
+/// When a request that started at `began` with `received` bytes so far drops
+/// below the minimum receive rate, if no more data arrives.
+fn rate_deadline(began: Instant, received: usize, config: &ServerConfig) -> Option<Instant> {
+    let timeouts = &config.timeouts;
+    if timeouts.min_rate == 0 {
+        return None;
+    }
+    let earned = Duration::from_secs_f64(received as f64 / timeouts.min_rate as f64);
+    began.checked_add(earned.max(timeouts.min_rate_grace))
+}
+
+/// Await `fut`, giving up at `deadline`.
+async fn until<F: Future>(deadline: Option<Instant>, fut: F) -> Option<F::Output> {
+    match deadline {
+        Some(at) => tokio::time::timeout_at(at.into(), fut).await.ok(),
+        None => Some(fut.await),
+    }
+}
+
+/// Pause after a failed `accept`.
+///
+/// Errors about a single connection (the client reset before it was
+/// accepted) are skipped. Anything else — usually the process running out
+/// of file descriptors (EMFILE/ENFILE) or memory — would otherwise fail
+/// again straight away, so accepting pauses, doubling from 5ms up to 1s,
+/// until an accept succeeds. Open connections keep being served meanwhile.
+#[derive(Default)]
+pub(crate) struct AcceptBackoff {
+    delay: Option<Duration>,
+}
+
+impl AcceptBackoff {
+    const FIRST: Duration = Duration::from_millis(5);
+    const MAX: Duration = Duration::from_secs(1);
+
+    pub(crate) fn reset(&mut self) {
+        self.delay = None;
+    }
+
+    pub(crate) async fn pause(&mut self, e: &std::io::Error, config: &ServerConfig) {
+        use std::io::ErrorKind;
+
+        if let Some(metrics) = &config.metrics {
+            metrics.accept_failed();
+        }
+        if matches!(
+            e.kind(),
+            ErrorKind::ConnectionAborted | ErrorKind::ConnectionReset | ErrorKind::Interrupted
+        ) {
+            return;
+        }
+        let delay = match self.delay {
+            Some(delay) => (delay * 2).min(Self::MAX),
+            None => {
+                eprintln!("[tachyon] accept failed: {}; backing off", e);
+                Self::FIRST
+            }
+        };
+        self.delay = Some(delay);
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// With `LoadShed::pause_accept`, wait out an open breaker before accepting.
+pub(crate) async fn overload_pause(config: &ServerConfig) {
+    if let Some(pause) = config.load_shed.as_ref().and_then(|s| s.accept_pause()) {
+        tokio::time::sleep(pause).await;
+    }
+}
+
+/// Count a connection refused at accept.
+pub(crate) fn count_rejected(config: &ServerConfig, reason: crate::metrics::Rejected) {
+    if let Some(metrics) = &config.metrics {
+        metrics.connection_rejected(reason);
+    }
+}
+
+/// The byte stream a connection is served over: a Tokio socket, or an
+/// io_uring one with the `io-uring` feature.
+pub(crate) trait Transport {
+    async fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize>;
+    async fn write_all(&mut self, data: &[u8]) -> std::io::Result<()>;
+    fn set_nodelay(&self, enabled: bool) -> std::io::Result<()>;
+
+    /// Whether a read can be dropped half-way without losing data, so the
+    /// stream can be watched for disconnects while a handler runs.
+    fn reports_disconnect(&self) -> bool {
+        true
+    }
+}
+
+impl Transport for TcpStream {
+    async fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        tokio::io::AsyncReadExt::read(self, buf).await
+    }
+
+    async fn write_all(&mut self, data: &[u8]) -> std::io::Result<()> {
+        tokio::io::AsyncWriteExt::write_all(self, data).await
+    }
+
+    fn set_nodelay(&self, enabled: bool) -> std::io::Result<()> {
+        TcpStream::set_nodelay(self, enabled)
+    }
+}
+
+/// Write `data` under the write timeout. `false` if it failed or timed out.
+pub(crate) async fn write_within<S: Transport>(
+    stream: &mut S,
+    data: &[u8],
+    config: &ServerConfig,
+) -> bool {
+    let at = deadline(Instant::now(), config.timeouts.write);
+    matches!(until(at, stream.write_all(data)).await, Some(Ok(())))
+}
+
+/// Send responses held back for pipelining. `false` if the write failed.
+async fn flush<S: Transport>(stream: &mut S, pending: &mut Vec<u8>, config: &ServerConfig) -> bool {
+    if pending.is_empty() {
+        return true;
+    }
+    let ok = write_within(stream, pending, config).await;
+    pending.clear();
+    ok
+}
+
+/// Send a streamed body as chunks, then the last chunk. `false` if the
+/// connection has to close: a write failed, the producer aborted or the
+/// client went away between chunks. Up to `room` bytes the client sends
+/// meanwhile end up in `early`, as with [`watching`].
+async fn send_stream<S: Transport>(
+    stream: &mut S,
+    mut body: crate::stream::BodyStream,
+    config: &ServerConfig,
+    room: usize,
+    early: &mut Vec<u8>,
+) -> bool {
+    let watch = if stream.reports_disconnect() { room } else { 0 };
+    let mut buf = vec![0; watch];
+    let mut frame = Vec::new();
+    loop {
+        let free = watch.saturating_sub(early.len());
+        let chunk = tokio::select! {
+            biased;
+            chunk = body.next() => chunk,
+            read = stream.read(&mut buf[..free]), if free > 0 => {
+                match read {
+                    Ok(0) | Err(_) => return false,
+                    Ok(n) => early.extend_from_slice(&buf[..n]),
+                }
+                continue;
+            }
+        };
+        let Some(chunk) = chunk else {
+            break;
+        };
+        let Some(data) = chunk else {
+            return false;
+        };
+        if data.is_empty() {
+            // An empty chunk would end the body.
+            continue;
+        }
+        frame.clear();
+        tachyon_http::chunked::write_chunk_size(&mut frame, data.len());
+        frame.extend_from_slice(&data);
+        frame.extend_from_slice(b"\r\n");
+        if !write_within(stream, &frame, config).await {
+            return false;
+        }
+    }
+    write_within(stream, b"0\r\n\r\n", config).await
+}
+
+/// Answer with an error page before closing the connection.
+pub(crate) async fn send_error<S: Transport>(
+    stream: &mut S,
+    buf: &mut [u8],
+    config: &ServerConfig,
+    status: u16,
+) {
+    let mut res = Response::new(
+        buf,
+        config.security.as_bytes(),
+        Encoding::Identity,
+        config.compression_threshold,
+    );
+    res.set_pretty_json(config.pretty_json);
+    res.error(status, &config.error_pages);
+    write_within(stream, res.data(), config).await;
+}
+
 /// The tachyon server.
 pub struct Server {
     config: ServerConfig,
+    listeners: Vec<std::net::TcpListener>,
+    routes: LiveRouter,
+    shutdown: Arc<Shutdown>,
+    listening: Listening,
+}
+
+/// The primary acceptor's listening sockets, once bound; what
+/// `HandoverHandle` passes on.
+pub(crate) type Listening = Arc<OnceLock<Vec<std::net::TcpListener>>>;
+
+/// The route table connections read from. Each request loads the current
+/// one, so a swap takes effect without touching open connections.
+pub(crate) type LiveRouter = Arc<ArcSwap<Router>>;
+
+/// Replaces the route table of a running server. See [`Server::reload_handle`].
+#[derive(Clone)]
+pub struct ReloadHandle {
+    routes: LiveRouter,
+    config: ServerConfig,
+    shutdown: Arc<Shutdown>,
+}
+
+impl ReloadHandle {
+    /// Atomically switch to `router`. Requests already running finish on
+    /// the old table; every later request, on any connection, uses the new
+    /// one. The old table's response caches are cleared, so nothing cached
+    /// under the old handlers is served again.
+    pub fn reload(&self, mut router: Router) {
+        router.prepare(&self.config);
+        let old = self.routes.swap(Arc::new(router));
+        let current = self.routes.load();
+        for cache in old.caches() {
+            cache.clear();
+            if let Some(metrics) = &self.config.metrics
+                && !current.caches().any(|c| c.ptr_eq(cache))
+            {
+                metrics.unwatch_cache(cache);
+            }
+        }
+    }
+
+    /// Replace one handler of the table currently served; see
+    /// [`Router::swap_handler`]. A later [`reload`](Self::reload) replaces
+    /// it along with the rest of the table.
+    pub fn swap_handler(
+        &self,
+        method: tachyon_http::methods::Method,
+        path: &str,
+        handler: Handler,
+    ) -> Option<Handler> {
+        self.routes.load().swap_handler(method, path, handler)
+    }
+
+    /// Drop the cached responses of `path` from the table currently
+    /// served; see [`Router::invalidate_cache`].
+    pub fn invalidate_cache(&self, path: &str) {
+        self.routes.load().invalidate_cache(path);
+    }
+
+    /// Empty every route cache of the table currently served.
+    pub fn invalidate_all(&self) {
+        self.routes.load().invalidate_all();
+    }
+
+    /// Cache counters of the table currently served; see
+    /// [`Router::cache_stats`].
+    pub fn cache_stats(&self) -> Vec<crate::cache::RouteCacheStats> {
+        self.routes.load().cache_stats()
+    }
+
+    /// Zero the cache counters of the table currently served.
+    pub fn reset_cache_stats(&self) {
+        self.routes.load().reset_cache_stats();
+    }
+
+    /// Fill the response cache of every cached GET route of the table
+    /// currently served by calling its handler once, as for a plain
+    /// `GET <path>` without `Accept-Encoding`, so the first client isn't
+    /// the one waiting on the handler. Routes whose handler times out,
+    /// panics, doesn't answer 200 or answers per client (see
+    /// [`Router::cached_route`]) stay cold. Returns how many responses
+    /// were cached. Static routes need no warming: they are rendered when
+    /// the server starts.
+    pub async fn warmup(&self) -> usize {
+        use std::panic::{AssertUnwindSafe, catch_unwind};
+
+        let router = self.routes.load_full();
+        let config = &self.config;
+        let mut buf = vec![0u8; config.buffer_size];
+        let mut warmed = 0;
+        for (path, handler, cache) in router.cached_gets() {
+            let mut raw = b"GET ".to_vec();
+            raw.extend_from_slice(path);
+            raw.extend_from_slice(b" HTTP/1.1\r\n\r\n");
+            let tachyon_http::parser::ParseResult::Complete(request) =
+                tachyon_http::parser::parse(&raw)
+            else {
+                continue;
+            };
+            if router
+                .policy_for(request.method, request.path)
+                .and_then(|policy| policy.check(&request))
+                .is_some()
+            {
+                continue;
+            }
+            let deadline = router
+                .deadline_for(request.method, request.path)
+                .unwrap_or(config.handler_timeout);
+            let outcome = run_handler(
+                &router,
+                handler,
+                &request,
+                deadline,
+                true,
+                &CancelToken::new(),
+                SocketAddr::from(([0, 0, 0, 0], 0)),
+            )
+            .await;
+            let HandlerOutcome::Done(write) = outcome else {
+                continue;
+            };
+            let mut res = Response::new(
+                &mut buf,
+                config.security.as_bytes(),
+                Encoding::Identity,
+                config.compression_threshold,
+            );
+            res.set_pretty_json(config.pretty_json);
+            if let Some(envelope) = router.envelope_for(request.path) {
+                res.set_envelope(envelope, None);
+            }
+            if config.etag != tachyon_http::etag::EtagMode::Off {
+                res.enable_etag(config.etag, None);
+            }
+            if catch_unwind(AssertUnwindSafe(|| write(&mut res))).is_ok()
+                && res.status() == Some(200)
+                && !res.is_streamed()
+                && let Some(entry) = res.cache_entry()
+            {
+                let key = ResponseCache::key(request.method, Encoding::Identity, request.path);
+                cache.put(key, &entry);
+                warmed += 1;
+            }
+        }
+        warmed
+    }
+
+    /// The route table currently served.
+    pub fn current(&self) -> Arc<Router> {
+        self.routes.load_full()
+    }
+
+    /// Close every connection open now without cutting any response
+    /// short: each one's next response carries `Connection: close`, and
+    /// idle keep-alive connections close right away. New connections are
+    /// unaffected. Use it after a reload that should not keep serving
+    /// clients over sockets set up under the old configuration.
+    pub fn recycle_connections(&self) {
+        self.shutdown.recycle();
+    }
 }
 
 impl Server {
     pub fn new(config: ServerConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            listeners: Vec::new(),
+            routes: Arc::new(ArcSwap::from_pointee(Router::new())),
+            shutdown: Arc::new(Shutdown::new()),
+            listening: Listening::default(),
+        }
+    }
+
+    /// A handle for swapping the route table while the server runs, e.g.
+    /// on a config reload. Reloads before `serve` are overwritten by the
+    /// router passed to it.
+    pub fn reload_handle(&self) -> ReloadHandle {
+        ReloadHandle {
+            routes: self.routes.clone(),
+            config: self.config.clone(),
+            shutdown: self.shutdown.clone(),
+        }
     }
 
-    /// Convert a bind address like "0.0.0.0:3000" to "127.0.0.1:3000".
+    /// A handle for passing the listening sockets to a new process and
+    /// draining this one. See [`crate::handover`].
+    #[cfg(unix)]
+    pub fn handover_handle(&self) -> crate::handover::HandoverHandle {
+        crate::handover::HandoverHandle::new(self.listening.clone(), self.shutdown.clone())
+    }
+
+    /// Serve on an already-bound listener (e.g. one handed over by a process
+    /// manager) instead of binding `ServerConfig::bind_addr`. Can be called
+    /// several times; once any listener is given, the configured addresses
+    /// are not bound at all. Extra acceptors share each listener.
+    pub fn listen_with(mut self, listener: std::net::TcpListener) -> Self {
+        self.listeners.push(listener);
+        self
+    }
+
+    /// Serve on the sockets passed by systemd socket activation
+    /// (`LISTEN_FDS` / `LISTEN_PID`) or by a [`crate::handover`] from the
+    /// previous process, as with [`listen_with`](Self::listen_with).
+    /// Without socket activation this changes nothing, so the server falls
+    /// back to its configured addresses. The variables are unset, so call
+    /// this before starting other threads that read the environment.
+    #[cfg(unix)]
+    pub fn listen_fds(mut self) -> std::io::Result<Self> {
+        self.listeners.extend(crate::utils::listen_fds()?);
+        Ok(self)
+    }
+
+    /// Convert a wildcard bind address like "0.0.0.0:3000" or "[::]:3000"
+    /// to the matching loopback address.
     fn to_loopback(bind_addr: &str) -> String {
         if let Some(pos) = bind_addr.rfind(':') {
             let port = &bind_addr[pos + 1..];
             let host = &bind_addr[..pos];
-            if host == "0.0.0.0" || host == "::" || host == "[::]" || host.is_empty() {
+            if host == "0.0.0.0" || host.is_empty() {
                 return format!("127.0.0.1:{}", port);
             }
+            // IPv6 loopback works whether or not the socket is dual-stack.
+            if host == "::" || host == "[::]" {
+                return format!("[::1]:{}", port);
+            }
         }
         bind_addr.to_string()
     }
@@ -52,34 +652,264 @@ impl Server {
     /// Start the server with the given handler.
     ///
     /// Creates a single-threaded Tokio runtime and blocks until the server stops.
-    pub fn run(self, handler: Handler) -> std::io::Result<()> {
-        let rt = tokio::runtime::Builder::new_current_thread()
-            .enable_io()
-            .enable_time()
-            .build()?;
-        rt.block_on(self.run_inner(handler))
+    pub fn run(self, handler: Handler) -> Result<(), TachyonError> {
+        self.serve(Router::with_fallback(handler))
     }
 
-    async fn run_inner(self, handler: Handler) -> std::io::Result<()> {
-        let listener = TcpListener::bind(&self.config.bind_addr).await?;
-
-        apply_socket_config(&listener, &self.config.socket);
+    /// Start the server with a route table. Static routes are rendered once
+    /// here; unmatched requests go to the router's fallback (or a 404).
+    ///
+    /// Builds the runtime described by `ServerConfig::runtime` (single-threaded
+    /// by default) and blocks until the server stops.
+    pub fn serve(self, router: Router) -> Result<(), TachyonError> {
+        #[cfg(all(feature = "io-uring", target_os = "linux"))]
+        if self.config.io_uring {
+            let shared = self.start(router)?;
+            spawn_acceptors(&shared, crate::uring::run_acceptor)?;
+            return crate::uring::run_acceptor(shared, true);
+        }
+        if self.config.io_uring {
+            eprintln!("[tachyon] io_uring needs Linux and the `io-uring` feature; using epoll.");
+        }
+        runtime(&self.config.runtime)?.block_on(self.serve_async(router))
+    }
 
+    /// Like [`serve`](Self::serve), on the caller's Tokio runtime.
+    /// Extra acceptors (`ServerConfig::acceptors`) still get their own threads.
+    pub async fn serve_async(self, router: Router) -> Result<(), TachyonError> {
+        let shared = self.start(router)?;
         crate::date::start_date_cache();
+        spawn_acceptors(&shared, |shared, primary| {
+            runtime(&shared.config.runtime)?.block_on(run_acceptor(shared, primary))
+        })?;
+        run_acceptor(shared, true).await
+    }
+
+    /// Bind now and [`serve`](Self::serve) on a background thread.
+    ///
+    /// The returned handle knows the actual local addresses — bind port 0
+    /// to get a free one, e.g. in tests — and can stop the server and wait
+    /// for it. Binding and config errors are returned here rather than
+    /// from the thread.
+    pub fn spawn(mut self, router: Router) -> Result<ServerHandle, TachyonError> {
+        self.config.validate()?;
+        if self.listeners.is_empty() {
+            // Bound once here and shared by every acceptor, so port 0
+            // resolves to a single port.
+            let addrs = std::iter::once(&self.config.bind_addr).chain(&self.config.extra_binds);
+            for addr in addrs {
+                let listener = bind_listener(addr, false, &self.config.socket)
+                    .map_err(|e| TachyonError::bind(addr, e))?;
+                self.listeners.push(listener);
+            }
+        }
+        let local_addrs = self
+            .listeners
+            .iter()
+            .map(std::net::TcpListener::local_addr)
+            .collect::<std::io::Result<_>>()?;
+        let shutdown = self.shutdown.clone();
+        let (done, result) = tokio::sync::oneshot::channel();
+        std::thread::Builder::new()
+            .name("tachyon-acceptor-0".to_string())
+            .spawn(move || {
+                let _ = done.send(self.serve(router));
+            })?;
+        Ok(ServerHandle {
+            local_addrs,
+            shutdown,
+            result,
+        })
+    }
+
+    /// Serve `router` as a tower `Service` (and, with the `hyper` feature,
+    /// a hyper one) instead of listening, e.g. to embed it in an existing
+    /// hyper or axum server or behind a custom listener. See [`crate::tower`].
+    #[cfg(feature = "tower")]
+    pub fn into_service(
+        self,
+        router: Router,
+    ) -> Result<crate::tower::TachyonService, TachyonError> {
+        self.start(router).map(crate::tower::TachyonService::new)
+    }
+
+    /// Prepare the router and share everything the acceptors need.
+    fn start(self, mut router: Router) -> Result<Arc<Shared>, TachyonError> {
+        self.config.validate()?;
+        router.prepare(&self.config);
+        self.routes.store(Arc::new(router));
+        Ok(Arc::new(Shared {
+            routes: self.routes,
+            config: Arc::new(self.config),
+            inherited: self.listeners.into(),
+            shutdown: self.shutdown,
+            listening: self.listening,
+        }))
+    }
+}
+
+/// A server started with [`Server::spawn`].
+pub struct ServerHandle {
+    local_addrs: Vec<SocketAddr>,
+    shutdown: Arc<Shutdown>,
+    result: tokio::sync::oneshot::Receiver<Result<(), TachyonError>>,
+}
+
+impl ServerHandle {
+    /// The address the server listens on (its `bind_addr`, with the port
+    /// the OS picked if it was 0).
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addrs[0]
+    }
+
+    /// Every listening address, `bind_addr` first.
+    pub fn local_addrs(&self) -> &[SocketAddr] {
+        &self.local_addrs
+    }
+
+    /// Start a graceful shutdown, as SIGTERM would. Returns immediately;
+    /// [`join`](Self::join) waits for the drain to finish.
+    pub fn shutdown(&self) {
+        self.shutdown.trigger();
+    }
+
+    /// Wait for the server to stop and return what `serve` returned.
+    pub async fn join(self) -> Result<(), TachyonError> {
+        self.result.await.unwrap_or(Err(TachyonError::Panicked))
+    }
+}
+
+/// State shared by the acceptors and connections of one running server.
+pub(crate) struct Shared {
+    pub(crate) routes: LiveRouter,
+    pub(crate) config: Arc<ServerConfig>,
+    /// Listeners from `Server::listen_with`; empty = bind the configured addresses.
+    pub(crate) inherited: Box<[std::net::TcpListener]>,
+    pub(crate) shutdown: Arc<Shutdown>,
+    pub(crate) listening: Listening,
+}
 
-        let config = Arc::new(self.config);
+/// Entry point of an acceptor thread.
+type AcceptorFn = fn(Arc<Shared>, bool) -> Result<(), TachyonError>;
+
+/// Start acceptors 1.. on their own threads; the caller runs the primary one.
+fn spawn_acceptors(shared: &Arc<Shared>, run: AcceptorFn) -> std::io::Result<()> {
+    let acceptors = shared.config.acceptors.max(1);
+    if acceptors > 1 && !cfg!(unix) {
+        eprintln!("[tachyon] Multiple acceptors need SO_REUSEPORT (unix); using one.");
+        return Ok(());
+    }
+    for id in 1..acceptors {
+        let shared = shared.clone();
+        std::thread::Builder::new()
+            .name(format!("tachyon-acceptor-{}", id))
+            .spawn(move || {
+                if let Err(e) = run(shared, false) {
+                    eprintln!("[tachyon] Acceptor {} stopped: {}", id, e);
+                }
+            })?;
+    }
+    Ok(())
+}
+
+fn runtime(config: &RuntimeConfig) -> Result<tokio::runtime::Runtime, TachyonError> {
+    let mut builder = if config.worker_threads == 1 {
+        tokio::runtime::Builder::new_current_thread()
+    } else {
+        let mut builder = tokio::runtime::Builder::new_multi_thread();
+        if config.worker_threads > 1 {
+            builder.worker_threads(config.worker_threads);
+        }
+        builder
+    };
+    builder
+        .max_blocking_threads(config.max_blocking_threads)
+        .thread_name(config.thread_name.clone())
+        .enable_io()
+        .enable_time()
+        .build()
+        .map_err(TachyonError::Runtime)
+}
+
+/// Bind every configured address (or adopt the inherited listeners) and
+/// serve them on the current runtime until shutdown. The primary acceptor
+/// also runs the warmup, listens for shutdown signals and runs the drain.
+async fn run_acceptor(shared: Arc<Shared>, primary: bool) -> Result<(), TachyonError> {
+    let mut listeners = bind_all(&shared, primary)?;
+    let first = listeners.remove(0);
+    for listener in listeners {
+        tokio::spawn(accept_loop(listener, shared.clone()));
+    }
+    if primary && shared.config.shutdown_signals {
+        let shutdown = shared.shutdown.clone();
+        tokio::spawn(async move {
+            crate::shutdown::signal().await;
+            shutdown.trigger();
+        });
+    }
+    accept_loop(first, shared.clone()).await;
+    finish(&shared, primary).await;
+    Ok(())
+}
+
+/// After this acceptor stopped accepting: the primary drains every
+/// connection; the others keep their runtime alive until it is done.
+pub(crate) async fn finish(shared: &Shared, primary: bool) {
+    if primary {
+        eprintln!("[tachyon] Shutting down, draining connections...");
+        let forced = shared.shutdown.drain(shared.config.drain_timeout).await;
+        if forced > 0 {
+            eprintln!("[tachyon] Drain timed out; closed {} connections", forced);
+        }
+    } else {
+        shared.shutdown.forced().await;
+    }
+}
+
+/// This acceptor's listeners: the configured addresses, or its own handles
+/// to the inherited sockets. With `primary`, also start the warmup.
+pub(crate) fn bind_all(shared: &Shared, primary: bool) -> Result<Vec<TcpListener>, TachyonError> {
+    let (config, inherited) = (&shared.config, &shared.inherited);
+    let mut listeners = Vec::with_capacity(1 + config.extra_binds.len());
+    let mut addrs = Vec::with_capacity(listeners.capacity());
+    if inherited.is_empty() {
+        // Several acceptors share each address through SO_REUSEPORT.
+        let shared = config.acceptors > 1;
+        for addr in std::iter::once(&config.bind_addr).chain(&config.extra_binds) {
+            let listener = bind_listener(addr, shared, &config.socket)
+                .map_err(|e| TachyonError::bind(addr, e))?;
+            listeners.push(TcpListener::from_std(listener)?);
+            addrs.push(addr.clone());
+        }
+    } else {
+        for listener in inherited {
+            // Each acceptor polls its own handle to the same socket.
+            let listener = listener.try_clone()?;
+            listener.set_nonblocking(true)?;
+            addrs.push(listener.local_addr()?.to_string());
+            listeners.push(TcpListener::from_std(listener)?);
+        }
+    }
+    for listener in &listeners {
+        apply_socket_config(listener, &config.socket);
+    }
+
+    if primary {
+        let handles = listeners
+            .iter()
+            .map(|l| Ok(socket2::SockRef::from(l).try_clone()?.into()))
+            .collect::<std::io::Result<_>>()?;
+        let _ = shared.listening.set(handles);
 
         // Warmup requests through the full pipeline to trigger V8 JIT.
-        let loopback_addr = Self::to_loopback(&config.bind_addr);
+        let loopback_addr = Server::to_loopback(&addrs[0]);
         let warmup_count: usize = 10;
         let warmup_completed = Arc::new(AtomicUsize::new(0));
-        let warmup_ready = Arc::new(AtomicBool::new(false));
 
         let warmup_completed2 = warmup_completed.clone();
-        let warmup_ready2 = warmup_ready.clone();
+        let bound = addrs.join(", ");
+        let acceptors = config.acceptors.max(1);
         tokio::spawn(async move {
-            use tokio::io::{AsyncReadExt, AsyncWriteExt};
-            use tokio::net::TcpStream;
             let t = Instant::now();
             for i in 0..warmup_count {
                 match TcpStream::connect(&loopback_addr).await {
@@ -105,134 +935,1278 @@ impl Server {
                 warmup_count,
                 t.elapsed()
             );
-            warmup_ready2.store(true, Ordering::Release);
+            if acceptors > 1 {
+                eprintln!("[tachyon] Listening on {} ({} acceptors)", bound, acceptors);
+            } else {
+                eprintln!("[tachyon] Listening on {}", bound);
+            }
         });
+    }
+    Ok(listeners)
+}
 
-        let mut warmup_printed = false;
-
-        loop {
-            let (stream, _addr) = listener.accept().await?;
-
-            if !warmup_printed && warmup_ready.load(Ordering::Acquire) {
-                eprintln!("[tachyon] Listening on {}", config.bind_addr,);
-                warmup_printed = true;
+/// Accept connections on `listener` and spawn a task per connection.
+async fn accept_loop(listener: TcpListener, shared: Arc<Shared>) {
+    let config = &shared.config;
+    let limit = config.connection_limit.as_ref();
+    let mut backoff = AcceptBackoff::default();
+    loop {
+        let accepted = async {
+            // Waiting here leaves new clients in the listen backlog.
+            overload_pause(config).await;
+            let reserved = match limit {
+                Some(limit) => limit.reserve().await,
+                None => None,
+            };
+            match listener.accept().await {
+                Ok(accepted) => Some((accepted, reserved)),
+                Err(e) => {
+                    backoff.pause(&e, config).await;
+                    None
+                }
             }
+        };
+        let ((stream, peer), reserved) = tokio::select! {
+            accepted = accepted => match accepted {
+                Some(accepted) => accepted,
+                None => continue,
+            },
+            () = shared.shutdown.draining() => return,
+        };
+        backoff.reset();
+        // IPv4 clients of a dual-stack socket arrive as ::ffff:a.b.c.d.
+        let peer = SocketAddr::new(peer.ip().to_canonical(), peer.port());
 
-            let handler = handler.clone();
-            let config = config.clone();
+        let rejected = config
+            .ip_filter
+            .as_ref()
+            .filter(|f| !f.allows(peer.ip()))
+            .map(|f| f.rejection());
+        if rejected.is_some() {
+            count_rejected(config, crate::metrics::Rejected::IpFilter);
+        }
+        if rejected == Some(Reject::Close) {
+            continue;
+        }
 
-            tokio::spawn(async move {
-                if config.socket.tcp_nodelay {
-                    let _ = stream.set_nodelay(true);
+        let slot = match limit {
+            Some(limit) => match limit.admit(reserved) {
+                Some(slot) => Some(slot),
+                None => {
+                    count_rejected(config, crate::metrics::Rejected::Limit);
+                    crate::limit::reject(stream);
+                    continue;
                 }
+            },
+            None => None,
+        };
 
-                let mut read_buf = tachyon_pool::pool::acquire();
-                let mut write_buf = tachyon_pool::pool::acquire();
-                let sec_headers = config.security.as_bytes();
-                let comp_threshold = config.compression_threshold;
+        tokio::spawn(serve_connection(
+            stream,
+            peer,
+            rejected,
+            slot,
+            shared.clone(),
+        ));
+    }
+}
 
-                // No split needed — reads/writes are sequential in the connection loop.
-                // Avoids Arc allocation that into_split() requires.
-                let mut stream = stream;
+/// Serve an accepted connection, holding its connection-limit slot, until
+/// it closes or shutdown forces it closed.
+pub(crate) fn serve_connection<S: Transport>(
+    stream: S,
+    peer: SocketAddr,
+    rejected: Option<Reject>,
+    slot: Option<OwnedSemaphorePermit>,
+    shared: Arc<Shared>,
+) -> impl Future<Output = ()> {
+    // Counted from accept, so a drain never misses a connection.
+    let open = shared.shutdown.track();
+    async move {
+        let conn = handle_connection(stream, peer, rejected, &shared);
+        tokio::select! {
+            () = crate::trace::connection(peer, conn) => {}
+            () = shared.shutdown.forced() => {}
+        }
+        drop((open, slot));
+    }
+}
+
+/// Serve requests on one connection until it closes.
+async fn handle_connection<S: Transport>(
+    stream: S,
+    peer: SocketAddr,
+    rejected: Option<Reject>,
+    shared: &Shared,
+) {
+    let (config, routes) = (&*shared.config, &shared.routes);
+    if config.socket.tcp_nodelay {
+        let _ = stream.set_nodelay(true);
+    }
+    let _connection = config.metrics.as_ref().map(|m| m.connection_opened());
+
+    tachyon_pool::pool::configure_thread_pool(config.pool_buffers, config.buffer_size);
+    let mut read_buf = tachyon_pool::pool::acquire();
+    let mut write_buf = tachyon_pool::pool::acquire();
+    let sec_headers = config.security.as_bytes();
+    let comp_threshold = config.compression_threshold;
+
+    // No split needed — reads/writes are sequential in the connection loop.
+    // Avoids Arc allocation that into_split() requires.
+    let mut stream = stream;
+
+    let mut buf_offset: usize = 0;
+    let mut buf_len: usize = 0;
+
+    // When the pending request started and when its headers were complete;
+    // the first request's clock starts at accept.
+    let timeouts = &config.timeouts;
+    let mut began = Some(Instant::now());
+    let mut body_began: Option<Instant> = None;
+
+    let mut served: usize = 0;
+    // Closed by a later `recycle_connections`.
+    let generation = shared.shutdown.generation();
+    // Responses to pipelined requests, sent before the next read.
+    let mut pending = Vec::new();
+    // Bytes read while watching a request for disconnects.
+    let mut early = Vec::new();
+
+    'conn: loop {
+        if buf_offset >= buf_len {
+            if !flush(&mut stream, &mut pending, config).await {
+                break;
+            }
+            buf_offset = 0;
+            let at = match began {
+                Some(began) => deadline(began, timeouts.header_read),
+                None => deadline(Instant::now(), timeouts.idle),
+            };
+            // Nothing of a request received yet: on timeout, shutdown or
+            // recycle, just close.
+            let read = until(at, stream.read(read_buf.as_write_buf()));
+            let n = tokio::select! {
+                read = read => match read {
+                    Some(Ok(n)) if n > 0 => n,
+                    _ => break,
+                },
+                () = shared.shutdown.closing(generation) => break,
+            };
+            buf_len = n;
+            read_buf.set_len(n);
+        }
 
-                let mut buf_offset: usize = 0;
-                let mut buf_len: usize = 0;
+        loop {
+            if !early.is_empty() {
+                read_buf.as_write_buf()[buf_len..buf_len + early.len()].copy_from_slice(&early);
+                buf_len += early.len();
+                read_buf.set_len(buf_len);
+                early.clear();
+            }
+            let room = read_buf.as_write_buf().len() - buf_len;
+            let data = &read_buf.as_write_buf()[buf_offset..buf_len];
+            if data.is_empty() {
+                break;
+            }
 
-                'conn: loop {
-                    if buf_offset >= buf_len {
+            let request = match crate::trace::parse(data, &config.http1.limits) {
+                tachyon_http::parser::ParseResult::Complete(req) => req,
+                tachyon_http::parser::ParseResult::Incomplete => {
+                    if !flush(&mut stream, &mut pending, config).await {
+                        break 'conn;
+                    }
+                    // Move the partial request to the front, then read more.
+                    if buf_offset > 0 {
+                        read_buf.as_write_buf().copy_within(buf_offset..buf_len, 0);
+                        buf_len -= buf_offset;
                         buf_offset = 0;
-                        let n = match stream.read(read_buf.as_write_buf()).await {
-                            Ok(0) => break,
-                            Ok(n) => n,
-                            Err(_) => break,
+                        read_buf.set_len(buf_len);
+                    }
+                    let headers_done = read_buf.as_write_buf()[..buf_len]
+                        .windows(4)
+                        .any(|w| w == b"\r\n\r\n");
+                    if buf_len == read_buf.as_write_buf().len() {
+                        // The request can never fit in the read buffer.
+                        let (status, limit) = if headers_done {
+                            (413, Limit::Body)
+                        } else if !read_buf.as_write_buf().contains(&b'\n') {
+                            (414, Limit::Uri)
+                        } else {
+                            (431, Limit::Headers)
                         };
-                        buf_len = n;
-                        read_buf.set_len(n);
+                        if let Some(hook) = routes.load().limit_hook() {
+                            hook(&LimitExceeded {
+                                peer,
+                                limit,
+                                status,
+                                received: buf_len,
+                                max: buf_len,
+                            });
+                        }
+                        send_error(&mut stream, write_buf.as_write_buf(), config, status).await;
+                        break 'conn;
                     }
-
-                    loop {
-                        let data = &read_buf.as_write_buf()[buf_offset..buf_len];
-                        if data.is_empty() {
-                            break;
+                    let start = *began.get_or_insert_with(Instant::now);
+                    let at = if headers_done {
+                        deadline(
+                            *body_began.get_or_insert_with(Instant::now),
+                            timeouts.body_read,
+                        )
+                    } else {
+                        deadline(start, timeouts.header_read)
+                    };
+                    // Slow senders: whichever limit comes first.
+                    let at = match (at, rate_deadline(start, buf_len, config)) {
+                        (Some(a), Some(b)) => Some(a.min(b)),
+                        (a, b) => a.or(b),
+                    };
+                    let read = stream.read(&mut read_buf.as_write_buf()[buf_len..]);
+                    let n = match until(at, read).await {
+                        Some(Ok(0)) | Some(Err(_)) => break 'conn,
+                        Some(Ok(n)) => n,
+                        None => {
+                            send_error(&mut stream, write_buf.as_write_buf(), config, 408).await;
+                            break 'conn;
+                        }
+                    };
+                    buf_len += n;
+                    read_buf.set_len(buf_len);
+                    continue;
+                }
+                tachyon_http::parser::ParseResult::Error(e) => {
+                    let limits = &config.http1.limits;
+                    let exceeded = match e {
+                        ParseError::UriTooLong => Some((Limit::Uri, limits.max_uri)),
+                        ParseError::HeaderTooLarge => {
+                            Some((Limit::HeaderSize, limits.max_header_size))
                         }
+                        ParseError::HeadersTooLong => {
+                            Some((Limit::HeaderCount, limits.max_headers))
+                        }
+                        _ => None,
+                    };
+                    if let Some((limit, max)) = exceeded
+                        && let Some(hook) = routes.load().limit_hook()
+                    {
+                        hook(&LimitExceeded {
+                            peer,
+                            limit,
+                            status: e.status(),
+                            received: buf_len - buf_offset,
+                            max,
+                        });
+                    }
+                    flush(&mut stream, &mut pending, config).await;
+                    send_error(&mut stream, write_buf.as_write_buf(), config, e.status()).await;
+                    break 'conn;
+                }
+            };
 
-                        let request = match tachyon_http::parser::parse(data) {
-                            tachyon_http::parser::ParseResult::Complete(req) => req,
-                            tachyon_http::parser::ParseResult::Incomplete => {
-                                if buf_offset > 0 {
-                                    let remaining = buf_len - buf_offset;
-                                    let wbuf = read_buf.as_write_buf();
-                                    wbuf.copy_within(buf_offset..buf_len, 0);
-                                    buf_offset = 0;
-                                    read_buf.set_len(remaining);
-                                    let n = match stream
-                                        .read(&mut read_buf.as_write_buf()[remaining..])
-                                        .await
-                                    {
-                                        Ok(0) => break 'conn,
-                                        Ok(n) => n,
-                                        Err(_) => break 'conn,
-                                    };
-                                    buf_len = remaining + n;
-                                    read_buf.set_len(buf_len);
-                                }
-                                break;
-                            }
-                            tachyon_http::parser::ParseResult::Error(_) => {
-                                let mut res = Response::new(
-                                    write_buf.as_write_buf(),
-                                    sec_headers,
-                                    false,
-                                    comp_threshold,
-                                );
-                                res.text(400, b"Bad Request");
-                                let _ = stream.write_all(res.data()).await;
-                                break 'conn;
-                            }
-                        };
+            buf_offset += request.consumed();
+            began = None;
+            body_began = None;
+            // Pin the route table for this request; a reload applies from
+            // the next one.
+            let router = routes.load_full();
+            let span = RequestSpan::new(&request);
 
-                        buf_offset += request.consumed();
+            // Single-pass: extract both flags at once instead of scanning headers twice
+            let (accept_encoding, connection_close) = request.connection_flags();
+            let encoding = accept_encoding.map_or(Encoding::Identity, |ae| {
+                tachyon_http::encoding::negotiate(ae, crate::compress::SUPPORTED)
+            });
 
-                        // Single-pass: extract both flags at once instead of scanning headers twice
-                        let (accepts_gzip, connection_close) = request.connection_flags();
+            let mut res = Response::new(
+                write_buf.as_write_buf(),
+                sec_headers,
+                encoding,
+                comp_threshold,
+            );
+            res.set_pretty_json(config.pretty_json);
+            let envelope = router.envelope_for(request.path);
+            if let Some(envelope) = envelope {
+                res.set_envelope(envelope, request.header(b"x-request-id"));
+            }
+            res.set_head_request(request.method == tachyon_http::methods::Method::Head);
+            if stream.reports_disconnect() {
+                res.allow_upgrade();
+            }
+            if rejected.is_some() {
+                res.error(403, &config.error_pages);
+                write_within(&mut stream, res.data(), config).await;
+                break 'conn;
+            }
+            let mut if_none_match = None;
+            if config.etag != tachyon_http::etag::EtagMode::Off
+                && matches!(
+                    request.method,
+                    tachyon_http::methods::Method::Get | tachyon_http::methods::Method::Head
+                )
+            {
+                if_none_match = request.header(b"if-none-match");
+                res.enable_etag(config.etag, if_none_match);
+            }
 
-                        let mut res = Response::new(
-                            write_buf.as_write_buf(),
-                            sec_headers,
-                            accepts_gzip,
-                            comp_threshold,
+            let hooks = router.after_response_hooks();
+            let observers = router.on_response_hooks();
+            crate::hooks::request_started(
+                router.on_request_hooks(),
+                request.method,
+                request.path,
+                peer,
+            );
+            let started = (!hooks.is_empty()
+                || !observers.is_empty()
+                || config.metrics.is_some()
+                || config.access_log.is_some()
+                || router.route_stats().is_some())
+            .then(Instant::now);
+            if let Some(metrics) = &config.metrics {
+                metrics.request_started();
+            }
+
+            let cors = config.cors.as_ref().zip(request.header(b"origin"));
+            let preflight = cors.is_some()
+                && request.method == tachyon_http::methods::Method::Options
+                && request.header(b"access-control-request-method").is_some();
+
+            let route = if preflight {
+                None
+            } else {
+                span.route(|| router.find(request.method, request.path))
+            };
+            // A route registered at the metrics path wins over the scrape.
+            let scrape = config.metrics.as_ref().filter(|m| {
+                !preflight && route.is_none() && m.is_scrape(request.method, request.path)
+            });
+
+            let mut cache_slot = None;
+            let mut live = None;
+            let mut timed_out = false;
+            let handler = match route {
+                _ if preflight => {
+                    if let Some((cors, origin)) = cors {
+                        cors.preflight(
+                            &mut res,
+                            origin,
+                            request.header(b"access-control-request-headers"),
                         );
-                        let write = handler(&request).await;
-                        if config.catch_panics {
+                    }
+                    None
+                }
+                _ if scrape.is_some() => {
+                    if let Some(metrics) = scrape {
+                        res.send(
+                            200,
+                            "text/plain; version=0.0.4; charset=utf-8",
+                            metrics.render().as_bytes(),
+                        );
+                    }
+                    None
+                }
+                Some(Route::Static(s)) => {
+                    // Fast path: pre-rendered bytes, no handler call at all.
+                    s.write(&mut res, encoding, if_none_match);
+                    None
+                }
+                Some(Route::Handler(h)) => Some(h),
+                Some(Route::Swappable(slot)) => Some(&**live.insert(slot.current())),
+                Some(Route::Cached(h, cache))
+                    if matches!(
+                        request.method,
+                        tachyon_http::methods::Method::Get | tachyon_http::methods::Method::Head
+                    ) =>
+                {
+                    let key = ResponseCache::key(request.method, encoding, request.path);
+                    match cache.get(&key) {
+                        Some(hit) => {
+                            res.write_hit(&hit);
+                            None
+                        }
+                        None => {
+                            cache_slot = Some((cache, key));
+                            Some(h)
+                        }
+                    }
+                }
+                Some(Route::Cached(h, _)) => Some(h),
+                None => router
+                    .files_for(request.path)
+                    .or_else(|| router.fallback_handler()),
+            };
+
+            // Body limit and content types of a `RouteConfig`.
+            let handler = handler.filter(|_| {
+                let rejected = router
+                    .policy_for(request.method, request.path)
+                    .and_then(|policy| policy.check(&request));
+                if let Some(status) = rejected {
+                    res.error(status, &config.error_pages);
+                }
+                rejected.is_none()
+            });
+
+            let mut permit = None;
+            let handler = match (handler, &config.load_shed) {
+                (Some(handler), Some(shed)) => match shed.admit() {
+                    Some(p) => {
+                        permit = Some(p);
+                        Some(handler)
+                    }
+                    None => {
+                        shed.reject(&mut res, &config.error_pages);
+                        None
+                    }
+                },
+                (handler, _) => handler,
+            };
+
+            match handler {
+                Some(handler) => {
+                    let deadline = router
+                        .deadline_for(request.method, request.path)
+                        .unwrap_or(config.handler_timeout);
+                    let mut panicked = None;
+                    // Fires when the client goes away during this request.
+                    let cancel = CancelToken::new();
+                    let outcome = span.handler(run_handler(
+                        &router,
+                        handler,
+                        &request,
+                        deadline,
+                        config.catch_panics,
+                        &cancel,
+                        peer,
+                    ));
+                    match watching(&mut stream, &cancel, room, &mut early, outcome).await {
+                        HandlerOutcome::Done(write) if config.catch_panics => {
                             use std::panic::{AssertUnwindSafe, catch_unwind};
-                            if catch_unwind(AssertUnwindSafe(|| {
+                            panicked = catch_unwind(AssertUnwindSafe(|| {
                                 write(&mut res);
                             }))
-                            .is_err()
-                            {
-                                eprintln!("[tachyon] Handler panicked");
-                                res = Response::new(
-                                    write_buf.as_write_buf(),
-                                    sec_headers,
-                                    accepts_gzip,
-                                    comp_threshold,
-                                );
-                                res.json(500, b"{\"error\":\"internal\"}");
-                            }
-                        } else {
+                            .err();
+                        }
+                        HandlerOutcome::Done(write) => {
                             write(&mut res);
-                        };
-
-                        if stream.write_all(res.data()).await.is_err() {
-                            break 'conn;
                         }
-
-                        if request.version_minor == 0 || connection_close {
-                            break 'conn;
+                        HandlerOutcome::TimedOut => {
+                            // Handler dropped; answer 504 instead.
+                            timed_out = true;
+                            match router.timeout_hook() {
+                                Some(hook) => hook(&request, &mut res),
+                                None => res.error(504, &config.error_pages),
+                            };
                         }
+                        HandlerOutcome::Panicked(payload) => panicked = Some(payload),
                     }
+                    if let Some(payload) = panicked {
+                        let message = panic_message(&*payload);
+                        eprintln!("[tachyon] Handler panicked: {}", message);
+                        if let Some(hook) = router.panic_hook() {
+                            hook(&request, message);
+                        }
+                        // Discard whatever was half-written; the
+                        // connection stays open for the next request.
+                        res = Response::new(
+                            write_buf.as_write_buf(),
+                            sec_headers,
+                            encoding,
+                            comp_threshold,
+                        );
+                        res.set_pretty_json(config.pretty_json);
+                        if let Some(envelope) = envelope {
+                            res.set_envelope(envelope, request.header(b"x-request-id"));
+                        }
+                        res.set_head_request(request.method == tachyon_http::methods::Method::Head);
+                        res.error(500, &config.error_pages);
+                    }
+                    if let Some((cache, key)) = cache_slot
+                        && res.status() == Some(200)
+                        && !res.is_streamed()
+                        && let Some(entry) = res.cache_entry()
+                    {
+                        cache.put(key, &entry);
+                    }
+                }
+                None if res.is_empty() => {
+                    let allowed = router.allowed_methods(request.path);
+                    if allowed.is_empty() {
+                        res.error(404, &config.error_pages);
+                    } else {
+                        let allow = allowed
+                            .iter()
+                            .map(|m| m.as_str())
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        res.header(b"Allow", allow.as_bytes());
+                        res.error(405, &config.error_pages);
+                    }
+                }
+                None => {}
+            }
+            drop(permit);
+
+            if let Some((cors, origin)) = cors
+                && !preflight
+            {
+                cors.apply(&mut res, origin);
+            }
+
+            let upgrade = res.take_upgrade();
+            served += 1;
+            let close = request.version_minor == 0
+                || connection_close
+                || !config.http1.keep_alive
+                || shared.shutdown.should_close(generation)
+                || served == config.http1.max_requests;
+            if close && request.version_minor == 1 && upgrade.is_none() {
+                res.close_connection();
+            }
+
+            let path = request.path;
+            let matched = (route.is_some() || scrape.is_some()).then(|| {
+                path.iter()
+                    .position(|&b| b == b'?')
+                    .map_or(path, |q| &path[..q])
+            });
+            if let Some(started) = started.filter(|_| !hooks.is_empty()) {
+                crate::hooks::run(hooks, &mut res, request.method, path, matched, started);
+            }
+            if let Some(started) = started.filter(|_| !observers.is_empty()) {
+                crate::hooks::response_written(
+                    observers,
+                    &res,
+                    request.method,
+                    path,
+                    matched,
+                    started,
+                );
+            }
+
+            if let (Some(metrics), Some(started)) = (&config.metrics, started) {
+                metrics.request_finished(
+                    request.method,
+                    matched,
+                    res.status().unwrap_or(0),
+                    started.elapsed(),
+                );
+            }
+            if let (Some(stats), Some(path), Some(started)) = (
+                router.route_stats(),
+                matched.filter(|_| route.is_some()),
+                started,
+            ) {
+                stats.record(
+                    request.method,
+                    path,
+                    res.status().unwrap_or(0),
+                    started.elapsed(),
+                    request.body.len(),
+                    res.body_len(),
+                );
+                if timed_out {
+                    stats.record_timeout(request.method, path);
+                }
+            }
+            if let (Some(log), Some(started)) = (&config.access_log, started) {
+                log.record(
+                    peer,
+                    &request,
+                    res.status().unwrap_or(0),
+                    res.len(),
+                    started.elapsed(),
+                );
+            }
+            span.finish(matched, res.status());
+
+            let body = res
+                .take_stream()
+                .filter(|_| request.method != tachyon_http::methods::Method::Head);
+            if config.http1.pipeline_flush
+                && !close
+                && buf_offset < buf_len
+                && body.is_none()
+                && upgrade.is_none()
+            {
+                // More requests already buffered: answer them in one write.
+                pending.extend_from_slice(res.data());
+                continue;
+            }
+            let mut written = if pending.is_empty() {
+                write_within(&mut stream, res.data(), config).await
+            } else {
+                pending.extend_from_slice(res.data());
+                flush(&mut stream, &mut pending, config).await
+            };
+            if let Some(body) = body
+                && written
+            {
+                written = send_stream(&mut stream, body, config, room, &mut early).await;
+            }
+            if let Some(session) = upgrade
+                && written
+            {
+                let leftover = [&data[request.consumed()..], &early[..]].concat();
+                let closing = shared.shutdown.closing(generation);
+                crate::ws::serve(&mut stream, leftover, session, config, closing).await;
+                break 'conn;
+            }
+            if !written || close {
+                break 'conn;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{TestServer, block_on, config, respond};
+    use std::io::{Read, Write};
+    use tachyon_http::methods::Method;
+
+    #[test]
+    fn wraps_sync_async_and_blocking_handlers() {
+        let greet = sync_handler(|req| {
+            let name = req.header(b"x-name").unwrap_or(b"?").to_vec();
+            Box::new(move |res: &mut Response| res.text(200, &name)) as WriteFn
+        });
+        let slow = async_handler(|req| {
+            let len = req.path.len();
+            async move {
+                tokio::task::yield_now().await;
+                Box::new(move |res: &mut Response| res.text(200, len.to_string().as_bytes()))
+                    as WriteFn
+            }
+        });
+        let heavy = blocking_handler(|req| {
+            let name = req.header(b"x-name").unwrap_or(b"?").to_ascii_uppercase();
+            let caller = std::thread::current().id();
+            move || {
+                assert_ne!(std::thread::current().id(), caller);
+                Box::new(move |res: &mut Response| res.text(200, &name)) as WriteFn
+            }
+        });
+        for (handler, body) in [(greet, "ada"), (slow, "3"), (heavy, "ADA")] {
+            let out = respond(&handler, b"GET /hi HTTP/1.1\r\nX-Name: ada\r\n\r\n");
+            assert!(out.ends_with(body));
+        }
+    }
+
+    #[test]
+    fn with_timeout_answers_504() {
+        let slow = with_timeout(
+            async_handler(|_| async {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                "late"
+            }),
+            Duration::from_millis(10),
+        );
+        let quick = with_timeout(sync_handler(|_| "ok"), Duration::from_millis(10));
+        for (handler, status) in [(slow, "HTTP/1.1 504"), (quick, "HTTP/1.1 200")] {
+            assert!(respond(&handler, b"GET /slow HTTP/1.1\r\n\r\n").starts_with(status));
+        }
+    }
+
+    #[test]
+    fn cancels_when_client_disconnects() {
+        use std::net::Shutdown;
+        use std::sync::mpsc;
+
+        let (started, handler_started) = mpsc::channel();
+        let mut router = Router::new();
+        router.route(
+            Method::Get,
+            "/a",
+            async_handler(move |_| {
+                let cancel = crate::cancel::token();
+                let _ = started.send(());
+                async move {
+                    let gone = tokio::time::timeout(Duration::from_secs(5), cancel.cancelled())
+                        .await
+                        .is_ok();
+                    if gone { "a:gone" } else { "a:live" }
+                }
+            }),
+        );
+        router.route(
+            Method::Get,
+            "/b",
+            sync_handler(|_| {
+                if crate::cancel::token().is_cancelled() {
+                    "b:gone"
+                } else {
+                    "b:live"
                 }
+            }),
+        );
+        let server = TestServer::spawn(router);
+        let pipelined = b"GET /a HTTP/1.1\r\n\r\nGET /b HTTP/1.1\r\nConnection: close\r\n\r\n";
+        let read_all = |mut stream: std::net::TcpStream| {
+            let mut out = String::new();
+            stream.read_to_string(&mut out).unwrap();
+            out
+        };
+
+        // Both requests arrive, then the client stops sending: /a is
+        // cancelled, and /b gets a token of its own.
+        let mut stream = server.connect();
+        stream.write_all(pipelined).unwrap();
+        stream.shutdown(Shutdown::Write).unwrap();
+        let out = read_all(stream);
+        assert!(out.contains("\r\n\r\na:goneHTTP/1.1 200"), "{out}");
+        assert!(out.ends_with("\r\n\r\nb:live"), "{out}");
+        handler_started.try_recv().unwrap();
+
+        // /b arrives while /a is watched; the watch goes on and still sees
+        // the client stop.
+        let mut stream = server.connect();
+        stream.write_all(&pipelined[..19]).unwrap();
+        handler_started
+            .recv_timeout(Duration::from_secs(5))
+            .unwrap();
+        stream.write_all(&pipelined[19..]).unwrap();
+        stream.shutdown(Shutdown::Write).unwrap();
+        let out = read_all(stream);
+        assert!(out.contains("\r\n\r\na:goneHTTP/1.1 200"), "{out}");
+        assert!(out.ends_with("\r\n\r\nb:live"), "{out}");
+
+        server.stop();
+    }
+
+    #[test]
+    fn applies_route_config() {
+        use crate::router::RouteConfig;
+
+        let tag: crate::middleware::Middleware = Arc::new(|req, next| {
+            let downstream = next.run(req);
+            Box::pin(async move {
+                let write = downstream.await;
+                Box::new(move |res: &mut Response| {
+                    res.header(b"X-Route", b"upload");
+                    write(res)
+                }) as WriteFn
+            })
+        });
+        let mut router = Router::new();
+        router.route_with(
+            Method::Post,
+            "/upload",
+            sync_handler(|req| format!("got {}", req.decoded_body().len())),
+            RouteConfig::new()
+                .body_limit(4)
+                .accept("Application/JSON")
+                .middleware(tag),
+        );
+        router.route(Method::Post, "/open", sync_handler(|_| "open"));
+        let server = TestServer::spawn(router);
+        let post = |path: &str, content_type: &str, body: &str| {
+            server.send(format!(
+                "POST {path} HTTP/1.1\r\nConnection: close\r\nContent-Type: {content_type}\r\n\
+                 Content-Length: {}\r\n\r\n{body}",
+                body.len()
+            ))
+        };
+
+        let out = post("/upload", "application/json; charset=utf-8", "[1]");
+        assert!(out.starts_with("HTTP/1.1 200") && out.contains("X-Route: upload\r\n"));
+        assert!(out.ends_with("got 3"));
+        assert!(post("/upload", "application/json", "[1,2]").starts_with("HTTP/1.1 413"));
+        assert!(post("/upload", "text/plain", "[1]").starts_with("HTTP/1.1 415"));
+        // The limit counts the decoded body, not the chunk framing.
+        let chunked = |body: &str| {
+            server.send(format!(
+                "POST /upload HTTP/1.1\r\nConnection: close\r\nContent-Type: application/json\r\n\
+                 Transfer-Encoding: chunked\r\n\r\n{body}"
+            ))
+        };
+        assert!(chunked("3\r\n[1]\r\n0\r\n\r\n").ends_with("got 3"));
+        assert!(chunked("2\r\n[1\r\n3\r\n,2]\r\n0\r\n\r\n").starts_with("HTTP/1.1 413"));
+        let out = post("/open", "text/plain", "anything at all");
+        assert!(out.starts_with("HTTP/1.1 200") && !out.contains("X-Route"));
+
+        server.stop();
+    }
+
+    #[test]
+    fn streams_response_bodies() {
+        let mut router = Router::new();
+        router.route(
+            Method::Get,
+            "/events",
+            async_handler(|_| async {
+                let (tx, body) = crate::stream::channel(2);
+                tokio::spawn(async move {
+                    for i in 0..3 {
+                        tokio::time::sleep(Duration::from_millis(5)).await;
+                        assert!(tx.send(format!("data: {i}\n\n")).await);
+                    }
+                });
+                Box::new(move |res: &mut Response| res.stream(200, "text/event-stream", body))
+                    as WriteFn
+            }),
+        );
+        router.route(
+            Method::Get,
+            "/broken",
+            async_handler(|_| async {
+                let (tx, body) = crate::stream::channel(2);
+                tokio::spawn(async move {
+                    tx.send("partial").await;
+                    tx.abort().await;
+                });
+                Box::new(move |res: &mut Response| res.stream(200, "text/plain", body)) as WriteFn
+            }),
+        );
+        router.static_response(Method::Get, "/next", "next", "text/plain");
+        let server = TestServer::spawn(router);
+        // The connection is reused once the body has ended.
+        let out = server
+            .send(b"GET /events HTTP/1.1\r\n\r\nGET /next HTTP/1.1\r\nConnection: close\r\n\r\n");
+        let (head, rest) = out.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("HTTP/1.1 200") && head.contains("Transfer-Encoding: chunked"));
+        assert!(head.contains("Content-Type: text/event-stream"));
+        assert!(rest.starts_with(
+            "9\r\ndata: 0\n\n\r\n9\r\ndata: 1\n\n\r\n9\r\ndata: 2\n\n\r\n0\r\n\r\nHTTP/1.1 200"
+        ));
+        assert!(rest.ends_with("\r\n\r\nnext"));
+
+        // Aborted: no final chunk, and the connection closes.
+        let out = server.send(b"GET /broken HTTP/1.1\r\n\r\n");
+        assert!(out.ends_with("\r\n\r\n7\r\npartial\r\n"), "{out}");
+
+        server.stop();
+    }
+
+    #[test]
+    fn serves_files() {
+        let root = std::env::temp_dir().join(format!("tachyon-files-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("docs")).unwrap();
+        std::fs::write(root.join("docs/index.html"), "<h1>docs</h1>").unwrap();
+        std::fs::write(root.join("app.js"), "console.log(1)").unwrap();
+        let big: Vec<u8> = (0..300_000u32).map(|i| i as u8).collect();
+        std::fs::write(root.join("big.bin"), &big).unwrap();
+
+        let mut router = Router::new();
+        router.files("/static/", crate::files::ServeDir::new(&root));
+        router.route(Method::Get, "/static/app.js", sync_handler(|_| "route"));
+        let cfg = config().error_page(404, "text/plain", &b"gone"[..]);
+        let server = TestServer::start(Server::new(cfg), router);
+        let get = |path: &str, headers: &str| {
+            server.send_bytes(format!(
+                "GET {path} HTTP/1.1\r\nConnection: close\r\n{headers}\r\n"
+            ))
+        };
+        let text = |out: Vec<u8>| String::from_utf8_lossy(&out).into_owned();
+
+        let out = text(get("/static/docs/", ""));
+        assert!(out.starts_with("HTTP/1.1 200") && out.ends_with("<h1>docs</h1>"));
+        assert!(out.contains("Content-Type: text/html; charset=utf-8\r\n"));
+        let etag = out
+            .lines()
+            .find_map(|l| l.strip_prefix("ETag: "))
+            .unwrap()
+            .to_string();
+        let out = text(get("/static/docs", &format!("If-None-Match: {etag}\r\n")));
+        assert!(out.starts_with("HTTP/1.1 304"), "{out}");
+
+        let out = text(get("/static/docs/index.html", "Range: bytes=1-2\r\n"));
+        assert!(out.starts_with("HTTP/1.1 206") && out.ends_with("\r\n\r\nh1"));
+        assert!(out.contains("Content-Range: bytes 1-2/13\r\n"));
+        let out = text(get("/static/docs/index.html", "Range: bytes=99-\r\n"));
+        assert!(
+            out.starts_with("HTTP/1.1 416") && out.contains("bytes */13"),
+            "{out}"
+        );
+
+        // Routes win over the mount; escapes and misses are 404.
+        assert!(text(get("/static/app.js", "")).ends_with("route"));
+        assert!(text(get("/static/../Cargo.toml", "")).starts_with("HTTP/1.1 404"));
+        let out = text(get("/static/nope.txt", ""));
+        assert!(
+            out.starts_with("HTTP/1.1 404") && out.ends_with("gone"),
+            "{out}"
+        );
+
+        let out = get("/static/big.bin", "");
+        let split = out.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+        assert!(text(out[..split].to_vec()).contains("Transfer-Encoding: chunked"));
+        assert_eq!(tachyon_http::chunked::decode(&out[split..]), big);
+
+        server.stop();
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn upgrades_websockets() {
+        use crate::ws::Message;
+
+        let mut router = Router::new();
+        router.route(
+            Method::Get,
+            "/echo",
+            sync_handler(|req| {
+                crate::ws::upgrade(req, |mut socket| async move {
+                    while let Some(Message::Text(text)) = socket.recv().await {
+                        socket.send(text).await;
+                    }
+                })
+            }),
+        );
+        let server = TestServer::spawn(router);
+        let masked = |b0: u8, payload: &[u8]| {
+            let mut out = vec![b0, 0x80 | payload.len() as u8, 0, 0, 0, 0];
+            out.extend_from_slice(payload);
+            out
+        };
+
+        let mut stream = server.connect();
+        let handshake = b"GET /echo HTTP/1.1\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+            Sec-WebSocket-Version: 13\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n";
+        // A frame sent right behind the handshake is not lost.
+        stream
+            .write_all(&[&handshake[..], &masked(0x81, b"hi")].concat())
+            .unwrap();
+        let mut out = Vec::new();
+        let mut buf = [0u8; 512];
+        while !out.ends_with(b"\x81\x02hi") {
+            let n = stream.read(&mut buf).unwrap();
+            assert!(n > 0, "{}", String::from_utf8_lossy(&out));
+            out.extend_from_slice(&buf[..n]);
+        }
+        let head = String::from_utf8_lossy(&out);
+        assert!(head.starts_with("HTTP/1.1 101 Switching Protocols\r\n"));
+        assert!(head.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"));
+        assert!(!head.contains("Content-Length"));
+
+        stream
+            .write_all(&masked(0x88, &1000u16.to_be_bytes()))
+            .unwrap();
+        let mut rest = Vec::new();
+        stream.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"\x88\x02\x03\xe8");
+
+        let out = server.send(b"GET /echo HTTP/1.1\r\nConnection: close\r\n\r\n");
+        assert!(out.starts_with("HTTP/1.1 426") && out.contains("Sec-WebSocket-Version: 13"));
+
+        server.stop();
+    }
+
+    #[test]
+    fn warms_cached_routes() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counted = calls.clone();
+        let mut router = Router::new();
+        router.cached_route(
+            Method::Get,
+            "/report",
+            sync_handler(move |_| {
+                counted.fetch_add(1, Ordering::Relaxed);
+                "report"
+            }),
+            ResponseCache::new(Duration::from_secs(60), 8),
+        );
+        router.cached_route(
+            Method::Get,
+            "/missing",
+            sync_handler(|_| (404, "no")),
+            ResponseCache::new(Duration::from_secs(60), 8),
+        );
+        let server = Server::new(config());
+        let reload = server.reload_handle();
+        let server = TestServer::start(server, router);
+        let get =
+            |path: &str| server.send(format!("GET {path} HTTP/1.1\r\nConnection: close\r\n\r\n"));
+        // Served once, so the table is in place.
+        assert!(get("/missing").starts_with("HTTP/1.1 404"));
+
+        assert_eq!(block_on(reload.warmup()), 1);
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        let out = get("/report");
+        assert!(out.starts_with("HTTP/1.1 200") && out.ends_with("\r\n\r\nreport"));
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        assert_eq!(reload.cache_stats()[1].stats.hits, 1);
+
+        server.stop();
+    }
+
+    #[test]
+    fn revalidates_cache_hits() {
+        let mut router = Router::new();
+        let cache = ResponseCache::new(Duration::from_secs(60), 8);
+        router.cached_route(Method::Get, "/report", sync_handler(|_| "report"), cache);
+        let server = TestServer::start(
+            Server::new(config().etag(tachyon_http::etag::EtagMode::Strong)),
+            router,
+        );
+        let get = |headers: &str| {
+            server.send(format!(
+                "GET /report HTTP/1.1\r\nConnection: close\r\n{headers}\r\n"
+            ))
+        };
+        let out = get("");
+        let etag = out.lines().find_map(|l| l.strip_prefix("ETag: ")).unwrap();
+
+        let out = get(&format!("If-None-Match: W/{etag}\r\n"));
+        assert!(out.starts_with("HTTP/1.1 304"), "{out}");
+        assert!(out.contains(&format!("ETag: {etag}\r\n")) && out.ends_with("\r\n\r\n"));
+        let out = get("If-None-Match: \"other\"\r\n");
+        assert!(
+            out.starts_with("HTTP/1.1 200") && out.ends_with("report"),
+            "{out}"
+        );
+
+        server.stop();
+    }
+
+    #[test]
+    fn caches_only_shared_responses() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counted = |header: Option<(&'static [u8], &'static [u8])>| {
+            let calls = calls.clone();
+            let handler: Handler = Arc::new(move |_| {
+                calls.fetch_add(1, Ordering::Relaxed);
+                Box::pin(std::future::ready(Box::new(move |res: &mut Response<'_>| {
+                    if let Some((name, value)) = header {
+                        res.header(name, value);
+                    }
+                    res.text(200, b"page")
+                }) as WriteFn))
             });
+            handler
+        };
+        let mut router = Router::new();
+        let cache = || ResponseCache::new(Duration::from_secs(60), 8);
+        router.cached_route(Method::Get, "/plain", counted(None), cache());
+        router.cached_route(
+            Method::Get,
+            "/encoded",
+            counted(Some((b"Vary", b"Accept-Encoding"))),
+            cache(),
+        );
+        router.cached_route(
+            Method::Get,
+            "/cookie",
+            counted(Some((b"Set-Cookie", b"session=abc"))),
+            cache(),
+        );
+        router.cached_route(
+            Method::Get,
+            "/per-user",
+            counted(Some((b"Vary", b"accept-encoding, Authorization"))),
+            cache(),
+        );
+        let server = TestServer::spawn(router);
+        let calls_for = |path: &str| {
+            let before = calls.load(Ordering::Relaxed);
+            for _ in 0..2 {
+                let out = server.send(format!("GET {path} HTTP/1.1\r\nConnection: close\r\n\r\n"));
+                assert!(out.ends_with("\r\n\r\npage"), "{out}");
+                assert_eq!(out.matches("\r\nDate: ").count(), 1, "{out}");
+            }
+            calls.load(Ordering::Relaxed) - before
+        };
+        assert_eq!(calls_for("/plain"), 1);
+        assert_eq!(calls_for("/encoded"), 1);
+        assert_eq!(calls_for("/cookie"), 2);
+        assert_eq!(calls_for("/per-user"), 2);
+
+        server.stop();
+    }
+
+    #[test]
+    fn routes_win_over_the_metrics_path() {
+        use crate::metrics::Metrics;
+
+        let get = |server: &TestServer, path: &str| {
+            server.send(format!("GET {path} HTTP/1.1\r\nConnection: close\r\n\r\n"))
+        };
+        let mut router = Router::new();
+        router.route(Method::Get, "/stats", sync_handler(|_| "mine"));
+        let server = TestServer::start(Server::new(config().metrics(Metrics::new())), router);
+        assert!(get(&server, "/metrics").contains("tachyon_requests_in_flight"));
+        server.stop();
+
+        let mut router = Router::new();
+        router.route(Method::Get, "/stats", sync_handler(|_| "mine"));
+        let metrics = Metrics::new().path("/stats");
+        let server = TestServer::start(Server::new(config().metrics(metrics)), router);
+        assert!(get(&server, "/stats").ends_with("\r\n\r\nmine"));
+        server.stop();
+    }
+
+    #[test]
+    fn times_out_stalled_phases() {
+        const BIG: usize = 32 << 20;
+        let mut router = Router::new();
+        router.route(Method::Get, "/", sync_handler(|_| "ok"));
+        router.route(Method::Get, "/big", sync_handler(|_| vec![b'x'; BIG]));
+        let limit = Duration::from_millis(150);
+        let cfg = config()
+            .header_read_timeout(limit)
+            .body_read_timeout(limit)
+            .write_timeout(limit)
+            .idle_timeout(limit)
+            .min_receive_rate(0, Duration::ZERO);
+        let server = TestServer::start(Server::new(cfg), router);
+        let stalled = |raw: &str| {
+            let mut stream = server.connect();
+            stream
+                .set_read_timeout(Some(Duration::from_secs(5)))
+                .unwrap();
+            stream.write_all(raw.as_bytes()).unwrap();
+            let mut out = String::new();
+            stream.read_to_string(&mut out).unwrap();
+            out
+        };
+
+        // Headers or body cut short: 408, then close.
+        let out = stalled("GET / HTTP/1.1\r\nHost: x");
+        assert!(out.starts_with("HTTP/1.1 408"), "{out}");
+        let out = stalled("POST / HTTP/1.1\r\nContent-Length: 10\r\n\r\nabc");
+        assert!(out.starts_with("HTTP/1.1 408"), "{out}");
+        // Idle after a response: closed without another one.
+        let out = stalled("GET / HTTP/1.1\r\n\r\n");
+        assert!(
+            out.starts_with("HTTP/1.1 200") && out.ends_with("\r\n\r\nok"),
+            "{out}"
+        );
+
+        // A client that stops reading is dropped mid-body.
+        let mut stream = server.connect();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        stream.write_all(b"GET /big HTTP/1.1\r\n\r\n").unwrap();
+        std::thread::sleep(limit * 4);
+        let mut out = Vec::new();
+        // Ends in EOF or a reset, not the read timeout.
+        if let Err(e) = stream.read_to_end(&mut out) {
+            assert_eq!(e.kind(), std::io::ErrorKind::ConnectionReset);
         }
+        assert!(out.len() < BIG, "sent the whole body");
+
+        server.stop();
+    }
+
+    #[test]
+    fn tunes_http1_connections() {
+        let spawn = |cfg: ServerConfig| {
+            let mut router = Router::new();
+            router.route(
+                Method::Get,
+                "/",
+                sync_handler(|_| {
+                    Box::new(|res: &mut Response| {
+                        res.header(b"x-Request-ID", b"7");
+                        res.text(200, b"ok")
+                    }) as WriteFn
+                }),
+            );
+            TestServer::start(Server::new(cfg), router)
+        };
+        let get = "GET / HTTP/1.1\r\n\r\n";
+        // Three requests on one connection, the last asking to close it.
+        let three = [get, get, "GET / HTTP/1.1\r\nConnection: close\r\n\r\n"].concat();
+        let responses = |out: &str| out.matches("HTTP/1.1 200").count();
+
+        // Keep-alive (the default) answers every request on the connection.
+        let server = spawn(config());
+        let out = server.send(&three);
+        assert_eq!(responses(&out), 3);
+        // Server headers are Title-Case; handler headers go out as written.
+        assert!(out.contains("Content-Length: 2\r\n") && out.contains("x-Request-ID: 7\r\n"));
+        server.stop();
+
+        let server = spawn(config().keep_alive(false));
+        let out = server.send(get.repeat(3));
+        assert_eq!(responses(&out), 1);
+        assert!(out.contains("Connection: close\r\n"), "{out}");
+        server.stop();
+
+        let server = spawn(config().max_requests_per_connection(2));
+        let out = server.send(get.repeat(3));
+        assert_eq!(responses(&out), 2);
+        assert_eq!(out.matches("Connection: close\r\n").count(), 1);
+        assert!(out.rfind("Connection: close").unwrap() > out.rfind("HTTP/1.1").unwrap());
+        server.stop();
+
+        // Pipelined requests are answered in a single write.
+        let server = spawn(config().pipeline_flush(true));
+        let mut stream = server.connect();
+        stream.write_all(three.as_bytes()).unwrap();
+        let mut buf = [0u8; 4096];
+        let n = stream.read(&mut buf).unwrap();
+        assert_eq!(responses(&String::from_utf8_lossy(&buf[..n])), 3);
+        drop(stream);
+        server.stop();
+    }
+
+    #[test]
+    fn cuts_off_slow_senders() {
+        let mut router = Router::new();
+        router.route(Method::Post, "/", sync_handler(|_| "ok"));
+        let grace = Duration::from_millis(200);
+        let cfg = config().min_receive_rate(1000, grace);
+        let server = TestServer::start(Server::new(cfg), router);
+        let head = "POST / HTTP/1.1\r\nConnection: close\r\nContent-Length: 4000\r\n\r\n";
+        let body = vec![b'x'; 4000];
+
+        // Well over 1000 B/s, with a pause past the grace period.
+        let mut stream = server.connect();
+        stream.write_all(head.as_bytes()).unwrap();
+        stream.write_all(&body[..2000]).unwrap();
+        std::thread::sleep(grace * 2);
+        stream.write_all(&body[2000..]).unwrap();
+        let mut out = String::new();
+        stream.read_to_string(&mut out).unwrap();
+        assert!(out.starts_with("HTTP/1.1 200"), "{out}");
+
+        // 100 B/s: still sending, but cut off once the grace period is over.
+        let mut stream = server.connect();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut writer = stream.try_clone().unwrap();
+        let trickle = std::thread::spawn(move || {
+            writer.write_all(head.as_bytes())?;
+            for chunk in body.chunks(10) {
+                std::thread::sleep(Duration::from_millis(100));
+                writer.write_all(chunk)?;
+            }
+            std::io::Result::Ok(())
+        });
+        let mut out = String::new();
+        let _ = stream.read_to_string(&mut out);
+        assert!(out.starts_with("HTTP/1.1 408"), "{out}");
+        drop(stream);
+        assert!(trickle.join().unwrap().is_err());
+
+        server.stop();
+    }
+
+    #[test]
+    fn spawn_binds_ephemeral_port() {
+        let mut router = Router::new();
+        router.static_response(Method::Get, "/health", "ok", "text/plain; charset=utf-8");
+        let server = TestServer::spawn(router);
+        let addr = server.addr();
+        assert_ne!(addr.port(), 0);
+
+        let mut stream = std::net::TcpStream::connect(addr).unwrap();
+        stream
+            .write_all(b"GET /health HTTP/1.1\r\nHost: x\r\nConnection: close\r\n\r\n")
+            .unwrap();
+        let mut out = String::new();
+        stream.read_to_string(&mut out).unwrap();
+        assert!(out.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(out.ends_with("\r\n\r\nok"));
+
+        server.stop();
+        assert!(std::net::TcpStream::connect(addr).is_err());
     }
 }
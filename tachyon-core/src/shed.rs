@@ -0,0 +1,246 @@
+//! Overload protection for handler routes.
+//!
+//! [`LoadShed`] answers 503 + `Retry-After` without running the handler when
+//! either limit is exceeded:
+//!
+//! - **in-flight**: more handler requests running than `max_in_flight`;
+//! - **latency**: the moving average of handler latency rises above
+//!   `max_latency`. The breaker then opens for `cooldown`, shedding every
+//!   handler request, and closes again with a fresh average.
+//!
+//! Shedding is a couple of atomic loads, so a saturated server keeps
+//! answering quickly instead of queueing. Static routes and cache hits are
+//! never shed — they are cheaper to serve than to refuse.
+//!
+//! With [`LoadShed::pause_accept`], acceptors also stop accepting while the
+//! breaker is open: new clients wait in the listen backlog instead of
+//! getting a 503.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::config::ErrorPages;
+use crate::response::Response;
+
+struct State {
+    in_flight: AtomicUsize,
+    /// Moving average of handler latency, µs.
+    ewma_us: AtomicU64,
+    /// Breaker open until this many µs after `epoch`; 0 = closed.
+    open_until_us: AtomicU64,
+    epoch: Instant,
+}
+
+/// Load-shedding policy and its live counters. Clones share state.
+#[derive(Clone)]
+pub struct LoadShed {
+    max_in_flight: usize,
+    max_latency: Option<Duration>,
+    cooldown: Duration,
+    retry_after: u32,
+    pause_accept: bool,
+    state: Arc<State>,
+}
+
+impl std::fmt::Debug for LoadShed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LoadShed")
+            .field("max_in_flight", &self.max_in_flight)
+            .field("max_latency", &self.max_latency)
+            .field("cooldown", &self.cooldown)
+            .field("retry_after", &self.retry_after)
+            .field("pause_accept", &self.pause_accept)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for LoadShed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LoadShed {
+    /// No limits yet: set at least one of `max_in_flight` / `max_latency`.
+    pub fn new() -> Self {
+        Self {
+            max_in_flight: usize::MAX,
+            max_latency: None,
+            cooldown: Duration::from_secs(1),
+            retry_after: 1,
+            pause_accept: false,
+            state: Arc::new(State {
+                in_flight: AtomicUsize::new(0),
+                ewma_us: AtomicU64::new(0),
+                open_until_us: AtomicU64::new(0),
+                epoch: Instant::now(),
+            }),
+        }
+    }
+
+    /// Shed when this many handler requests are already running.
+    pub fn max_in_flight(mut self, requests: usize) -> Self {
+        self.max_in_flight = requests;
+        self
+    }
+
+    /// Open the breaker when the average handler latency exceeds `latency`.
+    pub fn max_latency(mut self, latency: Duration) -> Self {
+        self.max_latency = Some(latency);
+        self
+    }
+
+    /// How long the breaker stays open (default: 1s).
+    pub fn cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// `Retry-After` seconds sent with shed responses (default: 1).
+    pub fn retry_after(mut self, seconds: u32) -> Self {
+        self.retry_after = seconds;
+        self
+    }
+
+    /// Stop accepting new connections while the breaker is open
+    /// (default: false).
+    pub fn pause_accept(mut self, enabled: bool) -> Self {
+        self.pause_accept = enabled;
+        self
+    }
+
+    /// Handler requests currently running.
+    pub fn in_flight(&self) -> usize {
+        self.state.in_flight.load(Ordering::Relaxed)
+    }
+
+    fn now_us(&self) -> u64 {
+        self.state.epoch.elapsed().as_micros() as u64
+    }
+
+    /// With `pause_accept`, how long the breaker stays open; `None` when
+    /// accepting should go on.
+    pub(crate) fn accept_pause(&self) -> Option<Duration> {
+        if !self.pause_accept {
+            return None;
+        }
+        let open_until = self.state.open_until_us.load(Ordering::Relaxed);
+        let left = open_until.checked_sub(self.now_us()).filter(|&us| us > 0)?;
+        Some(Duration::from_micros(left))
+    }
+
+    /// Admit a request, or `None` to shed it.
+    pub(crate) fn admit(&self) -> Option<Permit<'_>> {
+        let open_until = self.state.open_until_us.load(Ordering::Relaxed);
+        if open_until != 0 {
+            if self.now_us() < open_until {
+                return None;
+            }
+            // Cooldown over: close the breaker and start a fresh average.
+            if self
+                .state
+                .open_until_us
+                .compare_exchange(open_until, 0, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+            {
+                self.state.ewma_us.store(0, Ordering::Relaxed);
+            }
+        }
+        let running = self.state.in_flight.fetch_add(1, Ordering::Relaxed);
+        if running >= self.max_in_flight {
+            self.state.in_flight.fetch_sub(1, Ordering::Relaxed);
+            return None;
+        }
+        Some(Permit {
+            shed: self,
+            started: Instant::now(),
+        })
+    }
+
+    /// Write the shed response.
+    pub(crate) fn reject(&self, res: &mut Response<'_>, pages: &ErrorPages) -> usize {
+        res.header(b"Retry-After", self.retry_after.to_string().as_bytes());
+        res.error(503, pages)
+    }
+
+    fn finish(&self, latency: Duration) {
+        self.state.in_flight.fetch_sub(1, Ordering::Relaxed);
+        let Some(max) = self.max_latency else {
+            return;
+        };
+        let sample = latency.as_micros() as u64;
+        // ewma += (sample - ewma) / 8; the first sample seeds it.
+        let step = |avg: u64| {
+            if avg == 0 {
+                sample
+            } else {
+                avg - avg / 8 + sample / 8
+            }
+        };
+        let prev = self
+            .state
+            .ewma_us
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |avg| Some(step(avg)))
+            .unwrap_or(0);
+        let avg = step(prev);
+        if avg > max.as_micros() as u64 {
+            let until = self.now_us() + self.cooldown.as_micros() as u64;
+            let _ = self.state.open_until_us.compare_exchange(
+                0,
+                until.max(1),
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            );
+        }
+    }
+}
+
+/// A running handler request; counts it out and records its latency on drop.
+pub(crate) struct Permit<'a> {
+    shed: &'a LoadShed,
+    started: Instant,
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        self.shed.finish(self.started.elapsed());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sheds_on_concurrency_and_latency() {
+        let shed = LoadShed::new().max_in_flight(2);
+        let a = shed.admit();
+        let b = shed.admit();
+        assert!(a.is_some() && b.is_some());
+        assert!(shed.admit().is_none());
+        drop(a);
+        assert!(shed.admit().is_some());
+        assert_eq!(shed.in_flight(), 1);
+
+        let shed = LoadShed::new()
+            .max_latency(Duration::from_millis(1))
+            .cooldown(Duration::from_millis(20));
+        shed.finish_for_test(Duration::from_micros(500));
+        assert!(shed.admit().is_some());
+        shed.finish_for_test(Duration::from_millis(50));
+        assert!(shed.admit().is_none());
+        assert_eq!(shed.accept_pause(), None);
+        assert!(shed.clone().pause_accept(true).accept_pause().is_some());
+        std::thread::sleep(Duration::from_millis(25));
+        assert_eq!(shed.clone().pause_accept(true).accept_pause(), None);
+        assert!(shed.admit().is_some());
+    }
+
+    impl LoadShed {
+        fn finish_for_test(&self, latency: Duration) {
+            self.state.in_flight.fetch_add(1, Ordering::Relaxed);
+            self.finish(latency);
+        }
+    }
+}
@@ -0,0 +1,213 @@
+//! Graceful shutdown.
+//!
+//! Shutting down runs in two stages. While draining, acceptors stop
+//! accepting, idle keep-alive connections close, and each request already
+//! started gets its response (with `Connection: close`). If connections are
+//! still open when `ServerConfig::drain_timeout` runs out, the server forces
+//! them closed and `serve` returns.
+//!
+//! With `ServerConfig::shutdown_signals` (the default), SIGTERM and SIGINT
+//! (Ctrl-C on Windows) start the drain.
+//!
+//! Recycling (`ReloadHandle::recycle_connections`) applies the same close
+//! rules to the connections open at that moment while the server keeps
+//! accepting: each gets `Connection: close` on its next response, and idle
+//! ones close right away, so clients reconnect instead of being cut off.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use tokio::sync::{Notify, watch};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Stage {
+    Running,
+    Draining,
+    Forced,
+}
+
+/// Shutdown state shared by every acceptor and connection of one server.
+pub(crate) struct Shutdown {
+    stage: watch::Sender<Stage>,
+    active: AtomicUsize,
+    idle: Notify,
+    /// Bumped by each recycle; connections from an older generation close.
+    generation: watch::Sender<u64>,
+}
+
+impl Shutdown {
+    pub(crate) fn new() -> Self {
+        Self {
+            stage: watch::Sender::new(Stage::Running),
+            active: AtomicUsize::new(0),
+            idle: Notify::new(),
+            generation: watch::Sender::new(0),
+        }
+    }
+
+    /// Start draining. Later calls do nothing.
+    pub(crate) fn trigger(&self) {
+        self.advance(Stage::Draining);
+    }
+
+    pub(crate) fn is_draining(&self) -> bool {
+        *self.stage.borrow() >= Stage::Draining
+    }
+
+    /// Resolves once draining has started.
+    pub(crate) async fn draining(&self) {
+        self.reached(Stage::Draining).await;
+    }
+
+    /// Resolves once open connections must be dropped.
+    pub(crate) async fn forced(&self) {
+        self.reached(Stage::Forced).await;
+    }
+
+    /// Ask every connection open now to close after its current response.
+    pub(crate) fn recycle(&self) {
+        self.generation.send_modify(|g| *g += 1);
+    }
+
+    /// Tag for a new connection, checked by `should_close` and `closing`.
+    pub(crate) fn generation(&self) -> u64 {
+        *self.generation.borrow()
+    }
+
+    /// Whether a connection tagged `generation` must close after its
+    /// current response.
+    pub(crate) fn should_close(&self, generation: u64) -> bool {
+        self.is_draining() || self.generation() != generation
+    }
+
+    /// Resolves once a connection tagged `generation` must close.
+    pub(crate) async fn closing(&self, generation: u64) {
+        let mut rx = self.generation.subscribe();
+        tokio::select! {
+            () = self.draining() => {}
+            _ = rx.wait_for(|g| *g != generation) => {}
+        }
+    }
+
+    /// Count a connection as open until the guard drops.
+    pub(crate) fn track(self: &std::sync::Arc<Self>) -> ConnectionGuard {
+        self.active.fetch_add(1, Ordering::AcqRel);
+        ConnectionGuard(self.clone())
+    }
+
+    /// Wait up to `timeout` for open connections to close, then force the
+    /// rest. Returns how many were forced.
+    pub(crate) async fn drain(&self, timeout: Duration) -> usize {
+        let drained = async {
+            loop {
+                let mut notified = std::pin::pin!(self.idle.notified());
+                notified.as_mut().enable();
+                if self.active.load(Ordering::Acquire) == 0 {
+                    return;
+                }
+                notified.await;
+            }
+        };
+        let left = match tokio::time::timeout(timeout, drained).await {
+            Ok(()) => 0,
+            Err(_) => self.active.load(Ordering::Acquire),
+        };
+        self.advance(Stage::Forced);
+        left
+    }
+
+    fn advance(&self, to: Stage) {
+        self.stage.send_if_modified(|stage| {
+            let moved = *stage < to;
+            if moved {
+                *stage = to;
+            }
+            moved
+        });
+    }
+
+    async fn reached(&self, stage: Stage) {
+        let mut rx = self.stage.subscribe();
+        // The sender lives as long as `self`, so this cannot fail.
+        let _ = rx.wait_for(|s| *s >= stage).await;
+    }
+}
+
+/// An open connection; see [`Shutdown::track`].
+pub(crate) struct ConnectionGuard(std::sync::Arc<Shutdown>);
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        if self.0.active.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.0.idle.notify_waiters();
+        }
+    }
+}
+
+/// Resolves on SIGTERM or SIGINT (Ctrl-C elsewhere).
+pub(crate) async fn signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+        let (Ok(mut term), Ok(mut int)) = (
+            signal(SignalKind::terminate()),
+            signal(SignalKind::interrupt()),
+        ) else {
+            return std::future::pending().await;
+        };
+        tokio::select! {
+            _ = term.recv() => {}
+            _ = int.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        if tokio::signal::ctrl_c().await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn drains_then_forces() {
+        crate::testing::block_on(async {
+            let shutdown = Arc::new(Shutdown::new());
+            assert!(!shutdown.is_draining());
+
+            let quick = shutdown.track();
+            let stuck = shutdown.track();
+            shutdown.trigger();
+            shutdown.trigger();
+            assert!(shutdown.is_draining());
+            shutdown.draining().await;
+
+            drop(quick);
+            assert_eq!(shutdown.drain(Duration::from_millis(20)).await, 1);
+            shutdown.forced().await;
+            drop(stuck);
+
+            let clean = Arc::new(Shutdown::new());
+            let conn = clean.track();
+            clean.trigger();
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                drop(conn);
+            });
+            assert_eq!(clean.drain(Duration::from_secs(5)).await, 0);
+
+            let recycled = Arc::new(Shutdown::new());
+            let old = recycled.generation();
+            assert!(!recycled.should_close(old));
+            recycled.recycle();
+            assert!(recycled.should_close(old));
+            recycled.closing(old).await;
+            assert!(!recycled.should_close(recycled.generation()));
+            assert!(!recycled.is_draining());
+        });
+    }
+}
@@ -0,0 +1,176 @@
+//! Server-sent events: the `text/event-stream` format, sent over a
+//! [streamed body](crate::stream).
+//!
+//! ```ignore
+//! router.get("/ticks", async_handler(|_| async {
+//!     let (tx, body) = tachyon_core::stream::channel(16);
+//!     tokio::spawn(async move {
+//!         for i in 0.. {
+//!             let tick = Event::new(i.to_string()).event("tick").id(i.to_string());
+//!             if !tx.send(tick.encode()).await {
+//!                 break; // client gone
+//!             }
+//!             tokio::time::sleep(Duration::from_secs(1)).await;
+//!         }
+//!     });
+//!     Box::new(move |res: &mut Response| sse::respond(res, body)) as WriteFn
+//! }));
+//! ```
+
+use std::time::Duration;
+
+use crate::response::Response;
+use crate::stream::BodyStream;
+
+/// One event. `data` may span lines; each becomes its own `data:` field.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Event {
+    data: String,
+    event: Option<String>,
+    id: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl Event {
+    pub fn new(data: impl Into<String>) -> Self {
+        Self {
+            data: data.into(),
+            ..Self::default()
+        }
+    }
+
+    /// The event type, dispatched to `addEventListener(name)` in browsers
+    /// (default: "message").
+    pub fn event(mut self, name: impl Into<String>) -> Self {
+        self.event = Some(name.into());
+        self
+    }
+
+    /// Sent back by a reconnecting browser as `Last-Event-ID`.
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// How long a browser waits before reconnecting.
+    pub fn retry(mut self, after: Duration) -> Self {
+        self.retry = Some(after);
+        self
+    }
+
+    /// The event on the wire, ending with the blank line that dispatches it.
+    /// Line breaks in `event` and `id` are dropped.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.data.len() + 16);
+        let single_line = |s: &str| s.replace(['\r', '\n'], "");
+        if let Some(event) = &self.event {
+            out.extend_from_slice(format!("event: {}\n", single_line(event)).as_bytes());
+        }
+        if let Some(id) = &self.id {
+            out.extend_from_slice(format!("id: {}\n", single_line(id)).as_bytes());
+        }
+        if let Some(retry) = self.retry {
+            out.extend_from_slice(format!("retry: {}\n", retry.as_millis()).as_bytes());
+        }
+        for line in self.data.split('\n') {
+            out.extend_from_slice(b"data: ");
+            out.extend_from_slice(line.strip_suffix('\r').unwrap_or(line).as_bytes());
+            out.push(b'\n');
+        }
+        out.push(b'\n');
+        out
+    }
+}
+
+/// A comment line, ignored by clients: send one now and then to keep
+/// proxies from closing an idle stream.
+pub fn comment(text: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(text.len() + 3);
+    for line in text.split('\n') {
+        out.push(b':');
+        out.extend_from_slice(line.strip_suffix('\r').unwrap_or(line).as_bytes());
+        out.push(b'\n');
+    }
+    out
+}
+
+/// Answer with the event stream `body`, uncached.
+pub fn respond(res: &mut Response<'_>, body: BodyStream) -> usize {
+    res.header(b"Cache-Control", b"no-cache");
+    res.stream(200, "text/event-stream", body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_events() {
+        let event = Event::new("line one\r\nline two")
+            .event("up\ndate")
+            .id("7")
+            .retry(Duration::from_secs(3));
+        assert_eq!(
+            event.encode(),
+            b"event: update\nid: 7\nretry: 3000\ndata: line one\ndata: line two\n\n"
+        );
+        assert_eq!(Event::new("").encode(), b"data: \n\n");
+        assert_eq!(comment("ping"), b":ping\n");
+    }
+
+    #[test]
+    fn streams_until_the_client_leaves() {
+        use std::io::{Read, Write};
+        use std::sync::mpsc;
+
+        use tachyon_http::methods::Method;
+
+        use crate::router::Router;
+        use crate::server::{WriteFn, sync_handler};
+        use crate::testing::TestServer;
+
+        let (left, client_left) = mpsc::channel();
+        let mut router = Router::new();
+        router.route(
+            Method::Get,
+            "/events",
+            sync_handler(move |_| {
+                let (tx, body) = crate::stream::channel(16);
+                let left = left.clone();
+                tokio::spawn(async move {
+                    tx.send(Event::new("hello").id("1").encode()).await;
+                    tx.send(comment("ping")).await;
+                    // Idle until the client goes away.
+                    tx.closed().await;
+                    let _ = left.send(tx.send(comment("late")).await);
+                });
+                Box::new(move |res: &mut Response| respond(res, body)) as WriteFn
+            }),
+        );
+        let server = TestServer::spawn(router);
+
+        let mut stream = server.connect();
+        stream.write_all(b"GET /events HTTP/1.1\r\n\r\n").unwrap();
+        let mut out = Vec::new();
+        let mut buf = [0u8; 1024];
+        while !out.ends_with(b":ping\n\r\n") {
+            let n = stream.read(&mut buf).unwrap();
+            assert!(n > 0, "closed early");
+            out.extend_from_slice(&buf[..n]);
+        }
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("HTTP/1.1 200"));
+        assert!(out.contains("Content-Type: text/event-stream\r\n"));
+        assert!(out.contains("Cache-Control: no-cache\r\n"));
+        assert!(out.contains("Transfer-Encoding: chunked\r\n"));
+        assert!(out.contains("\r\nid: 1\ndata: hello\n\n\r\n"), "{out}");
+
+        drop(stream);
+        let sent = client_left
+            .recv_timeout(std::time::Duration::from_secs(5))
+            .expect("the producer never saw the client leave");
+        assert!(!sent);
+
+        server.stop();
+    }
+}
@@ -0,0 +1,302 @@
+//! Per-route request statistics.
+//!
+//! `Router::stats` returns a [`Stats`] handle and turns collection on. Each
+//! registered route gets atomic counters (requests, errors, body bytes in
+//! and out) and a fixed-bucket latency histogram; the table is frozen when
+//! the server starts, so recording is a hash lookup plus a few relaxed
+//! atomic adds — no locks. Call [`Stats::snapshot`] from anywhere (another
+//! thread, an admin route) to read them, and [`Stats::reset`] to start
+//! over.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use tachyon_http::methods::Method;
+
+/// Latency buckets: bucket `i` holds samples below 2^i µs; the last one
+/// catches everything from ~67 s up.
+const BUCKETS: usize = 27;
+
+struct Counters {
+    requests: AtomicU64,
+    errors: AtomicU64,
+    timeouts: AtomicU64,
+    sum_us: AtomicU64,
+    max_us: AtomicU64,
+    histogram: [AtomicU64; BUCKETS],
+    bytes_in: AtomicU64,
+    bytes_out: AtomicU64,
+    max_in: AtomicU64,
+}
+
+impl Counters {
+    fn new() -> Self {
+        Self {
+            requests: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            timeouts: AtomicU64::new(0),
+            sum_us: AtomicU64::new(0),
+            max_us: AtomicU64::new(0),
+            histogram: std::array::from_fn(|_| AtomicU64::new(0)),
+            bytes_in: AtomicU64::new(0),
+            bytes_out: AtomicU64::new(0),
+            max_in: AtomicU64::new(0),
+        }
+    }
+}
+
+type Table = HashMap<Method, HashMap<Box<[u8]>, Counters>>;
+
+struct Inner {
+    table: OnceLock<Table>,
+    /// Start of the throughput window: server start or the last reset.
+    since: Mutex<Instant>,
+}
+
+/// Handle to the per-route counters. Cheap to clone.
+#[derive(Clone)]
+pub struct Stats {
+    inner: Arc<Inner>,
+}
+
+/// Counters for one route at the time of [`Stats::snapshot`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteStats {
+    pub method: Method,
+    pub path: String,
+    pub requests: u64,
+    /// Responses with a 5xx status.
+    pub errors: u64,
+    /// Handlers that ran past their deadline (answered 504 or by the
+    /// timeout hook); counted in `errors` too when the answer is a 5xx.
+    pub timeouts: u64,
+    /// Average requests per second since the server started.
+    pub throughput: f64,
+    pub mean: Duration,
+    /// Percentiles are bucket upper bounds (powers of two in µs).
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+    /// Request body bytes received (as sent on the wire).
+    pub bytes_in: u64,
+    /// Response body bytes sent.
+    pub bytes_out: u64,
+    /// Largest request body seen.
+    pub max_body_in: u64,
+}
+
+impl Stats {
+    pub(crate) fn new() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                table: OnceLock::new(),
+                since: Mutex::new(Instant::now()),
+            }),
+        }
+    }
+
+    /// Create counters for every route. Called once by `Router::prepare`.
+    pub(crate) fn freeze<'a>(&self, routes: impl Iterator<Item = (Method, &'a [u8])>) {
+        let mut table = Table::new();
+        for (method, path) in routes {
+            table
+                .entry(method)
+                .or_default()
+                .insert(path.into(), Counters::new());
+        }
+        if self.inner.table.set(table).is_ok() {
+            *self.since() = Instant::now();
+        }
+    }
+
+    /// Count a request to the route registered as `method` + `path`.
+    #[inline]
+    pub(crate) fn record(
+        &self,
+        method: Method,
+        path: &[u8],
+        status: u16,
+        latency: Duration,
+        body_in: usize,
+        body_out: usize,
+    ) {
+        let Some(c) = self.counters(method, path) else {
+            return;
+        };
+        let us = latency.as_micros().min(u64::MAX as u128) as u64;
+        c.requests.fetch_add(1, Ordering::Relaxed);
+        if status >= 500 {
+            c.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        c.sum_us.fetch_add(us, Ordering::Relaxed);
+        c.max_us.fetch_max(us, Ordering::Relaxed);
+        let bucket = (u64::BITS - us.leading_zeros()) as usize;
+        c.histogram[bucket.min(BUCKETS - 1)].fetch_add(1, Ordering::Relaxed);
+        c.bytes_in.fetch_add(body_in as u64, Ordering::Relaxed);
+        c.bytes_out.fetch_add(body_out as u64, Ordering::Relaxed);
+        c.max_in.fetch_max(body_in as u64, Ordering::Relaxed);
+    }
+
+    /// Count a request whose handler ran past its deadline, on top of
+    /// [`record`](Self::record).
+    pub(crate) fn record_timeout(&self, method: Method, path: &[u8]) {
+        if let Some(c) = self.counters(method, path) {
+            c.timeouts.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn counters(&self, method: Method, path: &[u8]) -> Option<&Counters> {
+        let table = self.inner.table.get()?;
+        let find = |m: Method| table.get(&m).and_then(|paths| paths.get(path));
+        find(method).or_else(|| {
+            // HEAD is served by the GET route.
+            (method == Method::Head)
+                .then(|| find(Method::Get))
+                .flatten()
+        })
+    }
+
+    /// Current counters for every route, sorted by path then method.
+    /// Empty until the server has started.
+    pub fn snapshot(&self) -> Vec<RouteStats> {
+        let Some(table) = self.inner.table.get() else {
+            return Vec::new();
+        };
+        let elapsed = self.since().elapsed().as_secs_f64();
+        let mut out: Vec<RouteStats> = table
+            .iter()
+            .flat_map(|(&method, paths)| {
+                paths.iter().map(move |(path, c)| {
+                    let requests = c.requests.load(Ordering::Relaxed);
+                    let histogram: Vec<u64> = c
+                        .histogram
+                        .iter()
+                        .map(|b| b.load(Ordering::Relaxed))
+                        .collect();
+                    let sum = c.sum_us.load(Ordering::Relaxed);
+                    RouteStats {
+                        method,
+                        path: String::from_utf8_lossy(path).into_owned(),
+                        requests,
+                        errors: c.errors.load(Ordering::Relaxed),
+                        timeouts: c.timeouts.load(Ordering::Relaxed),
+                        throughput: if elapsed > 0.0 {
+                            requests as f64 / elapsed
+                        } else {
+                            0.0
+                        },
+                        mean: Duration::from_micros(sum.checked_div(requests).unwrap_or(0)),
+                        p50: percentile(&histogram, 0.50),
+                        p90: percentile(&histogram, 0.90),
+                        p99: percentile(&histogram, 0.99),
+                        max: Duration::from_micros(c.max_us.load(Ordering::Relaxed)),
+                        bytes_in: c.bytes_in.load(Ordering::Relaxed),
+                        bytes_out: c.bytes_out.load(Ordering::Relaxed),
+                        max_body_in: c.max_in.load(Ordering::Relaxed),
+                    }
+                })
+            })
+            .collect();
+        out.sort_by(|a, b| (&a.path, a.method as u8).cmp(&(&b.path, b.method as u8)));
+        out
+    }
+
+    /// Zero every counter; throughput is measured from now on.
+    pub fn reset(&self) {
+        let Some(table) = self.inner.table.get() else {
+            return;
+        };
+        for c in table.values().flat_map(HashMap::values) {
+            let histogram = c.histogram.iter();
+            for n in [
+                &c.requests,
+                &c.errors,
+                &c.timeouts,
+                &c.sum_us,
+                &c.max_us,
+                &c.bytes_in,
+                &c.bytes_out,
+                &c.max_in,
+            ]
+            .into_iter()
+            .chain(histogram)
+            {
+                n.store(0, Ordering::Relaxed);
+            }
+        }
+        *self.since() = Instant::now();
+    }
+
+    fn since(&self) -> std::sync::MutexGuard<'_, Instant> {
+        self.inner.since.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Upper bound of the bucket holding the `q` quantile.
+fn percentile(histogram: &[u64], q: f64) -> Duration {
+    let total: u64 = histogram.iter().sum();
+    if total == 0 {
+        return Duration::ZERO;
+    }
+    let rank = ((total as f64 * q).ceil() as u64).max(1);
+    let mut seen = 0;
+    for (i, &n) in histogram.iter().enumerate() {
+        seen += n;
+        if seen >= rank {
+            return Duration::from_micros(1u64 << i);
+        }
+    }
+    Duration::from_micros(1u64 << (BUCKETS - 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_per_route_percentiles() {
+        let stats = Stats::new();
+        assert!(stats.snapshot().is_empty());
+        stats.freeze([(Method::Get, &b"/a"[..]), (Method::Post, &b"/a"[..])].into_iter());
+
+        for _ in 0..98 {
+            stats.record(Method::Get, b"/a", 200, Duration::from_micros(100), 0, 10);
+        }
+        stats.record(Method::Head, b"/a", 200, Duration::from_micros(900), 0, 0);
+        stats.record(Method::Get, b"/a", 503, Duration::from_millis(5), 300, 20);
+        stats.record_timeout(Method::Get, b"/a");
+        stats.record(
+            Method::Get,
+            b"/unknown",
+            200,
+            Duration::from_micros(1),
+            5,
+            5,
+        );
+
+        let snap = stats.snapshot();
+        assert_eq!(snap.len(), 2);
+        let get = &snap[0];
+        assert_eq!((get.method, get.path.as_str()), (Method::Get, "/a"));
+        assert_eq!(get.requests, 100);
+        assert_eq!((get.errors, get.timeouts), (1, 1));
+        assert_eq!(get.p50, Duration::from_micros(128));
+        assert_eq!(get.p99, Duration::from_micros(1024));
+        assert_eq!(get.max, Duration::from_millis(5));
+        assert_eq!(
+            (get.bytes_in, get.bytes_out, get.max_body_in),
+            (300, 1000, 300)
+        );
+        assert_eq!(snap[1].requests, 0);
+
+        stats.reset();
+        let get = &stats.snapshot()[0];
+        assert_eq!((get.requests, get.timeouts, get.bytes_out), (0, 0, 0));
+        assert_eq!((get.p99, get.max), (Duration::ZERO, Duration::ZERO));
+        stats.record(Method::Get, b"/a", 200, Duration::from_micros(3), 0, 0);
+        assert_eq!(stats.snapshot()[0].p50, Duration::from_micros(4));
+    }
+}
@@ -0,0 +1,115 @@
+//! Streaming response bodies.
+//!
+//! A handler answers with [`Response::stream`](crate::response::Response::stream)
+//! and hands the [`BodySender`] to whatever produces the body. Every `send`
+//! goes out as one chunk of a `Transfer-Encoding: chunked` body as soon as
+//! the head has been written; dropping the sender ends the body.
+//!
+//! ```ignore
+//! router.get("/events", async_handler(|_| async {
+//!     let (tx, body) = tachyon_core::stream::channel(16);
+//!     tokio::spawn(async move {
+//!         for i in 0.. {
+//!             if !tx.send(format!("data: {i}\n\n")).await {
+//!                 break; // client gone
+//!             }
+//!             tokio::time::sleep(Duration::from_secs(1)).await;
+//!         }
+//!     });
+//!     Box::new(move |res: &mut Response| res.stream(200, "text/event-stream", body)) as WriteFn
+//! }));
+//! ```
+//!
+//! Streamed responses skip compression, ETags and the response cache, and
+//! the connection stays busy until the body ends. [`BodySender::abort`]
+//! closes the connection instead of ending the body, so the client sees
+//! a truncated response rather than a complete one.
+
+use tokio::sync::mpsc;
+
+/// A body chunk; `None` aborts the response.
+type Chunk = Option<Vec<u8>>;
+
+/// Feeds a streamed body. Cheap to clone; the body ends once every clone
+/// is dropped.
+#[derive(Clone, Debug)]
+pub struct BodySender(mpsc::Sender<Chunk>);
+
+/// The receiving side, passed to `Response::stream`.
+#[derive(Debug)]
+pub struct BodyStream(mpsc::Receiver<Chunk>);
+
+/// A body channel holding up to `buffer` chunks (at least 1) not yet
+/// written; `send` waits while it is full.
+pub fn channel(buffer: usize) -> (BodySender, BodyStream) {
+    let (tx, rx) = mpsc::channel(buffer.max(1));
+    (BodySender(tx), BodyStream(rx))
+}
+
+impl BodySender {
+    /// Queue `chunk`. `false` once the client has gone away (or the
+    /// response was never streamed): stop producing.
+    pub async fn send(&self, chunk: impl Into<Vec<u8>>) -> bool {
+        self.0.send(Some(chunk.into())).await.is_ok()
+    }
+
+    /// [`send`](Self::send) for producers outside the async runtime, e.g. a
+    /// plain thread; waits while the buffer is full. Panics if called from
+    /// async code.
+    pub fn blocking_send(&self, chunk: impl Into<Vec<u8>>) -> bool {
+        self.0.blocking_send(Some(chunk.into())).is_ok()
+    }
+
+    /// Whether the client has gone away.
+    pub fn is_closed(&self) -> bool {
+        self.0.is_closed()
+    }
+
+    /// Wait until the client has gone away (or the response is done).
+    pub async fn closed(&self) {
+        self.0.closed().await
+    }
+
+    /// Cut the response short: the connection is closed after the chunks
+    /// already queued, without the final empty chunk.
+    pub async fn abort(self) {
+        let _ = self.0.send(None).await;
+    }
+
+    /// [`abort`](Self::abort) outside the async runtime.
+    pub fn blocking_abort(self) {
+        let _ = self.0.blocking_send(None);
+    }
+}
+
+impl BodyStream {
+    /// Next chunk: `Some(Some(data))`, `Some(None)` if aborted, `None` at
+    /// the end of the body.
+    pub(crate) async fn next(&mut self) -> Option<Chunk> {
+        self.0.recv().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ends_when_every_sender_is_dropped() {
+        crate::testing::block_on(async {
+            let (tx, mut body) = channel(4);
+            let copy = tx.clone();
+            assert!(tx.send("a").await);
+            drop(tx);
+            assert!(copy.send(b"b".to_vec()).await);
+            drop(copy);
+            assert_eq!(body.next().await, Some(Some(b"a".to_vec())));
+            assert_eq!(body.next().await, Some(Some(b"b".to_vec())));
+            assert_eq!(body.next().await, None);
+
+            let (tx, body) = channel(1);
+            drop(body);
+            assert!(tx.is_closed() && !tx.send("late").await);
+        });
+    }
+}
@@ -0,0 +1,70 @@
+//! Server-side HTML rendering with Handlebars (`templates` feature).
+//!
+//! Build a [`Templates`] registry at startup, clone it into handlers (it is
+//! a cheap `Arc` handle) and answer with [`Response::render`]. In debug
+//! builds, templates registered from files are re-read on every render, so
+//! edits show up on refresh without restarting the server.
+//!
+//! [`Response::render`]: crate::response::Response::render
+
+use std::path::Path;
+use std::sync::Arc;
+
+use handlebars::Handlebars;
+pub use handlebars::{RenderError, TemplateError};
+use serde::Serialize;
+
+/// Shared, read-only template registry.
+#[derive(Clone)]
+pub struct Templates {
+    registry: Arc<Handlebars<'static>>,
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Templates {
+    /// Empty registry. HTML escaping is on; missing variables render empty.
+    /// Hot reload is on in debug builds.
+    pub fn new() -> Self {
+        let mut registry = Handlebars::new();
+        registry.set_dev_mode(cfg!(debug_assertions));
+        Self {
+            registry: Arc::new(registry),
+        }
+    }
+
+    /// Reload file templates on every render (default: debug builds only).
+    pub fn hot_reload(mut self, enabled: bool) -> Self {
+        Arc::make_mut(&mut self.registry).set_dev_mode(enabled);
+        self
+    }
+
+    /// Fail renders that reference a missing variable instead of printing "".
+    pub fn strict(mut self, enabled: bool) -> Self {
+        Arc::make_mut(&mut self.registry).set_strict_mode(enabled);
+        self
+    }
+
+    /// Register a template from a string.
+    pub fn register(&mut self, name: &str, source: &str) -> Result<(), TemplateError> {
+        Arc::make_mut(&mut self.registry).register_template_string(name, source)
+    }
+
+    /// Register a template from a file (hot-reloaded when enabled).
+    pub fn register_file(
+        &mut self,
+        name: &str,
+        path: impl AsRef<Path>,
+    ) -> Result<(), TemplateError> {
+        Arc::make_mut(&mut self.registry).register_template_file(name, path)
+    }
+
+    /// Render `name` with `context`.
+    pub fn render<T: Serialize>(&self, name: &str, context: &T) -> Result<String, RenderError> {
+        self.registry.render(name, context)
+    }
+}
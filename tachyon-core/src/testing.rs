@@ -0,0 +1,133 @@
+//! Fixtures shared by the tests of this crate and, with the `test-util`
+//! feature, of the crates built on it.
+
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+
+use tachyon_http::encoding::Encoding;
+use tachyon_http::http::Request;
+use tachyon_http::methods::Method;
+use tachyon_http::parser::{ParseResult, parse};
+
+use crate::cache::ResponseCache;
+use crate::config::ServerConfig;
+use crate::response::Response;
+use crate::router::Router;
+use crate::server::{Handler, Server, ServerHandle, WriteFn};
+
+/// Parse `raw`, which must hold one complete request.
+pub fn request(raw: &[u8]) -> Request<'_> {
+    match parse(raw) {
+        ParseResult::Complete(req) => *req,
+        _ => panic!("expected a complete request"),
+    }
+}
+
+/// Run `fut` to completion on a fresh current-thread runtime.
+pub fn block_on<F: Future>(fut: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(fut)
+}
+
+/// The response `write` renders, as text.
+pub fn render(write: WriteFn) -> String {
+    let mut buf = [0u8; 4096];
+    let mut res = Response::new(&mut buf, b"", Encoding::Identity, usize::MAX);
+    write(&mut res);
+    String::from_utf8_lossy(res.data()).into_owned()
+}
+
+/// Call `handler` with the request in `raw` and render its response.
+pub fn respond(handler: &Handler, raw: &[u8]) -> String {
+    let req = request(raw);
+    render(block_on(handler(&req)))
+}
+
+/// Look a GET of `target` up in `cache`, storing `data` on a miss the way
+/// a cached route does. True on a hit.
+pub fn cache_lookup(cache: &ResponseCache, target: &[u8], data: &[u8]) -> bool {
+    let key = ResponseCache::key(Method::Get, Encoding::Identity, target);
+    if cache.get(&key).is_some() {
+        return true;
+    }
+    cache.put(key, data);
+    false
+}
+
+/// A config for [`TestServer`]: an ephemeral loopback port, no signal
+/// handlers.
+pub fn config() -> ServerConfig {
+    ServerConfig::new()
+        .bind("127.0.0.1:0")
+        .shutdown_signals(false)
+}
+
+/// A spawned server, shut down and joined when dropped.
+pub struct TestServer {
+    handle: Option<ServerHandle>,
+    addr: SocketAddr,
+}
+
+impl TestServer {
+    /// Serve `router` with [`config`].
+    pub fn spawn(router: Router) -> Self {
+        Self::start(Server::new(config()), router)
+    }
+
+    /// Serve `router` on `server`, e.g. one built from a tuned [`config`]
+    /// or whose reload handle was taken first.
+    pub fn start(server: Server, router: Router) -> Self {
+        let handle = server.spawn(router).unwrap();
+        Self {
+            addr: handle.local_addr(),
+            handle: Some(handle),
+        }
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn connect(&self) -> TcpStream {
+        TcpStream::connect(self.addr).unwrap()
+    }
+
+    /// Send `raw` on a new connection and read until the server closes it.
+    pub fn send_bytes(&self, raw: impl AsRef<[u8]>) -> Vec<u8> {
+        let mut stream = self.connect();
+        stream.write_all(raw.as_ref()).unwrap();
+        let mut out = Vec::new();
+        // A reset after the last response still leaves what was read.
+        let _ = stream.read_to_end(&mut out);
+        out
+    }
+
+    /// [`send_bytes`](Self::send_bytes), as text.
+    pub fn send(&self, raw: impl AsRef<[u8]>) -> String {
+        String::from_utf8_lossy(&self.send_bytes(raw)).into_owned()
+    }
+
+    /// Shut down and wait for the server to stop.
+    pub fn stop(mut self) {
+        self.join();
+    }
+
+    fn join(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            handle.shutdown();
+            let joined = block_on(handle.join());
+            if !std::thread::panicking() {
+                joined.unwrap();
+            }
+        }
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.join();
+    }
+}
@@ -0,0 +1,489 @@
+//! Tower interop (`tower` feature).
+//!
+//! [`Server::into_service`](crate::server::Server::into_service) turns a
+//! router into a [`TachyonService`]: a tower `Service` over `http` requests
+//! that can be wrapped in tower layers and driven by any tower-speaking
+//! server. Each request takes the same path as one read off a socket —
+//! middleware, hooks, caching, compression, error pages — as a single
+//! `Connection: close` exchange over an in-memory transport, and the
+//! HTTP/1.1 response is converted back. Trailers of chunked responses are
+//! dropped.
+//!
+//! With the `hyper` feature it is also a `hyper::service::Service`, so it
+//! can be handed straight to `hyper::server::conn` (or behind axum's
+//! `fallback_service`) on a listener the application owns:
+//!
+//! ```ignore
+//! let service = Server::new(config).into_service(router)?;
+//! let (stream, peer) = listener.accept().await?;
+//! let service = service.with_peer(peer);
+//! http1::Builder::new().serve_connection(TokioIo::new(stream), service).await?;
+//! ```
+//!
+//! [`service_handler`] goes the other way and mounts a tower `Service` (with
+//! whatever layers it already has) as a route:
+//!
+//! ```ignore
+//! let svc = ServiceBuilder::new().layer(TimeoutLayer::new(secs)).service(inner);
+//! router.route(Method::Get, "/legacy", tower::service_handler(svc));
+//! ```
+
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use http::header;
+use http_body_util::{BodyExt, Full, LengthLimitError, Limited};
+use tower_service::Service;
+
+use crate::ip_filter::Reject;
+use crate::response::Response;
+use crate::server::{Handler, Shared, Transport, WriteFn, send_error, serve_connection};
+
+/// Headers that describe one HTTP/1.1 connection rather than the message.
+const HOP_BY_HOP: [header::HeaderName; 5] = [
+    header::CONNECTION,
+    header::TRANSFER_ENCODING,
+    header::TE,
+    header::UPGRADE,
+    header::HeaderName::from_static("keep-alive"),
+];
+
+/// A router as a tower `Service`; see the [module docs](self). Clones share
+/// the router, so `ReloadHandle` swaps apply to every clone.
+///
+/// The client address (for `ServerConfig::ip_filter`, access logs and
+/// [`ClientAddr`](crate::extract::ClientAddr)) is a `SocketAddr` request
+/// extension if there is one, else the [`with_peer`](Self::with_peer)
+/// address, else `0.0.0.0:0`. Requests the IP filter would close fail
+/// with `PermissionDenied`.
+#[derive(Clone)]
+pub struct TachyonService {
+    shared: Arc<Shared>,
+    peer: Option<SocketAddr>,
+    date: Arc<DateTicker>,
+}
+
+impl TachyonService {
+    pub(crate) fn new(shared: Arc<Shared>) -> Self {
+        Self {
+            shared,
+            peer: None,
+            date: Arc::default(),
+        }
+    }
+
+    /// A copy for one connection from `peer`.
+    pub fn with_peer(&self, peer: SocketAddr) -> Self {
+        Self {
+            peer: Some(peer),
+            ..self.clone()
+        }
+    }
+}
+
+/// Keeps the cached `Date` header ticking for a service. The ticker needs a
+/// runtime, so it starts with a request, and dies with that runtime: the
+/// next request, on whichever runtime serves it, starts another.
+#[derive(Default)]
+struct DateTicker(std::sync::Mutex<Option<tokio::task::JoinHandle<()>>>);
+
+impl DateTicker {
+    fn ensure(&self) {
+        let mut ticker = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if ticker.as_ref().is_none_or(|t| t.is_finished()) {
+            *ticker = Some(crate::date::spawn_ticker());
+        }
+    }
+}
+
+#[cfg(feature = "hyper")]
+impl<B> hyper::service::Service<http::Request<B>> for TachyonService
+where
+    B: http_body::Body + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    type Response = http::Response<Full<Bytes>>;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = io::Result<Self::Response>> + Send>>;
+
+    fn call(&self, req: http::Request<B>) -> Self::Future {
+        self.date.ensure();
+        Box::pin(respond(self.shared.clone(), self.peer, req))
+    }
+}
+
+impl<B> Service<http::Request<B>> for TachyonService
+where
+    B: http_body::Body + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    type Response = http::Response<Full<Bytes>>;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = io::Result<Self::Response>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        self.date.ensure();
+        Box::pin(respond(self.shared.clone(), self.peer, req))
+    }
+}
+
+async fn respond<B>(
+    shared: Arc<Shared>,
+    peer: Option<SocketAddr>,
+    req: http::Request<B>,
+) -> io::Result<http::Response<Full<Bytes>>>
+where
+    B: http_body::Body + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    let config = &shared.config;
+    let peer = req
+        .extensions()
+        .get::<SocketAddr>()
+        .copied()
+        .or(peer)
+        .map(|peer| SocketAddr::new(peer.ip().to_canonical(), peer.port()))
+        .unwrap_or_else(|| SocketAddr::from(([0, 0, 0, 0], 0)));
+    let rejected = config
+        .ip_filter
+        .as_ref()
+        .filter(|f| !f.allows(peer.ip()))
+        .map(|f| f.rejection());
+    if rejected.is_some() {
+        crate::server::count_rejected(config, crate::metrics::Rejected::IpFilter);
+    }
+    if rejected == Some(Reject::Close) {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "client address rejected by the IP filter",
+        ));
+    }
+
+    let (parts, body) = req.into_parts();
+    let mut exchange = Exchange::default();
+    // The whole request has to fit in one read buffer, as on a socket.
+    match Limited::new(body, config.buffer_size).collect().await {
+        Ok(body) => {
+            exchange.input = encode_request(&parts, &body.to_bytes());
+            serve_connection(&mut exchange, peer, rejected, None, shared.clone()).await;
+        }
+        Err(e) if e.is::<LengthLimitError>() => {
+            let mut buf = tachyon_pool::pool::acquire();
+            send_error(&mut &mut exchange, buf.as_write_buf(), config, 413).await;
+        }
+        Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+    }
+    decode_response(&exchange.output)
+}
+
+/// One request in, the response out.
+#[derive(Default)]
+struct Exchange {
+    input: Vec<u8>,
+    read: usize,
+    output: Vec<u8>,
+}
+
+impl Transport for &mut Exchange {
+    async fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let rest = &self.input[self.read..];
+        let n = rest.len().min(buf.len());
+        buf[..n].copy_from_slice(&rest[..n]);
+        self.read += n;
+        Ok(n)
+    }
+
+    async fn write_all(&mut self, data: &[u8]) -> io::Result<()> {
+        self.output.extend_from_slice(data);
+        Ok(())
+    }
+
+    fn set_nodelay(&self, _enabled: bool) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn reports_disconnect(&self) -> bool {
+        false
+    }
+}
+
+fn encode_request(parts: &http::request::Parts, body: &[u8]) -> Vec<u8> {
+    let target = parts.uri.path_and_query().map_or("/", |p| p.as_str());
+    let mut out = Vec::with_capacity(256 + target.len() + body.len());
+    out.extend_from_slice(parts.method.as_str().as_bytes());
+    out.push(b' ');
+    out.extend_from_slice(target.as_bytes());
+    out.extend_from_slice(b" HTTP/1.1\r\n");
+    // HTTP/2 clients send the host as the URI authority.
+    if let (false, Some(authority)) = (
+        parts.headers.contains_key(header::HOST),
+        parts.uri.authority(),
+    ) {
+        out.extend_from_slice(b"host: ");
+        out.extend_from_slice(authority.as_str().as_bytes());
+        out.extend_from_slice(b"\r\n");
+    }
+    for (name, value) in &parts.headers {
+        if *name == header::CONTENT_LENGTH || HOP_BY_HOP.contains(name) {
+            continue;
+        }
+        out.extend_from_slice(name.as_str().as_bytes());
+        out.extend_from_slice(b": ");
+        out.extend_from_slice(value.as_bytes());
+        out.extend_from_slice(b"\r\n");
+    }
+    out.extend_from_slice(b"content-length: ");
+    out.extend_from_slice(body.len().to_string().as_bytes());
+    out.extend_from_slice(b"\r\nconnection: close\r\n\r\n");
+    out.extend_from_slice(body);
+    out
+}
+
+fn decode_response(raw: &[u8]) -> io::Result<http::Response<Full<Bytes>>> {
+    let invalid =
+        |what: &str| io::Error::new(io::ErrorKind::InvalidData, format!("{what} response"));
+    let head = raw
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or_else(|| invalid("incomplete"))?;
+    let mut lines = raw[..head].split(|&b| b == b'\n').map(<[u8]>::trim_ascii);
+    let status = lines
+        .next()
+        .and_then(|line| line.get(9..12))
+        .and_then(|code| http::StatusCode::from_bytes(code).ok())
+        .ok_or_else(|| invalid("malformed"))?;
+    let mut response = http::Response::builder().status(status);
+    let mut chunked = false;
+    for line in lines {
+        let Some(colon) = line.iter().position(|&b| b == b':') else {
+            continue;
+        };
+        let (name, value) = (&line[..colon], line[colon + 1..].trim_ascii());
+        let Ok(name) = header::HeaderName::from_bytes(name) else {
+            continue;
+        };
+        if name == header::TRANSFER_ENCODING {
+            chunked = true;
+        }
+        if !HOP_BY_HOP.contains(&name) {
+            response = response.header(name, value);
+        }
+    }
+    let body = &raw[head + 4..];
+    let body = if chunked {
+        Bytes::from(tachyon_http::chunked::decode(body))
+    } else {
+        Bytes::copy_from_slice(body)
+    };
+    response
+        .body(Full::new(body))
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// A [`Handler`] that forwards requests to a tower `Service`. Requests the
+/// `http` crate cannot represent get a 400; a failed service or response
+/// body a 500.
+pub fn service_handler<S, B>(service: S) -> Handler
+where
+    S: Service<http::Request<Full<Bytes>>, Response = http::Response<B>>
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+    S::Future: Send,
+    S::Error: std::fmt::Display + Send,
+    B: http_body::Body + Send + 'static,
+    B::Data: Send,
+    B::Error: std::fmt::Display + Send,
+{
+    Arc::new(move |req| {
+        let request = to_http(req);
+        let mut service = service.clone();
+        Box::pin(async move {
+            let Some(request) = request else {
+                return Box::new(|res: &mut Response| res.text(400, b"Bad Request")) as WriteFn;
+            };
+            let ready = std::future::poll_fn(|cx| service.poll_ready(cx)).await;
+            let (parts, body) = match ready {
+                Ok(()) => match service.call(request).await {
+                    Ok(response) => response.into_parts(),
+                    Err(e) => return service_failed(e),
+                },
+                Err(e) => return service_failed(e),
+            };
+            match body.collect().await {
+                Ok(body) => from_http(parts, body.to_bytes()),
+                Err(e) => service_failed(e),
+            }
+        })
+    })
+}
+
+fn to_http(req: &tachyon_http::http::Request<'_>) -> Option<http::Request<Full<Bytes>>> {
+    let version = match req.version_minor {
+        0 => http::Version::HTTP_10,
+        _ => http::Version::HTTP_11,
+    };
+    let mut request = http::Request::builder()
+        .method(req.method.as_str())
+        .uri(req.path)
+        .version(version);
+    for h in req.headers[..req.header_count].iter().flatten() {
+        // The body below is already de-chunked.
+        if req.chunked && h.name.eq_ignore_ascii_case(b"transfer-encoding") {
+            continue;
+        }
+        request = request.header(h.name, h.value);
+    }
+    let body = Bytes::from(req.decoded_body().into_owned());
+    request.body(Full::new(body)).ok()
+}
+
+fn from_http(parts: http::response::Parts, body: Bytes) -> WriteFn {
+    Box::new(move |res: &mut Response| {
+        let mut content_type = "application/octet-stream";
+        for (name, value) in &parts.headers {
+            if *name == header::CONTENT_TYPE {
+                content_type = value.to_str().unwrap_or(content_type);
+            } else if *name != header::CONTENT_LENGTH
+                && *name != header::DATE
+                && !HOP_BY_HOP.contains(name)
+            {
+                res.header(name.as_str().as_bytes(), value.as_bytes());
+            }
+        }
+        res.send(parts.status.as_u16(), content_type, &body)
+    })
+}
+
+fn service_failed(e: impl std::fmt::Display) -> WriteFn {
+    eprintln!("[tachyon] Tower service failed: {}", e);
+    Box::new(|res: &mut Response| res.text(500, b"Internal Server Error"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ServerConfig;
+    use crate::router::Router;
+    use crate::server::{Server, sync_handler};
+    use tachyon_http::methods::Method;
+
+    /// Answers `<method> <path>` with the request body appended.
+    #[derive(Clone)]
+    struct Echo;
+
+    impl Service<http::Request<Full<Bytes>>> for Echo {
+        type Response = http::Response<Full<Bytes>>;
+        type Error = std::convert::Infallible;
+        type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: http::Request<Full<Bytes>>) -> Self::Future {
+            Box::pin(async move {
+                let head = format!("{} {} ", req.method(), req.uri());
+                let body = req.into_body().collect().await.unwrap().to_bytes();
+                let out = [head.as_bytes(), &body].concat();
+                Ok(http::Response::builder()
+                    .status(201)
+                    .header("x-echo", "1")
+                    .body(Full::new(Bytes::from(out)))
+                    .unwrap())
+            })
+        }
+    }
+
+    #[test]
+    fn round_trips_through_tower() {
+        let mut router = Router::new();
+        router.route(
+            Method::Get,
+            "/hi",
+            sync_handler(|req| {
+                format!(
+                    "hi {}",
+                    req.header(b"x-name")
+                        .map_or("?", |n| std::str::from_utf8(n).unwrap())
+                )
+            }),
+        );
+        router.route(Method::Post, "/echo", service_handler(Echo));
+        router.route(
+            Method::Get,
+            "/peer",
+            sync_handler(|_| {
+                crate::extract::client_addr().map_or_else(String::new, |peer| peer.to_string())
+            }),
+        );
+        let mut service = Server::new(ServerConfig::new().shutdown_signals(false))
+            .into_service(router)
+            .unwrap();
+
+        crate::testing::block_on(async {
+            let req = http::Request::get("http://example.test/hi")
+                .header("x-name", "ada")
+                .body(Full::new(Bytes::new()))
+                .unwrap();
+            let res = service.call(req).await.unwrap();
+            assert_eq!(res.status(), 200);
+            assert!(res.headers().get(header::CONNECTION).is_none());
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(&body[..], b"hi ada");
+
+            let req = http::Request::post("/echo?x=1")
+                .body(Full::new(Bytes::from_static(b"payload")))
+                .unwrap();
+            let res = service.call(req).await.unwrap();
+            assert_eq!(res.status(), 201);
+            assert_eq!(res.headers()["x-echo"], "1");
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(&body[..], b"POST /echo?x=1 payload");
+
+            #[cfg(feature = "hyper")]
+            {
+                let peer = "10.0.0.1:4000".parse().unwrap();
+                let req = http::Request::get("/peer")
+                    .body(Full::new(Bytes::new()))
+                    .unwrap();
+                let res = hyper::service::Service::call(&service.with_peer(peer), req)
+                    .await
+                    .unwrap();
+                let body = res.into_body().collect().await.unwrap().to_bytes();
+                assert_eq!(&body[..], b"10.0.0.1:4000");
+            }
+
+            let req = http::Request::get("/missing")
+                .body(Full::new(Bytes::new()))
+                .unwrap();
+            assert_eq!(service.call(req).await.unwrap().status(), 404);
+        });
+    }
+
+    #[test]
+    fn restarts_the_date_ticker_on_a_new_runtime() {
+        let ticker = DateTicker::default();
+        crate::testing::block_on(async { ticker.ensure() });
+        // That runtime is gone, and its ticker with it.
+        assert!(ticker.0.lock().unwrap().as_ref().unwrap().is_finished());
+
+        crate::testing::block_on(async {
+            ticker.ensure();
+            assert!(!ticker.0.lock().unwrap().as_ref().unwrap().is_finished());
+        });
+    }
+}
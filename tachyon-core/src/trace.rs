@@ -0,0 +1,120 @@
+//! `tracing` integration (`tracing` feature).
+//!
+//! Each connection gets a `connection{peer}` span; inside it, every request
+//! gets a `request{method, path, route, status, latency_us}` span with
+//! `parse`, `route` and `handler` child phases. Events go to whatever
+//! subscriber the application installed; with the `otel` feature the request
+//! span also continues an incoming W3C trace. Without the feature these are
+//! empty inline functions and the server loop pays nothing.
+
+use std::future::Future;
+use std::net::SocketAddr;
+
+use tachyon_http::http::Request;
+use tachyon_http::parser::{Limits, ParseResult};
+
+pub(crate) use imp::*;
+
+#[cfg(feature = "tracing")]
+mod imp {
+    use super::*;
+    use std::time::Instant;
+    use tracing::{Instrument, Span, field};
+
+    pub(crate) fn connection<F: Future>(
+        peer: SocketAddr,
+        fut: F,
+    ) -> impl Future<Output = F::Output> {
+        fut.instrument(tracing::debug_span!("connection", %peer))
+    }
+
+    /// Parse one request (headers plus chunked-body framing).
+    pub(crate) fn parse<'a>(data: &'a [u8], limits: &Limits) -> ParseResult<'a> {
+        tracing::trace_span!("parse", bytes = data.len())
+            .in_scope(|| tachyon_http::parser::parse_with(data, limits))
+    }
+
+    pub(crate) struct RequestSpan {
+        span: Span,
+        started: Instant,
+    }
+
+    impl RequestSpan {
+        pub(crate) fn new(request: &Request<'_>) -> Self {
+            let span = tracing::info_span!(
+                "request",
+                method = request.method.as_str(),
+                path = %String::from_utf8_lossy(request.path),
+                route = field::Empty,
+                status = field::Empty,
+                latency_us = field::Empty,
+            );
+            #[cfg(feature = "otel")]
+            crate::otel::set_remote_parent(&span, request);
+            Self {
+                span,
+                started: Instant::now(),
+            }
+        }
+
+        /// Run route lookup inside a `route` span.
+        pub(crate) fn route<T>(&self, find: impl FnOnce() -> T) -> T {
+            tracing::debug_span!(parent: &self.span, "route").in_scope(find)
+        }
+
+        pub(crate) fn handler<F: Future>(&self, fut: F) -> impl Future<Output = F::Output> {
+            fut.instrument(tracing::debug_span!(parent: &self.span, "handler"))
+        }
+
+        /// Record the matched route, final status and latency.
+        pub(crate) fn finish(&self, route: Option<&[u8]>, status: Option<u16>) {
+            if let Some(route) = route {
+                self.span.record(
+                    "route",
+                    tracing::field::display(String::from_utf8_lossy(route)),
+                );
+            }
+            let latency = self.started.elapsed().as_micros() as u64;
+            self.span.record("status", status.unwrap_or(0));
+            self.span.record("latency_us", latency);
+            tracing::debug!(parent: &self.span, status, latency_us = latency, "response");
+        }
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+mod imp {
+    use super::*;
+
+    #[inline(always)]
+    pub(crate) fn connection<F: Future>(_peer: SocketAddr, fut: F) -> F {
+        fut
+    }
+
+    #[inline(always)]
+    pub(crate) fn parse<'a>(data: &'a [u8], limits: &Limits) -> ParseResult<'a> {
+        tachyon_http::parser::parse_with(data, limits)
+    }
+
+    pub(crate) struct RequestSpan;
+
+    impl RequestSpan {
+        #[inline(always)]
+        pub(crate) fn new(_request: &Request<'_>) -> Self {
+            Self
+        }
+
+        #[inline(always)]
+        pub(crate) fn route<T>(&self, find: impl FnOnce() -> T) -> T {
+            find()
+        }
+
+        #[inline(always)]
+        pub(crate) fn handler<F: Future>(&self, fut: F) -> F {
+            fut
+        }
+
+        #[inline(always)]
+        pub(crate) fn finish(&self, _route: Option<&[u8]>, _status: Option<u16>) {}
+    }
+}
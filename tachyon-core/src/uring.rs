@@ -0,0 +1,201 @@
+//! io_uring backend (Linux, `io-uring` feature, `ServerConfig::io_uring`).
+//!
+//! Each acceptor thread runs a `tokio-uring` runtime: accepts, reads and
+//! writes are submitted to the thread's ring instead of waiting for epoll
+//! readiness and then issuing the syscall. Connections never leave the
+//! thread that accepted them, so set `acceptors` to the core count to use
+//! every core.
+//!
+//! Requests go through the same connection loop as the epoll path. io_uring
+//! owns a buffer while an operation is in flight, so each connection reads
+//! and writes through one owned buffer and copies to and from the pooled
+//! ones.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use tokio_uring::buf::BoundedBuf;
+use tokio_uring::net::{TcpListener, TcpStream};
+
+use crate::error::TachyonError;
+use crate::ip_filter::Reject;
+use crate::metrics::Rejected;
+use crate::server::{
+    AcceptBackoff, Shared, Transport, bind_all, count_rejected, finish, overload_pause,
+    serve_connection,
+};
+
+struct UringStream {
+    stream: TcpStream,
+    /// Handed to the kernel for each operation; empty after a cancelled one.
+    buf: Vec<u8>,
+}
+
+impl Transport for UringStream {
+    async fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut owned = std::mem::take(&mut self.buf);
+        owned.clear();
+        owned.reserve(buf.len());
+        let (result, slice) = self.stream.read(owned.slice(..buf.len())).await;
+        self.buf = slice.into_inner();
+        let n = result?;
+        buf[..n].copy_from_slice(&self.buf[..n]);
+        Ok(n)
+    }
+
+    async fn write_all(&mut self, data: &[u8]) -> std::io::Result<()> {
+        let mut owned = std::mem::take(&mut self.buf);
+        owned.clear();
+        owned.extend_from_slice(data);
+        let (result, owned) = self.stream.write_all(owned).await;
+        self.buf = owned;
+        result
+    }
+
+    fn set_nodelay(&self, enabled: bool) -> std::io::Result<()> {
+        self.stream.set_nodelay(enabled)
+    }
+
+    /// A dropped read may still complete in the kernel, taking the bytes
+    /// with it.
+    fn reports_disconnect(&self) -> bool {
+        false
+    }
+}
+
+impl Drop for UringStream {
+    fn drop(&mut self) {
+        // A cancelled read keeps the fd open until it completes; shutting the
+        // socket down completes it, so the peer sees the close right away.
+        let _ = self.stream.shutdown(std::net::Shutdown::Both);
+    }
+}
+
+/// Run one acceptor on the calling thread until shutdown.
+pub(crate) fn run_acceptor(shared: Arc<Shared>, primary: bool) -> Result<(), TachyonError> {
+    tokio_uring::start(async move {
+        if primary {
+            crate::date::start_date_cache();
+            if shared.config.shutdown_signals {
+                let shutdown = shared.shutdown.clone();
+                tokio_uring::spawn(async move {
+                    crate::shutdown::signal().await;
+                    shutdown.trigger();
+                });
+            }
+        }
+        let mut listeners = Vec::new();
+        for listener in bind_all(&shared, primary)? {
+            listeners.push(TcpListener::from_std(listener.into_std()?));
+        }
+        let first = listeners.remove(0);
+        for listener in listeners {
+            tokio_uring::spawn(accept_loop(listener, shared.clone()));
+        }
+        accept_loop(first, shared.clone()).await;
+        finish(&shared, primary).await;
+        Ok(())
+    })
+}
+
+/// Accept connections on `listener` and spawn a local task per connection.
+async fn accept_loop(listener: TcpListener, shared: Arc<Shared>) {
+    let config = &shared.config;
+    let limit = config.connection_limit.as_ref();
+    let mut backoff = AcceptBackoff::default();
+    loop {
+        let accepted = async {
+            overload_pause(config).await;
+            let reserved = match limit {
+                Some(limit) => limit.reserve().await,
+                None => None,
+            };
+            match listener.accept().await {
+                Ok(accepted) => Some((accepted, reserved)),
+                Err(e) => {
+                    backoff.pause(&e, config).await;
+                    None
+                }
+            }
+        };
+        let ((stream, peer), reserved): ((TcpStream, SocketAddr), _) = tokio::select! {
+            accepted = accepted => match accepted {
+                Some(accepted) => accepted,
+                None => continue,
+            },
+            () = shared.shutdown.draining() => return,
+        };
+        backoff.reset();
+        // IPv4 clients of a dual-stack socket arrive as ::ffff:a.b.c.d.
+        let peer = SocketAddr::new(peer.ip().to_canonical(), peer.port());
+
+        let rejected = config
+            .ip_filter
+            .as_ref()
+            .filter(|f| !f.allows(peer.ip()))
+            .map(|f| f.rejection());
+        if rejected.is_some() {
+            count_rejected(config, Rejected::IpFilter);
+        }
+        if rejected == Some(Reject::Close) {
+            continue;
+        }
+
+        let slot = match limit {
+            Some(limit) => match limit.admit(reserved) {
+                Some(slot) => Some(slot),
+                None => {
+                    count_rejected(config, Rejected::Limit);
+                    tokio_uring::spawn(async move {
+                        let _ = stream.write_all(crate::limit::REJECT_RESPONSE).await;
+                    });
+                    continue;
+                }
+            },
+            None => None,
+        };
+
+        let stream = UringStream {
+            stream,
+            buf: Vec::new(),
+        };
+        tokio_uring::spawn(serve_connection(
+            stream,
+            peer,
+            rejected,
+            slot,
+            shared.clone(),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::router::Router;
+    use crate::server::{Server, sync_handler};
+    use crate::testing::{TestServer, config};
+    use tachyon_http::methods::Method;
+
+    #[test]
+    fn serves_through_io_uring() {
+        let mut router = Router::new();
+        router.route(Method::Get, "/", sync_handler(|_| "ok"));
+        router.route(
+            Method::Post,
+            "/echo",
+            sync_handler(|req| req.decoded_body().into_owned()),
+        );
+        let server = TestServer::start(Server::new(config().io_uring(true)), router);
+
+        let out = server.send(
+            "GET / HTTP/1.1\r\n\r\n\
+             POST /echo HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello\
+             GET / HTTP/1.1\r\nConnection: close\r\n\r\n",
+        );
+        assert_eq!(out.matches("HTTP/1.1 200").count(), 3, "{out}");
+        assert!(out.contains("\r\n\r\nok") && out.contains("\r\n\r\nhello"));
+        assert!(out.ends_with("\r\n\r\nok"));
+
+        server.stop();
+    }
+}
@@ -5,6 +5,15 @@ use tokio::net::TcpListener;
 /// With `simd` feature: delegates to C via cxx bridge (handles all platforms cleanly).
 /// Without `simd`: applies the subset available through Rust's std/libc.
 pub fn apply_socket_config(listener: &TcpListener, socket: &crate::config::SocketConfig) {
+    if let Some((idle, interval)) = socket.keepalive {
+        let keepalive = socket2::TcpKeepalive::new()
+            .with_time(idle)
+            .with_interval(interval);
+        if let Err(e) = socket2::SockRef::from(listener).set_tcp_keepalive(&keepalive) {
+            eprintln!("[tachyon] TCP keepalive warning: {}", e);
+        }
+    }
+
     #[cfg(all(feature = "simd", unix))]
     {
         use std::os::fd::AsRawFd;
@@ -52,3 +61,136 @@ pub fn apply_socket_config(listener: &TcpListener, socket: &crate::config::Socke
         let _ = listener;
     }
 }
+
+/// Bind a listener on `addr`, trying each address it resolves to.
+///
+/// With `reuse_port`, SO_REUSEPORT is set before binding so several
+/// listeners (one per acceptor) can share the address; the kernel then
+/// spreads incoming connections across them. The backlog and IPV6_V6ONLY
+/// come from `config`; the rest of it is applied after binding.
+///
+/// The listener is non-blocking but not registered with a runtime, so
+/// this works before one is running.
+pub(crate) fn bind_listener(
+    addr: &str,
+    reuse_port: bool,
+    config: &crate::config::SocketConfig,
+) -> std::io::Result<std::net::TcpListener> {
+    use socket2::{Domain, Protocol, Socket, Type};
+    use std::net::ToSocketAddrs;
+    let mut last_err = None;
+    for addr in addr.to_socket_addrs()? {
+        let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+        if let (true, Some(only)) = (addr.is_ipv6(), config.ipv6_only) {
+            socket.set_only_v6(only)?;
+        }
+        #[cfg(unix)]
+        {
+            socket.set_reuse_address(true)?;
+            if reuse_port {
+                socket.set_reuse_port(true)?;
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = reuse_port;
+        socket.set_nonblocking(true)?;
+        let backlog = config.backlog.min(i32::MAX as u32) as i32;
+        match socket
+            .bind(&addr.into())
+            .and_then(|()| socket.listen(backlog))
+        {
+            Ok(()) => return Ok(socket.into()),
+            Err(e) => last_err = Some(e),
+        }
+    }
+    Err(last_err.unwrap_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "could not resolve to any address",
+        )
+    }))
+}
+
+/// Listening sockets passed by systemd socket activation (`LISTEN_FDS`,
+/// starting at fd 3). Empty when the variables are unset or meant for
+/// another process (`LISTEN_PID`). The variables are unset afterwards, like
+/// `sd_listen_fds(1)`, so child processes don't adopt the same fds.
+#[cfg(unix)]
+pub(crate) fn listen_fds() -> std::io::Result<Vec<std::net::TcpListener>> {
+    use std::os::fd::FromRawFd;
+
+    const FIRST_FD: i32 = 3;
+    let var = |name| std::env::var(name).ok().and_then(|v| v.parse::<u32>().ok());
+    let (pid, count) = (var("LISTEN_PID"), var("LISTEN_FDS"));
+    for name in ["LISTEN_PID", "LISTEN_FDS", "LISTEN_FDNAMES"] {
+        // SAFETY: read once while the server is being built, before it
+        // starts its threads; nothing else in this crate reads these.
+        unsafe { std::env::remove_var(name) };
+    }
+    if pid.is_some_and(|pid| pid != std::process::id()) {
+        return Ok(Vec::new());
+    }
+    let count = count.unwrap_or(0) as i32;
+    (FIRST_FD..FIRST_FD + count)
+        .map(|fd| {
+            // SAFETY: under the socket activation protocol these fds are open
+            // and belong to this process; they are adopted exactly once.
+            let listener = unsafe { std::net::TcpListener::from_raw_fd(fd) };
+            // Fails for anything that is not a bound socket.
+            listener.local_addr()?;
+            Ok(listener)
+        })
+        .collect()
+}
+
+/// Future adapter that turns a panic while polling into `Err(payload)`.
+pub(crate) struct CatchUnwind<F>(pub F);
+
+impl<F: std::future::Future + Unpin> std::future::Future for CatchUnwind<F> {
+    type Output = std::thread::Result<F::Output>;
+
+    fn poll(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Self::Output> {
+        use std::panic::{AssertUnwindSafe, catch_unwind};
+        use std::task::Poll;
+        let inner = &mut self.0;
+        match catch_unwind(AssertUnwindSafe(|| {
+            std::pin::Pin::new(&mut *inner).poll(cx)
+        })) {
+            Ok(Poll::Ready(out)) => Poll::Ready(Ok(out)),
+            Ok(Poll::Pending) => Poll::Pending,
+            Err(payload) => Poll::Ready(Err(payload)),
+        }
+    }
+}
+
+/// Best-effort text of a panic payload.
+pub(crate) fn panic_message(payload: &(dyn std::any::Any + Send)) -> &str {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s
+    } else {
+        "<non-string panic payload>"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(unix)]
+    #[test]
+    fn listen_fds_unsets_the_variables() {
+        // SAFETY: no other test reads or writes these variables.
+        unsafe {
+            std::env::set_var("LISTEN_PID", std::process::id().to_string());
+            std::env::set_var("LISTEN_FDS", "0");
+            std::env::set_var("LISTEN_FDNAMES", "");
+        }
+        assert!(super::listen_fds().unwrap().is_empty());
+        for name in ["LISTEN_PID", "LISTEN_FDS", "LISTEN_FDNAMES"] {
+            assert!(std::env::var_os(name).is_none(), "{name} still set");
+        }
+    }
+}
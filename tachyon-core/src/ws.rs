@@ -0,0 +1,517 @@
+//! WebSocket upgrades (RFC 6455).
+//!
+//! A handler answers an upgrade request with [`upgrade`], passing the
+//! session to run once the handshake is done:
+//!
+//! ```ignore
+//! router.get("/echo", sync_handler(|req| {
+//!     ws::upgrade(req, |mut socket| async move {
+//!         while let Some(msg) = socket.recv().await {
+//!             if let Message::Text(text) = msg {
+//!                 socket.send(text).await;
+//!             }
+//!         }
+//!     })
+//! }));
+//! ```
+//!
+//! The session runs on its own task while the connection forwards frames
+//! both ways; the socket closes when the session and every [`WsSender`]
+//! are done, or when the client closes it. Pings are answered, fragmented
+//! messages reassembled, and a message over [`MAX_MESSAGE`] closes the
+//! socket with 1009. Draining the server closes open sockets with 1001.
+//!
+//! Upgrades need a transport whose reads can be interrupted, so on
+//! io_uring and behind a `TachyonService` they are answered with 501.
+
+use std::future::Future;
+use std::pin::Pin;
+
+use tachyon_http::http::Request;
+use tokio::sync::mpsc;
+
+use crate::config::ServerConfig;
+use crate::extract::Rejection;
+use crate::response::{IntoResponse, Response};
+use crate::server::{Transport, WriteFn, write_within};
+
+/// Largest message accepted from a client, reassembled (1 MiB).
+pub const MAX_MESSAGE: usize = 1 << 20;
+
+/// Messages queued each way before the other side waits.
+const QUEUE: usize = 16;
+
+const GUID: &[u8] = b"258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// A WebSocket message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    /// Sent: close the socket with this code and reason. Received: the
+    /// client closed it (1005 if it gave no code); nothing follows.
+    Close(u16, String),
+}
+
+impl From<String> for Message {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+
+impl From<&str> for Message {
+    fn from(text: &str) -> Self {
+        Self::Text(text.to_string())
+    }
+}
+
+impl From<Vec<u8>> for Message {
+    fn from(data: Vec<u8>) -> Self {
+        Self::Binary(data)
+    }
+}
+
+impl From<&[u8]> for Message {
+    fn from(data: &[u8]) -> Self {
+        Self::Binary(data.to_vec())
+    }
+}
+
+/// Sends on a socket from anywhere. Cheap to clone.
+#[derive(Clone, Debug)]
+pub struct WsSender(mpsc::Sender<Message>);
+
+impl WsSender {
+    /// Queue `msg`. `false` once the socket is closed.
+    pub async fn send(&self, msg: impl Into<Message>) -> bool {
+        self.0.send(msg.into()).await.is_ok()
+    }
+
+    /// [`send`](Self::send) for code outside the async runtime; waits
+    /// while the queue is full. Panics if called from async code.
+    pub fn blocking_send(&self, msg: impl Into<Message>) -> bool {
+        self.0.blocking_send(msg.into()).is_ok()
+    }
+
+    /// Close the socket after the messages already queued.
+    pub async fn close(&self, code: u16, reason: &str) -> bool {
+        self.send(Message::Close(code, reason.to_string())).await
+    }
+
+    /// Whether the socket is closed.
+    pub fn is_closed(&self) -> bool {
+        self.0.is_closed()
+    }
+}
+
+/// An upgraded connection, handed to the session.
+#[derive(Debug)]
+pub struct WebSocket {
+    incoming: mpsc::Receiver<Message>,
+    sender: WsSender,
+}
+
+impl WebSocket {
+    /// Next message from the client; `None` once the socket is closed.
+    pub async fn recv(&mut self) -> Option<Message> {
+        self.incoming.recv().await
+    }
+
+    /// Queue `msg`. `false` once the socket is closed.
+    pub async fn send(&self, msg: impl Into<Message>) -> bool {
+        self.sender.send(msg).await
+    }
+
+    /// Close the socket after the messages already queued.
+    pub async fn close(&self, code: u16, reason: &str) -> bool {
+        self.sender.close(code, reason).await
+    }
+
+    /// A sender for other tasks; the socket stays open while one is alive.
+    pub fn sender(&self) -> WsSender {
+        self.sender.clone()
+    }
+}
+
+/// The session of an accepted upgrade, run by the server after the 101.
+pub(crate) type Session =
+    Box<dyn FnOnce(WebSocket) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send>;
+
+/// Accept `req` as a WebSocket upgrade and run `session` on the socket.
+/// Requests that are not a valid version 13 upgrade get 426 (missing
+/// `Upgrade`/`Connection` headers or another version) or 400.
+pub fn upgrade<F, Fut>(req: &Request<'_>, session: F) -> WriteFn
+where
+    F: FnOnce(WebSocket) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    match handshake(req) {
+        Ok(accept) => {
+            let session: Session = Box::new(move |socket| Box::pin(session(socket)));
+            Box::new(move |res: &mut Response| res.upgrade(&accept, session))
+        }
+        Err(rejection) => Box::new(move |res: &mut Response| {
+            if rejection.status() == 426 {
+                res.header(b"Sec-WebSocket-Version", b"13");
+            }
+            rejection.write(0, res)
+        }),
+    }
+}
+
+/// The `Sec-WebSocket-Accept` value for a valid upgrade request.
+fn handshake(req: &Request<'_>) -> Result<String, Rejection> {
+    let has = |name: &[u8], token: &[u8]| {
+        req.header(name).is_some_and(|value| {
+            value
+                .split(|&b| b == b',')
+                .any(|t| t.trim_ascii().eq_ignore_ascii_case(token))
+        })
+    };
+    if req.method != tachyon_http::methods::Method::Get
+        || req.version_minor != 1
+        || !has(b"upgrade", b"websocket")
+        || !has(b"connection", b"upgrade")
+        || req.header(b"sec-websocket-version") != Some(b"13")
+    {
+        return Err(Rejection::new(426, "websocket upgrade required"));
+    }
+    match req.header(b"sec-websocket-key") {
+        Some(key) if key.len() == 24 => Ok(base64(&sha1(&[key, GUID].concat()))),
+        _ => Err(Rejection::new(400, "invalid Sec-WebSocket-Key")),
+    }
+}
+
+/// Run an upgraded connection: `leftover` holds bytes read past the
+/// handshake, `closing` resolves when the server wants the socket closed.
+pub(crate) async fn serve<S: Transport>(
+    stream: &mut S,
+    leftover: Vec<u8>,
+    session: Session,
+    config: &ServerConfig,
+    closing: impl Future<Output = ()>,
+) {
+    let (in_tx, in_rx) = mpsc::channel(QUEUE);
+    let (out_tx, mut out_rx) = mpsc::channel(QUEUE);
+    tokio::spawn(session(WebSocket {
+        incoming: in_rx,
+        sender: WsSender(out_tx),
+    }));
+    tokio::pin!(closing);
+
+    let mut reader = Reader {
+        buf: leftover,
+        partial: None,
+    };
+    let mut buf = vec![0u8; 8 * 1024];
+    loop {
+        match reader.next() {
+            Ok(Some(Frame::Ping(payload))) => {
+                if !write_within(stream, &frame(0xA, &payload), config).await {
+                    return;
+                }
+                continue;
+            }
+            Ok(Some(Frame::Message(Message::Close(code, reason)))) => {
+                let reply = if code == 1005 {
+                    Vec::new()
+                } else {
+                    close_payload(code, "")
+                };
+                let _ = in_tx.try_send(Message::Close(code, reason));
+                write_within(stream, &frame(0x8, &reply), config).await;
+                return;
+            }
+            Ok(Some(Frame::Message(msg))) => {
+                // Keep sending while the session catches up on reading.
+                let permit = loop {
+                    tokio::select! {
+                        permit = in_tx.reserve() => break permit.ok(),
+                        msg = out_rx.recv() => {
+                            if !send(stream, msg, config).await {
+                                return;
+                            }
+                        }
+                    }
+                };
+                if let Some(permit) = permit {
+                    permit.send(msg);
+                }
+                continue;
+            }
+            Ok(None) => {}
+            Err(code) => {
+                write_within(stream, &frame(0x8, &close_payload(code, "")), config).await;
+                return;
+            }
+        }
+        tokio::select! {
+            read = stream.read(&mut buf) => match read {
+                Ok(n) if n > 0 => reader.buf.extend_from_slice(&buf[..n]),
+                _ => return,
+            },
+            msg = out_rx.recv() => {
+                if !send(stream, msg, config).await {
+                    return;
+                }
+            }
+            () = &mut closing => {
+                write_within(stream, &frame(0x8, &close_payload(1001, "")), config).await;
+                return;
+            }
+        }
+    }
+}
+
+/// Write one message from the session. `false` once the socket is done:
+/// it was closed, every sender is gone, or the write failed.
+async fn send<S: Transport>(stream: &mut S, msg: Option<Message>, config: &ServerConfig) -> bool {
+    let (opcode, payload) = match msg {
+        Some(Message::Text(text)) => (0x1, text.into_bytes()),
+        Some(Message::Binary(data)) => (0x2, data),
+        Some(Message::Close(code, reason)) => (0x8, close_payload(code, &reason)),
+        None => (0x8, close_payload(1000, "")),
+    };
+    write_within(stream, &frame(opcode, &payload), config).await && opcode != 0x8
+}
+
+/// A frame read from the client, past fragmentation.
+enum Frame {
+    Message(Message),
+    Ping(Vec<u8>),
+}
+
+/// Bytes read from the client and the message being reassembled.
+struct Reader {
+    buf: Vec<u8>,
+    partial: Option<(u8, Vec<u8>)>,
+}
+
+impl Reader {
+    /// The next complete frame, `Ok(None)` until more bytes arrive, or
+    /// the close code for a protocol violation.
+    fn next(&mut self) -> Result<Option<Frame>, u16> {
+        loop {
+            let Some((fin, opcode, payload)) = self.frame()? else {
+                return Ok(None);
+            };
+            match opcode {
+                0x8 => {
+                    let (code, reason) = match payload.split_first_chunk::<2>() {
+                        Some((code, reason)) => (
+                            u16::from_be_bytes(*code),
+                            String::from_utf8_lossy(reason).into_owned(),
+                        ),
+                        None => (1005, String::new()),
+                    };
+                    return Ok(Some(Frame::Message(Message::Close(code, reason))));
+                }
+                0x9 => return Ok(Some(Frame::Ping(payload))),
+                0xA => continue,
+                0x0 => {
+                    let Some((_, data)) = self.partial.as_mut() else {
+                        return Err(1002);
+                    };
+                    if data.len() + payload.len() > MAX_MESSAGE {
+                        return Err(1009);
+                    }
+                    data.extend_from_slice(&payload);
+                }
+                0x1 | 0x2 if self.partial.is_none() => self.partial = Some((opcode, payload)),
+                _ => return Err(1002),
+            }
+            if !fin {
+                continue;
+            }
+            let Some((opcode, data)) = self.partial.take() else {
+                continue;
+            };
+            let msg = match opcode {
+                0x1 => Message::Text(String::from_utf8(data).map_err(|_| 1007u16)?),
+                _ => Message::Binary(data),
+            };
+            return Ok(Some(Frame::Message(msg)));
+        }
+    }
+
+    /// Split one raw frame off the buffer: (fin, opcode, unmasked payload).
+    fn frame(&mut self) -> Result<Option<(bool, u8, Vec<u8>)>, u16> {
+        let buf = &self.buf;
+        let [b0, b1, ..] = buf[..] else {
+            return Ok(None);
+        };
+        let (fin, opcode) = (b0 & 0x80 != 0, b0 & 0x0f);
+        // Extensions are never negotiated, and clients must mask.
+        if b0 & 0x70 != 0 || b1 & 0x80 == 0 {
+            return Err(1002);
+        }
+        let (len, at) = match b1 & 0x7f {
+            126 => match buf.get(2..4) {
+                Some(len) => (u16::from_be_bytes([len[0], len[1]]) as u64, 4),
+                None => return Ok(None),
+            },
+            127 => match buf.get(2..10) {
+                Some(len) => (u64::from_be_bytes(len.try_into().unwrap()), 10),
+                None => return Ok(None),
+            },
+            len => (len as u64, 2),
+        };
+        if opcode >= 0x8 && (!fin || len > 125) {
+            return Err(1002);
+        }
+        if len > MAX_MESSAGE as u64 {
+            return Err(1009);
+        }
+        let end = at + 4 + len as usize;
+        if buf.len() < end {
+            return Ok(None);
+        }
+        let mask = [buf[at], buf[at + 1], buf[at + 2], buf[at + 3]];
+        let payload = buf[at + 4..end]
+            .iter()
+            .zip(mask.iter().cycle())
+            .map(|(b, m)| b ^ m)
+            .collect();
+        self.buf.drain(..end);
+        Ok(Some((fin, opcode, payload)))
+    }
+}
+
+/// An unmasked, unfragmented frame.
+fn frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(payload.len() + 10);
+    out.push(0x80 | opcode);
+    match payload.len() {
+        len @ 0..=125 => out.push(len as u8),
+        len @ 126..=0xffff => {
+            out.push(126);
+            out.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            out.push(127);
+            out.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    out.extend_from_slice(payload);
+    out
+}
+
+/// A close frame body; the reason is cut to fit in a control frame.
+fn close_payload(code: u16, reason: &str) -> Vec<u8> {
+    let mut end = reason.len().min(123);
+    while !reason.is_char_boundary(end) {
+        end -= 1;
+    }
+    [&code.to_be_bytes()[..], &reason.as_bytes()[..end]].concat()
+}
+
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    let mut msg = data.to_vec();
+    msg.push(0x80);
+    while msg.len() % 64 != 56 {
+        msg.push(0);
+    }
+    msg.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+    for block in msg.chunks_exact(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes(word.try_into().unwrap());
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let t = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            (e, d, c, b, a) = (d, c, b.rotate_left(30), a, t);
+        }
+        for (h, v) in h.iter_mut().zip([a, b, c, d, e]) {
+            *h = h.wrapping_add(v);
+        }
+    }
+    let mut out = [0u8; 20];
+    for (out, h) in out.chunks_exact_mut(4).zip(h) {
+        out.copy_from_slice(&h.to_be_bytes());
+    }
+    out
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i)) as usize & 63] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accept_key_matches_rfc_example() {
+        let key = b"dGhlIHNhbXBsZSBub25jZQ==";
+        assert_eq!(
+            base64(&sha1(&[&key[..], GUID].concat())),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+        assert_eq!(base64(b"ab"), "YWI=");
+    }
+
+    #[test]
+    fn reassembles_masked_fragments() {
+        let masked = |b0: u8, payload: &[u8]| {
+            let mask = [1, 2, 3, 4];
+            let mut out = vec![b0, 0x80 | payload.len() as u8];
+            out.extend_from_slice(&mask);
+            out.extend(payload.iter().zip(mask.iter().cycle()).map(|(b, m)| b ^ m));
+            out
+        };
+        let mut reader = Reader {
+            buf: [
+                masked(0x01, b"hel"),
+                masked(0x89, b"p"),
+                masked(0x80, b"lo"),
+            ]
+            .concat(),
+            partial: None,
+        };
+        assert!(matches!(reader.next(), Ok(Some(Frame::Ping(p))) if p == b"p"));
+        assert!(matches!(
+            reader.next(),
+            Ok(Some(Frame::Message(Message::Text(t)))) if t == "hello"
+        ));
+        assert!(matches!(reader.next(), Ok(None)));
+
+        reader.buf = masked(0x88, &close_payload(1000, "bye"));
+        assert!(matches!(
+            reader.next(),
+            Ok(Some(Frame::Message(Message::Close(1000, r)))) if r == "bye"
+        ));
+        reader.buf = vec![0x81, 0x01, b'x'];
+        assert!(matches!(reader.next(), Err(1002)));
+    }
+}
@@ -0,0 +1,159 @@
+//! `Transfer-Encoding: chunked` framing (RFC 9112 §7.1).
+//!
+//! The parser only *scans* chunked bodies to find where they end — no copy.
+//! Decoding into a contiguous body and walking the trailer section happen
+//! lazily, only when a handler asks for them.
+
+use crate::http::Header;
+use crate::utils::{find_crlf, parse_header};
+
+/// Result of scanning a chunked body.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Scan {
+    /// Body is complete. `trailers` is the offset of the trailer section,
+    /// `end` the total bytes consumed (including the final blank line).
+    Complete {
+        trailers: usize,
+        end: usize,
+    },
+    Incomplete,
+    Invalid,
+}
+
+/// Walk chunk-size lines to find the end of a chunked body starting at `buf[0]`.
+pub fn scan(buf: &[u8]) -> Scan {
+    let mut pos = 0;
+    loop {
+        let Some(line_end) = find_crlf(&buf[pos..]) else {
+            return Scan::Incomplete;
+        };
+        let Some(size) = parse_chunk_size(&buf[pos..pos + line_end]) else {
+            return Scan::Invalid;
+        };
+        pos += line_end + 2;
+
+        if size == 0 {
+            // Trailer section: header lines until an empty line.
+            let trailers = pos;
+            loop {
+                let Some(line_end) = find_crlf(&buf[pos..]) else {
+                    return Scan::Incomplete;
+                };
+                pos += line_end + 2;
+                if line_end == 0 {
+                    return Scan::Complete { trailers, end: pos };
+                }
+            }
+        }
+
+        let Some(data_end) = pos.checked_add(size) else {
+            return Scan::Invalid;
+        };
+        if buf.len() < data_end + 2 {
+            return Scan::Incomplete;
+        }
+        if &buf[data_end..data_end + 2] != b"\r\n" {
+            return Scan::Invalid;
+        }
+        pos = data_end + 2;
+    }
+}
+
+/// Concatenate the chunk payloads of a complete chunked body.
+/// `raw` must have been accepted by [`scan`].
+pub fn decode(raw: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(raw.len());
+    for payload in payloads(raw) {
+        out.extend_from_slice(payload);
+    }
+    out
+}
+
+/// Length of the body [`decode`] would return, without copying it.
+pub fn decoded_len(raw: &[u8]) -> usize {
+    payloads(raw).map(<[u8]>::len).sum()
+}
+
+/// The chunk payloads of a complete chunked body, in order.
+fn payloads(raw: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let mut pos = 0;
+    std::iter::from_fn(move || {
+        let line_end = find_crlf(&raw[pos..])?;
+        let size = parse_chunk_size(&raw[pos..pos + line_end]).unwrap_or(0);
+        pos += line_end + 2;
+        if size == 0 || pos + size > raw.len() {
+            return None;
+        }
+        let payload = &raw[pos..pos + size];
+        pos += size + 2;
+        Some(payload)
+    })
+}
+
+/// Iterator over trailer fields, borrowing from the request buffer.
+pub struct Trailers<'a> {
+    rest: &'a [u8],
+}
+
+impl<'a> Trailers<'a> {
+    pub fn new(section: &'a [u8]) -> Self {
+        Self { rest: section }
+    }
+
+    pub fn empty() -> Self {
+        Self { rest: &[] }
+    }
+}
+
+impl<'a> Iterator for Trailers<'a> {
+    type Item = Header<'a>;
+
+    fn next(&mut self) -> Option<Header<'a>> {
+        if self.rest.starts_with(b"\r\n") || self.rest.is_empty() {
+            return None;
+        }
+        let (header, rest) = parse_header(self.rest)?;
+        self.rest = rest;
+        Some(header)
+    }
+}
+
+/// Parse a chunk-size line: hex digits, optionally followed by `;ext`.
+fn parse_chunk_size(line: &[u8]) -> Option<usize> {
+    let digits = line
+        .iter()
+        .position(|&b| b == b';' || b == b' ' || b == b'\t')
+        .map_or(line, |i| &line[..i]);
+    if digits.is_empty() || digits.len() > 15 {
+        return None;
+    }
+    let mut size = 0usize;
+    for &b in digits {
+        let v = match b {
+            b'0'..=b'9' => b - b'0',
+            b'a'..=b'f' => b - b'a' + 10,
+            b'A'..=b'F' => b - b'A' + 10,
+            _ => return None,
+        };
+        size = (size << 4) | v as usize;
+    }
+    Some(size)
+}
+
+/// Append a chunk-size line (`<hex>\r\n`) to `out`.
+pub fn write_chunk_size(out: &mut Vec<u8>, size: usize) {
+    const HEX: [u8; 16] = *b"0123456789abcdef";
+    let mut tmp = [0u8; 16];
+    let mut i = tmp.len();
+    let mut v = size;
+    loop {
+        i -= 1;
+        tmp[i] = HEX[v & 0xf];
+        v >>= 4;
+        if v == 0 {
+            break;
+        }
+    }
+    out.extend_from_slice(&tmp[i..]);
+    out.extend_from_slice(b"\r\n");
+}
@@ -0,0 +1,110 @@
+use crate::utils::{eq_ignore_ascii_case, trim_ascii};
+
+/// Content codings the server knows how to produce.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Encoding {
+    /// No compression (default).
+    #[default]
+    Identity,
+    Gzip,
+    Brotli,
+    Zstd,
+}
+
+impl Encoding {
+    /// The `Accept-Encoding` / `Content-Encoding` token.
+    pub fn token(self) -> &'static [u8] {
+        match self {
+            Self::Identity => b"identity",
+            Self::Gzip => b"gzip",
+            Self::Brotli => b"br",
+            Self::Zstd => b"zstd",
+        }
+    }
+
+    /// The `Content-Encoding` header line alone (empty for identity).
+    pub fn content_encoding(self) -> &'static [u8] {
+        match self {
+            Self::Identity => b"",
+            Self::Gzip => crate::response::ENCODING_GZIP,
+            Self::Brotli => crate::response::ENCODING_BROTLI,
+            Self::Zstd => crate::response::ENCODING_ZSTD,
+        }
+    }
+
+    /// Pre-concatenated `Content-Encoding` + `Vary` headers — single memcpy per response.
+    pub fn headers(self) -> &'static [u8] {
+        match self {
+            Self::Identity => b"",
+            Self::Gzip => crate::response::GZIP_HEADERS,
+            Self::Brotli => crate::response::BROTLI_HEADERS,
+            Self::Zstd => crate::response::ZSTD_HEADERS,
+        }
+    }
+}
+
+/// Pick the best encoding from an `Accept-Encoding` header value.
+///
+/// `supported` is the server's preference order: among codings the client
+/// rates with the same q-value, the one listed first wins. Codings with
+/// `q=0` are refused, `*` covers anything not listed explicitly.
+/// Returns `Identity` when nothing acceptable is supported.
+pub fn negotiate(accept_encoding: &[u8], supported: &[Encoding]) -> Encoding {
+    let mut best = Encoding::Identity;
+    let mut best_q: u16 = 0;
+
+    for &enc in supported {
+        if enc == Encoding::Identity {
+            continue;
+        }
+        let q = quality_of(accept_encoding, enc.token());
+        if q > best_q {
+            best = enc;
+            best_q = q;
+        }
+    }
+    best
+}
+
+/// q-value (0..=1000) the client assigns to `token`, falling back to `*`.
+fn quality_of(accept_encoding: &[u8], token: &[u8]) -> u16 {
+    let mut wildcard: Option<u16> = None;
+    for item in accept_encoding.split(|&b| b == b',') {
+        let mut parts = item.split(|&b| b == b';');
+        let name = trim_ascii(parts.next().unwrap_or_default());
+        let q = parts
+            .map(trim_ascii)
+            .find_map(|p| p.strip_prefix(b"q=").or_else(|| p.strip_prefix(b"Q=")))
+            .map_or(1000, parse_qvalue);
+        if eq_ignore_ascii_case(name, token) {
+            return q;
+        }
+        if name == b"*" {
+            wildcard = Some(q);
+        }
+    }
+    wildcard.unwrap_or(0)
+}
+
+/// Parse an RFC 9110 qvalue ("1", "0.8", "0.125") into thousandths.
+fn parse_qvalue(v: &[u8]) -> u16 {
+    let mut it = v.iter();
+    let whole = match it.next() {
+        Some(b'1') => return 1000,
+        Some(b'0') => 0u16,
+        _ => return 0,
+    };
+    if it.next() != Some(&b'.') {
+        return whole;
+    }
+    let mut q = 0u16;
+    let mut scale = 100u16;
+    for &d in it.take(3) {
+        if !d.is_ascii_digit() {
+            break;
+        }
+        q += (d - b'0') as u16 * scale;
+        scale /= 10;
+    }
+    q
+}
@@ -0,0 +1,133 @@
+use crate::encoding::Encoding;
+use crate::utils::{trim_ascii, trim_ascii_start};
+
+/// ETag generation mode for buffered responses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EtagMode {
+    /// No ETag header, no conditional handling (default).
+    #[default]
+    Off,
+    /// Strong validator: `"<len>-<hash>"`, suffixed with the content coding
+    /// (`"<len>-<hash>-gzip"`) for compressed responses. Byte-for-byte
+    /// equality.
+    Strong,
+    /// Weak validator: `W/"<len>-<hash>"`. Semantic equivalence — survives
+    /// re-encoding (e.g. gzip), so it is the safer choice with compression.
+    Weak,
+}
+
+/// Max size: `W/"` (3) + 16 hex (len) + `-` + 16 hex (hash) + `-zstd` (5)
+/// + `"` = 42 bytes.
+const ETAG_MAX: usize = 48;
+
+/// A formatted ETag value, stored inline (no heap allocation).
+#[derive(Clone, Copy)]
+pub struct Etag {
+    buf: [u8; ETAG_MAX],
+    len: usize,
+}
+
+impl Etag {
+    /// Compute an ETag for `body`. Uses FNV-1a (64-bit) — not cryptographic,
+    /// but stable across restarts and fast enough to run on every response.
+    pub fn compute(body: &[u8], weak: bool) -> Self {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for &b in body {
+            hash ^= b as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+        Self::from_version(body.len() as u64, hash, weak)
+    }
+
+    /// An ETag for content known by its length and a version that changes
+    /// with it (e.g. a file's modification time), without reading it.
+    pub fn from_version(len: u64, version: u64, weak: bool) -> Self {
+        let mut buf = [0u8; ETAG_MAX];
+        let mut pos = 0;
+        if weak {
+            buf[..2].copy_from_slice(b"W/");
+            pos = 2;
+        }
+        buf[pos] = b'"';
+        pos += 1;
+        pos += write_hex(&mut buf[pos..], len);
+        buf[pos] = b'-';
+        pos += 1;
+        pos += write_hex(&mut buf[pos..], version);
+        buf[pos] = b'"';
+        pos += 1;
+        Self { buf, len: pos }
+    }
+
+    /// The tag of this content sent with `encoding`. Strong tags get the
+    /// coding as a suffix, since the bytes differ; weak ones are unchanged.
+    pub fn for_encoding(mut self, encoding: Encoding) -> Self {
+        if encoding == Encoding::Identity || self.buf.starts_with(b"W/") {
+            return self;
+        }
+        let token = encoding.token();
+        let quote = self.len - 1;
+        self.buf[quote] = b'-';
+        self.buf[quote + 1..quote + 1 + token.len()].copy_from_slice(token);
+        self.len += 1 + token.len();
+        self.buf[self.len - 1] = b'"';
+        self
+    }
+
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    /// Evaluate an `If-None-Match` header value against this ETag.
+    ///
+    /// Uses the weak comparison function (RFC 9110 §13.1.2): `W/` prefixes
+    /// are ignored on both sides. Handles `*` and comma-separated lists.
+    pub fn matches_if_none_match(&self, header: &[u8]) -> bool {
+        if_none_match(self.as_bytes(), header)
+    }
+}
+
+/// [`Etag::matches_if_none_match`] for an already formatted `etag`, e.g.
+/// one read back from a stored response.
+pub fn if_none_match(etag: &[u8], header: &[u8]) -> bool {
+    let header = trim_ascii_start(header);
+    if header == b"*" {
+        return true;
+    }
+    let ours = strip_weak(etag);
+    header
+        .split(|&b| b == b',')
+        .map(|tag| strip_weak(trim_ascii(tag)))
+        .any(|tag| tag == ours)
+}
+
+impl std::fmt::Debug for Etag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&String::from_utf8_lossy(self.as_bytes()))
+    }
+}
+
+#[inline]
+fn strip_weak(tag: &[u8]) -> &[u8] {
+    tag.strip_prefix(b"W/").unwrap_or(tag)
+}
+
+/// Lowercase hex without leading zeros. Returns bytes written (max 16).
+#[inline]
+fn write_hex(out: &mut [u8], mut v: u64) -> usize {
+    const HEX: [u8; 16] = *b"0123456789abcdef";
+    let mut tmp = [0u8; 16];
+    let mut i = tmp.len();
+    loop {
+        i -= 1;
+        tmp[i] = HEX[(v & 0xf) as usize];
+        v >>= 4;
+        if v == 0 {
+            break;
+        }
+    }
+    let n = tmp.len() - i;
+    out[..n].copy_from_slice(&tmp[i..]);
+    n
+}
@@ -1,4 +1,6 @@
-use crate::{methods::Method, utils::eq_ignore_ascii_case};
+use std::borrow::Cow;
+
+use crate::{chunked, methods::Method, utils::eq_ignore_ascii_case};
 
 /// Maximum number of headers we'll parse. FaF uses a similar fixed limit.
 pub const MAX_HEADERS: usize = 32;
@@ -22,9 +24,15 @@ pub struct Request<'a> {
     pub version_minor: u8, // 0 = HTTP/1.0, 1 = HTTP/1.1
     pub headers: [Option<Header<'a>>; MAX_HEADERS],
     pub header_count: usize,
+    /// Raw body bytes. For chunked requests this includes the chunk framing
+    /// and trailers — use `decoded_body()` / `trailers()` instead.
     pub body: &'a [u8],
     /// Offset where the body starts (for content-length validation)
     pub body_offset: usize,
+    /// `Transfer-Encoding: chunked` request.
+    pub chunked: bool,
+    /// Offset of the trailer section within `body` (chunked only).
+    pub trailer_offset: usize,
 }
 
 impl<'a> Request<'a> {
@@ -44,6 +52,50 @@ impl<'a> Request<'a> {
             .and_then(|s| s.trim().parse().ok())
     }
 
+    /// Whether the request declares `Transfer-Encoding: chunked`.
+    pub fn is_chunked_encoding(&self) -> bool {
+        self.header(b"transfer-encoding").is_some_and(|v| {
+            v.rsplit(|&b| b == b',').next().is_some_and(|last| {
+                eq_ignore_ascii_case(crate::utils::trim_ascii(last), b"chunked")
+            })
+        })
+    }
+
+    /// The request body with transfer framing removed. Borrowed for
+    /// Content-Length bodies; chunked bodies are decoded into a Vec.
+    pub fn decoded_body(&self) -> Cow<'a, [u8]> {
+        if self.chunked {
+            Cow::Owned(chunked::decode(self.body))
+        } else {
+            Cow::Borrowed(self.body)
+        }
+    }
+
+    /// Length of [`decoded_body`](Self::decoded_body), without decoding.
+    pub fn decoded_len(&self) -> usize {
+        if self.chunked {
+            chunked::decoded_len(self.body)
+        } else {
+            self.body.len()
+        }
+    }
+
+    /// Trailer fields sent after a chunked body. Empty for other requests.
+    pub fn trailers(&self) -> chunked::Trailers<'a> {
+        if self.chunked {
+            chunked::Trailers::new(&self.body[self.trailer_offset..])
+        } else {
+            chunked::Trailers::empty()
+        }
+    }
+
+    /// Find a trailer by name (case-insensitive).
+    pub fn trailer(&self, name: &[u8]) -> Option<&'a [u8]> {
+        self.trailers()
+            .find(|h| eq_ignore_ascii_case(h.name, name))
+            .map(|h| h.value)
+    }
+
     /// Path as UTF-8 string (most paths are ASCII, so this is cheap).
     pub fn path_str(&self) -> &str {
         // Safety: HTTP paths are required to be valid ASCII, and
@@ -52,23 +104,26 @@ impl<'a> Request<'a> {
         std::str::from_utf8(self.path).unwrap_or("/")
     }
 
-    /// Extract connection-related flags in a single pass over headers.
-    /// Returns (accepts_gzip, connection_close) to avoid scanning headers twice.
+    /// Extract connection-related headers in a single pass.
+    /// Returns (accept_encoding, connection_close) to avoid scanning headers twice.
     #[inline]
-    pub fn connection_flags(&self) -> (bool, bool) {
-        let mut gzip = false;
+    pub fn connection_flags(&self) -> (Option<&'a [u8]>, bool) {
+        let mut accept_encoding = None;
         let mut close = false;
         for h in self.headers[..self.header_count].iter().flatten() {
-            if !gzip && h.name.len() == 15 && eq_ignore_ascii_case(h.name, b"accept-encoding") {
-                gzip = h.value.windows(4).any(|w| w == b"gzip");
+            if accept_encoding.is_none()
+                && h.name.len() == 15
+                && eq_ignore_ascii_case(h.name, b"accept-encoding")
+            {
+                accept_encoding = Some(h.value);
             } else if !close && h.name.len() == 10 && eq_ignore_ascii_case(h.name, b"connection") {
                 close = h.value == b"close";
             }
-            if gzip && close {
+            if accept_encoding.is_some() && close {
                 break;
             }
         }
-        (gzip, close)
+        (accept_encoding, close)
     }
 
     /// Total bytes consumed by this request (headers + body).
@@ -215,6 +215,68 @@ impl<'a> JsonWriter<'a> {
 
 const HEX: [u8; 16] = *b"0123456789abcdef";
 
+/// Re-indent compact JSON for humans: two-space indent, one member per
+/// line, `": "` after keys, empty containers kept as `{}` / `[]`.
+/// Input is assumed valid; string contents are copied verbatim.
+pub fn pretty(json: &[u8]) -> Vec<u8> {
+    fn newline(out: &mut Vec<u8>, depth: usize) {
+        out.push(b'\n');
+        out.resize(out.len() + depth * 2, b' ');
+    }
+
+    let mut out = Vec::with_capacity(json.len() * 2);
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut i = 0;
+    while i < json.len() {
+        let b = json[i];
+        i += 1;
+        if in_string {
+            out.push(b);
+            if escaped {
+                escaped = false;
+            } else if b == b'\\' {
+                escaped = true;
+            } else if b == b'"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match b {
+            b'"' => {
+                in_string = true;
+                out.push(b);
+            }
+            b'{' | b'[' => {
+                let close = if b == b'{' { b'}' } else { b']' };
+                let next = json[i..].iter().position(|c| !c.is_ascii_whitespace());
+                if next.is_some_and(|n| json[i + n] == close) {
+                    out.extend_from_slice(&[b, close]);
+                    i += next.unwrap_or(0) + 1;
+                    continue;
+                }
+                depth += 1;
+                out.push(b);
+                newline(&mut out, depth);
+            }
+            b'}' | b']' => {
+                depth = depth.saturating_sub(1);
+                newline(&mut out, depth);
+                out.push(b);
+            }
+            b',' => {
+                out.push(b);
+                newline(&mut out, depth);
+            }
+            b':' => out.extend_from_slice(b": "),
+            b' ' | b'\t' | b'\n' | b'\r' => {}
+            _ => out.push(b),
+        }
+    }
+    out
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -252,7 +314,7 @@ mod tests {
             w.key("id").int(42);
             w.key("name").string("test");
             w.key("active").bool(true);
-            w.key("score").float(3.14);
+            w.key("score").float(2.5);
             w.key("tags").array(|w| {
                 w.string_raw("fast");
                 w.string_raw("zero-alloc");
@@ -263,7 +325,7 @@ mod tests {
         let s = std::str::from_utf8(&buf[..len]).unwrap();
         assert_eq!(
             s,
-            r#"{"id":42,"name":"test","active":true,"score":3.14,"tags":["fast","zero-alloc"],"meta":null}"#
+            r#"{"id":42,"name":"test","active":true,"score":2.5,"tags":["fast","zero-alloc"],"meta":null}"#
         );
     }
 
@@ -297,4 +359,13 @@ mod tests {
             r#"[{"id":1,"randomNumber":1000},{"id":2,"randomNumber":2000},{"id":3,"randomNumber":3000}]"#
         );
     }
+
+    #[test]
+    fn test_pretty() {
+        let compact = br#"{"a":[1,{"b":"x,:{\"}"}],"e":{},"f":[ ]}"#;
+        assert_eq!(
+            std::str::from_utf8(&pretty(compact)).unwrap(),
+            "{\n  \"a\": [\n    1,\n    {\n      \"b\": \"x,:{\\\"}\"\n    }\n  ],\n  \"e\": {},\n  \"f\": []\n}"
+        );
+    }
 }
@@ -4,7 +4,11 @@ mod utils;
 #[cfg(test)]
 mod tests;
 
+pub mod chunked;
+pub mod encoding;
+pub mod etag;
 pub mod http;
 pub mod json;
 pub mod parser;
 pub mod response;
+pub mod vary;
@@ -1,5 +1,5 @@
 /// HTTP method, stored as a tag to avoid string comparisons in routing.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Method {
     Get,
     Post,
@@ -10,3 +10,33 @@ pub enum Method {
     Options,
     Other,
 }
+
+impl Method {
+    /// Parse a method name (case-sensitive, as on the wire). Unknown → `Other`.
+    pub fn from_bytes(s: &[u8]) -> Self {
+        match s {
+            b"GET" => Self::Get,
+            b"POST" => Self::Post,
+            b"PUT" => Self::Put,
+            b"DELETE" => Self::Delete,
+            b"PATCH" => Self::Patch,
+            b"HEAD" => Self::Head,
+            b"OPTIONS" => Self::Options,
+            _ => Self::Other,
+        }
+    }
+
+    /// Zero-alloc method → static str.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Get => "GET",
+            Self::Post => "POST",
+            Self::Put => "PUT",
+            Self::Delete => "DELETE",
+            Self::Patch => "PATCH",
+            Self::Head => "HEAD",
+            Self::Options => "OPTIONS",
+            Self::Other => "OTHER",
+        }
+    }
+}
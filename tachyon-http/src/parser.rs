@@ -1,4 +1,5 @@
 use crate::{
+    chunked,
     http::{MAX_HEADERS, Request},
     utils::{find_header_end, parse_header, parse_method, parse_path, parse_version},
 };
@@ -12,15 +13,56 @@ pub enum ParseResult<'a> {
     Error(ParseError),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ParseError {
     InvalidMethod,
     InvalidPath,
     InvalidVersion,
+    /// More header fields than [`Limits::max_headers`].
     HeadersTooLong,
+    /// A header line longer than [`Limits::max_header_size`].
+    HeaderTooLarge,
+    /// A request target longer than [`Limits::max_uri`].
+    UriTooLong,
     MalformedHeader,
+    InvalidChunk,
+}
+
+impl ParseError {
+    /// Status to answer with: 414 or 431 for the size limits, else 400.
+    pub fn status(self) -> u16 {
+        match self {
+            ParseError::UriTooLong => 414,
+            ParseError::HeadersTooLong | ParseError::HeaderTooLarge => 431,
+            _ => 400,
+        }
+    }
+}
+
+/// Size limits on the request head.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Most header fields per request (default and maximum: [`MAX_HEADERS`]).
+    pub max_headers: usize,
+    /// Longest header line, `Name: value`, in bytes (default: 8 KiB).
+    pub max_header_size: usize,
+    /// Longest request target, path plus query, in bytes (default: 8 KiB).
+    pub max_uri: usize,
 }
 
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_headers: MAX_HEADERS,
+            max_header_size: 8 * 1024,
+            max_uri: 8 * 1024,
+        }
+    }
+}
+
+/// Request-line bytes besides the target: method, spaces, version, CRLF.
+const REQUEST_LINE_SLACK: usize = 32;
+
 /// Parse a complete HTTP request from a byte buffer.
 ///
 /// # Zero-copy guarantee
@@ -32,10 +74,22 @@ pub enum ParseError {
 /// Use `Request::consumed()` to find where the next pipelined request starts.
 #[inline]
 pub fn parse(buf: &[u8]) -> ParseResult<'_> {
+    parse_with(buf, &Limits::default())
+}
+
+/// [`parse`] with custom head [`Limits`]. An oversized request line or
+/// header is refused as soon as it is seen, before the head is complete.
+#[inline]
+pub fn parse_with<'a>(buf: &'a [u8], limits: &Limits) -> ParseResult<'a> {
     // Find end of headers (double CRLF)
     let header_end = match find_header_end(buf) {
         Some(pos) => pos,
-        None => return ParseResult::Incomplete,
+        None => {
+            return match check_partial(buf, limits) {
+                Some(e) => ParseResult::Error(e),
+                None => ParseResult::Incomplete,
+            };
+        }
     };
 
     let header_section = &buf[..header_end + 2];
@@ -50,6 +104,9 @@ pub fn parse(buf: &[u8]) -> ParseResult<'_> {
         Some(r) => r,
         None => return ParseResult::Error(ParseError::InvalidPath),
     };
+    if path.len() > limits.max_uri {
+        return ParseResult::Error(ParseError::UriTooLong);
+    }
 
     let (version_minor, rest) = match parse_version(rest) {
         Some(r) => r,
@@ -60,14 +117,18 @@ pub fn parse(buf: &[u8]) -> ParseResult<'_> {
     let mut headers = [None; MAX_HEADERS];
     let mut header_count = 0;
     let mut remaining = rest;
+    let max_headers = limits.max_headers.min(MAX_HEADERS);
 
     while !remaining.is_empty() && remaining != b"\r\n" {
-        if header_count >= MAX_HEADERS {
+        if header_count >= max_headers {
             return ParseResult::Error(ParseError::HeadersTooLong);
         }
 
         match parse_header(remaining) {
             Some((header, rest)) => {
+                if remaining.len() - rest.len() > limits.max_header_size + 2 {
+                    return ParseResult::Error(ParseError::HeaderTooLarge);
+                }
                 headers[header_count] = Some(header);
                 header_count += 1;
                 remaining = rest;
@@ -93,8 +154,25 @@ pub fn parse(buf: &[u8]) -> ParseResult<'_> {
         header_count,
         body: &[],
         body_offset,
+        chunked: false,
+        trailer_offset: 0,
     };
 
+    // Chunked framing wins over Content-Length (RFC 9112 §6.3). Scan to find
+    // where the body ends; decoding is deferred to `Request::decoded_body()`.
+    if req.is_chunked_encoding() {
+        match chunked::scan(&buf[body_offset..]) {
+            chunked::Scan::Complete { trailers, end } => {
+                req.chunked = true;
+                req.trailer_offset = trailers;
+                req.body = &buf[body_offset..body_offset + end];
+                return ParseResult::Complete(Box::new(req));
+            }
+            chunked::Scan::Incomplete => return ParseResult::Incomplete,
+            chunked::Scan::Invalid => return ParseResult::Error(ParseError::InvalidChunk),
+        }
+    }
+
     // Determine body length from Content-Length header (for pipelining support).
     // Without Content-Length, GET/HEAD/DELETE have no body; others consume all remaining bytes.
     let content_length = req.content_length().unwrap_or(0);
@@ -109,3 +187,25 @@ pub fn parse(buf: &[u8]) -> ParseResult<'_> {
 
     ParseResult::Complete(Box::new(req))
 }
+
+/// Limit checks on a head that has not fully arrived yet.
+fn check_partial(buf: &[u8], limits: &Limits) -> Option<ParseError> {
+    let mut lines = buf.split(|&b| b == b'\n');
+    let request_line = lines.next()?;
+    if request_line.len() > limits.max_uri + REQUEST_LINE_SLACK {
+        return Some(ParseError::UriTooLong);
+    }
+    let mut complete = 0;
+    for line in lines {
+        // Includes the `\r`; the last line may still be arriving.
+        if line.len() > limits.max_header_size + 1 {
+            return Some(ParseError::HeaderTooLarge);
+        }
+        complete += 1;
+    }
+    // The last piece is unterminated, so it is not a header yet.
+    if complete > limits.max_headers.min(MAX_HEADERS) + 1 {
+        return Some(ParseError::HeadersTooLong);
+    }
+    None
+}
@@ -1,25 +1,170 @@
 // Pre-formatted HTTP response headers. FaF keeps these as compile-time
 // constants to avoid any formatting at runtime.
 
+pub const STATUS_101: &[u8] = b"HTTP/1.1 101 Switching Protocols\r\n";
 pub const STATUS_200: &[u8] = b"HTTP/1.1 200 OK\r\n";
 pub const STATUS_201: &[u8] = b"HTTP/1.1 201 Created\r\n";
+pub const STATUS_202: &[u8] = b"HTTP/1.1 202 Accepted\r\n";
 pub const STATUS_204: &[u8] = b"HTTP/1.1 204 No Content\r\n";
+pub const STATUS_206: &[u8] = b"HTTP/1.1 206 Partial Content\r\n";
+pub const STATUS_301: &[u8] = b"HTTP/1.1 301 Moved Permanently\r\n";
+pub const STATUS_302: &[u8] = b"HTTP/1.1 302 Found\r\n";
+pub const STATUS_303: &[u8] = b"HTTP/1.1 303 See Other\r\n";
+pub const STATUS_304: &[u8] = b"HTTP/1.1 304 Not Modified\r\n";
+pub const STATUS_307: &[u8] = b"HTTP/1.1 307 Temporary Redirect\r\n";
+pub const STATUS_308: &[u8] = b"HTTP/1.1 308 Permanent Redirect\r\n";
 pub const STATUS_400: &[u8] = b"HTTP/1.1 400 Bad Request\r\n";
+pub const STATUS_401: &[u8] = b"HTTP/1.1 401 Unauthorized\r\n";
+pub const STATUS_403: &[u8] = b"HTTP/1.1 403 Forbidden\r\n";
 pub const STATUS_404: &[u8] = b"HTTP/1.1 404 Not Found\r\n";
+pub const STATUS_405: &[u8] = b"HTTP/1.1 405 Method Not Allowed\r\n";
+pub const STATUS_408: &[u8] = b"HTTP/1.1 408 Request Timeout\r\n";
+pub const STATUS_409: &[u8] = b"HTTP/1.1 409 Conflict\r\n";
+pub const STATUS_410: &[u8] = b"HTTP/1.1 410 Gone\r\n";
+pub const STATUS_412: &[u8] = b"HTTP/1.1 412 Precondition Failed\r\n";
+pub const STATUS_413: &[u8] = b"HTTP/1.1 413 Payload Too Large\r\n";
+pub const STATUS_414: &[u8] = b"HTTP/1.1 414 URI Too Long\r\n";
+pub const STATUS_415: &[u8] = b"HTTP/1.1 415 Unsupported Media Type\r\n";
+pub const STATUS_416: &[u8] = b"HTTP/1.1 416 Range Not Satisfiable\r\n";
+pub const STATUS_422: &[u8] = b"HTTP/1.1 422 Unprocessable Entity\r\n";
+pub const STATUS_426: &[u8] = b"HTTP/1.1 426 Upgrade Required\r\n";
+pub const STATUS_429: &[u8] = b"HTTP/1.1 429 Too Many Requests\r\n";
+pub const STATUS_431: &[u8] = b"HTTP/1.1 431 Request Header Fields Too Large\r\n";
 pub const STATUS_500: &[u8] = b"HTTP/1.1 500 Internal Server Error\r\n";
+pub const STATUS_501: &[u8] = b"HTTP/1.1 501 Not Implemented\r\n";
+pub const STATUS_502: &[u8] = b"HTTP/1.1 502 Bad Gateway\r\n";
+pub const STATUS_503: &[u8] = b"HTTP/1.1 503 Service Unavailable\r\n";
+pub const STATUS_504: &[u8] = b"HTTP/1.1 504 Gateway Timeout\r\n";
+
+/// Map a status code to its pre-formatted status line.
+/// Unknown codes fall back to 500 — we never format status lines at runtime.
+#[inline]
+pub fn status_line(code: u16) -> &'static [u8] {
+    match code {
+        101 => STATUS_101,
+        200 => STATUS_200,
+        201 => STATUS_201,
+        202 => STATUS_202,
+        204 => STATUS_204,
+        206 => STATUS_206,
+        301 => STATUS_301,
+        302 => STATUS_302,
+        303 => STATUS_303,
+        304 => STATUS_304,
+        307 => STATUS_307,
+        308 => STATUS_308,
+        400 => STATUS_400,
+        401 => STATUS_401,
+        403 => STATUS_403,
+        404 => STATUS_404,
+        405 => STATUS_405,
+        408 => STATUS_408,
+        409 => STATUS_409,
+        410 => STATUS_410,
+        412 => STATUS_412,
+        413 => STATUS_413,
+        414 => STATUS_414,
+        415 => STATUS_415,
+        416 => STATUS_416,
+        422 => STATUS_422,
+        426 => STATUS_426,
+        429 => STATUS_429,
+        431 => STATUS_431,
+        501 => STATUS_501,
+        502 => STATUS_502,
+        503 => STATUS_503,
+        504 => STATUS_504,
+        _ => STATUS_500,
+    }
+}
 
 pub const CONTENT_JSON: &[u8] = b"Content-Type: application/json\r\n";
 pub const CONTENT_HTML: &[u8] = b"Content-Type: text/html; charset=utf-8\r\n";
 pub const CONTENT_TEXT: &[u8] = b"Content-Type: text/plain; charset=utf-8\r\n";
+pub const CONTENT_PROBLEM_JSON: &[u8] = b"Content-Type: application/problem+json\r\n";
+pub const CONTENT_OCTET: &[u8] = b"Content-Type: application/octet-stream\r\n";
 pub const CONNECTION_CLOSE: &[u8] = b"Connection: close\r\n";
 pub const CONNECTION_KEEP: &[u8] = b"Connection: keep-alive\r\n";
 pub const ENCODING_GZIP: &[u8] = b"Content-Encoding: gzip\r\n";
+pub const ENCODING_BROTLI: &[u8] = b"Content-Encoding: br\r\n";
+pub const ENCODING_ZSTD: &[u8] = b"Content-Encoding: zstd\r\n";
 pub const VARY_ACCEPT_ENCODING: &[u8] = b"Vary: Accept-Encoding\r\n";
 /// Pre-concatenated gzip headers — single memcpy instead of two.
 pub const GZIP_HEADERS: &[u8] = b"Content-Encoding: gzip\r\nVary: Accept-Encoding\r\n";
+pub const BROTLI_HEADERS: &[u8] = b"Content-Encoding: br\r\nVary: Accept-Encoding\r\n";
+pub const ZSTD_HEADERS: &[u8] = b"Content-Encoding: zstd\r\nVary: Accept-Encoding\r\n";
+pub const ETAG_PREFIX: &[u8] = b"ETag: ";
+pub const TRANSFER_CHUNKED: &[u8] = b"Transfer-Encoding: chunked\r\n";
 pub const CRLF: &[u8] = b"\r\n";
 
+/// Build a `Content-Disposition: attachment` header value for `filename`.
+///
+/// Always emits an ASCII `filename="..."` fallback (non-ASCII, quotes,
+/// backslashes and control chars replaced by `_`). When the name isn't plain
+/// ASCII, adds `filename*=UTF-8''<pct-encoded>` (RFC 6266 / RFC 5987) so
+/// modern clients get the exact name.
+pub fn content_disposition(filename: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(32 + filename.len() * 2);
+    out.extend_from_slice(b"attachment; filename=\"");
+    let mut plain = true;
+    for &b in filename.as_bytes() {
+        if b.is_ascii_graphic() && b != b'"' && b != b'\\' || b == b' ' {
+            out.push(b);
+        } else {
+            plain = false;
+            // One `_` per char, not per byte: skip UTF-8 continuation bytes.
+            if b & 0xC0 != 0x80 {
+                out.push(b'_');
+            }
+        }
+    }
+    out.push(b'"');
+    if !plain {
+        out.extend_from_slice(b"; filename*=UTF-8''");
+        for &b in filename.as_bytes() {
+            // RFC 5987 attr-char
+            if b.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&b) {
+                out.push(b);
+            } else {
+                const HEX: [u8; 16] = *b"0123456789ABCDEF";
+                out.extend_from_slice(&[b'%', HEX[(b >> 4) as usize], HEX[(b & 0xf) as usize]]);
+            }
+        }
+    }
+    out
+}
+
+/// Statuses that never carry a message body (RFC 9110 §6.4.1):
+/// 1xx, 204 No Content and 304 Not Modified.
+#[inline]
+pub fn is_bodiless_status(code: u16) -> bool {
+    (100..200).contains(&code) || code == 204 || code == 304
+}
+
 const CL_PREFIX: &[u8] = b"Content-Length: ";
+const CT_PREFIX: &[u8] = b"Content-Type: ";
+
+/// Build a `Content-Type: <mime>\r\n` header line.
+///
+/// Common types resolve to the pre-formatted constants (no allocation);
+/// anything else is formatted once into a small Vec.
+pub fn content_type_header(mime: &str) -> std::borrow::Cow<'static, [u8]> {
+    use std::borrow::Cow;
+    match mime {
+        "application/json" => Cow::Borrowed(CONTENT_JSON),
+        "text/html; charset=utf-8" => Cow::Borrowed(CONTENT_HTML),
+        "text/plain; charset=utf-8" => Cow::Borrowed(CONTENT_TEXT),
+        "application/octet-stream" => Cow::Borrowed(CONTENT_OCTET),
+        _ => {
+            let mut h = Vec::with_capacity(CT_PREFIX.len() + mime.len() + 2);
+            h.extend_from_slice(CT_PREFIX);
+            // Strip CR/LF so a handler-supplied type can't inject headers.
+            h.extend(mime.bytes().filter(|&b| b != b'\r' && b != b'\n'));
+            h.extend_from_slice(CRLF);
+            Cow::Owned(h)
+        }
+    }
+}
 
 /// Write "Content-Length: NNN\r\n" directly into `out` with zero heap allocation.
 /// Returns the number of bytes written.
@@ -205,3 +350,47 @@ pub fn write_response_vec(
     buf.extend_from_slice(body);
     buf
 }
+
+/// Calculate the size of a bodiless response (no Content-Type, no Content-Length).
+#[inline]
+pub fn empty_response_size(
+    status: &[u8],
+    security_headers: &[u8],
+    custom_headers: &[u8],
+    date_header: &[u8],
+) -> usize {
+    status.len()
+        + CONNECTION_KEEP.len()
+        + date_header.len()
+        + security_headers.len()
+        + custom_headers.len()
+        + CRLF.len()
+}
+
+/// Write a bodiless response (e.g. 304 Not Modified) into a buffer, returning
+/// bytes written. Content-Type and Content-Length are omitted: for a 304 they
+/// would describe the cached representation, not this message.
+///
+/// **Caller must ensure `buf` is large enough** — use `empty_response_size()` to check.
+#[inline]
+pub fn write_empty_response(
+    buf: &mut [u8],
+    status: &[u8],
+    security_headers: &[u8],
+    custom_headers: &[u8],
+    date_header: &[u8],
+) -> usize {
+    let mut pos = 0;
+    for src in [
+        status,
+        CONNECTION_KEEP,
+        date_header,
+        security_headers,
+        custom_headers,
+        CRLF,
+    ] {
+        buf[pos..pos + src.len()].copy_from_slice(src);
+        pos += src.len();
+    }
+    pos
+}
@@ -1,7 +1,10 @@
 use crate::{
+    encoding::Encoding,
+    etag::Etag,
     methods::Method,
-    parser::{ParseResult, parse},
+    parser::{Limits, ParseError, ParseResult, parse, parse_with},
     response,
+    vary::Vary,
 };
 
 #[test]
@@ -138,3 +141,200 @@ fn write_response_with_date_header() {
     assert!(resp.contains("Date: Mon, 16 Mar 2026 12:00:00 GMT"));
     assert!(resp.ends_with("{}"));
 }
+
+#[test]
+fn etag_strong_and_weak() {
+    let strong = Etag::compute(b"hello", false);
+    let weak = Etag::compute(b"hello", true);
+    assert!(strong.as_bytes().starts_with(b"\"5-"));
+    assert!(strong.as_bytes().ends_with(b"\""));
+    assert!(weak.as_bytes().starts_with(b"W/\"5-"));
+    assert_eq!(&weak.as_bytes()[2..], strong.as_bytes());
+    assert_ne!(
+        Etag::compute(b"hello", false).as_bytes(),
+        Etag::compute(b"hellp", false).as_bytes()
+    );
+}
+
+#[test]
+fn etag_varies_by_encoding() {
+    let strong = Etag::compute(b"hello", false);
+    let gzip = strong.for_encoding(Encoding::Gzip);
+    assert_eq!(
+        gzip.as_bytes(),
+        [
+            &strong.as_bytes()[..strong.as_bytes().len() - 1],
+            b"-gzip\""
+        ]
+        .concat()
+    );
+    assert!(!gzip.matches_if_none_match(strong.as_bytes()));
+    assert_eq!(
+        strong.for_encoding(Encoding::Identity).as_bytes(),
+        strong.as_bytes()
+    );
+    let weak = Etag::compute(b"hello", true);
+    assert_eq!(
+        weak.for_encoding(Encoding::Zstd).as_bytes(),
+        weak.as_bytes()
+    );
+}
+
+#[test]
+fn etag_if_none_match() {
+    let tag = Etag::compute(b"{\"ok\":true}", false);
+    let value = std::str::from_utf8(tag.as_bytes()).unwrap();
+    assert!(tag.matches_if_none_match(value.as_bytes()));
+    assert!(tag.matches_if_none_match(format!("W/{}", value).as_bytes()));
+    assert!(tag.matches_if_none_match(format!("\"nope\", {} ", value).as_bytes()));
+    assert!(tag.matches_if_none_match(b"*"));
+    assert!(!tag.matches_if_none_match(b"\"nope\""));
+}
+
+#[test]
+fn write_empty_response_has_no_length() {
+    let mut buf = [0u8; 4096];
+    let n = response::write_empty_response(
+        &mut buf,
+        response::status_line(304),
+        response::SECURITY_NONE,
+        b"ETag: \"1-2\"\r\n",
+        b"",
+    );
+    let resp = std::str::from_utf8(&buf[..n]).unwrap();
+    assert!(resp.starts_with("HTTP/1.1 304 Not Modified"));
+    assert!(resp.contains("ETag: \"1-2\""));
+    assert!(!resp.contains("Content-Length"));
+    assert!(resp.ends_with("\r\n\r\n"));
+}
+
+#[test]
+fn content_type_header_fast_path_and_custom() {
+    assert_eq!(
+        &*response::content_type_header("application/json"),
+        response::CONTENT_JSON
+    );
+    assert_eq!(
+        &*response::content_type_header("image/png"),
+        b"Content-Type: image/png\r\n"
+    );
+    assert_eq!(
+        &*response::content_type_header("text/css\r\nX-Evil: 1"),
+        b"Content-Type: text/cssX-Evil: 1\r\n"
+    );
+}
+
+#[test]
+fn negotiate_encoding() {
+    use crate::encoding::{Encoding, negotiate};
+    let all = [Encoding::Zstd, Encoding::Brotli, Encoding::Gzip];
+    assert_eq!(negotiate(b"gzip, deflate, br", &all), Encoding::Brotli);
+    assert_eq!(
+        negotiate(b"gzip, deflate, br", &[Encoding::Gzip]),
+        Encoding::Gzip
+    );
+    assert_eq!(negotiate(b"br;q=0.5, gzip;q=0.8", &all), Encoding::Gzip);
+    assert_eq!(negotiate(b"br;q=0, gzip;q=0", &all), Encoding::Identity);
+    assert_eq!(negotiate(b"*", &all), Encoding::Zstd);
+    assert_eq!(negotiate(b"*;q=0.1, zstd;q=0", &all), Encoding::Brotli);
+    assert_eq!(negotiate(b"identity", &all), Encoding::Identity);
+    assert_eq!(negotiate(b"GZIP", &all), Encoding::Gzip);
+}
+
+#[test]
+fn parse_chunked_with_trailers() {
+    let raw = b"POST /upload HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n\
+5\r\nhello\r\n6;ext=1\r\n world\r\n0\r\nChecksum: abc\r\nGrpc-Status: 0\r\n\r\n\
+GET /next HTTP/1.1\r\n\r\n";
+    match parse(raw) {
+        ParseResult::Complete(req) => {
+            assert!(req.chunked);
+            assert_eq!(&*req.decoded_body(), b"hello world");
+            assert_eq!(req.decoded_len(), 11);
+            assert_eq!(req.trailer(b"checksum"), Some(b"abc".as_ref()));
+            assert_eq!(req.trailers().count(), 2);
+            assert!(raw[req.consumed()..].starts_with(b"GET /next"));
+        }
+        other => panic!("Expected Complete, got {:?}", other),
+    }
+}
+
+#[test]
+fn parse_chunked_incomplete_and_invalid() {
+    let partial = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhel";
+    assert!(matches!(parse(partial), ParseResult::Incomplete));
+    let bad = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\nzz\r\n";
+    assert!(matches!(parse(bad), ParseResult::Error(_)));
+}
+
+#[test]
+fn head_limits() {
+    let limits = Limits {
+        max_headers: 2,
+        max_header_size: 16,
+        max_uri: 10,
+    };
+    let error = |raw: &[u8]| match parse_with(raw, &limits) {
+        ParseResult::Error(e) => Some(e),
+        _ => None,
+    };
+    assert_eq!(error(b"GET /short HTTP/1.1\r\nA: 1\r\nB: 2\r\n\r\n"), None);
+    assert_eq!(
+        error(b"GET /much/too/long HTTP/1.1\r\n\r\n"),
+        Some(ParseError::UriTooLong)
+    );
+    assert_eq!(
+        error(b"GET / HTTP/1.1\r\nA: 1\r\nB: 2\r\nC: 3\r\n\r\n"),
+        Some(ParseError::HeadersTooLong)
+    );
+    assert_eq!(
+        error(b"GET / HTTP/1.1\r\nCookie: 0123456789abcdef\r\n\r\n"),
+        Some(ParseError::HeaderTooLarge)
+    );
+    // Refused before the head is complete.
+    assert_eq!(
+        error(b"GET / HTTP/1.1\r\nCookie: 0123456789abcdef"),
+        Some(ParseError::HeaderTooLarge)
+    );
+    assert_eq!(error(&[b'a'; 64]), Some(ParseError::UriTooLong));
+    assert_eq!(error(b"GET / HTTP/1.1\r\nA: 1\r\nB: 2\r\nC"), None);
+    assert_eq!(ParseError::UriTooLong.status(), 414);
+    assert_eq!(ParseError::HeaderTooLarge.status(), 431);
+    assert_eq!(ParseError::InvalidMethod.status(), 400);
+}
+
+#[test]
+fn content_disposition_encoding() {
+    assert_eq!(
+        response::content_disposition("report 2026.pdf"),
+        b"attachment; filename=\"report 2026.pdf\"".to_vec()
+    );
+    assert_eq!(
+        response::content_disposition("résumé \"final\".txt"),
+        "attachment; filename=\"r_sum_ _final_.txt\"; filename*=UTF-8''r%C3%A9sum%C3%A9%20%22final%22.txt"
+            .as_bytes()
+            .to_vec()
+    );
+    // CR/LF can never leak into the header
+    assert!(!response::content_disposition("a\r\nX: y").contains(&b'\n'));
+}
+
+#[test]
+fn vary_accumulates_members() {
+    let mut vary = Vary::new();
+    let mut out = Vec::new();
+    vary.write_header(&mut out);
+    assert!(out.is_empty());
+
+    vary.add(b"accept-encoding");
+    vary.add(b"X-Tenant, Accept-Encoding");
+    vary.add(b"x-tenant");
+    vary.add(b"Origin");
+    vary.write_header(&mut out);
+    assert_eq!(out, b"Vary: Accept-Encoding, Origin, X-Tenant\r\n");
+
+    vary.add(b"*");
+    out.clear();
+    vary.write_header(&mut out);
+    assert_eq!(out, b"Vary: *\r\n");
+}
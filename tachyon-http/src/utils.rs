@@ -129,3 +129,13 @@ pub fn trim_ascii_start(buf: &[u8]) -> &[u8] {
         .unwrap_or(buf.len());
     &buf[start..]
 }
+
+#[inline(always)]
+pub fn trim_ascii(buf: &[u8]) -> &[u8] {
+    let buf = trim_ascii_start(buf);
+    let end = buf
+        .iter()
+        .rposition(|&b| b != b' ' && b != b'\t')
+        .map_or(0, |i| i + 1);
+    &buf[..end]
+}
@@ -0,0 +1,96 @@
+use crate::utils::{eq_ignore_ascii_case, trim_ascii};
+
+/// Request headers the common negotiation features depend on, tracked as
+/// bits so the usual case never allocates.
+const KNOWN: [&[u8]; 6] = [
+    b"Accept-Encoding",
+    b"Accept",
+    b"Accept-Language",
+    b"Origin",
+    b"Cookie",
+    b"Authorization",
+];
+
+/// The set of request headers that influenced a response, rendered as a
+/// single `Vary` header. Members are de-duplicated case-insensitively;
+/// adding `*` collapses the set to `Vary: *`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Vary {
+    known: u8,
+    any: bool,
+    /// Other header names, `, `-separated.
+    custom: Vec<u8>,
+}
+
+impl Vary {
+    pub const fn new() -> Self {
+        Self {
+            known: 0,
+            any: false,
+            custom: Vec::new(),
+        }
+    }
+
+    /// Add one header name, or a comma-separated list as found in an
+    /// existing `Vary` value.
+    pub fn add(&mut self, names: &[u8]) {
+        for name in names.split(|&b| b == b',').map(trim_ascii) {
+            if name.is_empty() || name.iter().any(|&b| b == b'\r' || b == b'\n') {
+                continue;
+            }
+            if name == b"*" {
+                self.any = true;
+            } else if let Some(i) = KNOWN.iter().position(|k| eq_ignore_ascii_case(k, name)) {
+                self.known |= 1 << i;
+            } else if !self.contains(name) {
+                if !self.custom.is_empty() {
+                    self.custom.extend_from_slice(b", ");
+                }
+                self.custom.extend_from_slice(name);
+            }
+        }
+    }
+
+    /// Whether `name` is already a member.
+    pub fn contains(&self, name: &[u8]) -> bool {
+        if let Some(i) = KNOWN.iter().position(|k| eq_ignore_ascii_case(k, name)) {
+            return self.known & (1 << i) != 0;
+        }
+        self.custom
+            .split(|&b| b == b',')
+            .any(|m| eq_ignore_ascii_case(trim_ascii(m), name))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.known == 0 && !self.any && self.custom.is_empty()
+    }
+
+    /// Append the `Vary: ...\r\n` header line to `out` (nothing if empty).
+    pub fn write_header(&self, out: &mut Vec<u8>) {
+        if self.is_empty() {
+            return;
+        }
+        out.extend_from_slice(b"Vary: ");
+        if self.any {
+            out.extend_from_slice(b"*\r\n");
+            return;
+        }
+        let mut first = true;
+        for (i, name) in KNOWN.iter().enumerate() {
+            if self.known & (1 << i) != 0 {
+                if !first {
+                    out.extend_from_slice(b", ");
+                }
+                out.extend_from_slice(name);
+                first = false;
+            }
+        }
+        if !self.custom.is_empty() {
+            if !first {
+                out.extend_from_slice(b", ");
+            }
+            out.extend_from_slice(&self.custom);
+        }
+        out.extend_from_slice(b"\r\n");
+    }
+}
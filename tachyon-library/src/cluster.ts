@@ -0,0 +1,104 @@
+import cluster from "node:cluster";
+import type { TachyonRawRouteStats } from "@tachyon-rs/server";
+
+type StatsReply = { type: 'tachyon:stats', id: number, stats: Record<string, TachyonRawRouteStats> }
+
+let nextRequest = 0
+
+/** Whether this process should fork `size` workers instead of listening. */
+export function isPrimary(size?: number) {
+  return (size ?? 1) > 1 && cluster.isPrimary
+}
+
+/** Fork `size` workers running this script; one that dies is forked again. */
+export function fork(size: number) {
+  for (let i = 0; i < size; i++) cluster.fork()
+  cluster.on('exit', (worker) => {
+    if (!worker.exitedAfterDisconnect) cluster.fork()
+  })
+}
+
+/** In a worker, answer the primary's requests for `stats` and `close`. */
+export function serveWorker(stats: () => Record<string, TachyonRawRouteStats>, close: () => Promise<void>) {
+  if (!cluster.isWorker) return
+  process.on('message', (msg: { type?: string, id?: number }) => {
+    if (msg?.type === 'tachyon:stats') {
+      process.send?.({ type: 'tachyon:stats', id: msg.id, stats: stats() } satisfies StatsReply)
+    } else if (msg?.type === 'tachyon:close') {
+      void close().then(() => cluster.worker?.disconnect())
+    }
+  })
+}
+
+/** The route counters of every worker, added up. */
+export async function collectStats(): Promise<Record<string, TachyonRawRouteStats>> {
+  const workers = Object.values(cluster.workers ?? {}).filter((w) => w !== undefined)
+  const id = nextRequest++
+  const replies = await Promise.all(workers.map((worker) => new Promise<Record<string, TachyonRawRouteStats>>((resolve) => {
+    const onMessage = (msg: StatsReply) => {
+      if (msg?.type !== 'tachyon:stats' || msg.id !== id) return
+      worker.off('message', onMessage)
+      worker.off('exit', onExit)
+      resolve(msg.stats)
+    }
+    const onExit = () => {
+      worker.off('message', onMessage)
+      resolve({})
+    }
+    worker.on('message', onMessage)
+    worker.once('exit', onExit)
+    worker.send({ type: 'tachyon:stats', id })
+  })))
+  return mergeStats(replies)
+}
+
+/** Ask every worker to close, and wait until they have exited. */
+export async function closeWorkers() {
+  const workers = Object.values(cluster.workers ?? {}).filter((w) => w !== undefined)
+  await Promise.all(workers.map((worker) => new Promise<void>((resolve) => {
+    worker.once('exit', () => resolve())
+    worker.send({ type: 'tachyon:close' })
+  })))
+}
+
+/**
+ * Add up per-process route counters. Means are weighted by requests;
+ * percentiles and maxima are the highest of any process.
+ */
+export function mergeStats(all: Record<string, TachyonRawRouteStats>[]) {
+  const merged: Record<string, TachyonRawRouteStats> = {}
+  for (const stats of all) {
+    for (const [route, s] of Object.entries(stats)) {
+      const m = merged[route]
+      if (!m) {
+        merged[route] = { ...s, cache: s.cache && { ...s.cache, shardEntries: [...s.cache.shardEntries] } }
+        continue
+      }
+      const requests = m.requests + s.requests
+      m.meanMs = requests ? (m.meanMs * m.requests + s.meanMs * s.requests) / requests : 0
+      m.requests = requests
+      m.errors += s.errors
+      m.timeouts += s.timeouts
+      m.throughput += s.throughput
+      m.p50Ms = Math.max(m.p50Ms, s.p50Ms)
+      m.p90Ms = Math.max(m.p90Ms, s.p90Ms)
+      m.p99Ms = Math.max(m.p99Ms, s.p99Ms)
+      m.maxMs = Math.max(m.maxMs, s.maxMs)
+      m.bytesIn += s.bytesIn
+      m.bytesOut += s.bytesOut
+      m.maxBodyIn = Math.max(m.maxBodyIn, s.maxBodyIn)
+      if (m.cache && s.cache) {
+        m.cache.hits += s.cache.hits
+        m.cache.misses += s.cache.misses
+        const lookups = m.cache.hits + m.cache.misses
+        m.cache.hitRate = lookups ? m.cache.hits / lookups : 0
+        m.cache.entries += s.cache.entries
+        m.cache.evictions += s.cache.evictions
+        s.cache.shardEntries.forEach((n, i) => m.cache!.shardEntries[i] = (m.cache!.shardEntries[i] ?? 0) + n)
+      } else {
+        m.cache ??= s.cache
+      }
+    }
+  }
+  return merged
+}
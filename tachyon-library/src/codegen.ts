@@ -0,0 +1,119 @@
+/** A registered route, as listed by `routeList()`. */
+export interface RouteInfo {
+  method: string
+  path: string
+  /** From the route's `types` option */
+  types?: RouteTypes
+}
+
+/**
+ * TypeScript source for the types of a route, written into its client stub:
+ * `{ response: 'User', body: 'NewUser', queryParams: '{ notify?: string }' }`.
+ * Type arguments are gone by the time the route is registered, so these
+ * repeat them as text.
+ */
+export interface RouteTypes {
+  /** What the route answers with */
+  response?: string
+  /** The JSON request body (POST and PUT) */
+  body?: string
+  /** The query parameters */
+  queryParams?: string
+}
+
+/** Settings for `clientSource`. */
+export interface ClientOptions {
+  /** Name of the exported factory. Default: 'createClient' */
+  name?: string
+  /** Lines put first, to import the types routes name: `import type { User } from './types'` */
+  imports?: string[]
+}
+
+/** `GET /users/me` → `getUsersMe`, `GET /` → `getRoot`. */
+function stubName(route: RouteInfo) {
+  const words = route.path.split(/[^A-Za-z0-9]+/).filter(Boolean)
+  const tail = words.length ? words : ['root']
+  return route.method.toLowerCase() + tail.map((w) => w[0]!.toUpperCase() + w.slice(1)).join('')
+}
+
+const runtime = `export interface RequestOptions<P extends Record<string, string> = Record<string, string>, B = unknown> {
+  /** Query parameters */
+  queryParams?: P
+  /** Sent as JSON */
+  body?: B
+  headers?: Record<string, string>
+}
+
+export class ClientError extends Error {
+  constructor(readonly status: number, readonly body: string) {
+    super(\`HTTP \${status}\`)
+  }
+}
+`
+
+/**
+ * The type parameters and parameter list of `route`'s stub. Types recorded
+ * with the route are written in; the others stay type arguments for the
+ * caller: `client.postUsers<User, NewUser>({ body })`.
+ */
+function stubSignature(route: RouteInfo) {
+  const types = route.types ?? {}
+  const generics: string[] = []
+  const typeOf = (recorded: string | undefined, param: string) => {
+    if (recorded) return recorded
+    generics.push(param)
+    return param.split(/[ =]/)[0]!
+  }
+  const response = typeOf(types.response, 'T = unknown')
+  const body = route.method === 'GET' || route.method === 'DELETE'
+    ? 'never'
+    : typeOf(types.body, 'B = unknown')
+  const params = typeOf(types.queryParams, 'P extends Record<string, string> = Record<string, string>')
+  const list = generics.length ? `<${generics.join(', ')}>` : ''
+  return { response, args: `${list}(options?: RequestOptions<${params}, ${body}>)` }
+}
+
+/**
+ * TypeScript source of a small `fetch` client with one stub per route,
+ * typed with the routes' `types` (see `stubSignature`).
+ */
+export function clientSource(routes: RouteInfo[], options?: ClientOptions) {
+  const taken = new Map<string, number>()
+  const stubs = routes.map((route) => {
+    let name = stubName(route)
+    const seen = taken.get(name) ?? 0
+    taken.set(name, seen + 1)
+    if (seen) name += seen + 1
+    const { response, args } = stubSignature(route)
+    return `    /** ${route.method} ${route.path} */\n    ${name}: ${args} =>\n      call<${response}>(${JSON.stringify(route.method)}, ${JSON.stringify(route.path)}, options),`
+  })
+  const imports = options?.imports?.length ? options.imports.join('\n') + '\n\n' : ''
+
+  return `// Generated by tachyon-rs from the app's routes.
+
+${imports}${runtime}
+export function ${options?.name ?? 'createClient'}(baseUrl: string, init?: RequestInit) {
+  async function call<T>(method: string, path: string, options?: RequestOptions<Record<string, string>, unknown>): Promise<T> {
+    const query = options?.queryParams ? '?' + new URLSearchParams(options.queryParams) : ''
+    const json = options?.body !== undefined
+    const res = await fetch(baseUrl + path + query, {
+      ...init,
+      method,
+      headers: {
+        ...(json ? { 'content-type': 'application/json' } : {}),
+        ...Object.fromEntries(new Headers(init?.headers)),
+        ...options?.headers,
+      },
+      body: json ? JSON.stringify(options!.body) : undefined,
+    })
+    if (!res.ok) throw new ClientError(res.status, await res.text())
+    const type = res.headers.get('content-type') ?? ''
+    return (type.includes('json') ? res.json() : res.text()) as Promise<T>
+  }
+
+  return {
+${stubs.join('\n')}
+  }
+}
+`
+}
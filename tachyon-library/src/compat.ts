@@ -0,0 +1,167 @@
+import type { TachyonRequest } from "./request";
+import { TachyonResponse, type CookieOptions } from "./response";
+import type { RouteHandler } from "./tachyon";
+
+/** The parts of an Express request that Tachyon can fill in. */
+export interface ExpressRequest {
+  method: string
+  /** Path and query string, as sent */
+  url: string
+  /** Path without the query string */
+  path: string
+  /** Query parameters; the first value wins when a name repeats */
+  query: Record<string, string>
+  /** Always empty: Tachyon routes match whole paths */
+  params: Record<string, string>
+  /** Request headers, lowercase names */
+  headers: Record<string, string>
+  /** JSON bodies parsed, anything else as text */
+  body: unknown
+  ip: string
+  get(name: string): string | undefined
+  /** The underlying request */
+  raw: TachyonRequest
+}
+
+/** Express-style response: answer with `send`, `json`, `end` or `sendStatus`. */
+export class ExpressResponse {
+  statusCode = 200
+  headersSent = false
+  private res = new TachyonResponse(200, '')
+  private headers = new Map<string, string>()
+
+  constructor(private done: (res: TachyonResponse) => void) { }
+
+  status(code: number) {
+    this.statusCode = code
+    return this
+  }
+
+  set(name: string, value: string) {
+    this.headers.set(name.toLowerCase(), value)
+    return this
+  }
+
+  setHeader(name: string, value: string) {
+    return this.set(name, value)
+  }
+
+  get(name: string) {
+    return this.headers.get(name.toLowerCase())
+  }
+
+  /** `'json'`, `'text'`, `'html'` or any MIME type */
+  type(mime: string) {
+    return this.set('content-type', mime === 'json' ? 'application/json' : mime)
+  }
+
+  cookie(name: string, value: string, options?: CookieOptions) {
+    this.res.cookie(name, value, options)
+    return this
+  }
+
+  clearCookie(name: string, options?: Pick<CookieOptions, 'path' | 'domain'>) {
+    this.res.clearCookie(name, options)
+    return this
+  }
+
+  /** Strings go out as HTML, buffers as octet-stream and anything else as JSON, unless `type` was set. */
+  send(body?: string | Uint8Array | object | null) {
+    if (body === undefined || body === null) return this.end()
+    if (typeof body === 'string') return this.finish(body, 'html')
+    if (body instanceof Uint8Array) return this.finish(body, 'application/octet-stream')
+    return this.json(body)
+  }
+
+  json(body: unknown) {
+    return this.finish(JSON.stringify(body) ?? '', 'application/json')
+  }
+
+  end(body?: string | Uint8Array) {
+    return this.finish(body ?? '', 'text')
+  }
+
+  sendStatus(code: number) {
+    return this.status(code).finish(String(code), 'text')
+  }
+
+  private finish(body: string | Uint8Array, fallbackType: string) {
+    if (this.headersSent) return this
+    this.headersSent = true
+    const res = this.res
+    res.status = this.statusCode
+    res.body = body
+    const contentType = this.headers.get('content-type')
+    res.type(contentType ?? fallbackType)
+    for (const [name, value] of this.headers) {
+      if (name !== 'content-type') res.header(name, value)
+    }
+    this.done(res)
+    return this
+  }
+}
+
+export type ExpressHandler = (
+  req: ExpressRequest,
+  res: ExpressResponse,
+  next: (err?: unknown) => void,
+) => void | Promise<void>
+
+function toExpressRequest(req: TachyonRequest): ExpressRequest {
+  const q = req.path.indexOf('?')
+  let body: unknown
+  let parsed = false
+  return {
+    method: req.method,
+    url: req.path,
+    path: q === -1 ? req.path : req.path.slice(0, q),
+    query: { ...req.queryParams },
+    params: {},
+    headers: Object.fromEntries(req.headers),
+    get body() {
+      if (!parsed) {
+        parsed = true
+        body = req.header('content-type')?.startsWith('application/json') ? req.json() : req.body
+      }
+      return body
+    },
+    set body(value: unknown) {
+      parsed = true
+      body = value
+    },
+    ip: req.ip,
+    get: (name) => req.header(name),
+    raw: req,
+  }
+}
+
+/**
+ * Run an Express-style `(req, res, next)` handler as a Tachyon route, so
+ * existing handlers port with few changes:
+ *
+ * ```typescript
+ * app.get('/users', express((req, res) => {
+ *   res.status(200).json({ page: req.query.page })
+ * }))
+ * ```
+ *
+ * The route answers once the handler calls `send`, `json`, `end` or
+ * `sendStatus`; a handler that throws, rejects or calls `next(err)`
+ * answers 500, and `next()` answers 404. Middleware chains and path
+ * parameters are not supported.
+ */
+export function express(handler: ExpressHandler): RouteHandler {
+  return (req) => new Promise<TachyonResponse>((resolve, reject) => {
+    const res = new ExpressResponse(resolve)
+    const next = (err?: unknown) => {
+      if (err !== undefined) reject(err)
+      else res.sendStatus(404)
+    }
+    try {
+      const result = handler(toExpressRequest(req), res, next)
+      if (result instanceof Promise) result.catch(reject)
+    } catch (err) {
+      reject(err)
+    }
+  })
+}
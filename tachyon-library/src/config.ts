@@ -1,4 +1,16 @@
+import type { RouteTypes } from "./codegen";
+import type { TachyonRequest } from "./request";
+import type { TachyonResponse } from "./response";
+
 export type SecurityPreset = 'none' | 'basic' | 'strict'
+export type EtagMode = 'off' | 'strong' | 'weak'
+export type TimeoutPolicy = 'respond' | 'wait'
+
+/**
+ * Shapes the response for an error a route throws or rejects with. If it
+ * throws in turn, the client gets a 500.
+ */
+export type ErrorHandler = (err: unknown, req: TachyonRequest) => TachyonResponse | Promise<TachyonResponse>
 
 export interface TachyonConfig {
   security?: SecurityPreset
@@ -6,4 +18,115 @@ export interface TachyonConfig {
   compressionThreshold?: number
   /** Catch panics in handlers. Disable for max performance in controlled environments. Default: true */
   catchPanics?: boolean
+  /** ETag generation for 2xx GET/HEAD responses; matching If-None-Match returns 304. Default: 'off' */
+  etag?: EtagMode
+  /** Custom bodies for errors Tachyon answers itself (404, 405, 413, 500, 504, ...), keyed by status. */
+  errorPages?: Record<number, ErrorPage>
+  /** Pretty-print JSON responses for debugging. Keep off in production. Default: false */
+  prettyJson?: boolean
+  /** Answer a route that throws (or rejects) with this response instead of a bare 500. */
+  onError?: ErrorHandler
+  /** Put the message and stack of an unhandled route or hook error in its 500 body. Keep off in production. Default: false */
+  exposeErrors?: boolean
+  /** Requests that may wait for a busy JS thread; past that they get 503 + `Retry-After` from Rust. Default: unlimited */
+  maxQueuedCalls?: number
+  /** Locks each route cache (`cacheTtlMs`) is split over. Default: 1 */
+  cacheShards?: number
+  /** Enable CORS. Preflight OPTIONS requests are answered natively. */
+  cors?: CorsConfig
+  /** Acceptor threads sharing the port via SO_REUSEPORT, e.g. one per core (Unix only). Default: 1 */
+  acceptors?: number
+  /**
+   * Processes sharing the port via SO_REUSEPORT (Unix only). `listen` in the
+   * primary forks this many workers running the same script, which listen
+   * in its place; see `clusterStats`. Needs a fixed port. Default: 1
+   */
+  cluster?: number
+  /** Cap on open connections; further clients wait until one closes. Default: unlimited */
+  maxConnections?: number
+  /** Listen backlog: connections queued before they are accepted (1-65535). Default: 1024 */
+  backlog?: number
+  /** Per-connection buffer in bytes; requests larger than this get 413/431 (1 KiB-64 MiB). Default: 8192 */
+  bufferSize?: number
+  /** Send TCP keepalive probes after this many idle seconds. Default: OS setting */
+  tcpKeepaliveSecs?: number
+  /** Serve only IPv6 on `[::]` addresses (true) or IPv4 too (false). Default: OS setting (dual-stack on Linux/macOS) */
+  ipv6Only?: boolean
+  /** Answer 504 if a handler takes longer than this many seconds. Default: 30 */
+  timeoutSecs?: number
+  /** At the deadline, answer 504 ('respond') or let the handler finish ('wait'). Default: 'respond' */
+  onTimeout?: TimeoutPolicy
+  /** How long `close()` waits for open connections before cutting them off, in milliseconds. Default: 30000 */
+  drainTimeoutMs?: number
+}
+
+export interface AddressInfo {
+  /** IP address, e.g. '127.0.0.1' or '::1' */
+  address: string
+  port: number
+}
+
+export interface ListenOptions {
+  /** Address to bind, e.g. '127.0.0.1' or '::1'. Default: '0.0.0.0' */
+  host?: string
+  /** Called with the bound address once the server is listening */
+  onListening?: (address: AddressInfo) => void
+  /** Aborting it closes the server, like `close()` */
+  signal?: AbortSignal
+}
+
+export interface CorsConfig {
+  /** Allowed origins. Default: any origin */
+  origins?: string[]
+  /** Default: GET, HEAD, POST, PUT, PATCH, DELETE */
+  methods?: string[]
+  /** Allowed request headers. Default: whatever the browser asks for */
+  headers?: string[]
+  /** Response headers readable by scripts */
+  exposeHeaders?: string[]
+  /** Allow cookies / Authorization. Default: false */
+  credentials?: boolean
+  /** Preflight cache lifetime in seconds */
+  maxAge?: number
+}
+
+export interface ErrorPage {
+  body: string
+  /** 'json' | 'text' | 'html' or any MIME type. Default: 'json' */
+  contentType?: string
+}
+
+/** Settings for a directory served with `static`. */
+export interface StaticOptions {
+  /** Let browsers cache files this many seconds (`Cache-Control: public, max-age`) */
+  maxAge?: number
+  /** File answering for a directory, or `false` to answer 404. Default: 'index.html' */
+  index?: string | false
+}
+
+/** Settings for a single route; anything unset follows the server config. */
+export interface RouteOptions {
+  /** Reject request bodies larger than this many bytes with 413 */
+  bodyLimit?: number
+  /** Answer 504 if the handler takes longer than this many milliseconds */
+  timeoutMs?: number
+  /** 'wait' lets this route's handler run as long as it takes */
+  onTimeout?: TimeoutPolicy
+  /** Serve successful GET responses from a cache for this many milliseconds */
+  cacheTtlMs?: number
+  /** Responses the route cache keeps, one per distinct query string. Default: 1024 */
+  cacheMaxEntries?: number
+  /** Locks the route cache is split over. Default: `cacheShards` of the server config */
+  cacheShards?: number
+  /** Which response a full route cache drops: least recently used, segmented LRU that protects responses read more than once, or W-TinyLFU that only lets a new response replace one asked for less often lately. Default: 'lru' */
+  cacheEviction?: 'lru' | 'slru' | 'tinylfu'
+  /** Accepted request body types ('json', 'text' or any MIME type); others get 415 */
+  accept?: string[]
+  /**
+   * Run the route on the worker threads that joined this pool (see
+   * `joinPool`), round robin. The handler given here answers until one has.
+   */
+  pool?: string
+  /** Types of the route's stub in `clientSource()`, as TypeScript source */
+  types?: RouteTypes
 }
\ No newline at end of file
@@ -1,6 +1,16 @@
+export { clientSource } from "./codegen";
+export type { ClientOptions, RouteInfo, RouteTypes } from "./codegen";
+export { express, ExpressResponse } from "./compat";
+export type { ExpressHandler, ExpressRequest } from "./compat";
 export { status } from "./helper";
 export { Tachyon } from "./tachyon";
 export { TachyonRequest } from "./request";
 export { TachyonResponse } from "./response";
-export type { OnRequestHook, OnResponseHook } from "./tachyon";
-export type { SecurityPreset, TachyonConfig } from "./config";
+export type { CookieOptions } from "./response";
+export type { MultipartOptions, Params } from "./request";
+export { TachyonSseStream } from "./sse";
+export type { SseEvent, SseHandler } from "./sse";
+export { TachyonWebSocket } from "./websocket";
+export type { OnRequestHook, OnResponseHook, Plugin, RequestHook, RouteHandler, RouteResponse } from "./tachyon";
+export type { AddressInfo, CorsConfig, ErrorHandler, ErrorPage, EtagMode, ListenOptions, RouteOptions, SecurityPreset, StaticOptions, TachyonConfig, TimeoutPolicy } from "./config";
+export type { WebSocketHandlers } from "./websocket";
@@ -1,17 +1,117 @@
-import type { TachyonRawRequest } from "@tachyon-rs/server";
+import { parseMultipart, type TachyonRawMultipart, type TachyonRawRequest } from "@tachyon-rs/server";
 
-class TachyonRequest {
+export interface MultipartOptions {
+  /** Write uploaded files to this directory and report their `path` instead of their `data`. Delete them when done. */
+  uploadDir?: string
+}
+
+/**
+ * Requests running in JS, by id, with the controller behind `signal` once
+ * a handler asked for it.
+ */
+const running = new Map<number, AbortController | undefined>()
+
+/** Fire the `signal` of request `id`, if it is still running. */
+export function abortRequest(id: number) {
+  if (!running.has(id)) return
+  const controller = running.get(id) ?? new AbortController()
+  running.set(id, controller)
+  controller.abort(new DOMException('The client went away or the request timed out', 'AbortError'))
+}
+
+/** Forget a request once its handler has answered. */
+export function settleRequest(req: TachyonRequest<any, any>) {
+  if (req.requestId !== undefined) running.delete(req.requestId)
+}
+
+/** Query parameters as an object of strings. */
+export type Params = Record<string, string>
+
+/**
+ * A request to a route. `Body` types what `json()` returns and `Params` the
+ * query parameters, e.g. `TachyonRequest<NewUser, { page?: string }>`; both
+ * are declarations, not checked at runtime.
+ */
+class TachyonRequest<Body = unknown, P extends Params = Params> {
   method: string
+  /** Path and query string, as sent */
   path: string
-  body: string | undefined
+  /** Query string without the `?` */
+  query: string | undefined
+  /** Query parameters, decoded in Rust; the first value wins when a name repeats */
+  queryParams: P
+  /** Raw body bytes, shared with Rust (no copy) */
+  bytes: Uint8Array | undefined
+  /** Client address, "ip:port" ("[ip]:port" for IPv6) */
+  remoteAddr: string
+  /** @internal Set for route requests, while `onAbort` is wired */
+  readonly requestId: number | undefined
+  private _signal: AbortSignal | undefined
   private _headersRaw: string
   private _headers: Map<string, string> | undefined
+  private _body: string | undefined
+  private _json: unknown
 
   constructor(raw: TachyonRawRequest) {
     this.method = raw.method
     this.path = raw.path
-    this.body = raw.body
+    this.query = raw.query
+    this.queryParams = (raw.params ?? {}) as P
+    // JSON bodies arrive parsed; `null` is a parsed body too.
+    this._json = raw.json
+    this.bytes = raw.body
+    this.remoteAddr = raw.remoteAddr
     this._headersRaw = raw.headers
+    this.requestId = raw.requestId
+    if (raw.requestId !== undefined) running.set(raw.requestId, undefined)
+  }
+
+  /**
+   * Aborted when the client disconnects or the route's timeout passes, so
+   * pending work (`fetch`, queries) can stop. Never fires for requests
+   * that aren't routed to a handler.
+   */
+  get signal(): AbortSignal {
+    if (this._signal) return this._signal
+    const id = this.requestId
+    let controller = id === undefined ? undefined : running.get(id)
+    if (!controller) {
+      controller = new AbortController()
+      if (id !== undefined && running.has(id)) running.set(id, controller)
+    }
+    return this._signal = controller.signal
+  }
+
+  /** Body as UTF-8 text, decoded on first access */
+  get body(): string | undefined {
+    if (this._body === undefined && this.bytes) this._body = new TextDecoder().decode(this.bytes)
+    return this._body
+  }
+
+  /** Body parsed as JSON (in Rust for JSON content types, else on first call); throws on invalid JSON */
+  json<T = Body>(): T | undefined {
+    if (this._json === undefined && this.bytes) this._json = JSON.parse(this.body!)
+    return this._json as T | undefined
+  }
+
+  /**
+   * Parse a `multipart/form-data` body (form fields and file uploads) in
+   * Rust. Rejects if the request isn't one or the body is malformed.
+   */
+  multipart(options?: MultipartOptions): Promise<TachyonRawMultipart> {
+    return parseMultipart(this.header('content-type') ?? '', this.bytes ?? new Uint8Array(), options)
+  }
+
+  /** Client IP, without the port */
+  get ip(): string {
+    const colon = this.remoteAddr.lastIndexOf(':')
+    const host = colon === -1 ? this.remoteAddr : this.remoteAddr.slice(0, colon)
+    return host.startsWith('[') ? host.slice(1, -1) : host
+  }
+
+  /** First value of query parameter `name` */
+  param(name: keyof P & string): string | undefined {
+    return Object.hasOwn(this.queryParams, name) ? this.queryParams[name] : undefined
   }
 
   header(name: string): string | undefined {
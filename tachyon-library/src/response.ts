@@ -1,3 +1,23 @@
+import { TachyonRawBodyWriter, type TachyonRawCookie } from "@tachyon-rs/server";
+
+type BodySource = AsyncIterable<string | Uint8Array>
+
+function isBodySource(body: unknown): body is BodySource {
+  return typeof body === 'object' && body !== null && Symbol.asyncIterator in body
+}
+
+/** Feed `source` into `writer`; stops early once the client is gone. */
+async function pipe(source: BodySource, writer: TachyonRawBodyWriter) {
+  try {
+    for await (const chunk of source) {
+      if (!(await writer.write(chunk))) return
+    }
+    writer.end()
+  } catch {
+    await writer.abort()
+  }
+}
+
 interface RustJsonField {
   key?: string
   value?: string
@@ -34,25 +54,87 @@ function convertValue(value: unknown): RustJsonField {
   }
 }
 
+export interface CookieOptions {
+  /** Default: '/' */
+  path?: string
+  domain?: string
+  /** Lifetime in seconds. Default: a session cookie */
+  maxAge?: number
+  secure?: boolean
+  httpOnly?: boolean
+  /** 'none' also sets `secure` */
+  sameSite?: 'strict' | 'lax' | 'none'
+}
+
 class TachyonResponse {
   private headers: { name: string; value: string }[] = []
-  private _contentType: 'json' | 'text' = 'json'
+  private cookies: TachyonRawCookie[] = []
+  private _contentType: string = 'json'
+  private _attachment: string | undefined
 
-  constructor(public status: number, public body: string | Record<string, unknown> | Array<Record<string, unknown>>) { }
+  /**
+   * `body` may also be an async iterable of chunks (a Node `Readable`, an
+   * async generator, …): it is streamed as a chunked response, e.g. for
+   * server-sent events with `.type('text/event-stream')`.
+   */
+  constructor(public status: number, public body: string | Uint8Array | Record<string, unknown> | Array<Record<string, unknown>> | BodySource) { }
 
   header(name: string, value: string) {
     this.headers.push({ name, value })
     return this
   }
 
+  /**
+   * Set a cookie. Names and values are sent as given: encode anything
+   * outside printable ASCII (and `;`, `,`, `"`, `\`, spaces) first, or
+   * the cookie is dropped.
+   */
+  cookie(name: string, value: string, options: CookieOptions = {}) {
+    this.cookies.push({
+      name,
+      value,
+      path: options.path ?? '/',
+      domain: options.domain,
+      maxAgeSecs: options.maxAge,
+      secure: options.secure,
+      httpOnly: options.httpOnly,
+      sameSite: options.sameSite,
+    })
+    return this
+  }
+
+  /** Delete a cookie on the client; pass the `path`/`domain` it was set with. */
+  clearCookie(name: string, options: Pick<CookieOptions, 'path' | 'domain'> = {}) {
+    return this.cookie(name, '', { ...options, maxAge: 0 })
+  }
+
   /** Set response content type to plain text. */
   text() {
     this._contentType = 'text'
     return this
   }
 
+  /** Set response content type to HTML. */
+  html() {
+    this._contentType = 'html'
+    return this
+  }
+
+  /** Set an arbitrary response content type, e.g. `type('image/svg+xml')`. */
+  type(mime: string) {
+    this._contentType = mime
+    return this
+  }
+
+  /** Serve as a file download with the given filename (Content-Disposition: attachment). */
+  attachment(filename: string) {
+    this._attachment = filename
+    return this
+  }
+
   private convertToRustJson(): { json?: RustJsonField[]; array?: RustJsonField[] } | undefined {
     if (typeof this.body !== 'object' || this.body === null) return undefined
+    if (this.body instanceof Uint8Array || isBodySource(this.body)) return undefined
 
     if (Array.isArray(this.body)) {
       return {
@@ -69,15 +151,36 @@ class TachyonResponse {
   }
 
   toRaw() {
+    if (isBodySource(this.body)) {
+      const writer = new TachyonRawBodyWriter()
+      void pipe(this.body, writer)
+      return {
+        status: this.status,
+        contentType: this._contentType === 'json' ? 'application/octet-stream' : this._contentType,
+        headers: this.headers.length > 0 ? this.headers : undefined,
+        cookies: this.cookies.length > 0 ? this.cookies : undefined,
+        attachment: this._attachment,
+        streamId: writer.id,
+      }
+    }
+
     const rustJson = this.convertToRustJson()
+    const bytes = this.body instanceof Uint8Array ? this.body : undefined
+
+    let contentType = this._contentType
+    if (contentType === 'json' && typeof this.body === 'string') contentType = 'text'
+    if (contentType === 'json' && bytes) contentType = 'application/octet-stream'
 
     return {
       status: this.status,
       body: typeof this.body === 'string' ? this.body : undefined,
-      contentType: typeof this.body === 'string' ? 'text' : this._contentType,
+      bytes,
+      contentType,
       headers: this.headers.length > 0 ? this.headers : undefined,
+      cookies: this.cookies.length > 0 ? this.cookies : undefined,
       json: rustJson?.json,
       array: rustJson?.array,
+      attachment: this._attachment,
     }
   }
 }
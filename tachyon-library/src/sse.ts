@@ -0,0 +1,74 @@
+import type { TachyonRawSseStream } from "@tachyon-rs/server";
+import type { TachyonRequest } from "./request";
+
+/** One server-sent event. Non-string `data` is sent as JSON. */
+export interface SseEvent {
+  data: unknown
+  /** Event type for `addEventListener` in the browser. Default: 'message' */
+  event?: string
+  /** Sent back by a reconnecting browser as the `Last-Event-ID` header */
+  id?: string
+  /** How long a browser waits before reconnecting, in milliseconds */
+  retry?: number
+}
+
+/** An open event stream. */
+class TachyonSseStream {
+  /** The request that opened the stream */
+  readonly request: TachyonRequest
+  /** Free for per-stream state */
+  data: Record<string, unknown> = {}
+  private raw: TachyonRawSseStream
+  private onClose: (() => void)[] = []
+  private ended = false
+
+  constructor(raw: TachyonRawSseStream, request: TachyonRequest) {
+    this.raw = raw
+    this.request = request
+  }
+
+  get id(): number {
+    return this.raw.id
+  }
+
+  /**
+   * Send an event (a string is sent as its `data`). Resolves `false` once
+   * the client is gone: stop producing. Await each send before the next.
+   */
+  send(event: SseEvent | string): Promise<boolean> {
+    const { data, event: name, id, retry } = typeof event === 'string' ? { data: event } as SseEvent : event
+    return this.raw.send({
+      data: typeof data === 'string' ? data : JSON.stringify(data) ?? '',
+      event: name,
+      id,
+      retryMs: retry,
+    })
+  }
+
+  /** Send a comment, ignored by clients, e.g. to keep idle proxies from closing the stream. */
+  comment(text?: string): Promise<boolean> {
+    return this.raw.comment(text)
+  }
+
+  /** End the stream. */
+  close() {
+    this.raw.close()
+  }
+
+  /** Call `callback` once the stream is closed, by either side. */
+  closed(callback: () => void) {
+    if (this.ended) callback()
+    else this.onClose.push(callback)
+  }
+
+  /** @internal */
+  _closed() {
+    this.ended = true
+    for (const callback of this.onClose.splice(0)) callback()
+  }
+}
+
+/** Called once per client that opens the stream. */
+export type SseHandler = (stream: TachyonSseStream) => void | Promise<void>
+
+export { TachyonSseStream }
@@ -1,8 +1,12 @@
-import { TachyonRawServer } from "@tachyon-rs/server";
-import { TachyonRequest } from "./request";
+import { joinPool, leavePool, TachyonRawServer, type TachyonRawRequest } from "@tachyon-rs/server";
+import { abortRequest, settleRequest, TachyonRequest, type Params } from "./request";
 import { TachyonResponse } from "./response";
-import type { TachyonConfig } from "./config";
+import type { AddressInfo, ListenOptions, RouteOptions, StaticOptions, TachyonConfig } from "./config";
+import { clientSource, type ClientOptions, type RouteInfo } from "./codegen";
+import { closeWorkers, collectStats, fork, isPrimary, serveWorker } from "./cluster";
 import { status } from "./helper";
+import { TachyonSseStream, type SseHandler } from "./sse";
+import { TachyonWebSocket, type WebSocketHandlers } from "./websocket";
 
 const methods = ["GET", "POST", "PUT", "DELETE"]
 
@@ -20,6 +24,26 @@ export type OnRequestHook = (req: TachyonRequest) => TachyonResponse | void
  */
 export type OnResponseHook = (req: TachyonRequest, res: TachyonResponse) => TachyonResponse | void
 
+/**
+ * Route handler. May be `async`: the response is sent once the Promise
+ * settles, and a rejected Promise answers 500. `Body` and `P` type the
+ * request's JSON body and query parameters.
+ */
+export type RouteHandler<Body = unknown, P extends Params = Params> =
+  (req: TachyonRequest<Body, P>) => TachyonResponse | Promise<TachyonResponse>
+
+/** What a route answers with: a handler, or a constant body. */
+export type RouteResponse<Body = unknown, P extends Params = Params> =
+  RouteHandler<Body, P> | string | Record<string, unknown>
+
+/**
+ * Request hook passed to `use`. Runs in Rust's request path, before the
+ * route's plugins and handler, for every route handler; may be `async`.
+ * - Return a `TachyonResponse` to answer with it and skip the rest.
+ * - Return `void` to let the request through.
+ */
+export type RequestHook = (req: TachyonRequest) => TachyonResponse | void | Promise<TachyonResponse | void>
+
 export type Plugin = {
   pre?: OnRequestHook,
   pos?: OnResponseHook,
@@ -27,84 +51,433 @@ export type Plugin = {
 
 class Tachyon {
 
-  private routes: Map<string, (req: TachyonRequest) => TachyonResponse>;
+  private routes: Map<string, RouteHandler>;
+  private routeOptions: Map<string, RouteOptions> = new Map();
   private plugins: Plugin[] = []
+  private hooks: RequestHook[] = []
+  private sockets: Map<string, WebSocketHandlers> = new Map();
+  private streams: Map<string, SseHandler> = new Map();
+  private statics: Map<string, { dir: string, options?: StaticOptions }> = new Map();
+  private fallbackRoute?: RouteHandler;
   private config: TachyonConfig;
+  private server?: TachyonRawServer;
+  /** Set in a `cluster` primary once it has forked its workers. */
+  private forked = false;
 
   constructor(config?: TachyonConfig) {
     this.routes = new Map();
     this.config = config ?? {};
   }
 
-  public use(plugin: Plugin) {
-    this.plugins.push(plugin)
+  /** Add a plugin (`pre` / `pos` hooks) or a request hook, in order. */
+  public use(plugin: Plugin | RequestHook) {
+    if (typeof plugin === 'function') this.hooks.push(plugin)
+    else this.plugins.push(plugin)
     return this
   }
 
-  private transformToResponse(response: ((req: TachyonRequest) => TachyonResponse) | string | Record<string, unknown> | Array<Record<string, unknown>>) {
+  private transformToResponse(response: RouteHandler | string | Record<string, unknown> | Array<Record<string, unknown>>) {
     return typeof response === "function" ? response : () => status(200, response)
   }
 
-  public get(path: string, response: ((req: TachyonRequest) => TachyonResponse) | string | Record<string, unknown>) {
-    this.routes.set('0@'+path, this.transformToResponse(response))
+  public get<Body = unknown, P extends Params = Params>(path: string, response: RouteResponse<Body, P>, options?: RouteOptions) {
+    return this.register('0@'+path, response as RouteResponse, options)
+  }
+
+  public post<Body = unknown, P extends Params = Params>(path: string, response: RouteResponse<Body, P>, options?: RouteOptions) {
+    return this.register('1@'+path, response as RouteResponse, options)
+  }
+
+  public put<Body = unknown, P extends Params = Params>(path: string, response: RouteResponse<Body, P>, options?: RouteOptions) {
+    return this.register('2@'+path, response as RouteResponse, options)
+  }
+
+  public delete<Body = unknown, P extends Params = Params>(path: string, response: RouteResponse<Body, P>, options?: RouteOptions) {
+    return this.register('3@'+path, response as RouteResponse, options)
+  }
+
+  /** Accept WebSocket connections on `path`. */
+  public ws(path: string, handlers: WebSocketHandlers) {
+    this.sockets.set(path, handlers)
+    return this
+  }
+
+  /**
+   * Answer requests no route or `static` directory matches, instead of the
+   * built-in 404: e.g. serve an SPA's `index.html`, or a custom not-found
+   * page with `status(404, ...)`. Plugins and `onError` apply as for routes.
+   */
+  public fallback<Body = unknown, P extends Params = Params>(response: RouteResponse<Body, P>) {
+    this.fallbackRoute = this.transformToResponse(response as RouteResponse)
+    this.server?.fallback(this.callback(() => this.fallbackRoute))
     return this
   }
 
-  public post(path: string, response: ((req: TachyonRequest) => TachyonResponse) | string | Record<string, unknown>) {
-    this.routes.set('1@'+path, this.transformToResponse(response))
+  /**
+   * Answer GET `path` with a server-sent event stream: `handler` runs for
+   * every client that connects, and sends on the stream until it closes.
+   */
+  public sse(path: string, handler: SseHandler) {
+    this.streams.set(path, handler)
     return this
   }
 
-  public put(path: string, response: ((req: TachyonRequest) => TachyonResponse) | string | Record<string, unknown>) {
-    this.routes.set('2@'+path, this.transformToResponse(response))
+  /**
+   * Serve the files of `dir` under `prefix`, e.g. `static('/public', './assets')`
+   * answers `GET /public/app.css` with `./assets/app.css`. Files are read in
+   * Rust, with ETags, ranges and cache headers; routes take precedence.
+   */
+  public static(prefix: string, dir: string, options?: StaticOptions) {
+    this.statics.set(prefix, { dir, options })
+    this.server?.files(prefix, dir, this.fileOptions(options))
+    return this
+  }
+
+  private fileOptions(options?: StaticOptions) {
+    return options && {
+      maxAgeSecs: options.maxAge,
+      index: options.index === false ? '' : options.index,
+    }
+  }
+
+  private register(key: string, response: RouteResponse, options?: RouteOptions) {
+    this.routes.set(key, this.transformToResponse(response))
+    if (options) this.routeOptions.set(key, options)
+    else this.routeOptions.delete(key)
+    // While listening, the route (with its options) is served at once.
+    if (this.server) this.bind(this.server, key)
     return this
   }
 
-  public delete(path: string, response: ((req: TachyonRequest) => TachyonResponse) | string | Record<string, unknown>) {
-    this.routes.set('3@'+path, this.transformToResponse(response))
+  private optionsFor(key: string): RouteOptions | undefined {
+    const options = this.routeOptions.get(key)
+    if (options?.cacheTtlMs === undefined) return options
+    return { ...options, cacheShards: options.cacheShards ?? this.config.cacheShards }
+  }
+
+  /** Register the route stored under `key` with `server`. */
+  private bind(server: Pick<TachyonRawServer, 'route'>, key: string) {
+    const atIdx = key.indexOf('@')
+    const method = parseInt(key.slice(0, atIdx))
+    const path = key.slice(atIdx + 1)
+    // Looked up per request so `replace` takes effect immediately.
+    server.route(methods[method] ?? 'GET', path, this.callback(() => this.routes.get(key)), this.optionsFor(key))
+  }
+
+  /**
+   * The native callback running `route()` with the plugins and `onError`.
+   * A call that finds no route (`unregister`ed while it waited for the JS
+   * thread) answers the 404 later requests get.
+   */
+  private callback(route: () => RouteHandler | undefined) {
+    const plugins = this.plugins
+    const onError = this.config.onError
+    const notFound = this.config.errorPages?.[404] ?? { body: '{"error":"not found"}' }
+
+    const handle = (req: TachyonRequest, handler: RouteHandler) => {
+      // --- Pre-request hooks ---
+      for (const plugin of plugins) {
+        const result = plugin.pre?.(req)
+        if (result) return result.toRaw()
+      }
+
+      // --- Post-response hooks ---
+      const finish = (res: TachyonResponse) => {
+        for (const plugin of plugins) {
+          const result = plugin.pos?.(req, res)
+          if (result) res = result
+        }
+        return typeof (res as any).toRaw === 'function' ? (res as any).toRaw() : res as any
+      }
+
+      // --- Route handler ---
+      const res = handler(req)
+      return res instanceof Promise ? res.then(finish) : finish(res)
+    }
+
+    const respond = (req: TachyonRequest, handler: RouteHandler) => {
+      if (!onError) return handle(req, handler)
+
+      // --- Error handler ---
+      const recover = (err: unknown) => {
+        const res = onError(err, req)
+        return res instanceof Promise ? res.then((r) => r.toRaw()) : res.toRaw()
+      }
+      try {
+        const res = handle(req, handler)
+        return res instanceof Promise ? res.catch(recover) : res
+      } catch (err) {
+        return recover(err)
+      }
+    }
+
+    return (raw: TachyonRawRequest) => {
+      const handler = route()
+      if (!handler) return { status: 404, body: notFound.body, contentType: notFound.contentType }
+      const req = new TachyonRequest(raw)
+      let res
+      try {
+        res = respond(req, handler)
+      } catch (err) {
+        settleRequest(req)
+        throw err
+      }
+      if (!(res instanceof Promise)) {
+        settleRequest(req)
+        return res
+      }
+      return res.finally(() => settleRequest(req))
+    }
+  }
+
+  /**
+   * Swap the handler of an existing route, also while the server runs.
+   * Requests already running finish on the old handler. Returns `false`
+   * (changing nothing) if `method` + `path` was never registered.
+   */
+  public replace<Body = unknown, P extends Params = Params>(method: string, path: string, response: RouteResponse<Body, P>) {
+    const key = methods.indexOf(method.toUpperCase()) + '@' + path
+    if (!this.routes.has(key)) return false
+    this.routes.set(key, this.transformToResponse(response))
+    return true
+  }
+
+  /**
+   * Remove a route, also while the server runs: requests already running
+   * finish, later ones get 404, as do those still waiting for the JS
+   * thread. Returns `false` if `method` + `path` was never registered.
+   */
+  public unregister(method: string, path: string) {
+    const key = methods.indexOf(method.toUpperCase()) + '@' + path
+    if (!this.routes.delete(key)) return false
+    this.routeOptions.delete(key)
+    this.server?.unregister(method.toUpperCase(), path)
+    return true
+  }
+
+  /** Every route registered so far (handlers, not `static` or `ws` paths). */
+  public routeList(): RouteInfo[] {
+    return [...this.routes.keys()].map((key) => {
+      const at = key.indexOf('@')
+      const types = this.routeOptions.get(key)?.types
+      return { method: methods[parseInt(key.slice(0, at))] ?? 'GET', path: key.slice(at + 1), ...(types && { types }) }
+    })
+  }
+
+  /**
+   * TypeScript source of a typed `fetch` client for the routes registered
+   * so far, e.g. written to a file by a build script. Stubs are typed with
+   * the routes' `types` option:
+   *
+   * ```typescript
+   * app.post<NewUser>('/users', createUser, { types: { response: 'User', body: 'NewUser' } })
+   * await Bun.write('client.ts', app.clientSource({ imports: ["import type { User, NewUser } from './types'"] }))
+   * const api = createClient('http://localhost:3000')
+   * const user = await api.postUsers({ body: { name: 'Ada' } }) // User
+   * ```
+   */
+  public clientSource(options?: ClientOptions) {
+    return clientSource(this.routeList(), options)
+  }
+
+  /**
+   * Serve this app's routes from the current thread, usually a
+   * `worker_threads` Worker, for the routes the main thread registered
+   * with `{ pool }`. Call it instead of `listen`; plugins and `onError`
+   * run here, route options are the main thread's.
+   */
+  public joinPool(pool: string) {
+    const worker = { route: (method: string, path: string, handler: Parameters<typeof joinPool>[3]) => joinPool(pool, method, path, handler) }
+    for (const key of this.routes.keys()) this.bind(worker, key)
     return this
   }
 
-  public listen(port: number) {
+  /** Stop serving `pool` from this thread, so the worker can exit. */
+  public leavePool(pool: string) {
+    leavePool(pool)
+  }
+
+  /**
+   * Listen on `options.host` (default: all IPv4 interfaces). Port 0 picks
+   * a free port; the bound port is returned.
+   */
+  public listen(port: number, options?: ListenOptions): number {
+    const host = options?.host ?? '0.0.0.0'
+    const bound = this.listenOn((host.includes(':') ? `[${host}]` : host) + ':' + port)
+    // A cluster primary forwards to its workers, which report their own.
+    const address = this.address()
+    if (address) options?.onListening?.(address)
+    const signal = options?.signal
+    if (signal?.aborted) void this.close()
+    else signal?.addEventListener('abort', () => void this.close(), { once: true })
+    return bound
+  }
+
+  /** The first address the server listens on; `undefined` unless listening. */
+  public address(): AddressInfo | undefined {
+    return this.server?.addresses()[0]
+  }
+
+  /**
+   * Listen on one or more addresses, e.g. `'127.0.0.1:3000'`, `'[::]:3000'`
+   * or several ports at once. With `cluster`, the primary process forks the
+   * workers instead and returns the requested port.
+   */
+  public listenOn(...addrs: [string, ...string[]]): number {
+    const [bindAddr, ...extraBinds] = addrs
+    const clustered = (this.config.cluster ?? 1) > 1
+    if (isPrimary(this.config.cluster)) {
+      fork(this.config.cluster!)
+      this.forked = true
+      return Number(bindAddr.slice(bindAddr.lastIndexOf(':') + 1))
+    }
     const server = new TachyonRawServer({
-      bindAddr: '0.0.0.0:' + port,
+      bindAddr,
+      extraBinds,
+      reusePort: clustered || undefined,
+      acceptors: this.config.acceptors,
+      maxConnections: this.config.maxConnections,
+      backlog: this.config.backlog,
+      bufferSize: this.config.bufferSize,
+      tcpKeepaliveSecs: this.config.tcpKeepaliveSecs,
+      ipv6Only: this.config.ipv6Only,
+      drainTimeoutMs: this.config.drainTimeoutMs,
+      exposeErrors: this.config.exposeErrors,
+      maxQueuedCalls: this.config.maxQueuedCalls,
+      timeoutSecs: this.config.timeoutSecs,
+      onTimeout: this.config.onTimeout,
       security: this.config.security ?? 'basic',
       compressionThreshold: this.config.compressionThreshold,
       catchPanics: this.config.catchPanics,
+      etag: this.config.etag,
+      prettyJson: this.config.prettyJson,
+      cors: this.config.cors && {
+        origins: this.config.cors.origins,
+        methods: this.config.cors.methods,
+        headers: this.config.cors.headers,
+        exposeHeaders: this.config.cors.exposeHeaders,
+        credentials: this.config.cors.credentials,
+        maxAgeSecs: this.config.cors.maxAge,
+      },
+      errorPages: Object.entries(this.config.errorPages ?? {}).map(([status, page]) => ({
+        status: Number(status),
+        body: page.body,
+        contentType: page.contentType,
+      })),
     })
 
-    const plugins = this.plugins
+    for (const hook of this.hooks) {
+      server.use((raw) => {
+        const res = hook(new TachyonRequest(raw))
+        return res instanceof Promise ? res.then((r) => r?.toRaw()) : res?.toRaw()
+      })
+    }
 
     // Register each route individually — Rust dispatches with O(1) HashMap lookup.
     // Unknown paths return 404 entirely in Rust, zero JS call overhead.
-    for (const [key, handler] of this.routes) {
-      const atIdx = key.indexOf('@')
-      const method = parseInt(key.slice(0, atIdx))
-      const path = key.slice(atIdx + 1)
+    for (const key of this.routes.keys()) this.bind(server, key)
 
-      server.route(methods[method] ?? 'GET', path, (raw) => {
-        const req = new TachyonRequest(raw)
-
-        // --- Pre-request hooks ---
-        for (const plugin of plugins) {
-          const result = plugin.pre?.(req)
-          if (result) return result.toRaw()
-        }
+    for (const [prefix, { dir, options }] of this.statics) {
+      server.files(prefix, dir, this.fileOptions(options))
+    }
 
-        // --- Route handler ---
-        let res = handler(req)
+    if (this.fallbackRoute) server.fallback(this.callback(() => this.fallbackRoute))
+    server.onAbort((id) => abortRequest(id))
 
-        // --- Post-response hooks ---
-        for (const plugin of plugins) {
-          const result = plugin.pos?.(req, res)
-          if (result) res = result
+    for (const [path, handlers] of this.sockets) {
+      const open = new Map<number, TachyonWebSocket>()
+      server.ws(path, (event) => {
+        if (event.kind === 'open') {
+          const ws = new TachyonWebSocket(event.socket, new TachyonRequest(event.request!))
+          open.set(ws.id, ws)
+          handlers.open?.(ws)
+          return
+        }
+        const ws = open.get(event.socket.id)
+        if (!ws) return
+        if (event.kind === 'message') {
+          handlers.message?.(ws, event.text ?? event.data!)
+        } else {
+          open.delete(ws.id)
+          handlers.close?.(ws, event.code ?? 1006, event.reason ?? '')
         }
+      })
+    }
 
-        return typeof (res as any).toRaw === 'function' ? (res as any).toRaw() : res as any
+    for (const [path, handler] of this.streams) {
+      const open = new Map<number, TachyonSseStream>()
+      server.sse(path, (event) => {
+        if (event.kind === 'open') {
+          const stream = new TachyonSseStream(event.stream, new TachyonRequest(event.request!))
+          open.set(stream.id, stream)
+          const done = handler(stream)
+          if (done instanceof Promise) done.catch(() => stream.close())
+          return
+        }
+        const stream = open.get(event.stream.id)
+        open.delete(event.stream.id)
+        stream?._closed()
       })
     }
 
-    server.listen()
+    const port = server.listen()
+    this.server = server
+    if (clustered) serveWorker(() => this.stats(), () => this.close())
+    return port
+  }
+
+  /** Drop the cached responses of `path` (routes with `cacheTtlMs`), e.g. after the data behind it changed. */
+  public invalidateCache(path: string) {
+    this.server?.invalidateCache(path)
+  }
+
+  /** Empty every route cache. */
+  public invalidateAll() {
+    this.server?.invalidateAll()
+  }
+
+  /** Call every cached GET route once so its first client gets a cache hit. Resolves with how many responses were cached. */
+  public async warmup() {
+    return (await this.server?.warmup()) ?? 0
+  }
+
+  /** Hits, misses, hit rate, evictions and per-shard sizes of every route cache. */
+  public cacheStats() {
+    return this.server?.getCacheStats() ?? []
+  }
+
+  /**
+   * Request count, errors, latency percentiles (ms), throughput and body
+   * sizes of every route, keyed by method and path (`'GET /users'`), with
+   * the cache counters of cached routes.
+   */
+  public stats() {
+    return this.server?.getStats() ?? {}
+  }
+
+  /**
+   * `stats()` of every `cluster` worker, added up: counts and throughput
+   * are summed, means weighted, percentiles the highest of any worker.
+   * The same as `stats()` outside a cluster primary.
+   */
+  public async clusterStats() {
+    return this.forked ? collectStats() : this.stats()
+  }
+
+  /** Zero the counters behind `stats()` and `cacheStats()`. */
+  public resetStats() {
+    this.server?.resetStats()
+  }
+
+  /**
+   * Stop accepting and close connections once their current response is
+   * sent. Resolves when the last one has closed (or `drainTimeoutMs` ran
+   * out), after which nothing keeps the process alive. A `cluster`
+   * primary closes every worker and waits for them to exit.
+   */
+  public async close() {
+    if (this.forked) await closeWorkers()
+    await this.server?.close()
   }
 
 }
@@ -0,0 +1,41 @@
+import type { TachyonRawWebSocket } from "@tachyon-rs/server";
+import type { TachyonRequest } from "./request";
+
+/** An open WebSocket; the same object is passed to every handler of one socket. */
+class TachyonWebSocket {
+  /** The upgrade request */
+  readonly request: TachyonRequest
+  /** Free for per-socket state, e.g. the user it belongs to */
+  data: Record<string, unknown> = {}
+  private raw: TachyonRawWebSocket
+
+  constructor(raw: TachyonRawWebSocket, request: TachyonRequest) {
+    this.raw = raw
+    this.request = request
+  }
+
+  get id(): number {
+    return this.raw.id
+  }
+
+  /** Send a text or binary message. Resolves `false` once the socket is closed. */
+  send(message: string | Uint8Array): Promise<boolean> {
+    return this.raw.send(message)
+  }
+
+  /** Close the socket (default code: 1000) after the messages already sent. */
+  close(code?: number, reason?: string): Promise<boolean> {
+    return this.raw.close(code, reason)
+  }
+}
+
+/** Callbacks of a `ws` route, called in order: `open`, any `message`s, then `close`. */
+export type WebSocketHandlers = {
+  open?: (ws: TachyonWebSocket) => void,
+  /** Text messages arrive as strings, binary ones as Buffers. */
+  message?: (ws: TachyonWebSocket, message: string | Buffer) => void,
+  /** `code` is 1005 if the client gave none, 1006 if the connection dropped. */
+  close?: (ws: TachyonWebSocket, code: number, reason: string) => void,
+}
+
+export { TachyonWebSocket }
@@ -0,0 +1,218 @@
+//! Route attributes for tachyon-core.
+//!
+//! ```ignore
+//! use tachyon_core::extract::{Json, State};
+//! use tachyon_macros::{get, post, routes};
+//!
+//! #[get("/users")]
+//! async fn list(State(db): State<Db>) -> String { /* … */ }
+//!
+//! #[post("/users")]
+//! async fn create(State(db): State<Db>, Json(user): Json<NewUser>) -> (u16, String) { /* … */ }
+//!
+//! router.mount_with_state(db, routes![list, create]);
+//! ```
+//!
+//! An attribute leaves the function as written and adds a hidden
+//! `Endpoint` constructor next to it; `routes!` collects those. Arguments
+//! are extracted as with `tachyon_core::extract::handler`, and the state
+//! type is inferred from the `State` extractors of every endpoint mounted
+//! together (`()` for [`Router::mount`]).
+//!
+//! Paths are checked at compile time. Routes match exact paths, so path
+//! parameters (`/users/:id`, `/files/*rest`, `/users/{id}`) are rejected;
+//! take the `Uri` extractor and parse the path in the handler instead.
+//!
+//! [`Router::mount`]: https://docs.rs/tachyon-core/latest/tachyon_core/router/struct.Router.html#method.mount
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{FnArg, Ident, ItemFn, LitStr, Path, Token, parse_macro_input};
+
+/// `#[get("/path")]`: serve the `async fn` below for GET requests to `/path`.
+#[proc_macro_attribute]
+pub fn get(attr: TokenStream, item: TokenStream) -> TokenStream {
+    with_method("Get", attr, item)
+}
+
+/// `#[post("/path")]`, as [`get`](macro@get).
+#[proc_macro_attribute]
+pub fn post(attr: TokenStream, item: TokenStream) -> TokenStream {
+    with_method("Post", attr, item)
+}
+
+/// `#[put("/path")]`, as [`get`](macro@get).
+#[proc_macro_attribute]
+pub fn put(attr: TokenStream, item: TokenStream) -> TokenStream {
+    with_method("Put", attr, item)
+}
+
+/// `#[delete("/path")]`, as [`get`](macro@get).
+#[proc_macro_attribute]
+pub fn delete(attr: TokenStream, item: TokenStream) -> TokenStream {
+    with_method("Delete", attr, item)
+}
+
+/// `#[patch("/path")]`, as [`get`](macro@get).
+#[proc_macro_attribute]
+pub fn patch(attr: TokenStream, item: TokenStream) -> TokenStream {
+    with_method("Patch", attr, item)
+}
+
+/// `#[head("/path")]`, as [`get`](macro@get). GET routes answer HEAD
+/// already; this is for handlers that treat it differently.
+#[proc_macro_attribute]
+pub fn head(attr: TokenStream, item: TokenStream) -> TokenStream {
+    with_method("Head", attr, item)
+}
+
+/// `#[options("/path")]`, as [`get`](macro@get).
+#[proc_macro_attribute]
+pub fn options(attr: TokenStream, item: TokenStream) -> TokenStream {
+    with_method("Options", attr, item)
+}
+
+/// `#[route(PATCH, "/path")]`: the method as an argument.
+#[proc_macro_attribute]
+pub fn route(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let MethodAndPath { method, path } = parse_macro_input!(attr as MethodAndPath);
+    let item = parse_macro_input!(item as ItemFn);
+    let variant = match method.to_string().to_ascii_uppercase().as_str() {
+        "GET" => "Get",
+        "POST" => "Post",
+        "PUT" => "Put",
+        "DELETE" => "Delete",
+        "PATCH" => "Patch",
+        "HEAD" => "Head",
+        "OPTIONS" => "Options",
+        _ => {
+            return syn::Error::new(
+                method.span(),
+                "expected GET, POST, PUT, DELETE, PATCH, HEAD or OPTIONS",
+            )
+            .into_compile_error()
+            .into();
+        }
+    };
+    expand(variant, &path, item)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// `routes![list, users::create]`: the endpoints of functions marked with a
+/// route attribute, for `Router::mount`.
+#[proc_macro]
+pub fn routes(input: TokenStream) -> TokenStream {
+    let paths = parse_macro_input!(input with Punctuated::<Path, Token![,]>::parse_terminated);
+    let endpoints = paths.into_iter().map(|mut path| {
+        if let Some(last) = path.segments.last_mut() {
+            last.ident = hidden_name(&last.ident);
+        }
+        quote! { #path() }
+    });
+    quote! { [#(#endpoints),*] }.into()
+}
+
+fn with_method(variant: &str, attr: TokenStream, item: TokenStream) -> TokenStream {
+    let path = parse_macro_input!(attr as LitStr);
+    let item = parse_macro_input!(item as ItemFn);
+    expand(variant, &path, item)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+struct MethodAndPath {
+    method: Ident,
+    path: LitStr,
+}
+
+impl Parse for MethodAndPath {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let method = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let path = input.parse()?;
+        Ok(Self { method, path })
+    }
+}
+
+fn hidden_name(name: &Ident) -> Ident {
+    format_ident!("__tachyon_route_{}", name)
+}
+
+fn expand(variant: &str, path: &LitStr, item: ItemFn) -> syn::Result<TokenStream2> {
+    check_path(path)?;
+    let sig = &item.sig;
+    if sig.asyncness.is_none() {
+        return Err(syn::Error::new_spanned(
+            sig.fn_token,
+            "route handlers must be `async fn`",
+        ));
+    }
+    if !sig.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &sig.generics,
+            "route handlers cannot be generic",
+        ));
+    }
+    let mut args = Vec::with_capacity(sig.inputs.len());
+    for input in &sig.inputs {
+        match input {
+            FnArg::Typed(arg) => args.push(&*arg.ty),
+            FnArg::Receiver(receiver) => {
+                return Err(syn::Error::new_spanned(
+                    receiver,
+                    "route handlers cannot take `self`",
+                ));
+            }
+        }
+    }
+
+    let vis = &item.vis;
+    let name = &sig.ident;
+    let hidden = hidden_name(name);
+    let method = format_ident!("{}", variant);
+    Ok(quote! {
+        #item
+
+        #[doc(hidden)]
+        #vis fn #hidden<S>() -> ::tachyon_core::router::Endpoint<S>
+        where
+            S: ::std::marker::Send + ::std::marker::Sync + 'static,
+            #(#args: ::tachyon_core::extract::FromRequest<S>,)*
+        {
+            ::tachyon_core::router::Endpoint::new(
+                ::tachyon_core::tachyon_http::methods::Method::#method,
+                #path,
+                |state| ::tachyon_core::extract::with_state::<S, (#(#args,)*), _>(state, #name),
+            )
+        }
+    })
+}
+
+/// Reject paths the exact-match router could never serve.
+fn check_path(path: &LitStr) -> syn::Result<()> {
+    let value = path.value();
+    let error = |message: &str| Err(syn::Error::new(path.span(), message));
+    if !value.starts_with('/') {
+        return error("route paths must start with `/`");
+    }
+    if value.contains(['?', '#']) {
+        return error("route paths cannot contain a query or fragment");
+    }
+    if value.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return error("route paths cannot contain whitespace");
+    }
+    if value
+        .split('/')
+        .any(|segment| segment.starts_with([':', '*']) || segment.contains(['{', '}']))
+    {
+        return error(
+            "path parameters are not supported: routes match exact paths \
+             (take the `Uri` extractor and parse the path instead)",
+        );
+    }
+    Ok(())
+}
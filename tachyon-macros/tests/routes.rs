@@ -0,0 +1,50 @@
+use tachyon_core::extract::{Body, State};
+use tachyon_core::router::{Route, Router};
+use tachyon_core::tachyon_http::methods::Method;
+use tachyon_core::testing::{request, respond};
+use tachyon_macros::{get, post, route, routes};
+
+#[get("/hello")]
+async fn hello() -> &'static str {
+    "hello"
+}
+
+#[post("/count")]
+async fn count(State(prefix): State<&'static str>, Body(body): Body) -> String {
+    format!("{prefix}{}", body.len())
+}
+
+mod nested {
+    use super::*;
+
+    #[route(PATCH, "/nested")]
+    pub async fn patched() -> (u16, &'static str) {
+        (202, "patched")
+    }
+}
+
+fn call(router: &Router, raw: &[u8]) -> String {
+    let req = request(raw);
+    let Some(Route::Handler(handler)) = router.find(req.method, req.path) else {
+        panic!("no route for {}", String::from_utf8_lossy(req.path));
+    };
+    respond(handler, raw)
+}
+
+#[test]
+fn mounts_attributed_handlers() {
+    let mut router = Router::new();
+    router.mount(routes![nested::patched]);
+    router.mount_with_state("n=", routes![hello, count]);
+    assert_eq!(router.len(), 3);
+    assert!(router.find(Method::Get, b"/hello").is_some());
+
+    assert!(call(&router, b"GET /hello HTTP/1.1\r\n\r\n").ends_with("\r\n\r\nhello"));
+    let out = call(
+        &router,
+        b"POST /count HTTP/1.1\r\nContent-Length: 3\r\n\r\nabc",
+    );
+    assert!(out.ends_with("\r\n\r\nn=3"));
+    let out = call(&router, b"PATCH /nested HTTP/1.1\r\n\r\n");
+    assert!(out.starts_with("HTTP/1.1 202"));
+}
@@ -1,28 +1,264 @@
 /* auto-generated by NAPI-RS */
 /* eslint-disable */
+/**
+ * Writes a streamed response body from JavaScript.
+ *
+ * ```typescript
+ * const writer = new TachyonRawBodyWriter()
+ * void (async () => {
+ *   for await (const chunk of source) {
+ *     if (!(await writer.write(chunk))) return // client gone
+ *   }
+ *   writer.end()
+ * })()
+ * return { status: 200, contentType: 'text/event-stream', streamId: writer.id }
+ * ```
+ */
+export declare class TachyonRawBodyWriter {
+  /** `buffer`: chunks queued before `write` waits for the client (default: 16). */
+  constructor(buffer?: number | undefined | null)
+  /**
+   * Pass as `streamId` in the route's response. Unused for a minute, the
+   * body is dropped.
+   */
+  get id(): number
+  /**
+   * Queue a chunk. Resolves `false` once the client has gone away or the
+   * writer was ended: stop producing. Await each write before the next,
+   * or chunks may be reordered.
+   */
+  write(chunk: string | Uint8Array): Promise<boolean>
+  /** End the body once the chunks already written are sent. */
+  end(): void
+  /**
+   * Cut the response short: the client sees a truncated body and the
+   * connection is closed.
+   */
+  abort(): Promise<boolean>
+}
+
 /** The Tachyon server instance. Routes are registered in Rust for zero-overhead dispatch. */
 export declare class TachyonRawServer {
   constructor(config?: TachyonRawConfig | undefined | null)
   /**
    * Register a route handler. Called once per route at startup from TypeScript.
    *
-   * The handler receives a `TachyonRawRequest` and returns a `TachyonRawResponse`.
+   * The handler receives a `TachyonRawRequest` and returns a `TachyonRawResponse`,
+   * or a Promise of one (an `async` handler); a rejected Promise answers 500.
    * Routing and 404 handling happen entirely in Rust — no JS call for unmatched paths.
+   * `options` limits, caches or times out this route alone; requests it
+   * rejects never reach JavaScript. Registering `method` + `path` again
+   * replaces the route, also while listening: with the same (uncached)
+   * options, in place; otherwise by rebuilding the route table, which
+   * empties its caches and restarts its counters. With `options.pool` the
+   * route runs on worker threads instead (see `joinPool`).
    */
-  route(method: string, path: string, handler: (arg: TachyonRawRequest) => TachyonRawResponse): void
+  route(method: string, path: string, handler: (arg: TachyonRawRequest) => Promise<TachyonRawResponse> | TachyonRawResponse, options?: TachyonRawRouteOptions | undefined | null): void
+  /**
+   * Register a hook that sees every request to a route handler before it
+   * runs, in registration order. Returning a `TachyonRawResponse` (or a
+   * Promise of one) answers with it and skips the rest; returning nothing
+   * lets the request through. A hook that throws or rejects answers 500.
+   * Static routes and cache hits are answered before hooks run.
+   */
+  use(hook: (arg: TachyonRawRequest) => Promise<TachyonRawResponse | undefined | null> | TachyonRawResponse | undefined | null): void
+  /**
+   * Accept WebSocket upgrades on `path` (a GET route). `onEvent` gets the
+   * `open`, `message` and `close` events of every socket, each carrying a
+   * `TachyonRawWebSocket` to send on. Other requests to `path` get 426.
+   */
+  ws(path: string, onEvent: (arg: TachyonRawWsEvent) => void): void
+  /**
+   * Answer GET `path` with a server-sent event stream. `onEvent` gets its
+   * `open` event, carrying a `TachyonRawSseStream` to send on, and its
+   * `close` once the client goes away or the stream is closed.
+   */
+  sse(path: string, onEvent: (arg: TachyonRawSseEvent) => void): void
+  /**
+   * Register a constant response. Rendered once in Rust at `listen()` —
+   * requests to it never reach JavaScript. Ideal for health checks.
+   *
+   * `contentType` accepts "json" | "text" | "html" or any MIME type (default: "json").
+   */
+  staticRoute(method: string, path: string, body: string, contentType?: string | undefined | null): void
+  /**
+   * Remove the route registered as `method` + `path` (and its deadline).
+   * While listening, requests already running finish and later ones get
+   * 404. Returns `false` if there was no such route.
+   */
+  unregister(method: string, path: string): boolean
+  /**
+   * Serve the files of directory `dir` under `prefix`, read in Rust with
+   * ETags, ranges and cache headers: `GET <prefix>/css/app.css` answers
+   * with `<dir>/css/app.css`. Routes take precedence.
+   */
+  files(prefix: string, dir: string, options?: TachyonRawFileOptions | undefined | null): void
+  /**
+   * Answer requests that match no route or `files` directory with
+   * `handler` instead of the built-in 404, e.g. for an SPA's index page
+   * or a custom not-found page. The response status is the handler's
+   * (default: 200). Replaces an earlier fallback, also while listening.
+   */
+  fallback(handler: (arg: TachyonRawRequest) => Promise<TachyonRawResponse> | TachyonRawResponse): void
+  /**
+   * Call `callback` with a request's `requestId` when the response to a
+   * route callback is no longer wanted: the client went away or the
+   * deadline passed first. Without it, requests carry no `requestId`.
+   * Watching for disconnects reads ahead on the connection while the
+   * callback runs.
+   */
+  onAbort(callback: (arg: number) => void): void
+  /**
+   * Give one route its own response deadline, overriding `timeoutSecs`.
+   * If the JS handler hasn't answered by then, the client gets a 504
+   * (customizable via `errorPages`).
+   */
+  deadline(method: string, path: string, timeoutMs: number): void
   /**
    * Start the server. Must be called after all routes are registered.
    *
    * Builds an O(1) route map and starts the Tokio runtime on a background thread.
+   * Returns the port bound for `bindAddr` — the one the OS picked when it is 0.
+   */
+  listen(): number
+  /**
+   * Every listening address, `bindAddr` first; empty before `listen` and
+   * after `close`.
    */
-  listen(): void
+  addresses(): Array<TachyonRawAddress>
+  /**
+   * Drop the cached responses of `path` (any query string) from the route
+   * caches (`cacheTtlMs`). Does nothing before `listen`.
+   */
+  invalidateCache(path: string): void
+  /** Empty every route cache. Does nothing before `listen`. */
+  invalidateAll(): void
+  /**
+   * Call every cached GET route (`cacheTtlMs`) once and keep its response,
+   * so the first client isn't the one waiting on the handler. Resolves
+   * with how many responses were cached; 0 before `listen`.
+   */
+  warmup(): Promise<number>
+  /** Counters of every route cache (`cacheTtlMs`); empty before `listen`. */
+  getCacheStats(): Array<TachyonRawCacheStats>
+  /**
+   * Counters of every route, keyed by method and path ("GET /users");
+   * empty before `listen`. Still readable after `close`, without `cache`.
+   */
+  getStats(): Record<string, TachyonRawRouteStats>
+  /** Zero the route and cache counters; cached responses are kept. */
+  resetStats(): void
+  /**
+   * Stop accepting and close connections once their current response is
+   * sent. Resolves when the last one has closed (or was cut off after
+   * `drainTimeoutMs`) and the route callbacks are released, so the
+   * process can exit; resolves at once before `listen`.
+   */
+  close(): Promise<void>
+}
+
+/**
+ * An open event stream. The same stream is passed with both events;
+ * compare `id`s, not objects.
+ */
+export declare class TachyonRawSseStream {
+  /** Unique per stream for the life of the process. */
+  get id(): number
+  /**
+   * Send an event. Resolves `false` once the client is gone or the stream
+   * closed: stop producing. Await each send before the next, or events
+   * may be reordered.
+   */
+  send(message: TachyonRawSseMessage): Promise<boolean>
+  /**
+   * Send a comment, ignored by clients; keeps idle proxies from closing
+   * the stream.
+   */
+  comment(text?: string | undefined | null): Promise<boolean>
+  /** End the stream once the events already sent are written. */
+  close(): void
+}
+
+/**
+ * An open WebSocket. The same socket is passed with every event; compare
+ * `id`s, not objects.
+ */
+export declare class TachyonRawWebSocket {
+  /** Unique per socket for the life of the process. */
+  get id(): number
+  /**
+   * Send a text (string) or binary (Uint8Array) message. Resolves `false`
+   * once the socket is closed.
+   */
+  send(data: string | Uint8Array): Promise<boolean>
+  /** Close the socket (default code: 1000) after the messages already sent. */
+  close(code?: number | undefined | null, reason?: string | undefined | null): Promise<boolean>
+}
+
+/**
+ * Serve route `method` + `path` of `pool` from this thread, usually a
+ * `worker_threads` Worker: requests to a route registered with
+ * `{ pool }` are spread round robin over every thread that joined it.
+ * The thread leaves when it exits or calls `leavePool`.
+ */
+export declare function joinPool(pool: string, method: string, path: string, handler: (arg: TachyonRawRequest) => Promise<TachyonRawResponse> | TachyonRawResponse): void
+
+/** Stop serving `pool` from this thread; requests already running finish. */
+export declare function leavePool(pool: string): void
+
+/**
+ * Parse a `multipart/form-data` body off the JS thread. Rejects if
+ * `contentType` isn't multipart/form-data or the body is malformed.
+ */
+export declare function parseMultipart(contentType: string, body: Uint8Array, options?: TachyonRawMultipartOptions | undefined | null): Promise<TachyonRawMultipart>
+
+/** An address the server listens on. */
+export interface TachyonRawAddress {
+  /** IP address, e.g. "127.0.0.1" or "::1" (no brackets). */
+  address: string
+  port: number
+}
+
+/** Counters of one route cache, from `getCacheStats`. */
+export interface TachyonRawCacheStats {
+  method: string
+  path: string
+  hits: number
+  misses: number
+  /** hits / (hits + misses), 0 before the first request. */
+  hitRate: number
+  entries: number
+  /** Fresh responses dropped to make room for new ones. */
+  evictions: number
+  /** Entries held by each shard (`cacheShards`). */
+  shardEntries: Array<number>
 }
 
 /** Server configuration exposed to TypeScript. */
 export interface TachyonRawConfig {
   bindAddr?: string
+  /** More addresses to listen on alongside `bindAddr`. */
+  extraBinds?: Array<string>
+  /** Acceptor threads sharing the addresses via SO_REUSEPORT (default: 1). */
+  acceptors?: number
+  /** Runtime worker threads: 1 (default) = single-threaded, 0 = one per core. */
+  workerThreads?: number
+  /** Cap on open connections; further clients wait in the listen backlog. */
+  maxConnections?: number
+  /** Listen backlog, 1-65535 (default: 1024). */
+  backlog?: number
+  /** Per-connection read/write buffer in bytes, 1 KiB-64 MiB (default: 8192). */
   bufferSize?: number
+  /** TCP keepalive: seconds idle before the first probe (default: OS setting). */
+  tcpKeepaliveSecs?: number
+  /** IPV6_V6ONLY for IPv6 addresses; false = `[::]` also accepts IPv4 (default: OS setting). */
+  ipv6Only?: boolean
   timeoutSecs?: number
+  /**
+   * "respond" (504 once `timeoutSecs` runs out) | "wait" (no deadline)
+   * (default: "respond").
+   */
+  onTimeout?: string
   tcpNodelay?: boolean
   reusePort?: boolean
   tcpFastopen?: boolean
@@ -32,6 +268,79 @@ export interface TachyonRawConfig {
   security?: string
   compressionThreshold?: number
   catchPanics?: boolean
+  /** "strong" | "weak" | "off" (default: "off"). */
+  etag?: string
+  /** Replacement bodies for errors the server generates itself (404, 405, 413, 500, 504, …). */
+  errorPages?: Array<TachyonRawErrorPage>
+  /** Pretty-print JSON bodies, including error payloads. Development only. */
+  prettyJson?: boolean
+  /** CORS policy. Preflights are answered in Rust without calling JS. */
+  cors?: TachyonRawCors
+  /**
+   * How long `close()` waits for open connections before cutting them
+   * off, in milliseconds (default: 30000).
+   */
+  drainTimeoutMs?: number
+  /**
+   * Put the message and stack of an error a route callback or `use` hook
+   * throws (or rejects with) in its 500 response. Development only
+   * (default: false).
+   */
+  exposeErrors?: boolean
+  /**
+   * Route callbacks (pooled ones included) and `use` hooks that may wait
+   * for a JS thread at once; while that many are queued, further requests
+   * get 503 + `Retry-After` (or the `errorPages` 503) without calling JS
+   * (default: unlimited).
+   */
+  maxQueuedCalls?: number
+}
+
+/** A cookie to set on the client. */
+export interface TachyonRawCookie {
+  name: string
+  value: string
+  path?: string
+  domain?: string
+  /** Lifetime in seconds; 0 deletes the cookie. Unset = session cookie. */
+  maxAgeSecs?: number
+  secure?: boolean
+  httpOnly?: boolean
+  /** "strict" | "lax" | "none" ("none" implies `secure`). */
+  sameSite?: string
+}
+
+/** CORS policy exposed to TypeScript. */
+export interface TachyonRawCors {
+  /** Allowed origins. Omit (or include "*") to allow any origin. */
+  origins?: Array<string>
+  /** Allowed methods (default: GET, HEAD, POST, PUT, PATCH, DELETE). */
+  methods?: Array<string>
+  /** Allowed request headers (default: mirror the preflight's request). */
+  headers?: Array<string>
+  /** Response headers readable by scripts. */
+  exposeHeaders?: Array<string>
+  credentials?: boolean
+  maxAgeSecs?: number
+}
+
+/** A custom body for a server-generated error status. */
+export interface TachyonRawErrorPage {
+  status: number
+  body: string
+  /** "json" | "text" | "html" or any MIME type (default: "json"). */
+  contentType?: string
+}
+
+/** Settings for a directory mounted with `files`. */
+export interface TachyonRawFileOptions {
+  /** `Cache-Control: public, max-age=<secs>` on every file. */
+  maxAgeSecs?: number
+  /**
+   * File answering for a directory; "" answers directories 404
+   * (default: "index.html").
+   */
+  index?: string
 }
 
 /** A single HTTP header key-value pair. */
@@ -73,6 +382,22 @@ export interface TachyonRawJsonField {
   children?: Array<TachyonRawJsonField>
 }
 
+/** A parsed `multipart/form-data` body. */
+export interface TachyonRawMultipart {
+  /** Text fields; the first value wins when a name repeats. */
+  fields: Record<string, string>
+  files: Array<TachyonRawUpload>
+}
+
+/** How `parseMultipart` hands over uploaded files. */
+export interface TachyonRawMultipartOptions {
+  /**
+   * Write each file to a new file in this directory and return its `path`
+   * instead of its `data`; the caller deletes it when done.
+   */
+  uploadDir?: string
+}
+
 /**
  * Parsed HTTP request exposed to TypeScript callbacks.
  *
@@ -85,12 +410,36 @@ export interface TachyonRawJsonField {
  */
 export interface TachyonRawRequest {
   method: string
+  /** Request target as sent: path and query string. */
   path: string
-  body?: string
+  /** Query string without the `?`, if there is one. */
+  query?: string
+  /**
+   * The query string decoded into an object; the first value wins when a
+   * name repeats.
+   */
+  params?: Record<string, string>
+  /**
+   * JSON bodies (`application/json`, `*+json`), parsed in Rust and built
+   * as a JS value; unset if the body isn't valid JSON.
+   */
+  json?: any
+  /**
+   * Raw body bytes (chunked bodies already decoded), handed to JS as an
+   * external Buffer: no copy into the JS heap and no UTF-8 validation.
+   */
+  body?: Buffer
   /** Flat header string: "name\tvalue
   name2\tvalue2
   " — 1 alloc instead of 20+ */
   headers: string
+  /** Client address, "ip:port" ("[ip]:port" for IPv6). */
+  remoteAddr: string
+  /**
+   * Set for route callbacks once `onAbort` is registered: the id it
+   * reports if this request is aborted.
+   */
+  requestId?: number
 }
 
 /**
@@ -107,16 +456,30 @@ export interface TachyonRawRequest {
  *
  * // 3. body — plain text or custom content type
  * return { status: 200, body: "Hello!", contentType: "text" };
+ *
+ * // 4. bytes — binary body (Buffer or any Uint8Array), sent as-is
+ * return { status: 200, bytes: png, contentType: "image/png" };
+ *
+ * // 5. streamId — chunked body written through a TachyonRawBodyWriter
+ * return { status: 200, streamId: writer.id, contentType: "text/event-stream" };
  * ```
  */
 export interface TachyonRawResponse {
   status?: number
   /**
    * Plain body string. Used with `contentType` to set the response type.
-   * Lowest priority — ignored if `json` or `array` is present.
+   * Lowest priority — ignored if `json`, `array` or `bytes` is present.
    */
   body?: string
-  /** "json" | "text" | "html" (default: "json"). Only applies to `body`. */
+  /**
+   * Binary body (Buffer or any Uint8Array). Read straight from JS memory —
+   * no UTF-8 round trip. Ignored if `json` or `array` is present.
+   */
+  bytes?: Uint8Array
+  /**
+   * "json" | "text" | "html" or any MIME type, e.g. "image/svg+xml".
+   * Applies to `body` (default: "json") and `bytes` (default: "application/octet-stream").
+   */
   contentType?: string
   /** Custom HTTP headers as key-value pairs (e.g., CORS, Cache-Control). */
   headers?: Array<TachyonRawHeader>
@@ -127,4 +490,130 @@ export interface TachyonRawResponse {
   json?: Array<TachyonRawJsonField>
   /** JSON array — elements serialized as `[...]` by Rust's zero-alloc JsonWriter. */
   array?: Array<TachyonRawJsonField>
+  /** Download filename. Sets `Content-Disposition: attachment` (RFC 6266/5987 encoded). */
+  attachment?: string
+  /**
+   * `Set-Cookie` headers. A cookie with characters it can't carry (e.g.
+   * `;` in the value) is dropped.
+   */
+  cookies?: Array<TachyonRawCookie>
+  /**
+   * Stream the body from a `TachyonRawBodyWriter` (its `id`) instead of
+   * sending `json`, `array`, `bytes` or `body`. `contentType` defaults to
+   * "application/octet-stream".
+   */
+  streamId?: number
+}
+
+/**
+ * Counters of one route, from `getStats`. Latencies are in
+ * milliseconds; percentiles are bucket upper bounds.
+ */
+export interface TachyonRawRouteStats {
+  requests: number
+  /** Responses with a 5xx status. */
+  errors: number
+  /** Handlers that ran past their deadline. */
+  timeouts: number
+  /** Requests per second since `listen` (or `resetStats`). */
+  throughput: number
+  meanMs: number
+  p50Ms: number
+  p90Ms: number
+  p99Ms: number
+  maxMs: number
+  /** Request body bytes received. */
+  bytesIn: number
+  /** Response body bytes sent. */
+  bytesOut: number
+  /** Largest request body seen. */
+  maxBodyIn: number
+  /** Set for routes with `cacheTtlMs`. */
+  cache?: TachyonRawCacheStats
+}
+
+/** Settings for one route; anything unset follows the server config. */
+export interface TachyonRawRouteOptions {
+  /** Reject request bodies larger than this many bytes with 413. */
+  bodyLimit?: number
+  /** Response deadline in milliseconds, overriding `timeoutSecs`. */
+  timeoutMs?: number
+  /** "respond" | "wait": "wait" lifts the deadline for this route. */
+  onTimeout?: string
+  /** Serve successful GET responses from a cache for this long. */
+  cacheTtlMs?: number
+  /** Responses the cache keeps, one per distinct query string (default: 1024). */
+  cacheMaxEntries?: number
+  /** Locks the cache is split over (default: 1). */
+  cacheShards?: number
+  /** "lru" | "slru" | "tinylfu": which response a full cache drops (default: "lru"). */
+  cacheEviction?: string
+  /** Accepted request body types, e.g. "application/json"; others get 415. */
+  accept?: Array<string>
+  /**
+   * Worker pool to run the route on: each request goes to the next
+   * callback joined for this route with `joinPool`. The route's own
+   * callback answers while none has joined.
+   */
+  pool?: string
+}
+
+/**
+ * What happened on a stream, passed to the `sse` callback: `open` once,
+ * then `close` when the client goes away or the stream is closed.
+ */
+export interface TachyonRawSseEvent {
+  /** "open" | "close" */
+  kind: string
+  stream: TachyonRawSseStream
+  /** `open`: the request. */
+  request?: TachyonRawRequest
+}
+
+/** One server-sent event. */
+export interface TachyonRawSseMessage {
+  /** May span lines. */
+  data: string
+  /** Event type for `addEventListener` (default: "message"). */
+  event?: string
+  /** Sent back by a reconnecting browser as `Last-Event-ID`. */
+  id?: string
+  /** How long a browser waits before reconnecting, in milliseconds. */
+  retryMs?: number
+}
+
+/** One uploaded file. */
+export interface TachyonRawUpload {
+  /** The form field it was sent as. */
+  field: string
+  /** As sent by the client: don't use it as a path. */
+  filename: string
+  contentType?: string
+  size: number
+  /** The contents, unless `uploadDir` was given. */
+  data?: Buffer
+  /** Where the contents were written, with `uploadDir`. */
+  path?: string
+}
+
+/**
+ * What happened on a socket, passed to the `ws` callback. Events of one
+ * socket arrive in order: `open`, any `message`s, then `close`.
+ */
+export interface TachyonRawWsEvent {
+  /** "open" | "message" | "close" */
+  kind: string
+  socket: TachyonRawWebSocket
+  /** `open`: the upgrade request. */
+  request?: TachyonRawRequest
+  /** `message`: a text message. */
+  text?: string
+  /** `message`: a binary message. */
+  data?: Buffer
+  /**
+   * `close`: the client's close code; 1005 if it sent none, 1006 if the
+   * connection dropped without a close.
+   */
+  code?: number
+  reason?: string
 }
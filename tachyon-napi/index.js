@@ -576,4 +576,9 @@ if (!nativeBinding) {
 }
 
 module.exports = nativeBinding
+module.exports.TachyonRawBodyWriter = nativeBinding.TachyonRawBodyWriter
 module.exports.TachyonRawServer = nativeBinding.TachyonRawServer
+module.exports.TachyonRawWebSocket = nativeBinding.TachyonRawWebSocket
+module.exports.joinPool = nativeBinding.joinPool
+module.exports.leavePool = nativeBinding.leavePool
+module.exports.parseMultipart = nativeBinding.parseMultipart
@@ -1,4 +1,7 @@
 #![deny(clippy::all)]
+use std::collections::HashMap;
+
+use napi::bindgen_prelude::{Buffer, Uint8Array};
 use napi_derive::napi;
 
 /// Parsed HTTP request exposed to TypeScript callbacks.
@@ -12,10 +15,26 @@ use napi_derive::napi;
 #[napi(object)]
 pub struct TachyonRawRequest {
   pub method: String,
+  /// Request target as sent: path and query string.
   pub path: String,
-  pub body: Option<String>,
+  /// Query string without the `?`, if there is one.
+  pub query: Option<String>,
+  /// The query string decoded into an object; the first value wins when a
+  /// name repeats.
+  pub params: Option<HashMap<String, String>>,
+  /// JSON bodies (`application/json`, `*+json`), parsed in Rust and built
+  /// as a JS value; unset if the body isn't valid JSON.
+  pub json: Option<serde_json::Value>,
+  /// Raw body bytes (chunked bodies already decoded), handed to JS as an
+  /// external Buffer: no copy into the JS heap and no UTF-8 validation.
+  pub body: Option<Buffer>,
   /// Flat header string: "name\tvalue\nname2\tvalue2\n" — 1 alloc instead of 20+
   pub headers: String,
+  /// Client address, "ip:port" ("[ip]:port" for IPv6).
+  pub remote_addr: String,
+  /// Set for route callbacks once `onAbort` is registered: the id it
+  /// reports if this request is aborted.
+  pub request_id: Option<u32>,
 }
 
 /// Response from TypeScript handler.
@@ -31,14 +50,24 @@ pub struct TachyonRawRequest {
 ///
 /// // 3. body — plain text or custom content type
 /// return { status: 200, body: "Hello!", contentType: "text" };
+///
+/// // 4. bytes — binary body (Buffer or any Uint8Array), sent as-is
+/// return { status: 200, bytes: png, contentType: "image/png" };
+///
+/// // 5. streamId — chunked body written through a TachyonRawBodyWriter
+/// return { status: 200, streamId: writer.id, contentType: "text/event-stream" };
 /// ```
 #[napi(object)]
 pub struct TachyonRawResponse {
   pub status: Option<u32>, // default: 200
   /// Plain body string. Used with `contentType` to set the response type.
-  /// Lowest priority — ignored if `json` or `array` is present.
+  /// Lowest priority — ignored if `json`, `array` or `bytes` is present.
   pub body: Option<String>,
-  /// "json" | "text" | "html" (default: "json"). Only applies to `body`.
+  /// Binary body (Buffer or any Uint8Array). Read straight from JS memory —
+  /// no UTF-8 round trip. Ignored if `json` or `array` is present.
+  pub bytes: Option<Uint8Array>,
+  /// "json" | "text" | "html" or any MIME type, e.g. "image/svg+xml".
+  /// Applies to `body` (default: "json") and `bytes` (default: "application/octet-stream").
   pub content_type: Option<String>,
   /// Custom HTTP headers as key-value pairs (e.g., CORS, Cache-Control).
   pub headers: Option<Vec<TachyonRawHeader>>,
@@ -47,6 +76,15 @@ pub struct TachyonRawResponse {
   pub json: Option<Vec<TachyonRawJsonField>>,
   /// JSON array — elements serialized as `[...]` by Rust's zero-alloc JsonWriter.
   pub array: Option<Vec<TachyonRawJsonField>>,
+  /// Download filename. Sets `Content-Disposition: attachment` (RFC 6266/5987 encoded).
+  pub attachment: Option<String>,
+  /// `Set-Cookie` headers. A cookie with characters it can't carry (e.g.
+  /// `;` in the value) is dropped.
+  pub cookies: Option<Vec<TachyonRawCookie>>,
+  /// Stream the body from a `TachyonRawBodyWriter` (its `id`) instead of
+  /// sending `json`, `array`, `bytes` or `body`. `contentType` defaults to
+  /// "application/octet-stream".
+  pub stream_id: Option<u32>,
 }
 
 /// A typed JSON node for zero-alloc serialization via Rust's JsonWriter.
@@ -87,3 +125,43 @@ pub struct TachyonRawHeader {
   pub name: String,
   pub value: String,
 }
+
+/// A cookie to set on the client.
+#[napi(object)]
+pub struct TachyonRawCookie {
+  pub name: String,
+  pub value: String,
+  pub path: Option<String>,
+  pub domain: Option<String>,
+  /// Lifetime in seconds; 0 deletes the cookie. Unset = session cookie.
+  pub max_age_secs: Option<u32>,
+  pub secure: Option<bool>,
+  pub http_only: Option<bool>,
+  /// "strict" | "lax" | "none" ("none" implies `secure`).
+  pub same_site: Option<String>,
+}
+
+impl From<&TachyonRawCookie> for tachyon_core::cookie::Cookie {
+  fn from(raw: &TachyonRawCookie) -> Self {
+    use tachyon_core::cookie::{Cookie, SameSite};
+
+    let mut cookie = Cookie::new(&raw.name, &raw.value)
+      .secure(raw.secure.unwrap_or(false))
+      .http_only(raw.http_only.unwrap_or(false));
+    if let Some(path) = &raw.path {
+      cookie = cookie.path(path);
+    }
+    if let Some(domain) = &raw.domain {
+      cookie = cookie.domain(domain);
+    }
+    if let Some(secs) = raw.max_age_secs {
+      cookie = cookie.max_age(std::time::Duration::from_secs(secs as u64));
+    }
+    match raw.same_site.as_deref() {
+      Some("strict") => cookie.same_site(SameSite::Strict),
+      Some("lax") => cookie.same_site(SameSite::Lax),
+      Some("none") => cookie.same_site(SameSite::None),
+      _ => cookie,
+    }
+  }
+}
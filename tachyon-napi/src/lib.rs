@@ -3,18 +3,27 @@
 #[global_allocator]
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
-use std::collections::HashMap;
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
 
+use napi::bindgen_prelude::{
+  CallbackContext, Either, FromNapiValue, Function, JsObjectValue, Promise, PromiseRaw, TypeName,
+  Unknown, ValidateNapiValue,
+};
 use napi::threadsafe_function::ThreadsafeFunctionCallMode;
-use napi::{Result, Status, bindgen_prelude::Function};
+use napi::{JsValue, Result, Status};
 use napi_derive::napi;
+use tachyon_http::methods::Method;
 
 use crate::handle::{TachyonRawJsonField, TachyonRawRequest, TachyonRawResponse};
 
 mod handle;
+pub mod multipart;
+mod sse;
+mod stream;
+pub mod workers;
+mod ws;
 
 /// Per-route async handler: receives owned request, returns a WriteFn future.
 type AsyncRouteFn = Arc<
@@ -23,50 +32,175 @@ type AsyncRouteFn = Arc<
     + Sync,
 >;
 
-/// Map: method_id → path_bytes → handler
-type RouteMap = Arc<HashMap<u8, HashMap<Box<[u8]>, AsyncRouteFn>>>;
-
-/// Zero-alloc method → u8 id for HashMap key.
-#[inline(always)]
-fn method_to_id(m: tachyon_http::methods::Method) -> u8 {
-  match m {
-    tachyon_http::methods::Method::Get => 0,
-    tachyon_http::methods::Method::Post => 1,
-    tachyon_http::methods::Method::Put => 2,
-    tachyon_http::methods::Method::Delete => 3,
-    tachyon_http::methods::Method::Patch => 4,
-    tachyon_http::methods::Method::Head => 5,
-    tachyon_http::methods::Method::Options => 6,
-    tachyon_http::methods::Method::Other => 7,
-  }
-}
-
-/// Zero-alloc method → static str. For passing to JS handlers.
-#[inline(always)]
-fn method_str(m: tachyon_http::methods::Method) -> &'static str {
-  match m {
-    tachyon_http::methods::Method::Get => "GET",
-    tachyon_http::methods::Method::Post => "POST",
-    tachyon_http::methods::Method::Put => "PUT",
-    tachyon_http::methods::Method::Delete => "DELETE",
-    tachyon_http::methods::Method::Patch => "PATCH",
-    tachyon_http::methods::Method::Head => "HEAD",
-    tachyon_http::methods::Method::Options => "OPTIONS",
-    tachyon_http::methods::Method::Other => "OTHER",
-  }
-}
-
-/// Parse string method name → u8 id (used when registering routes from JS).
-fn str_to_method_id(s: &str) -> u8 {
-  match s {
-    "GET" => 0,
-    "POST" => 1,
-    "PUT" => 2,
-    "DELETE" => 3,
-    "PATCH" => 4,
-    "HEAD" => 5,
-    "OPTIONS" => 6,
-    _ => 7,
+/// Calls a JS route callback (or, with `R` = `Option<TachyonRawResponse>`,
+/// a `use` hook) from any thread, holding the call's place in the
+/// `CallQueue`, if any, until the JS thread takes it.
+type JsCallFn<R = TachyonRawResponse> = Arc<
+  dyn Fn(
+      TachyonRawRequest,
+      Option<Queued>,
+    ) -> Pin<Box<dyn Future<Output = std::result::Result<R, CallError>> + Send>>
+    + Send
+    + Sync,
+>;
+
+/// Why a route callback gave no response.
+enum CallError {
+  /// It threw or rejected.
+  Threw(napi::Error),
+  /// It couldn't be called.
+  Failed,
+  /// `maxQueuedCalls` calls were already waiting for the JS thread.
+  Busy,
+}
+
+/// Route callbacks and hooks waiting for the JS thread, bounded by
+/// `maxQueuedCalls`.
+struct CallQueue {
+  queued: std::sync::atomic::AtomicUsize,
+  limit: usize,
+}
+
+impl CallQueue {
+  /// A place in `queue`, if there is one; `Busy` when it is full.
+  fn enter_opt(queue: Option<&Arc<Self>>) -> std::result::Result<Option<Queued>, CallError> {
+    match queue {
+      Some(queue) => queue.enter().map(Some).ok_or(CallError::Busy),
+      None => Ok(None),
+    }
+  }
+
+  /// Take a place in the queue, given back when the guard drops; `None`
+  /// if the queue is full.
+  fn enter(self: &Arc<Self>) -> Option<Queued> {
+    use std::sync::atomic::Ordering;
+    if self.queued.fetch_add(1, Ordering::AcqRel) >= self.limit {
+      self.queued.fetch_sub(1, Ordering::AcqRel);
+      return None;
+    }
+    Some(Queued(self.clone()))
+  }
+}
+
+/// A call's place in a `CallQueue`.
+struct Queued(Arc<CallQueue>);
+
+impl Drop for Queued {
+  fn drop(&mut self) {
+    self
+      .0
+      .queued
+      .fetch_sub(1, std::sync::atomic::Ordering::AcqRel);
+  }
+}
+
+/// What a JS route callback returns. The Promise comes first: a plain
+/// object would accept it too.
+type JsReturn = Either<JsPromise<TachyonRawResponse>, TachyonRawResponse>;
+
+/// What a `use` hook returns: a response to answer with, or nothing to
+/// let the request through.
+type JsHookReturn = Either<JsPromise<Option<TachyonRawResponse>>, Option<TachyonRawResponse>>;
+
+/// A Promise awaited from Rust, like napi's `Promise`, except that its
+/// rejection keeps the error's stack: the reason reads
+/// "`String(err)`\n`err.stack`", as napi reports a synchronous throw.
+pub struct JsPromise<T>(tokio::sync::oneshot::Receiver<Result<T>>);
+
+impl<T: FromNapiValue> TypeName for JsPromise<T> {
+  fn type_name() -> &'static str {
+    "Promise"
+  }
+
+  fn value_type() -> napi::ValueType {
+    napi::ValueType::Object
+  }
+}
+
+impl<T: FromNapiValue + 'static> ValidateNapiValue for JsPromise<T> {
+  unsafe fn validate(
+    env: napi::sys::napi_env,
+    value: napi::sys::napi_value,
+  ) -> Result<napi::sys::napi_value> {
+    unsafe { Promise::<T>::validate(env, value) }
+  }
+}
+
+impl<T: FromNapiValue> FromNapiValue for JsPromise<T> {
+  unsafe fn from_napi_value(
+    env: napi::sys::napi_env,
+    value: napi::sys::napi_value,
+  ) -> Result<Self> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let promise = unsafe { PromiseRaw::<T>::from_napi_value(env, value)? };
+    let tx = std::rc::Rc::new(std::cell::Cell::new(Some(tx)));
+    let tx_in_catch = tx.clone();
+    promise
+      .then(move |ctx| {
+        if let Some(tx) = tx.take() {
+          let _ = tx.send(Ok(ctx.value));
+        }
+        Ok(())
+      })?
+      .catch(move |ctx: CallbackContext<Unknown>| {
+        if let Some(tx) = tx_in_catch.take() {
+          let _ = tx.send(Err(rejection(ctx.value)));
+        }
+        Ok(())
+      })?;
+    Ok(Self(rx))
+  }
+}
+
+impl<T> Future for JsPromise<T> {
+  type Output = Result<T>;
+
+  fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> std::task::Poll<Result<T>> {
+    Pin::new(&mut self.0)
+      .poll(cx)
+      .map(|settled| settled.unwrap_or_else(|_| Err(napi::Error::from_reason("promise dropped"))))
+  }
+}
+
+/// The error a Promise rejected with, its stack appended when it has one.
+fn rejection(value: Unknown<'_>) -> napi::Error {
+  let stack = match value.get_type() {
+    Ok(napi::ValueType::Object) => value
+      .coerce_to_object()
+      .and_then(|err| err.get_named_property::<Option<String>>("stack"))
+      .ok()
+      .flatten(),
+    _ => None,
+  };
+  let message = value
+    .coerce_to_string()
+    .and_then(|s| s.into_utf8())
+    .and_then(|s| s.into_owned())
+    .unwrap_or_default();
+  napi::Error::from_reason(match stack {
+    Some(stack) => format!("{message}\n{stack}"),
+    None => message,
+  })
+}
+
+/// A route registered from JS, kept until `listen()` builds the core router.
+enum JsRoute {
+  Handler(AsyncRouteFn, TachyonRawRouteOptions),
+  Static {
+    body: Vec<u8>,
+    content_type: String,
+  },
+  /// Answered by a handler in Rust: WebSocket upgrades, event streams.
+  Native(tachyon_core::server::Handler),
+}
+
+/// Resolve the `contentType` shorthands ("json" | "text" | "html") to a MIME type.
+fn resolve_mime(content_type: &str) -> &str {
+  match content_type {
+    "json" => "application/json",
+    "text" | "plain" => "text/plain; charset=utf-8",
+    "html" => "text/html; charset=utf-8",
+    mime => mime,
   }
 }
 
@@ -124,19 +258,139 @@ fn write_json_field(w: &mut tachyon_http::json::JsonWriter, f: &TachyonRawJsonFi
   }
 }
 
-/// Build the WriteFn closure from a JS response (or None on handler error).
-fn make_write_fn(ts_res_opt: Option<TachyonRawResponse>) -> tachyon_core::server::WriteFn {
+/// Make a route callback callable from the server's threads. Calls are
+/// queued for the JS thread without blocking; the caller takes their place
+/// in a `CallQueue` first, with `CallQueue::enter_opt`.
+fn js_call<R>(handler: Function<TachyonRawRequest, Either<JsPromise<R>, R>>) -> Result<JsCallFn<R>>
+where
+  R: FromNapiValue + ValidateNapiValue + TypeName + Send + 'static,
+{
+  // Let Rust infer the full ThreadsafeFunction type from the Function parameter.
+  let ts_fn = Arc::new(handler.build_threadsafe_function().build()?);
+  Ok(Arc::new(
+    move |req: TachyonRawRequest, queued: Option<Queued>| {
+      let ts_fn = ts_fn.clone();
+      Box::pin(async move {
+        let (tx, rx) = tokio::sync::oneshot::channel::<napi::Result<Either<JsPromise<R>, R>>>();
+        let status = ts_fn.call_with_return_value(
+          req,
+          ThreadsafeFunctionCallMode::NonBlocking,
+          move |result: napi::Result<Either<JsPromise<R>, R>>, _env| {
+            // The callback has run: it no longer waits for the JS thread.
+            drop(queued);
+            let _ = tx.send(result);
+            Ok(())
+          },
+        );
+        if status != Status::Ok {
+          return Err(CallError::Failed);
+        }
+        match rx.await {
+          // Settled on the JS thread; awaiting it here blocks nothing.
+          Ok(Ok(Either::A(promise))) => promise.await.map_err(CallError::Threw),
+          Ok(Ok(Either::B(ts_res))) => Ok(ts_res),
+          Ok(Err(e)) => Err(CallError::Threw(e)),
+          Err(_) => Err(CallError::Failed),
+        }
+      }) as Pin<Box<dyn Future<Output = _> + Send>>
+    },
+  ))
+}
+
+/// Call a route callback with `req`, once it has a place in `queue`, and
+/// write what it answers.
+fn answer(
+  call: JsCallFn,
+  req: TachyonRawRequest,
+  queue: Option<&Arc<CallQueue>>,
+  expose_errors: bool,
+  pages: Arc<tachyon_core::config::ErrorPages>,
+) -> Pin<Box<dyn Future<Output = tachyon_core::server::WriteFn> + Send>> {
+  let called = CallQueue::enter_opt(queue).map(|queued| call(req, queued));
+  Box::pin(async move {
+    let answered = match called {
+      Ok(called) => called.await,
+      Err(e) => Err(e),
+    };
+    match answered {
+      Ok(ts_res) => make_write_fn(Ok(ts_res)),
+      Err(e) => failed(e, expose_errors, pages),
+    }
+  })
+}
+
+/// The answer to a callback or hook that gave no response: a 500, with
+/// the error when `expose_errors`, or a 503 when `maxQueuedCalls` turned
+/// it away.
+fn failed(
+  e: CallError,
+  expose_errors: bool,
+  pages: Arc<tachyon_core::config::ErrorPages>,
+) -> tachyon_core::server::WriteFn {
+  match e {
+    CallError::Threw(e) => make_write_fn(Err(expose_errors.then(|| e.reason.clone()))),
+    CallError::Failed => make_write_fn(Err(None)),
+    CallError::Busy => Box::new(move |res: &mut tachyon_core::response::Response<'_>| {
+      res.header(b"Retry-After", b"1");
+      res.error(503, &pages)
+    }),
+  }
+}
+
+/// Build the WriteFn closure from a JS response, or a 500 when the callback
+/// threw or rejected; `Err` carries the error if it may be shown, its
+/// message on the first line and its stack, if any, after.
+fn make_write_fn(
+  ts_res: std::result::Result<TachyonRawResponse, Option<String>>,
+) -> tachyon_core::server::WriteFn {
+  // Claimed now, so a response that is never written (the connection
+  // closed, a hook replaced it) drops the body along with it.
+  let body = match &ts_res {
+    Ok(ts_res) => ts_res.stream_id.map(stream::take),
+    Err(_) => None,
+  };
   Box::new(
-    move |res: &mut tachyon_core::response::Response<'_>| match ts_res_opt {
-      None => res.json(500, b"{\"error\":\"handler error\"}"),
-      Some(ts_res) => {
+    move |res: &mut tachyon_core::response::Response<'_>| match ts_res {
+      Err(None) => res.json(500, b"{\"error\":\"handler error\"}"),
+      Err(Some(error)) => res.json_writer(500, |w| {
+        let (message, stack) = match error.split_once('\n') {
+          Some((message, stack)) => (message, Some(stack)),
+          None => (error.as_str(), None),
+        };
+        w.object(|w| {
+          w.key("error").string("handler error");
+          w.key("message").string(message);
+          if let Some(stack) = stack {
+            w.key("stack").string(stack);
+          }
+        });
+      }),
+      Ok(ts_res) => {
         let status_code = ts_res.status.unwrap_or(200) as u16;
         if let Some(headers) = &ts_res.headers {
           for h in headers {
             res.header(h.name.as_bytes(), h.value.as_bytes());
           }
         }
-        if let Some(fields) = &ts_res.json {
+        for cookie in ts_res.cookies.iter().flatten() {
+          res.set_cookie(&cookie.into());
+        }
+        if let Some(filename) = &ts_res.attachment {
+          let disposition = tachyon_http::response::content_disposition(filename);
+          res.header(b"Content-Disposition", &disposition);
+        }
+        if let Some(body) = body {
+          match body {
+            Some(body) => {
+              let mime = ts_res
+                .content_type
+                .as_deref()
+                .unwrap_or("application/octet-stream");
+              res.stream(status_code, resolve_mime(mime), body)
+            }
+            None => res.json(500, b"{\"error\":\"unknown streamId\"}"),
+          }
+        } else if let Some(fields) = &ts_res.json {
           res.json_writer(status_code, |w| {
             w.object(|w| {
               for f in fields {
@@ -152,11 +406,19 @@ fn make_write_fn(ts_res_opt: Option<TachyonRawResponse>) -> tachyon_core::server
               }
             });
           })
+        } else if let Some(bytes) = &ts_res.bytes {
+          let mime = ts_res
+            .content_type
+            .as_deref()
+            .unwrap_or("application/octet-stream");
+          res.send(status_code, resolve_mime(mime), bytes)
         } else {
           let body = ts_res.body.as_deref().unwrap_or("");
           match ts_res.content_type.as_deref().unwrap_or("json") {
+            "json" => res.json(status_code, body.as_bytes()),
             "text" | "plain" => res.text(status_code, body.as_bytes()),
-            _ => res.json(status_code, body.as_bytes()),
+            "html" => res.html(status_code, body.as_bytes()),
+            mime => res.send(status_code, mime, body.as_bytes()),
           }
         }
       }
@@ -169,8 +431,26 @@ fn make_write_fn(ts_res_opt: Option<TachyonRawResponse>) -> tachyon_core::server
 #[derive(Debug, Clone)]
 pub struct TachyonRawConfig {
   pub bind_addr: Option<String>,
+  /// More addresses to listen on alongside `bind_addr`.
+  pub extra_binds: Option<Vec<String>>,
+  /// Acceptor threads sharing the addresses via SO_REUSEPORT (default: 1).
+  pub acceptors: Option<u32>,
+  /// Runtime worker threads: 1 (default) = single-threaded, 0 = one per core.
+  pub worker_threads: Option<u32>,
+  /// Cap on open connections; further clients wait in the listen backlog.
+  pub max_connections: Option<u32>,
+  /// Listen backlog, 1-65535 (default: 1024).
+  pub backlog: Option<u32>,
+  /// Per-connection read/write buffer in bytes, 1 KiB-64 MiB (default: 8192).
   pub buffer_size: Option<u32>,
+  /// TCP keepalive: seconds idle before the first probe (default: OS setting).
+  pub tcp_keepalive_secs: Option<u32>,
+  /// IPV6_V6ONLY for IPv6 addresses; false = `[::]` also accepts IPv4 (default: OS setting).
+  pub ipv6_only: Option<bool>,
   pub timeout_secs: Option<u32>,
+  /// "respond" (504 once `timeoutSecs` runs out) | "wait" (no deadline)
+  /// (default: "respond").
+  pub on_timeout: Option<String>,
   pub tcp_nodelay: Option<bool>,
   pub reuse_port: Option<bool>,
   pub tcp_fastopen: Option<bool>,
@@ -180,17 +460,297 @@ pub struct TachyonRawConfig {
   pub security: Option<String>,
   pub compression_threshold: Option<i32>,
   pub catch_panics: Option<bool>,
+  /// "strong" | "weak" | "off" (default: "off").
+  pub etag: Option<String>,
+  /// Replacement bodies for errors the server generates itself (404, 405, 413, 500, 504, …).
+  pub error_pages: Option<Vec<TachyonRawErrorPage>>,
+  /// Pretty-print JSON bodies, including error payloads. Development only.
+  pub pretty_json: Option<bool>,
+  /// CORS policy. Preflights are answered in Rust without calling JS.
+  pub cors: Option<TachyonRawCors>,
+  /// How long `close()` waits for open connections before cutting them
+  /// off, in milliseconds (default: 30000).
+  pub drain_timeout_ms: Option<u32>,
+  /// Put the message and stack of an error a route callback or `use` hook
+  /// throws (or rejects with) in its 500 response. Development only
+  /// (default: false).
+  pub expose_errors: Option<bool>,
+  /// Route callbacks (pooled ones included) and `use` hooks that may wait
+  /// for a JS thread at once; while that many are queued, further requests
+  /// get 503 + `Retry-After` (or the `errorPages` 503) without calling JS
+  /// (default: unlimited).
+  pub max_queued_calls: Option<u32>,
+}
+
+/// CORS policy exposed to TypeScript.
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct TachyonRawCors {
+  /// Allowed origins. Omit (or include "*") to allow any origin.
+  pub origins: Option<Vec<String>>,
+  /// Allowed methods (default: GET, HEAD, POST, PUT, PATCH, DELETE).
+  pub methods: Option<Vec<String>>,
+  /// Allowed request headers (default: mirror the preflight's request).
+  pub headers: Option<Vec<String>>,
+  /// Response headers readable by scripts.
+  pub expose_headers: Option<Vec<String>>,
+  pub credentials: Option<bool>,
+  pub max_age_secs: Option<u32>,
+}
+
+impl From<TachyonRawCors> for tachyon_core::cors::Cors {
+  fn from(ts: TachyonRawCors) -> Self {
+    let mut cors = tachyon_core::cors::Cors::new();
+    for origin in ts.origins.unwrap_or_default() {
+      if origin == "*" {
+        cors = cors.allow_any_origin();
+        break;
+      }
+      cors = cors.allow_origin(&origin);
+    }
+    if let Some(methods) = ts.methods {
+      let methods: Vec<Method> = methods
+        .iter()
+        .map(|m| Method::from_bytes(m.to_ascii_uppercase().as_bytes()))
+        .collect();
+      cors = cors.allow_methods(&methods);
+    }
+    if let Some(headers) = ts.headers {
+      let headers: Vec<&str> = headers.iter().map(String::as_str).collect();
+      cors = cors.allow_headers(&headers);
+    }
+    if let Some(headers) = ts.expose_headers {
+      let headers: Vec<&str> = headers.iter().map(String::as_str).collect();
+      cors = cors.expose_headers(&headers);
+    }
+    if let Some(v) = ts.credentials {
+      cors = cors.allow_credentials(v);
+    }
+    if let Some(secs) = ts.max_age_secs {
+      cors = cors.max_age(std::time::Duration::from_secs(secs as u64));
+    }
+    cors
+  }
+}
+
+/// Settings for one route; anything unset follows the server config.
+#[napi(object)]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TachyonRawRouteOptions {
+  /// Reject request bodies larger than this many bytes with 413.
+  pub body_limit: Option<u32>,
+  /// Response deadline in milliseconds, overriding `timeoutSecs`.
+  pub timeout_ms: Option<u32>,
+  /// "respond" | "wait": "wait" lifts the deadline for this route.
+  pub on_timeout: Option<String>,
+  /// Serve successful GET responses from a cache for this long.
+  pub cache_ttl_ms: Option<u32>,
+  /// Responses the cache keeps, one per distinct query string (default: 1024).
+  pub cache_max_entries: Option<u32>,
+  /// Locks the cache is split over (default: 1).
+  pub cache_shards: Option<u32>,
+  /// "lru" | "slru" | "tinylfu": which response a full cache drops (default: "lru").
+  pub cache_eviction: Option<String>,
+  /// Accepted request body types, e.g. "application/json"; others get 415.
+  pub accept: Option<Vec<String>>,
+  /// Worker pool to run the route on: each request goes to the next
+  /// callback joined for this route with `joinPool`. The route's own
+  /// callback answers while none has joined.
+  pub pool: Option<String>,
+}
+
+impl From<TachyonRawRouteOptions> for tachyon_core::router::RouteConfig {
+  fn from(opts: TachyonRawRouteOptions) -> Self {
+    let mut config = Self::new();
+    if let Some(bytes) = opts.body_limit {
+      config = config.body_limit(bytes as usize);
+    }
+    if let Some(ms) = opts.timeout_ms {
+      config = config.timeout(std::time::Duration::from_millis(ms as u64));
+    }
+    if opts.on_timeout.as_deref() == Some("wait") {
+      config = config.timeout(std::time::Duration::MAX);
+    }
+    if let Some(ms) = opts.cache_ttl_ms {
+      let ttl = std::time::Duration::from_millis(ms as u64);
+      let entries = opts
+        .cache_max_entries
+        .map_or(ROUTE_CACHE_ENTRIES, |n| n as usize);
+      let shards = opts.cache_shards.unwrap_or(1) as usize;
+      let eviction = match opts.cache_eviction.as_deref() {
+        Some("slru") => tachyon_core::cache::Eviction::SegmentedLru,
+        Some("tinylfu") => tachyon_core::cache::Eviction::TinyLfu,
+        _ => tachyon_core::cache::Eviction::Lru,
+      };
+      config = config.cache(tachyon_core::cache::ResponseCache::with_eviction(
+        ttl, entries, shards, eviction,
+      ));
+    }
+    for content_type in opts.accept.unwrap_or_default() {
+      config = config.accept(resolve_mime(&content_type));
+    }
+    config
+  }
+}
+
+/// Default `cacheMaxEntries`. In `tachyon-core/benches/cache.rs`, hits on
+/// 1024 entries ran about 20% faster than on 16384, and more shards than
+/// the default 1 gained nothing on one CPU.
+const ROUTE_CACHE_ENTRIES: usize = 1024;
+
+/// Counters of one route cache, from `getCacheStats`.
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct TachyonRawCacheStats {
+  pub method: String,
+  pub path: String,
+  pub hits: f64,
+  pub misses: f64,
+  /// hits / (hits + misses), 0 before the first request.
+  pub hit_rate: f64,
+  pub entries: u32,
+  /// Fresh responses dropped to make room for new ones.
+  pub evictions: f64,
+  /// Entries held by each shard (`cacheShards`).
+  pub shard_entries: Vec<u32>,
+}
+
+impl From<tachyon_core::cache::RouteCacheStats> for TachyonRawCacheStats {
+  fn from(route: tachyon_core::cache::RouteCacheStats) -> Self {
+    let stats = route.stats;
+    Self {
+      method: route.method.as_str().to_string(),
+      path: route.path,
+      hits: stats.hits as f64,
+      misses: stats.misses as f64,
+      hit_rate: stats.hit_rate(),
+      entries: stats.entries as u32,
+      evictions: stats.evictions as f64,
+      shard_entries: route.shard_entries.into_iter().map(|n| n as u32).collect(),
+    }
+  }
+}
+
+/// Counters of one route, from `getStats`. Latencies are in
+/// milliseconds; percentiles are bucket upper bounds.
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct TachyonRawRouteStats {
+  pub requests: f64,
+  /// Responses with a 5xx status.
+  pub errors: f64,
+  /// Handlers that ran past their deadline.
+  pub timeouts: f64,
+  /// Requests per second since `listen` (or `resetStats`).
+  pub throughput: f64,
+  pub mean_ms: f64,
+  pub p50_ms: f64,
+  pub p90_ms: f64,
+  pub p99_ms: f64,
+  pub max_ms: f64,
+  /// Request body bytes received.
+  pub bytes_in: f64,
+  /// Response body bytes sent.
+  pub bytes_out: f64,
+  /// Largest request body seen.
+  pub max_body_in: f64,
+  /// Set for routes with `cacheTtlMs`.
+  pub cache: Option<TachyonRawCacheStats>,
+}
+
+impl From<tachyon_core::stats::RouteStats> for TachyonRawRouteStats {
+  fn from(route: tachyon_core::stats::RouteStats) -> Self {
+    let ms = |d: std::time::Duration| d.as_secs_f64() * 1000.0;
+    Self {
+      requests: route.requests as f64,
+      errors: route.errors as f64,
+      timeouts: route.timeouts as f64,
+      throughput: route.throughput,
+      mean_ms: ms(route.mean),
+      p50_ms: ms(route.p50),
+      p90_ms: ms(route.p90),
+      p99_ms: ms(route.p99),
+      max_ms: ms(route.max),
+      bytes_in: route.bytes_in as f64,
+      bytes_out: route.bytes_out as f64,
+      max_body_in: route.max_body_in as f64,
+      cache: None,
+    }
+  }
+}
+
+/// An address the server listens on.
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct TachyonRawAddress {
+  /// IP address, e.g. "127.0.0.1" or "::1" (no brackets).
+  pub address: String,
+  pub port: u32,
+}
+
+/// Settings for a directory mounted with `files`.
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct TachyonRawFileOptions {
+  /// `Cache-Control: public, max-age=<secs>` on every file.
+  pub max_age_secs: Option<u32>,
+  /// File answering for a directory; "" answers directories 404
+  /// (default: "index.html").
+  pub index: Option<String>,
+}
+
+/// A custom body for a server-generated error status.
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct TachyonRawErrorPage {
+  pub status: u32,
+  pub body: String,
+  /// "json" | "text" | "html" or any MIME type (default: "json").
+  pub content_type: Option<String>,
 }
 
 impl From<TachyonRawConfig> for tachyon_core::config::ServerConfig {
   fn from(ts: TachyonRawConfig) -> Self {
-    let mut config = tachyon_core::config::ServerConfig::new();
+    // Node owns the process signals; installing handlers here would keep
+    // Ctrl-C from exiting it.
+    let mut config = tachyon_core::config::ServerConfig::new().shutdown_signals(false);
     if let Some(addr) = ts.bind_addr {
       config = config.bind(&addr);
     }
+    for addr in ts.extra_binds.iter().flatten() {
+      config = config.bind_also(addr);
+    }
+    if let Some(n) = ts.acceptors {
+      config = config.acceptors(n as usize);
+    }
+    if let Some(n) = ts.worker_threads {
+      config = config.worker_threads(n as usize);
+    }
+    if let Some(n) = ts.max_connections {
+      config = config.connection_limit(tachyon_core::limit::ConnectionLimit::new(n as usize));
+    }
+    if let Some(n) = ts.backlog {
+      config = config.backlog(n);
+    }
+    if let Some(size) = ts.buffer_size {
+      config.buffer_size = size as usize;
+    }
+    if let Some(secs) = ts.tcp_keepalive_secs {
+      let idle = std::time::Duration::from_secs(secs as u64);
+      config = config.tcp_keepalive(idle, idle.min(std::time::Duration::from_secs(10)));
+    }
+    if let Some(v) = ts.ipv6_only {
+      config = config.ipv6_only(v);
+    }
     if let Some(t) = ts.timeout_secs {
       config = config.timeout(std::time::Duration::from_secs(t as u64));
     }
+    if ts.on_timeout.as_deref() == Some("wait") {
+      config = config.timeout(std::time::Duration::MAX);
+    }
+    if let Some(ms) = ts.drain_timeout_ms {
+      config = config.drain_timeout(std::time::Duration::from_millis(ms as u64));
+    }
     if let Some(v) = ts.tcp_nodelay {
       config = config.tcp_nodelay(v);
     }
@@ -227,6 +787,24 @@ impl From<TachyonRawConfig> for tachyon_core::config::ServerConfig {
     if let Some(v) = ts.catch_panics {
       config = config.catch_panics(v);
     }
+    if let Some(ref s) = ts.etag {
+      let mode = match s.as_str() {
+        "strong" => tachyon_http::etag::EtagMode::Strong,
+        "weak" => tachyon_http::etag::EtagMode::Weak,
+        _ => tachyon_http::etag::EtagMode::Off,
+      };
+      config = config.etag(mode);
+    }
+    if let Some(cors) = ts.cors {
+      config = config.cors(cors.into());
+    }
+    if let Some(v) = ts.pretty_json {
+      config = config.pretty_json(v);
+    }
+    for page in ts.error_pages.unwrap_or_default() {
+      let mime = resolve_mime(page.content_type.as_deref().unwrap_or("json"));
+      config = config.error_page(page.status as u16, mime, page.body.into_bytes());
+    }
     config
   }
 }
@@ -235,127 +813,656 @@ impl From<TachyonRawConfig> for tachyon_core::config::ServerConfig {
 #[napi]
 pub struct TachyonRawServer {
   config: tachyon_core::config::ServerConfig,
-  /// Registered routes: (method_id, path_bytes, handler)
-  routes: Vec<(u8, Box<[u8]>, AsyncRouteFn)>,
+  /// `exposeErrors`: callback and hook errors show in the 500.
+  expose_errors: bool,
+  /// `maxQueuedCalls`, shared by every route callback and hook.
+  queue: Option<Arc<CallQueue>>,
+  /// `errorPages`, for the answers made here rather than in the core.
+  pages: Arc<tachyon_core::config::ErrorPages>,
+  /// Registered routes: (method, path, target)
+  routes: Vec<(Method, String, JsRoute)>,
+  /// Per-route response deadlines: (method, path, deadline)
+  deadlines: Vec<(Method, String, std::time::Duration)>,
+  /// Request hooks from `use`, in registration order.
+  hooks: Vec<tachyon_core::middleware::Middleware>,
+  /// Directories mounted with `files`: (path prefix, directory)
+  files: Vec<(String, tachyon_core::files::ServeDir)>,
+  /// Answers requests no route (or directory) matches, from `fallback`.
+  fallback: Option<AsyncRouteFn>,
+  /// Told about route requests that are no longer wanted, from `onAbort`.
+  on_abort: Option<AbortFn>,
+  /// Set once `listen` has started the server.
+  handle: Option<tachyon_core::server::ServerHandle>,
+  /// Reaches the running route table (for cache invalidation).
+  reload: Option<tachyon_core::server::ReloadHandle>,
+  /// Per-route counters of the running server.
+  stats: Option<tachyon_core::stats::Stats>,
 }
 
 #[napi]
 impl TachyonRawServer {
   #[napi(constructor)]
   pub fn new(config: Option<TachyonRawConfig>) -> Self {
-    let config = config.map(|c| c.into()).unwrap_or_default();
+    let expose_errors = config.as_ref().and_then(|c| c.expose_errors) == Some(true);
+    let queue = config
+      .as_ref()
+      .and_then(|c| c.max_queued_calls)
+      .map(|limit| {
+        Arc::new(CallQueue {
+          queued: Default::default(),
+          limit: limit as usize,
+        })
+      });
+    let config: tachyon_core::config::ServerConfig = config.map(|c| c.into()).unwrap_or_default();
     Self {
+      pages: Arc::new(config.error_pages.clone()),
       config,
+      expose_errors,
+      queue,
       routes: Vec::new(),
+      deadlines: Vec::new(),
+      hooks: Vec::new(),
+      files: Vec::new(),
+      fallback: None,
+      on_abort: None,
+      handle: None,
+      reload: None,
+      stats: None,
     }
   }
 
   /// Register a route handler. Called once per route at startup from TypeScript.
   ///
-  /// The handler receives a `TachyonRawRequest` and returns a `TachyonRawResponse`.
+  /// The handler receives a `TachyonRawRequest` and returns a `TachyonRawResponse`,
+  /// or a Promise of one (an `async` handler); a rejected Promise answers 500.
   /// Routing and 404 handling happen entirely in Rust — no JS call for unmatched paths.
+  /// `options` limits, caches or times out this route alone; requests it
+  /// rejects never reach JavaScript. Registering `method` + `path` again
+  /// replaces the route, also while listening: with the same (uncached)
+  /// options, in place; otherwise by rebuilding the route table, which
+  /// empties its caches and restarts its counters. With `options.pool` the
+  /// route runs on worker threads instead (see `joinPool`).
   #[napi]
   pub fn route(
     &mut self,
     method: String,
     path: String,
-    handler: Function<TachyonRawRequest, TachyonRawResponse>,
+    handler: Function<TachyonRawRequest, JsReturn>,
+    options: Option<TachyonRawRouteOptions>,
   ) -> Result<()> {
-    // Let Rust infer the full ThreadsafeFunction type from the Function parameter.
-    let ts_fn = Arc::new(handler.build_threadsafe_function().build()?);
+    let call = js_call(handler)?;
+    let queue = self.queue.clone();
+    let expose_errors = self.expose_errors;
+    let pages = self.pages.clone();
+    let method = Method::from_bytes(method.as_bytes());
+    let options = options.unwrap_or_default();
+    let pool = options
+      .pool
+      .as_deref()
+      .map(|pool| workers::members(pool, method, &path));
 
     let route_fn: AsyncRouteFn = Arc::new(move |req: TachyonRawRequest| {
-      let ts_fn = ts_fn.clone();
-      Box::pin(async move {
-        let (tx, rx) = tokio::sync::oneshot::channel::<Option<TachyonRawResponse>>();
-        let status = ts_fn.call_with_return_value(
-          req,
-          ThreadsafeFunctionCallMode::NonBlocking,
-          move |result: napi::Result<TachyonRawResponse>, _env| {
-            let _ = tx.send(result.ok());
-            Ok(())
-          },
-        );
-        let ts_res_opt = if status != Status::Ok {
-          None
-        } else {
-          rx.await.ok().flatten()
-        };
-        make_write_fn(ts_res_opt)
-      }) as Pin<Box<dyn Future<Output = tachyon_core::server::WriteFn> + Send>>
+      let call = pool
+        .as_ref()
+        .and_then(|members| members.pick())
+        .unwrap_or_else(|| call.clone());
+      answer(call, req, queue.as_ref(), expose_errors, pages.clone())
     });
 
-    let method_id = str_to_method_id(&method);
-    let path_bytes: Box<[u8]> = path.into_bytes().into_boxed_slice();
-    self.routes.push((method_id, path_bytes, route_fn));
+    self.set_route(method, path, JsRoute::Handler(route_fn, options));
+    Ok(())
+  }
+
+  /// Register a hook that sees every request to a route handler before it
+  /// runs, in registration order. Returning a `TachyonRawResponse` (or a
+  /// Promise of one) answers with it and skips the rest; returning nothing
+  /// lets the request through. A hook that throws or rejects answers 500.
+  /// Static routes and cache hits are answered before hooks run.
+  #[napi(js_name = "use")]
+  pub fn use_hook(&mut self, hook: Function<TachyonRawRequest, JsHookReturn>) -> Result<()> {
+    let call = js_call(hook)?;
+    let queue = self.queue.clone();
+    let expose_errors = self.expose_errors;
+    let pages = self.pages.clone();
+    self.hooks.push(Arc::new(
+      move |req: &tachyon_http::http::Request<'_>, next: tachyon_core::middleware::Next| {
+        let called =
+          CallQueue::enter_opt(queue.as_ref()).map(|queued| call(raw_request(req), queued));
+        // Not polled unless the hook lets the request through.
+        let downstream = next.run(req);
+        let pages = pages.clone();
+        Box::pin(async move {
+          let answered = match called {
+            Ok(called) => called.await,
+            Err(e) => Err(e),
+          };
+          match answered {
+            Ok(None) => downstream.await,
+            Ok(Some(ts_res)) => make_write_fn(Ok(ts_res)),
+            Err(e) => failed(e, expose_errors, pages),
+          }
+        })
+      },
+    ));
+    Ok(())
+  }
+
+  /// Accept WebSocket upgrades on `path` (a GET route). `onEvent` gets the
+  /// `open`, `message` and `close` events of every socket, each carrying a
+  /// `TachyonRawWebSocket` to send on. Other requests to `path` get 426.
+  #[napi]
+  pub fn ws(&mut self, path: String, on_event: Function<ws::TachyonRawWsEvent, ()>) -> Result<()> {
+    self.set_route(Method::Get, path, JsRoute::Native(ws::handler(on_event)?));
+    Ok(())
+  }
+
+  /// Answer GET `path` with a server-sent event stream. `onEvent` gets its
+  /// `open` event, carrying a `TachyonRawSseStream` to send on, and its
+  /// `close` once the client goes away or the stream is closed.
+  #[napi]
+  pub fn sse(
+    &mut self,
+    path: String,
+    on_event: Function<sse::TachyonRawSseEvent, ()>,
+  ) -> Result<()> {
+    self.set_route(Method::Get, path, JsRoute::Native(sse::handler(on_event)?));
+    Ok(())
+  }
+
+  /// Register a constant response. Rendered once in Rust at `listen()` —
+  /// requests to it never reach JavaScript. Ideal for health checks.
+  ///
+  /// `contentType` accepts "json" | "text" | "html" or any MIME type (default: "json").
+  #[napi]
+  pub fn static_route(
+    &mut self,
+    method: String,
+    path: String,
+    body: String,
+    content_type: Option<String>,
+  ) {
+    let content_type = resolve_mime(content_type.as_deref().unwrap_or("json")).to_string();
+    self.set_route(
+      Method::from_bytes(method.as_bytes()),
+      path,
+      JsRoute::Static {
+        body: body.into_bytes(),
+        content_type,
+      },
+    );
+  }
+
+  /// Remove the route registered as `method` + `path` (and its deadline).
+  /// While listening, requests already running finish and later ones get
+  /// 404. Returns `false` if there was no such route.
+  #[napi]
+  pub fn unregister(&mut self, method: String, path: String) -> bool {
+    let method = Method::from_bytes(method.as_bytes());
+    let before = self.routes.len();
+    self
+      .routes
+      .retain(|(m, p, _)| (*m, p.as_str()) != (method, path.as_str()));
+    if self.routes.len() == before {
+      return false;
+    }
+    self
+      .deadlines
+      .retain(|(m, p, _)| (*m, p.as_str()) != (method, path.as_str()));
+    self.reload();
+    true
+  }
+
+  /// Serve the files of directory `dir` under `prefix`, read in Rust with
+  /// ETags, ranges and cache headers: `GET <prefix>/css/app.css` answers
+  /// with `<dir>/css/app.css`. Routes take precedence.
+  #[napi]
+  pub fn files(&mut self, prefix: String, dir: String, options: Option<TachyonRawFileOptions>) {
+    let mut serve = tachyon_core::files::ServeDir::new(dir);
+    let options = options.unwrap_or_default();
+    if let Some(index) = options.index {
+      serve = serve.index(Some(&index).filter(|i| !i.is_empty()).map(String::as_str));
+    }
+    if let Some(secs) = options.max_age_secs {
+      serve = serve.max_age(std::time::Duration::from_secs(secs as u64));
+    }
+    self.files.retain(|(p, _)| *p != prefix);
+    self.files.push((prefix, serve));
+    self.reload();
+  }
+
+  /// Answer requests that match no route or `files` directory with
+  /// `handler` instead of the built-in 404, e.g. for an SPA's index page
+  /// or a custom not-found page. The response status is the handler's
+  /// (default: 200). Replaces an earlier fallback, also while listening.
+  #[napi]
+  pub fn fallback(&mut self, handler: Function<TachyonRawRequest, JsReturn>) -> Result<()> {
+    let call = js_call(handler)?;
+    let queue = self.queue.clone();
+    let expose_errors = self.expose_errors;
+    let pages = self.pages.clone();
+    self.fallback = Some(Arc::new(move |req: TachyonRawRequest| {
+      answer(
+        call.clone(),
+        req,
+        queue.as_ref(),
+        expose_errors,
+        pages.clone(),
+      )
+    }));
+    self.reload();
+    Ok(())
+  }
+
+  /// Call `callback` with a request's `requestId` when the response to a
+  /// route callback is no longer wanted: the client went away or the
+  /// deadline passed first. Without it, requests carry no `requestId`.
+  /// Watching for disconnects reads ahead on the connection while the
+  /// callback runs.
+  #[napi]
+  pub fn on_abort(&mut self, callback: Function<u32, ()>) -> Result<()> {
+    let ts_fn = Arc::new(callback.build_threadsafe_function().build()?);
+    self.on_abort = Some(Arc::new(move |id: u32| {
+      ts_fn.call(id, ThreadsafeFunctionCallMode::NonBlocking);
+    }));
+    self.reload();
     Ok(())
   }
 
+  /// Give one route its own response deadline, overriding `timeoutSecs`.
+  /// If the JS handler hasn't answered by then, the client gets a 504
+  /// (customizable via `errorPages`).
+  #[napi]
+  pub fn deadline(&mut self, method: String, path: String, timeout_ms: u32) {
+    self.deadlines.push((
+      Method::from_bytes(method.as_bytes()),
+      path,
+      std::time::Duration::from_millis(timeout_ms as u64),
+    ));
+  }
+
   /// Start the server. Must be called after all routes are registered.
   ///
   /// Builds an O(1) route map and starts the Tokio runtime on a background thread.
+  /// Returns the port bound for `bindAddr` — the one the OS picked when it is 0.
   #[napi]
-  pub fn listen(&self) -> Result<()> {
-    // Build O(1) lookup maps
-    let mut route_map: HashMap<u8, HashMap<Box<[u8]>, AsyncRouteFn>> = HashMap::new();
-    for (method_id, path, handler) in &self.routes {
-      route_map
-        .entry(*method_id)
-        .or_default()
-        .insert(path.clone(), handler.clone());
-    }
-    let route_map: RouteMap = Arc::new(route_map);
-
-    let rust_handler: tachyon_core::server::Handler =
-      Arc::new(move |req: &tachyon_http::http::Request<'_>| {
-        let method_id = method_to_id(req.method);
-
-        // Strip query string for routing lookup
-        let full_path = req.path_str();
-        let route_path = full_path.split('?').next().unwrap_or(full_path);
-
-        let handler = route_map
-          .get(&method_id)
-          .and_then(|m| m.get(route_path.as_bytes()));
-
-        if let Some(handler) = handler {
-          // Extract all request data synchronously — owned, so the future is 'static
-          let method = method_str(req.method).to_string();
-          let path = full_path.to_string();
-          let body = if req.body.is_empty() {
-            None
-          } else {
-            Some(match std::str::from_utf8(req.body) {
-              Ok(s) => s.to_string(),
-              Err(_) => String::from_utf8_lossy(req.body).into_owned(),
-            })
-          };
-          let headers = build_flat_headers(req);
-          let ts_req = TachyonRawRequest {
-            method,
-            path,
-            body,
-            headers,
-          };
-          let handler = handler.clone();
-          Box::pin(async move { handler(ts_req).await })
-        } else {
-          // 404 handled entirely in Rust — zero JS overhead
-          Box::pin(async move {
-            Box::new(|res: &mut tachyon_core::response::Response<'_>| {
-              res.json(404, b"{\"error\":\"not found\"}")
-            }) as tachyon_core::server::WriteFn
-          })
-        }
-      });
+  pub fn listen(&mut self) -> Result<u32> {
+    let mut router = self.router();
+    let stats = router.stats();
+    // Unmatched paths get the core's 404 — zero JS overhead.
 
+    // Config and bind errors fail `listen` itself, not the background thread.
     let server = tachyon_core::server::Server::new(self.config.clone());
-    std::thread::spawn(move || {
-      if let Err(e) = server.run(rust_handler) {
-        eprintln!("[tachyon] Server error: {}", e);
+    let reload = server.reload_handle();
+    let handle = server
+      .spawn(router)
+      .map_err(|e| napi::Error::from_reason(e.to_string()))?;
+    let port = handle.local_addr().port();
+    self.handle = Some(handle);
+    self.reload = Some(reload);
+    self.stats = Some(stats);
+    Ok(port as u32)
+  }
+
+  /// Every listening address, `bindAddr` first; empty before `listen` and
+  /// after `close`.
+  #[napi]
+  pub fn addresses(&self) -> Vec<TachyonRawAddress> {
+    self.handle.as_ref().map_or_else(Vec::new, |handle| {
+      handle
+        .local_addrs()
+        .iter()
+        .map(|addr| TachyonRawAddress {
+          address: addr.ip().to_string(),
+          port: addr.port() as u32,
+        })
+        .collect()
+    })
+  }
+
+  /// Drop the cached responses of `path` (any query string) from the route
+  /// caches (`cacheTtlMs`). Does nothing before `listen`.
+  #[napi]
+  pub fn invalidate_cache(&self, path: String) {
+    if let Some(reload) = &self.reload {
+      reload.invalidate_cache(&path);
+    }
+  }
+
+  /// Empty every route cache. Does nothing before `listen`.
+  #[napi]
+  pub fn invalidate_all(&self) {
+    if let Some(reload) = &self.reload {
+      reload.invalidate_all();
+    }
+  }
+
+  /// Call every cached GET route (`cacheTtlMs`) once and keep its response,
+  /// so the first client isn't the one waiting on the handler. Resolves
+  /// with how many responses were cached; 0 before `listen`.
+  #[napi]
+  pub fn warmup(&self) -> napi::bindgen_prelude::AsyncTask<Warmup> {
+    napi::bindgen_prelude::AsyncTask::new(Warmup(self.reload.clone()))
+  }
+
+  /// Counters of every route cache (`cacheTtlMs`); empty before `listen`.
+  #[napi]
+  pub fn get_cache_stats(&self) -> Vec<TachyonRawCacheStats> {
+    self.reload.as_ref().map_or_else(Vec::new, |reload| {
+      reload.cache_stats().into_iter().map(Into::into).collect()
+    })
+  }
+
+  /// Counters of every route, keyed by method and path ("GET /users");
+  /// empty before `listen`. Still readable after `close`, without `cache`.
+  #[napi]
+  pub fn get_stats(&self) -> std::collections::HashMap<String, TachyonRawRouteStats> {
+    let Some(stats) = &self.stats else {
+      return std::collections::HashMap::new();
+    };
+    let mut caches: std::collections::HashMap<_, _> = self
+      .reload
+      .as_ref()
+      .map_or_else(Vec::new, |reload| reload.cache_stats())
+      .into_iter()
+      .map(|cache| ((cache.method, cache.path.clone()), cache))
+      .collect();
+    stats
+      .snapshot()
+      .into_iter()
+      .map(|route| {
+        let key = format!("{} {}", route.method.as_str(), route.path);
+        let cache = caches.remove(&(route.method, route.path.clone()));
+        let mut out = TachyonRawRouteStats::from(route);
+        out.cache = cache.map(Into::into);
+        (key, out)
+      })
+      .collect()
+  }
+
+  /// Zero the route and cache counters; cached responses are kept.
+  #[napi]
+  pub fn reset_stats(&self) {
+    if let Some(stats) = &self.stats {
+      stats.reset();
+    }
+    if let Some(reload) = &self.reload {
+      reload.reset_cache_stats();
+    }
+  }
+
+  /// Stop accepting and close connections once their current response is
+  /// sent. Resolves when the last one has closed (or was cut off after
+  /// `drainTimeoutMs`) and the route callbacks are released, so the
+  /// process can exit; resolves at once before `listen`.
+  #[napi]
+  pub fn close(&mut self) -> napi::bindgen_prelude::AsyncTask<Close> {
+    let handle = self.handle.take();
+    if let Some(handle) = &handle {
+      handle.shutdown();
+    }
+    // The running table is dropped with the server; these would keep
+    // the callbacks alive.
+    self.reload = None;
+    self.routes.clear();
+    self.hooks.clear();
+    self.fallback = None;
+    self.on_abort = None;
+    napi::bindgen_prelude::AsyncTask::new(Close(handle))
+  }
+}
+
+impl TachyonRawServer {
+  /// Register `route`, replacing one already at `method` + `path`; while
+  /// listening the new table is served at once.
+  fn set_route(&mut self, method: Method, path: String, route: JsRoute) {
+    let Some(entry) = self
+      .routes
+      .iter_mut()
+      .find(|(m, p, _)| (*m, p.as_str()) == (method, path.as_str()))
+    else {
+      self.routes.push((method, path, route));
+      self.reload();
+      return;
+    };
+    // A callback replaced under the same options is swapped in place,
+    // keeping the running table's caches and counters.
+    let swapped = match (&entry.2, &route, &self.reload) {
+      (JsRoute::Handler(_, old), JsRoute::Handler(route_fn, options), Some(reload))
+        if old == options && options.cache_ttl_ms.is_none() =>
+      {
+        let handler = js_handler(route_fn.clone(), self.on_abort.clone());
+        reload.swap_handler(method, &path, handler).is_some()
       }
-    });
+      _ => false,
+    };
+    entry.2 = route;
+    if !swapped {
+      self.reload();
+    }
+  }
+
+  /// The route table for everything registered so far.
+  fn router(&self) -> tachyon_core::router::Router {
+    let mut router = tachyon_core::router::Router::new();
+    for (method, path, route) in &self.routes {
+      match route {
+        JsRoute::Handler(route_fn, options) => {
+          let handler = js_handler(route_fn.clone(), self.on_abort.clone());
+          let config = tachyon_core::router::RouteConfig::from(options.clone()).swappable();
+          router.route_with(*method, path, handler, config);
+        }
+        JsRoute::Static { body, content_type } => {
+          router.static_response(*method, path, body.clone(), content_type);
+        }
+        JsRoute::Native(handler) => {
+          router.route(*method, path, handler.clone());
+        }
+      }
+    }
+    for (method, path, deadline) in &self.deadlines {
+      router.deadline(*method, path, *deadline);
+    }
+    for hook in &self.hooks {
+      router.middleware(hook.clone());
+    }
+    for (prefix, dir) in &self.files {
+      router.files(prefix, dir.clone());
+    }
+    if let Some(fallback) = &self.fallback {
+      router.fallback(js_handler(fallback.clone(), self.on_abort.clone()));
+    }
+    router
+  }
+
+  /// Swap the running server over to the current routes. Route counters
+  /// start again from zero.
+  fn reload(&mut self) {
+    if let Some(reload) = &self.reload {
+      let mut router = self.router();
+      self.stats = Some(router.stats());
+      reload.reload(router);
+    }
+  }
+}
+
+/// Waits for the server to drain off the JS thread, which has to stay
+/// free to answer the requests still running.
+pub struct Close(Option<tachyon_core::server::ServerHandle>);
+
+impl napi::Task for Close {
+  type Output = ();
+  type JsValue = ();
+
+  fn compute(&mut self) -> Result<()> {
+    let Some(handle) = self.0.take() else {
+      return Ok(());
+    };
+    let rt = tokio::runtime::Builder::new_current_thread()
+      .build()
+      .map_err(|e| napi::Error::from_reason(e.to_string()))?;
+    rt.block_on(handle.join())
+      .map_err(|e| napi::Error::from_reason(e.to_string()))
+  }
 
+  fn resolve(&mut self, _env: napi::Env, _: ()) -> Result<()> {
     Ok(())
   }
 }
+
+/// Runs `ReloadHandle::warmup` off the JS thread, which has to stay free
+/// to answer the route callbacks it calls.
+pub struct Warmup(Option<tachyon_core::server::ReloadHandle>);
+
+impl napi::Task for Warmup {
+  type Output = usize;
+  type JsValue = u32;
+
+  fn compute(&mut self) -> Result<usize> {
+    let Some(reload) = &self.0 else {
+      return Ok(0);
+    };
+    let rt = tokio::runtime::Builder::new_current_thread()
+      .enable_time()
+      .build()
+      .map_err(|e| napi::Error::from_reason(e.to_string()))?;
+    Ok(rt.block_on(reload.warmup()))
+  }
+
+  fn resolve(&mut self, _env: napi::Env, warmed: usize) -> Result<u32> {
+    Ok(warmed as u32)
+  }
+}
+
+/// Copy what JS sees of a request into owned data.
+fn raw_request(req: &tachyon_http::http::Request<'_>) -> TachyonRawRequest {
+  let raw_body = req.decoded_body();
+  let json = (!raw_body.is_empty() && is_json(req.header(b"content-type")))
+    .then(|| serde_json::from_slice(&raw_body).ok())
+    .flatten();
+  let body = (!raw_body.is_empty()).then(|| raw_body.into_owned().into());
+  let path = req.path_str();
+  let query = path.split_once('?').map(|(_, query)| query);
+  TachyonRawRequest {
+    method: req.method.as_str().to_string(),
+    path: path.to_string(),
+    query: query.map(str::to_string),
+    params: query.map(query_params),
+    request_id: None,
+    json,
+    body,
+    headers: build_flat_headers(req),
+    remote_addr: tachyon_core::extract::client_addr()
+      .map_or_else(String::new, |peer| peer.to_string()),
+  }
+}
+
+/// Whether a `Content-Type` is JSON: `application/json` or a `+json` type.
+fn is_json(content_type: Option<&[u8]>) -> bool {
+  let Some(content_type) = content_type else {
+    return false;
+  };
+  let mime = content_type
+    .split(|&b| b == b';')
+    .next()
+    .unwrap_or_default()
+    .trim_ascii();
+  mime.eq_ignore_ascii_case(b"application/json")
+    || mime.len() > 5 && mime[mime.len() - 5..].eq_ignore_ascii_case(b"+json")
+}
+
+/// Decode `a=1&b=x+y` into an object, as `URLSearchParams` would; the
+/// first value wins when a name repeats.
+fn query_params(query: &str) -> std::collections::HashMap<String, String> {
+  let mut params = std::collections::HashMap::new();
+  for pair in query.split('&').filter(|p| !p.is_empty()) {
+    let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+    params
+      .entry(form_decode(name))
+      .or_insert_with(|| form_decode(value));
+  }
+  params
+}
+
+/// `application/x-www-form-urlencoded` decoding: `+` is a space and bad
+/// escapes are kept as they are.
+fn form_decode(s: &str) -> String {
+  let bytes = s.as_bytes();
+  let mut out = Vec::with_capacity(bytes.len());
+  let mut i = 0;
+  while i < bytes.len() {
+    let hex = |b: u8| (b as char).to_digit(16);
+    match bytes[i] {
+      b'+' => out.push(b' '),
+      b'%' if i + 2 < bytes.len() => match (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+        (Some(hi), Some(lo)) => {
+          out.push((hi * 16 + lo) as u8);
+          i += 2;
+        }
+        _ => out.push(b'%'),
+      },
+      b => out.push(b),
+    }
+    i += 1;
+  }
+  String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Wrap a JS route callback into a core handler: extract the request into
+/// owned data synchronously, so the returned future is 'static. With
+/// `on_abort`, the request gets an id that is reported to it if the client
+/// goes away or the deadline passes before the response is written.
+fn js_handler(route_fn: AsyncRouteFn, on_abort: Option<AbortFn>) -> tachyon_core::server::Handler {
+  Arc::new(move |req: &tachyon_http::http::Request<'_>| {
+    let mut ts_req = raw_request(req);
+    let route_fn = route_fn.clone();
+    let Some(on_abort) = on_abort.clone() else {
+      return Box::pin(async move { route_fn(ts_req).await });
+    };
+    let cancel = tachyon_core::cancel::token();
+    let mut abort = Abort {
+      id: NEXT_REQUEST_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed),
+      on_abort: Some(on_abort),
+    };
+    ts_req.request_id = Some(abort.id);
+    Box::pin(async move {
+      let run = route_fn(ts_req);
+      tokio::pin!(run);
+      let write = tokio::select! {
+        write = &mut run => write,
+        () = cancel.cancelled() => {
+          abort.fire();
+          run.await
+        }
+      };
+      // Answered in time: nothing to report.
+      abort.on_abort.take();
+      write
+    })
+  })
+}
+
+/// Reports an aborted request's id to JS, from `onAbort`.
+type AbortFn = Arc<dyn Fn(u32) + Send + Sync>;
+
+static NEXT_REQUEST_ID: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(1);
+
+/// A route callback's request until it is answered. Dropped unanswered,
+/// e.g. at its deadline, it reports the request aborted.
+struct Abort {
+  id: u32,
+  on_abort: Option<AbortFn>,
+}
+
+impl Abort {
+  fn fire(&mut self) {
+    if let Some(on_abort) = self.on_abort.take() {
+      on_abort(self.id);
+    }
+  }
+}
+
+impl Drop for Abort {
+  fn drop(&mut self) {
+    self.fire();
+  }
+}
@@ -0,0 +1,160 @@
+#![deny(clippy::all)]
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use napi::bindgen_prelude::{AsyncTask, Buffer, Uint8Array};
+use napi::{Env, Result, Task};
+use napi_derive::napi;
+use tachyon_core::multipart::Multipart;
+
+static NEXT_FILE: AtomicU64 = AtomicU64::new(0);
+
+/// How `parseMultipart` hands over uploaded files.
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct TachyonRawMultipartOptions {
+  /// Write each file to a new file in this directory and return its `path`
+  /// instead of its `data`; the caller deletes it when done.
+  pub upload_dir: Option<String>,
+}
+
+/// A parsed `multipart/form-data` body.
+#[napi(object, object_from_js = false)]
+pub struct TachyonRawMultipart {
+  /// Text fields; the first value wins when a name repeats.
+  pub fields: HashMap<String, String>,
+  pub files: Vec<TachyonRawUpload>,
+}
+
+/// One uploaded file.
+#[napi(object, object_from_js = false)]
+pub struct TachyonRawUpload {
+  /// The form field it was sent as.
+  pub field: String,
+  /// As sent by the client: don't use it as a path.
+  pub filename: String,
+  pub content_type: Option<String>,
+  pub size: u32,
+  /// The contents, unless `uploadDir` was given.
+  pub data: Option<Buffer>,
+  /// Where the contents were written, with `uploadDir`.
+  pub path: Option<String>,
+}
+
+/// Parse a `multipart/form-data` body off the JS thread. Rejects if
+/// `contentType` isn't multipart/form-data or the body is malformed.
+#[napi]
+pub fn parse_multipart(
+  content_type: String,
+  body: Uint8Array,
+  options: Option<TachyonRawMultipartOptions>,
+) -> AsyncTask<ParseMultipart> {
+  AsyncTask::new(ParseMultipart {
+    content_type,
+    body: body.to_vec(),
+    upload_dir: options.and_then(|o| o.upload_dir).map(PathBuf::from),
+  })
+}
+
+pub struct ParseMultipart {
+  content_type: String,
+  body: Vec<u8>,
+  upload_dir: Option<PathBuf>,
+}
+
+/// A file upload before it is handed to JS.
+pub struct Upload {
+  field: String,
+  filename: String,
+  content_type: Option<String>,
+  size: usize,
+  data: Option<Vec<u8>>,
+  path: Option<String>,
+}
+
+impl Task for ParseMultipart {
+  type Output = (HashMap<String, String>, Vec<Upload>);
+  type JsValue = TachyonRawMultipart;
+
+  fn compute(&mut self) -> Result<Self::Output> {
+    let form = Multipart::parse(self.content_type.as_bytes(), &self.body)
+      .map_err(|e| napi::Error::from_reason(e.message().to_string()))?;
+    let mut fields = HashMap::new();
+    let mut files = Vec::new();
+    for part in form.0 {
+      let Some(filename) = part.filename else {
+        fields
+          .entry(part.name)
+          .or_insert_with(|| String::from_utf8_lossy(&part.data).into_owned());
+        continue;
+      };
+      let mut upload = Upload {
+        field: part.name,
+        filename,
+        content_type: part.content_type,
+        size: part.data.len(),
+        data: None,
+        path: None,
+      };
+      match &self.upload_dir {
+        Some(dir) => match save(dir, &part.data) {
+          Ok(path) => upload.path = Some(path),
+          Err(e) => {
+            // Don't leave the files of a failed upload behind.
+            for path in files.iter().filter_map(|f: &Upload| f.path.as_ref()) {
+              let _ = std::fs::remove_file(path);
+            }
+            return Err(e);
+          }
+        },
+        None => upload.data = Some(part.data),
+      }
+      files.push(upload);
+    }
+    Ok((fields, files))
+  }
+
+  fn resolve(&mut self, _env: Env, (fields, files): Self::Output) -> Result<TachyonRawMultipart> {
+    Ok(TachyonRawMultipart {
+      fields,
+      files: files
+        .into_iter()
+        .map(|f| TachyonRawUpload {
+          field: f.field,
+          filename: f.filename,
+          content_type: f.content_type,
+          size: f.size as u32,
+          data: f.data.map(Into::into),
+          path: f.path,
+        })
+        .collect(),
+    })
+  }
+}
+
+/// Write `data` to a new file in `dir`, named so it can't collide or be
+/// chosen by the client.
+fn save(dir: &std::path::Path, data: &[u8]) -> Result<String> {
+  let nanos = std::time::SystemTime::now()
+    .duration_since(std::time::UNIX_EPOCH)
+    .unwrap_or_default()
+    .as_nanos();
+  let name = format!(
+    "upload-{}-{nanos:x}-{}",
+    std::process::id(),
+    NEXT_FILE.fetch_add(1, Ordering::Relaxed)
+  );
+  let path = dir.join(name);
+  let mut file = std::fs::OpenOptions::new()
+    .write(true)
+    .create_new(true)
+    .open(&path)
+    .map_err(|e| napi::Error::from_reason(format!("{}: {e}", path.display())))?;
+  if let Err(e) = file.write_all(data) {
+    let _ = std::fs::remove_file(&path);
+    return Err(napi::Error::from_reason(format!("{}: {e}", path.display())));
+  }
+  Ok(path.to_string_lossy().into_owned())
+}
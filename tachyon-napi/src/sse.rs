@@ -0,0 +1,181 @@
+#![deny(clippy::all)]
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+use napi::bindgen_prelude::{Function, PromiseRaw};
+use napi::threadsafe_function::ThreadsafeFunctionCallMode;
+use napi::{Env, Result};
+use napi_derive::napi;
+use tachyon_core::sse::Event;
+use tachyon_core::stream::BodySender;
+use tokio::sync::Notify;
+
+use crate::handle::TachyonRawRequest;
+
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+
+/// One server-sent event.
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct TachyonRawSseMessage {
+  /// May span lines.
+  pub data: String,
+  /// Event type for `addEventListener` (default: "message").
+  pub event: Option<String>,
+  /// Sent back by a reconnecting browser as `Last-Event-ID`.
+  pub id: Option<String>,
+  /// How long a browser waits before reconnecting, in milliseconds.
+  pub retry_ms: Option<u32>,
+}
+
+impl From<TachyonRawSseMessage> for Event {
+  fn from(msg: TachyonRawSseMessage) -> Self {
+    let mut event = Event::new(msg.data);
+    if let Some(name) = msg.event {
+      event = event.event(name);
+    }
+    if let Some(id) = msg.id {
+      event = event.id(id);
+    }
+    if let Some(ms) = msg.retry_ms {
+      event = event.retry(std::time::Duration::from_millis(ms as u64));
+    }
+    event
+  }
+}
+
+/// What happened on a stream, passed to the `sse` callback: `open` once,
+/// then `close` when the client goes away or the stream is closed.
+#[napi(object, object_from_js = false)]
+pub struct TachyonRawSseEvent {
+  /// "open" | "close"
+  pub kind: String,
+  pub stream: TachyonRawSseStream,
+  /// `open`: the request.
+  pub request: Option<TachyonRawRequest>,
+}
+
+struct Shared {
+  tx: Mutex<Option<BodySender>>,
+  /// Woken by `close`, so the stream ends without waiting on the client.
+  ended: Notify,
+}
+
+/// An open event stream. The same stream is passed with both events;
+/// compare `id`s, not objects.
+#[napi]
+#[derive(Clone)]
+pub struct TachyonRawSseStream {
+  id: u32,
+  shared: Arc<Shared>,
+}
+
+#[napi]
+impl TachyonRawSseStream {
+  /// Unique per stream for the life of the process.
+  #[napi(getter)]
+  pub fn id(&self) -> u32 {
+    self.id
+  }
+
+  /// Send an event. Resolves `false` once the client is gone or the stream
+  /// closed: stop producing. Await each send before the next, or events
+  /// may be reordered.
+  #[napi(ts_return_type = "Promise<boolean>")]
+  pub fn send<'env>(
+    &self,
+    env: &'env Env,
+    message: TachyonRawSseMessage,
+  ) -> Result<PromiseRaw<'env, bool>> {
+    self.queue(env, Event::from(message).encode())
+  }
+
+  /// Send a comment, ignored by clients; keeps idle proxies from closing
+  /// the stream.
+  #[napi(ts_return_type = "Promise<boolean>")]
+  pub fn comment<'env>(
+    &self,
+    env: &'env Env,
+    text: Option<String>,
+  ) -> Result<PromiseRaw<'env, bool>> {
+    let line = tachyon_core::sse::comment(text.as_deref().unwrap_or(""));
+    self.queue(env, line)
+  }
+
+  /// End the stream once the events already sent are written.
+  #[napi]
+  pub fn close(&self) {
+    self
+      .shared
+      .tx
+      .lock()
+      .unwrap_or_else(|e| e.into_inner())
+      .take();
+    self.shared.ended.notify_one();
+  }
+}
+
+impl TachyonRawSseStream {
+  /// Resolves once `data` is queued, without holding the JS thread.
+  fn queue<'env>(&self, env: &'env Env, data: Vec<u8>) -> Result<PromiseRaw<'env, bool>> {
+    let tx = self
+      .shared
+      .tx
+      .lock()
+      .unwrap_or_else(|e| e.into_inner())
+      .clone();
+    env.spawn_future(async move {
+      Ok(match tx {
+        Some(tx) => tx.send(data).await,
+        None => false,
+      })
+    })
+  }
+}
+
+/// A core handler answering every request with an event stream whose
+/// `open` and `close` go to `on_event`.
+pub(crate) fn handler(
+  on_event: Function<TachyonRawSseEvent, ()>,
+) -> Result<tachyon_core::server::Handler> {
+  let ts_fn = Arc::new(on_event.build_threadsafe_function().build()?);
+  Ok(Arc::new(move |req: &tachyon_http::http::Request<'_>| {
+    let (tx, body) = tachyon_core::stream::channel(16);
+    let stream = TachyonRawSseStream {
+      id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+      shared: Arc::new(Shared {
+        tx: Mutex::new(Some(tx.clone())),
+        ended: Notify::new(),
+      }),
+    };
+    ts_fn.call(
+      TachyonRawSseEvent {
+        kind: "open".to_string(),
+        stream: stream.clone(),
+        request: Some(crate::raw_request(req)),
+      },
+      ThreadsafeFunctionCallMode::NonBlocking,
+    );
+    let ts_fn = ts_fn.clone();
+    tokio::spawn(async move {
+      tokio::select! {
+        _ = tx.closed() => {}
+        _ = stream.shared.ended.notified() => {}
+      }
+      // Let the body end once JS drops its sender too.
+      drop(tx);
+      stream.close();
+      ts_fn.call(
+        TachyonRawSseEvent {
+          kind: "close".to_string(),
+          stream,
+          request: None,
+        },
+        ThreadsafeFunctionCallMode::NonBlocking,
+      );
+    });
+    let write: tachyon_core::server::WriteFn =
+      Box::new(move |res| tachyon_core::sse::respond(res, body));
+    Box::pin(std::future::ready(write))
+  }))
+}
@@ -0,0 +1,112 @@
+#![deny(clippy::all)]
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use napi::bindgen_prelude::{Either, PromiseRaw, Uint8Array};
+use napi::{Env, Result};
+use napi_derive::napi;
+use tachyon_core::stream::{BodySender, BodyStream};
+
+/// Bodies created in JS and not yet picked up by a response, by id, with
+/// when they were created.
+static PENDING: LazyLock<Mutex<HashMap<u32, (BodyStream, Instant)>>> =
+  LazyLock::new(Default::default);
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+
+/// How long a body may wait for its response. One still pending by then
+/// belongs to a request that ended without it (the handler timed out or
+/// threw): it is dropped, so its writes resolve `false`.
+const UNCLAIMED: Duration = Duration::from_secs(60);
+
+/// The body a response's `streamId` refers to; each one can be used once.
+pub(crate) fn take(id: u32) -> Option<BodyStream> {
+  PENDING
+    .lock()
+    .unwrap_or_else(|e| e.into_inner())
+    .remove(&id)
+    .map(|(body, _)| body)
+}
+
+/// Writes a streamed response body from JavaScript.
+///
+/// ```typescript
+/// const writer = new TachyonRawBodyWriter()
+/// void (async () => {
+///   for await (const chunk of source) {
+///     if (!(await writer.write(chunk))) return // client gone
+///   }
+///   writer.end()
+/// })()
+/// return { status: 200, contentType: 'text/event-stream', streamId: writer.id }
+/// ```
+#[napi]
+pub struct TachyonRawBodyWriter {
+  id: u32,
+  tx: Option<BodySender>,
+}
+
+#[napi]
+impl TachyonRawBodyWriter {
+  /// `buffer`: chunks queued before `write` waits for the client (default: 16).
+  #[napi(constructor)]
+  pub fn new(buffer: Option<u32>) -> Self {
+    let (tx, body) = tachyon_core::stream::channel(buffer.map_or(16, |n| n as usize));
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let now = Instant::now();
+    let mut pending = PENDING.lock().unwrap_or_else(|e| e.into_inner());
+    pending.retain(|_, (_, created)| now.duration_since(*created) < UNCLAIMED);
+    pending.insert(id, (body, now));
+    Self { id, tx: Some(tx) }
+  }
+
+  /// Pass as `streamId` in the route's response. Unused for a minute, the
+  /// body is dropped.
+  #[napi(getter)]
+  pub fn id(&self) -> u32 {
+    self.id
+  }
+
+  /// Queue a chunk. Resolves `false` once the client has gone away or the
+  /// writer was ended: stop producing. Await each write before the next,
+  /// or chunks may be reordered.
+  #[napi(ts_return_type = "Promise<boolean>")]
+  pub fn write<'env>(
+    &self,
+    env: &'env Env,
+    chunk: Either<String, Uint8Array>,
+  ) -> Result<PromiseRaw<'env, bool>> {
+    let data = match chunk {
+      Either::A(text) => text.into_bytes(),
+      Either::B(bytes) => bytes.to_vec(),
+    };
+    let tx = self.tx.clone();
+    env.spawn_future(async move {
+      Ok(match tx {
+        Some(tx) => tx.send(data).await,
+        None => false,
+      })
+    })
+  }
+
+  /// End the body once the chunks already written are sent.
+  #[napi]
+  pub fn end(&mut self) {
+    self.tx = None;
+  }
+
+  /// Cut the response short: the client sees a truncated body and the
+  /// connection is closed.
+  #[napi(ts_return_type = "Promise<boolean>")]
+  pub fn abort<'env>(&mut self, env: &'env Env) -> Result<PromiseRaw<'env, bool>> {
+    let tx = self.tx.take();
+    env.spawn_future(async move {
+      let Some(tx) = tx else {
+        return Ok(false);
+      };
+      tx.abort().await;
+      Ok(true)
+    })
+  }
+}
@@ -0,0 +1,118 @@
+#![deny(clippy::all)]
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::thread::ThreadId;
+
+use arc_swap::ArcSwap;
+use napi::bindgen_prelude::Function;
+use napi::{Env, Result};
+use napi_derive::napi;
+use tachyon_http::methods::Method;
+
+use crate::handle::TachyonRawRequest;
+use crate::{JsCallFn, JsReturn, js_call};
+
+/// The callbacks worker threads joined for one route of a pool. Routes
+/// hold on to it from registration, so picking a callback takes no lock.
+#[derive(Default)]
+pub(crate) struct Members {
+  next: AtomicUsize,
+  calls: ArcSwap<Vec<(ThreadId, JsCallFn)>>,
+}
+
+impl Members {
+  /// The next joined callback, round robin; `None` while none has joined.
+  pub(crate) fn pick(&self) -> Option<JsCallFn> {
+    let calls = self.calls.load();
+    let i = self.next.fetch_add(1, Ordering::Relaxed);
+    calls
+      .get(i % calls.len().max(1))
+      .map(|(_, call)| call.clone())
+  }
+
+  /// Drop the callbacks `thread` joined with. Called with `POOLS` locked,
+  /// which keeps writers in turn.
+  fn leave(&self, thread: ThreadId) {
+    let calls = self.calls.load();
+    if calls.iter().any(|(t, _)| *t == thread) {
+      let kept = calls
+        .iter()
+        .filter(|(t, _)| *t != thread)
+        .cloned()
+        .collect();
+      self.calls.store(Arc::new(kept));
+    }
+  }
+}
+
+/// Pool members by (pool, method, path). Entries stay once created: the
+/// routes registered for them keep them too.
+type Pools = HashMap<(String, Method, String), Arc<Members>>;
+
+/// Every pool's members. The addon is loaded once per process, so the main
+/// thread and the workers share this table.
+static POOLS: LazyLock<Mutex<Pools>> = LazyLock::new(Default::default);
+
+fn pools() -> std::sync::MutexGuard<'static, Pools> {
+  POOLS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// The members of route `method` + `path` in `pool`, for a route
+/// registered with `{ pool }` to pick from.
+pub(crate) fn members(pool: &str, method: Method, path: &str) -> Arc<Members> {
+  pools()
+    .entry((pool.to_string(), method, path.to_string()))
+    .or_default()
+    .clone()
+}
+
+/// Serve route `method` + `path` of `pool` from this thread, usually a
+/// `worker_threads` Worker: requests to a route registered with
+/// `{ pool }` are spread round robin over every thread that joined it.
+/// The thread leaves when it exits or calls `leavePool`.
+#[napi]
+pub fn join_pool(
+  env: Env,
+  pool: String,
+  method: String,
+  path: String,
+  handler: Function<TachyonRawRequest, JsReturn>,
+) -> Result<()> {
+  let call = js_call(handler)?;
+  let thread = std::thread::current().id();
+  let first = {
+    let mut pools = pools();
+    let first = !pools
+      .values()
+      .any(|m| m.calls.load().iter().any(|(t, _)| *t == thread));
+    let key = (pool, Method::from_bytes(method.as_bytes()), path);
+    let members = pools.entry(key).or_default();
+    let mut calls = Vec::clone(&members.calls.load());
+    calls.push((thread, call));
+    members.calls.store(Arc::new(calls));
+    first
+  };
+  if first {
+    env.add_env_cleanup_hook(thread, leave)?;
+  }
+  Ok(())
+}
+
+/// Stop serving `pool` from this thread; requests already running finish.
+#[napi]
+pub fn leave_pool(pool: String) {
+  let thread = std::thread::current().id();
+  for ((name, _, _), members) in pools().iter() {
+    if *name == pool {
+      members.leave(thread);
+    }
+  }
+}
+
+/// Drop every callback `thread` joined with, when its JS environment goes.
+fn leave(thread: ThreadId) {
+  for members in pools().values() {
+    members.leave(thread);
+  }
+}
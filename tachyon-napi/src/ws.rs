@@ -0,0 +1,144 @@
+#![deny(clippy::all)]
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use napi::bindgen_prelude::{AsyncTask, Buffer, Either, Function, Uint8Array};
+use napi::threadsafe_function::ThreadsafeFunctionCallMode;
+use napi::{Env, Result, Task};
+use napi_derive::napi;
+use tachyon_core::ws::{Message, WebSocket, WsSender};
+
+use crate::handle::TachyonRawRequest;
+
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+
+/// What happened on a socket, passed to the `ws` callback. Events of one
+/// socket arrive in order: `open`, any `message`s, then `close`.
+#[napi(object, object_from_js = false)]
+pub struct TachyonRawWsEvent {
+  /// "open" | "message" | "close"
+  pub kind: String,
+  pub socket: TachyonRawWebSocket,
+  /// `open`: the upgrade request.
+  pub request: Option<TachyonRawRequest>,
+  /// `message`: a text message.
+  pub text: Option<String>,
+  /// `message`: a binary message.
+  pub data: Option<Buffer>,
+  /// `close`: the client's close code; 1005 if it sent none, 1006 if the
+  /// connection dropped without a close.
+  pub code: Option<u32>,
+  pub reason: Option<String>,
+}
+
+impl TachyonRawWsEvent {
+  fn new(kind: &str, socket: &TachyonRawWebSocket) -> Self {
+    Self {
+      kind: kind.to_string(),
+      socket: socket.clone(),
+      request: None,
+      text: None,
+      data: None,
+      code: None,
+      reason: None,
+    }
+  }
+}
+
+/// An open WebSocket. The same socket is passed with every event; compare
+/// `id`s, not objects.
+#[napi]
+#[derive(Clone)]
+pub struct TachyonRawWebSocket {
+  id: u32,
+  tx: WsSender,
+}
+
+#[napi]
+impl TachyonRawWebSocket {
+  /// Unique per socket for the life of the process.
+  #[napi(getter)]
+  pub fn id(&self) -> u32 {
+    self.id
+  }
+
+  /// Send a text (string) or binary (Uint8Array) message. Resolves `false`
+  /// once the socket is closed.
+  #[napi]
+  pub fn send(&self, data: Either<String, Uint8Array>) -> AsyncTask<SendMessage> {
+    let msg = match data {
+      Either::A(text) => Message::Text(text),
+      Either::B(bytes) => Message::Binary(bytes.to_vec()),
+    };
+    AsyncTask::new(SendMessage(self.tx.clone(), Some(msg)))
+  }
+
+  /// Close the socket (default code: 1000) after the messages already sent.
+  #[napi]
+  pub fn close(&self, code: Option<u32>, reason: Option<String>) -> AsyncTask<SendMessage> {
+    let code = code.map_or(1000, |code| code as u16);
+    let msg = Message::Close(code, reason.unwrap_or_default());
+    AsyncTask::new(SendMessage(self.tx.clone(), Some(msg)))
+  }
+}
+
+/// Queues one message off the JS thread.
+pub struct SendMessage(WsSender, Option<Message>);
+
+impl Task for SendMessage {
+  type Output = bool;
+  type JsValue = bool;
+
+  fn compute(&mut self) -> Result<bool> {
+    Ok(self.1.take().is_some_and(|msg| self.0.blocking_send(msg)))
+  }
+
+  fn resolve(&mut self, _env: Env, sent: bool) -> Result<bool> {
+    Ok(sent)
+  }
+}
+
+/// A core handler upgrading every request to a socket whose events go to
+/// `on_event`.
+pub(crate) fn handler(
+  on_event: Function<TachyonRawWsEvent, ()>,
+) -> Result<tachyon_core::server::Handler> {
+  let ts_fn = Arc::new(on_event.build_threadsafe_function().build()?);
+  Ok(Arc::new(move |req: &tachyon_http::http::Request<'_>| {
+    let request = crate::raw_request(req);
+    let ts_fn = ts_fn.clone();
+    let write = tachyon_core::ws::upgrade(req, move |mut socket: WebSocket| async move {
+      let handle = TachyonRawWebSocket {
+        id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+        tx: socket.sender(),
+      };
+      let emit = |event| {
+        ts_fn.call(event, ThreadsafeFunctionCallMode::NonBlocking);
+      };
+      emit(TachyonRawWsEvent {
+        request: Some(request),
+        ..TachyonRawWsEvent::new("open", &handle)
+      });
+      let mut closed = (1006, String::new());
+      while let Some(msg) = socket.recv().await {
+        match msg {
+          Message::Text(text) => emit(TachyonRawWsEvent {
+            text: Some(text),
+            ..TachyonRawWsEvent::new("message", &handle)
+          }),
+          Message::Binary(data) => emit(TachyonRawWsEvent {
+            data: Some(data.into()),
+            ..TachyonRawWsEvent::new("message", &handle)
+          }),
+          Message::Close(code, reason) => closed = (code, reason),
+        }
+      }
+      emit(TachyonRawWsEvent {
+        code: Some(closed.0 as u32),
+        reason: Some(closed.1),
+        ..TachyonRawWsEvent::new("close", &handle)
+      });
+    });
+    Box::pin(std::future::ready(write))
+  }))
+}
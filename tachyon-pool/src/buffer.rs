@@ -1,5 +1,5 @@
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     ops::{Deref, DerefMut},
 };
 
@@ -14,8 +14,8 @@ use std::{
 /// This means idle servers use near-zero memory, while active servers
 /// build up a hot pool of reusable buffers over time.
 pub struct BufferPool {
-    buf_size: usize,
-    max_capacity: usize,
+    buf_size: Cell<usize>,
+    max_capacity: Cell<usize>,
     buffers: RefCell<Vec<Vec<u8>>>,
     #[cfg(debug_assertions)]
     stats: RefCell<PoolStats>,
@@ -41,8 +41,8 @@ impl BufferPool {
     /// Buffers returned via `release()` are kept up to `max_capacity`.
     pub fn new(max_capacity: usize, buf_size: usize) -> Self {
         Self {
-            buf_size,
-            max_capacity,
+            buf_size: Cell::new(buf_size),
+            max_capacity: Cell::new(max_capacity),
             buffers: RefCell::new(Vec::with_capacity(max_capacity)),
             #[cfg(debug_assertions)]
             stats: RefCell::new(PoolStats::default()),
@@ -62,7 +62,7 @@ impl BufferPool {
             {
                 self.stats.borrow_mut().alloc_misses += 1;
             }
-            vec![0u8; self.buf_size]
+            vec![0u8; self.buf_size.get()]
         });
 
         BufGuard {
@@ -80,7 +80,8 @@ impl BufferPool {
         }
 
         let mut buffers = self.buffers.borrow_mut();
-        if buffers.len() < self.max_capacity {
+        // Buffers sized before a `configure` are not reused.
+        if buffers.len() < self.max_capacity.get() && buf.capacity() == self.buf_size.get() {
             // Reset length but keep capacity — the allocation is reused.
             buf.clear();
             buffers.push(buf);
@@ -90,10 +91,25 @@ impl BufferPool {
 
     /// Replace all pooled buffers with `capacity` fresh buffers of `buf_size` bytes.
     pub fn reinit(&self, capacity: usize, buf_size: usize) {
+        self.buf_size.set(buf_size);
+        self.max_capacity.set(capacity);
         let mut buffers = self.buffers.borrow_mut();
         buffers.clear();
         buffers.extend((0..capacity).map(|_| vec![0u8; buf_size]));
     }
+
+    /// Change the cap and buffer size without preallocating; pooled buffers
+    /// of another size are dropped. A no-op when nothing changes.
+    pub fn configure(&self, max_capacity: usize, buf_size: usize) {
+        if self.buf_size.get() == buf_size && self.max_capacity.get() == max_capacity {
+            return;
+        }
+        self.buf_size.set(buf_size);
+        self.max_capacity.set(max_capacity);
+        let mut buffers = self.buffers.borrow_mut();
+        buffers.retain(|b| b.capacity() == buf_size);
+        buffers.truncate(max_capacity);
+    }
 }
 
 impl BufGuard {
@@ -26,6 +26,12 @@ pub fn init_thread_pool(capacity: usize, buf_size: usize) {
     THREAD_POOL.with(|pool| pool.reinit(capacity, buf_size));
 }
 
+/// Size the current thread's pool lazily: no buffers are allocated, and
+/// calling it again with the same values is just a comparison.
+pub fn configure_thread_pool(capacity: usize, buf_size: usize) {
+    THREAD_POOL.with(|pool| pool.configure(capacity, buf_size));
+}
+
 /// Return a buffer to the current thread's pool.
 /// Usually you don't call this directly — `BufGuard` does it on drop.
 pub fn release(guard: BufGuard) {